@@ -1,6 +1,14 @@
 // Adapted from https://github.com/vercel/next.js/blob/canary/packages/next/client/dev/error-overlay/websocket.ts
 
-let source: WebSocket;
+type Transport = {
+  send(data: string): void;
+  close(): void;
+};
+
+let source: Transport | null = null;
+// Set once a WebSocket connection failed before it was ever opened. Further
+// connections will use Server-Sent Events instead.
+let useEventSource = false;
 const eventCallbacks: ((event: WebsocketEvent) => void)[] = [];
 
 // TODO: add timeout again
@@ -26,12 +34,14 @@ type WebsocketEvent =
       message: MessageEvent;
     };
 
+export type HMRTransport = "auto" | "websocket" | "eventsource";
+
 export function addEventListener(cb: (event: WebsocketEvent) => void) {
   eventCallbacks.push(cb);
 }
 
 export function sendMessage(data: any) {
-  if (!source || source.readyState !== source.OPEN) return;
+  if (!source) return;
   return source.send(data);
 }
 
@@ -40,13 +50,15 @@ export type HMROptions = {
   assetPrefix: string;
   timeout?: number;
   log?: boolean;
+  transport?: HMRTransport;
 };
 
 export function connectHMR(options: HMROptions) {
-  const { timeout = 5 * 1000 } = options;
+  const { timeout = 5 * 1000, transport = "auto" } = options;
 
   function init() {
     if (source) source.close();
+    source = null;
 
     console.log("[HMR] connecting...");
 
@@ -75,7 +87,8 @@ export function connectHMR(options: HMROptions) {
     // let timer: NodeJS.Timeout
 
     function handleDisconnect() {
-      source.close();
+      if (source) source.close();
+      source = null;
       setTimeout(init, timeout);
     }
 
@@ -91,11 +104,91 @@ export function connectHMR(options: HMROptions) {
       url = `${protocol}://${assetPrefix.split("://")[1]}`;
     }
 
-    source = new window.WebSocket(`${url}${options.path}`);
-    source.onopen = handleOnline;
-    source.onerror = handleDisconnect;
-    source.onmessage = handleMessage;
+    if (
+      transport === "eventsource" ||
+      (transport === "auto" && useEventSource)
+    ) {
+      // The event stream is served over plain HTTP(S) on the same path.
+      const httpUrl = url.replace(/^ws/, "http");
+      source = connectEventSource(`${httpUrl}${options.path}`, {
+        onOpen: handleOnline,
+        onMessage: handleMessage,
+        onError: handleDisconnect,
+      });
+      return;
+    }
+
+    let opened = false;
+    const ws = new window.WebSocket(`${url}${options.path}`);
+    ws.onopen = () => {
+      opened = true;
+      handleOnline();
+    };
+    ws.onerror = () => {
+      if (!opened && transport === "auto" && !useEventSource) {
+        console.log(
+          "[HMR] WebSocket connection failed, falling back to Server-Sent Events"
+        );
+        useEventSource = true;
+      }
+      handleDisconnect();
+    };
+    ws.onmessage = handleMessage;
+    source = {
+      send(data) {
+        if (ws.readyState !== ws.OPEN) return;
+        ws.send(data);
+      },
+      close() {
+        ws.close();
+      },
+    };
   }
 
   init();
 }
+
+type EventSourceHandlers = {
+  onOpen: () => void;
+  onMessage: (event: MessageEvent) => void;
+  onError: () => void;
+};
+
+function connectEventSource(
+  url: string,
+  handlers: EventSourceHandlers
+): Transport {
+  const eventSource = new window.EventSource(url);
+  let sessionId: string | null = null;
+  // Messages are posted one after another so the server receives them in
+  // order.
+  let pending: Promise<unknown> = Promise.resolve();
+
+  eventSource.addEventListener("session", (event) => {
+    sessionId = (event as MessageEvent).data;
+    handlers.onOpen();
+  });
+  eventSource.onmessage = handlers.onMessage;
+  eventSource.onerror = handlers.onError;
+
+  return {
+    send(data) {
+      if (sessionId == null) return;
+      const sessionUrl = `${url}/${sessionId}`;
+      pending = pending
+        .then(() =>
+          fetch(sessionUrl, {
+            method: "POST",
+            headers: { "Content-Type": "application/json" },
+            body: data,
+          })
+        )
+        .catch((err) => {
+          console.error("[HMR] failed to send message", err);
+        });
+    },
+    close() {
+      eventSource.close();
+    },
+  };
+}
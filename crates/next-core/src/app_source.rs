@@ -85,6 +85,7 @@ async fn next_client_transition(
         project_path,
         execution_context,
         client_environment,
+        env,
         ty,
         next_config,
     );
@@ -212,6 +213,7 @@ fn app_context(
             execution_context,
             client_ty,
             server_root,
+            env,
             browserslist_query,
             next_config,
         )
@@ -17,25 +17,10 @@ pub async fn load_env(project_path: FileSystemPathVc) -> Result<ProcessEnvVc> {
     let node_env = env.read("NODE_ENV").await?;
     let node_env = node_env.as_deref().unwrap_or("development");
 
-    let files = [
-        Some(format!(".env.{node_env}.local")),
-        if node_env == "test" {
-            None
-        } else {
-            Some(".env.local".into())
-        },
-        Some(format!(".env.{node_env}")),
-        Some(".env".into()),
-    ]
-    .into_iter()
-    .flatten();
-
-    let env = files.fold(env, |prior, f| {
-        let path = project_path.join(&f);
-        TryDotenvProcessEnvVc::new(prior, path).as_process_env()
-    });
-
-    Ok(env)
+    Ok(
+        TryDotenvProcessEnvVc::new_layered(env, project_path, node_env.to_string())
+            .as_process_env(),
+    )
 }
 
 /// Creates a ProcessEnvVc safe to use in JS, by stringifying and encoding as
@@ -41,6 +41,7 @@ pub async fn get_fallback_page(
         project_path,
         execution_context,
         environment,
+        env,
         ty,
         next_config,
     );
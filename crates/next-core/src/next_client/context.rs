@@ -3,7 +3,7 @@ use std::collections::HashMap;
 
 use anyhow::Result;
 use turbo_tasks::Value;
-use turbo_tasks_env::ProcessEnvVc;
+use turbo_tasks_env::{FilterProcessEnvVc, ProcessEnvVc};
 use turbo_tasks_fs::FileSystemPathVc;
 use turbopack::{
     module_options::{
@@ -99,6 +99,7 @@ pub async fn get_client_module_options_context(
     project_path: FileSystemPathVc,
     execution_context: ExecutionContextVc,
     env: EnvironmentVc,
+    process_env: ProcessEnvVc,
     ty: Value<ClientContextType>,
     next_config: NextConfigVc,
 ) -> Result<ModuleOptionsContextVc> {
@@ -111,6 +112,9 @@ pub async fn get_client_module_options_context(
 
     let module_options_context = ModuleOptionsContext {
         preset_env_versions: Some(env),
+        // `NEXT_PUBLIC_` variables are inlined into client code, so branches
+        // depending on them are removed
+        inline_env: Some(FilterProcessEnvVc::new(process_env, "NEXT_PUBLIC_".to_string()).into()),
         execution_context: Some(execution_context),
         ..Default::default()
     };
@@ -146,6 +150,7 @@ pub fn get_client_asset_context(
     project_path: FileSystemPathVc,
     execution_context: ExecutionContextVc,
     environment: EnvironmentVc,
+    process_env: ProcessEnvVc,
     ty: Value<ClientContextType>,
     next_config: NextConfigVc,
 ) -> AssetContextVc {
@@ -154,6 +159,7 @@ pub fn get_client_asset_context(
         project_path,
         execution_context,
         environment,
+        process_env,
         ty,
         next_config,
     );
@@ -176,7 +182,7 @@ pub fn get_client_chunking_context(
     environment: EnvironmentVc,
     ty: Value<ClientContextType>,
 ) -> ChunkingContextVc {
-    DevChunkingContextVc::builder(
+    let builder = DevChunkingContextVc::builder(
         project_path,
         server_root,
         match ty.into_value() {
@@ -188,8 +194,13 @@ pub fn get_client_chunking_context(
         get_client_assets_path(server_root, ty),
         environment,
     )
-    .hot_module_replacement()
-    .build()
+    .hot_module_replacement();
+
+    match ty.into_value() {
+        // Next.js brings its own error overlay.
+        ClientContextType::Pages { .. } | ClientContextType::App { .. } => builder.build(),
+        ClientContextType::Fallback | ClientContextType::Other => builder.error_overlay().build(),
+    }
 }
 
 #[turbo_tasks::function]
@@ -1,5 +1,6 @@
 use anyhow::Result;
 use turbo_tasks::Value;
+use turbo_tasks_env::ProcessEnvVc;
 use turbo_tasks_fs::FileSystemPathVc;
 use turbopack::{
     ecmascript::chunk::EcmascriptChunkPlaceableVc,
@@ -37,6 +38,7 @@ impl NextClientChunksTransitionVc {
         execution_context: ExecutionContextVc,
         ty: Value<ClientContextType>,
         server_root: FileSystemPathVc,
+        process_env: ProcessEnvVc,
         browserslist_query: &str,
         next_config: NextConfigVc,
     ) -> NextClientChunksTransitionVc {
@@ -48,6 +50,7 @@ impl NextClientChunksTransitionVc {
             project_path,
             execution_context,
             client_environment,
+            process_env,
             ty,
             next_config,
         );
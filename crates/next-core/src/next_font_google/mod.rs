@@ -2,10 +2,10 @@ use anyhow::{bail, Context, Result};
 use indexmap::IndexMap;
 use indoc::formatdoc;
 use once_cell::sync::Lazy;
-use turbo_tasks::primitives::{OptionStringVc, OptionU16Vc, StringVc, U32Vc};
+use turbo_tasks::primitives::{BoolVc, OptionStringVc, OptionU16Vc, StringVc, U32Vc};
 use turbo_tasks_env::{CommandLineProcessEnvVc, ProcessEnv};
-use turbo_tasks_fetch::fetch;
-use turbo_tasks_fs::{FileContent, FileSystemPathVc};
+use turbo_tasks_fetch::{fetch, fetch_cached, HttpCacheVc};
+use turbo_tasks_fs::{to_sys_path, FileContent, FileSystemPathVc};
 use turbo_tasks_hash::hash_xxh3_hash64;
 use turbopack_core::{
     issue::IssueSeverity,
@@ -154,14 +154,25 @@ impl ImportMappingReplacement for NextFontGoogleCssModuleReplacer {
             get_request_id(*query_vc).await?
         ));
 
-        let stylesheet_res = fetch(
-            stylesheet_url,
-            OptionStringVc::cell(Some(
-                "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like \
-                 Gecko) Chrome/104.0.0.0 Safari/537.36"
-                    .to_owned(),
-            )),
-        )
+        let user_agent = OptionStringVc::cell(Some(
+            "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like \
+             Gecko) Chrome/104.0.0.0 Safari/537.36"
+                .to_owned(),
+        ));
+        // Stylesheets are cached on disk, so they are available offline once
+        // they have been requested
+        let cache_dir = to_sys_path(self.project_path.join(".next/cache/fetch")).await?;
+        let stylesheet_res = match cache_dir {
+            Some(cache_dir) => fetch_cached(
+                stylesheet_url,
+                user_agent,
+                HttpCacheVc::new(
+                    cache_dir.to_string_lossy().into_owned(),
+                    *is_offline().await?,
+                ),
+            ),
+            None => fetch(stylesheet_url, user_agent),
+        }
         .await?;
 
         let stylesheet = match &*stylesheet_res {
@@ -275,6 +286,19 @@ async fn get_request_hash(query_vc: QueryMapVc) -> Result<U32Vc> {
     ))
 }
 
+/// Whether stylesheets are only served from the cache, without sending
+/// requests. This is enabled by setting `NEXT_OFFLINE`, e.g. with `next-dev
+/// --offline`.
+#[turbo_tasks::function]
+async fn is_offline() -> Result<BoolVc> {
+    let env = CommandLineProcessEnvVc::new();
+    let offline = env.read("NEXT_OFFLINE").await?;
+    Ok(BoolVc::cell(matches!(
+        offline.as_deref(),
+        Some(value) if !matches!(value, "" | "0" | "false")
+    )))
+}
+
 #[turbo_tasks::function]
 async fn get_stylesheet_url_from_options(options: NextFontGoogleOptionsVc) -> Result<StringVc> {
     #[allow(unused_mut, unused_assignments)] // This is used in test environments
@@ -97,6 +97,7 @@ pub async fn create_page_source(
         project_path,
         execution_context,
         client_environment,
+        env,
         client_ty,
         next_config,
     );
@@ -146,6 +147,7 @@ pub async fn create_page_source(
                     execution_context,
                     client_ty,
                     server_root,
+                    env,
                     browserslist_query,
                     next_config,
                 )
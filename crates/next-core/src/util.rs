@@ -2,7 +2,7 @@ use anyhow::{anyhow, bail, Result};
 use turbo_tasks::{primitives::StringVc, ValueToString};
 use turbo_tasks_fs::FileSystemPathVc;
 use turbopack::condition::ContextCondition;
-use turbopack_node::path_regex::{PathRegexBuilder, PathRegexVc};
+use turbopack_node::path_regex::{PathRegex, PathRegexVc};
 
 use crate::next_config::NextConfigVc;
 
@@ -43,46 +43,9 @@ pub async fn pathname_for_path(
 /// named capture groups for every dynamic segment.
 #[turbo_tasks::function]
 pub async fn regular_expression_for_path(pathname: StringVc) -> Result<PathRegexVc> {
-    let path = pathname.await?;
-
-    let mut path_regex = PathRegexBuilder::new();
-    for segment in path.split('/') {
-        if let Some(segment) = segment.strip_prefix('[') {
-            if let Some(segment) = segment.strip_prefix("[...") {
-                if let Some((placeholder, rem)) = segment.split_once("]]") {
-                    path_regex.push_optional_catch_all(placeholder, rem);
-                } else {
-                    bail!(
-                        "path ({}) contains '[[' without matching ']]' at '[[...{}'",
-                        path,
-                        segment
-                    );
-                }
-            } else if let Some(segment) = segment.strip_prefix("...") {
-                if let Some((placeholder, rem)) = segment.split_once(']') {
-                    path_regex.push_catch_all(placeholder, rem);
-                } else {
-                    bail!(
-                        "path ({}) contains '[' without matching ']' at '[...{}'",
-                        path,
-                        segment
-                    );
-                }
-            } else if let Some((placeholder, rem)) = segment.split_once(']') {
-                path_regex.push_dynamic_segment(placeholder, rem);
-            } else {
-                bail!(
-                    "path ({}) contains '[' without matching ']' at '[{}'",
-                    path,
-                    segment
-                );
-            }
-        } else {
-            path_regex.push_static_segment(segment);
-        }
-    }
-
-    Ok(PathRegexVc::cell(path_regex.build()?))
+    Ok(PathRegexVc::cell(PathRegex::from_pathname(
+        &pathname.await?,
+    )?))
 }
 
 // Adapted from https://github.com/vercel/next.js/blob/canary/packages/next/shared/lib/router/utils/get-asset-path-from-route.ts
@@ -42,6 +42,7 @@ pub async fn create_web_entry_source(
         project_root,
         execution_context,
         environment,
+        env,
         ty,
         next_config,
     );
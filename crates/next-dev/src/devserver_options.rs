@@ -78,6 +78,62 @@ pub struct DevServerOptions {
     #[cfg_attr(feature = "serializable", serde(default))]
     pub full_stats: bool,
 
+    /// Write a Chrome trace of all handled requests and their phases to the
+    /// given file. It can be loaded in `chrome://tracing` or Perfetto.
+    #[cfg_attr(feature = "cli", clap(long, value_parser))]
+    #[cfg_attr(feature = "serializable", serde(default))]
+    pub request_trace: Option<PathBuf>,
+
+    /// Persist the task graph to the given directory and restore it on the
+    /// next start, so only work affected by changes made in between is
+    /// repeated. Requires the `persistent_cache` feature.
+    #[cfg_attr(feature = "cli", clap(long, value_parser))]
+    #[cfg_attr(feature = "serializable", serde(default))]
+    pub persistent_cache: Option<PathBuf>,
+
+    /// Notice changes by scanning the project every given number of
+    /// milliseconds instead of relying on file system events, which are not
+    /// delivered e. g. in Docker bind mounts or on network file systems.
+    #[cfg_attr(
+        feature = "cli",
+        clap(long, value_parser = clap::value_parser!(u64).range(1..))
+    )]
+    #[cfg_attr(feature = "serializable", serde(default))]
+    pub poll_interval: Option<u64>,
+
+    /// With `--poll-interval`, also compare file contents, to notice changes
+    /// that keep the modification time and size of a file.
+    #[cfg_attr(feature = "cli", clap(long))]
+    #[cfg_attr(feature = "serializable", serde(default))]
+    pub poll_compare_contents: bool,
+
+    /// With `--poll-interval`, also scan `node_modules` and `.git`
+    /// directories. They are skipped by default, as scanning the installed
+    /// packages makes every poll slow.
+    #[cfg_attr(feature = "cli", clap(long))]
+    #[cfg_attr(feature = "serializable", serde(default))]
+    pub poll_dependencies: bool,
+
+    /// Serve fixture files from the given directory, relative to the app
+    /// directory, as a mock API under `/api/`. Fixtures take precedence over
+    /// API routes with the same path.
+    #[cfg_attr(feature = "cli", clap(long, value_parser))]
+    #[cfg_attr(feature = "serializable", serde(default))]
+    pub mock_api: Option<PathBuf>,
+
+    /// With `--mock-api`, delay every mock response by the given number of
+    /// milliseconds.
+    #[cfg_attr(feature = "cli", clap(long, value_parser, default_value_t = 0))]
+    #[cfg_attr(feature = "serializable", serde(default))]
+    pub mock_api_delay: u64,
+
+    /// Serve Google Fonts stylesheets only from the cache in
+    /// `.next/cache/fetch`, without sending requests. Fonts that haven't been
+    /// cached yet fail to load. This is the same as setting `NEXT_OFFLINE=1`.
+    #[cfg_attr(feature = "cli", clap(long))]
+    #[cfg_attr(feature = "serializable", serde(default))]
+    pub offline: bool,
+
     // ==
     // = Inherited options from next-dev, need revisit later.
     // ==
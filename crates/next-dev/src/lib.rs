@@ -10,7 +10,7 @@ use std::{
     future::{join, Future},
     io::{stdout, Write},
     net::{IpAddr, SocketAddr},
-    path::MAIN_SEPARATOR,
+    path::{PathBuf, MAIN_SEPARATOR},
     sync::Arc,
     time::{Duration, Instant},
 };
@@ -25,10 +25,13 @@ use next_core::{
 use owo_colors::OwoColorize;
 use turbo_malloc::TurboMalloc;
 use turbo_tasks::{
+    backend::Backend,
+    run_once_with_priority,
     util::{FormatBytes, FormatDuration},
-    RawVc, StatsType, TransientInstance, TransientValue, TurboTasks, TurboTasksBackendApi, Value,
+    RawVc, StatsType, TaskPriority, TransientInstance, TransientValue, TurboTasks, TurboTasksApi,
+    TurboTasksBackendApi, Value,
 };
-use turbo_tasks_fs::{DiskFileSystemVc, FileSystemVc};
+use turbo_tasks_fs::{DiskFileSystemOptions, DiskFileSystemVc, FileSystemVc, WatchMode};
 use turbo_tasks_memory::MemoryBackend;
 use turbopack_cli_utils::issue::{ConsoleUi, ConsoleUiVc, LogOptions};
 use turbopack_core::{
@@ -39,14 +42,15 @@ use turbopack_core::{
 };
 use turbopack_dev_server::{
     introspect::IntrospectionSource,
+    request_log::{ChromeTraceRequestLogger, ConsoleRequestLogger, RequestLogger},
     source::{
         combined::CombinedContentSourceVc, router::RouterContentSource,
         source_maps::SourceMapContentSourceVc, static_assets::StaticAssetsContentSourceVc,
-        ContentSourceVc,
+        ContentSourceData, ContentSourceVc,
     },
     DevServer, DevServerBuilder,
 };
-use turbopack_node::execution_context::ExecutionContextVc;
+use turbopack_node::{execution_context::ExecutionContextVc, mock_api::MockApiContentSourceVc};
 
 #[derive(Clone)]
 pub enum EntryRequest {
@@ -55,7 +59,10 @@ pub enum EntryRequest {
 }
 
 pub struct NextDevServerBuilder {
-    turbo_tasks: Arc<TurboTasks<MemoryBackend>>,
+    turbo_tasks: Arc<dyn TurboTasksApi>,
+    /// Only available with the in-memory backend, which supports the
+    /// `__turbo_tasks__/` visualizations.
+    memory_turbo_tasks: Option<Arc<TurboTasks<MemoryBackend>>>,
     project_dir: String,
     root_dir: String,
     entry_requests: Vec<EntryRequest>,
@@ -67,6 +74,9 @@ pub struct NextDevServerBuilder {
     show_all: bool,
     log_detail: bool,
     allow_retry: bool,
+    request_trace: Option<PathBuf>,
+    watch_mode: WatchMode,
+    mock_api: Option<(String, u64)>,
 }
 
 impl NextDevServerBuilder {
@@ -74,9 +84,22 @@ impl NextDevServerBuilder {
         turbo_tasks: Arc<TurboTasks<MemoryBackend>>,
         project_dir: String,
         root_dir: String,
+    ) -> NextDevServerBuilder {
+        let mut builder = Self::new_with_backend(turbo_tasks.clone(), project_dir, root_dir);
+        builder.memory_turbo_tasks = Some(turbo_tasks);
+        builder
+    }
+
+    /// Creates a builder for a server running on any backend, e.g. one with a
+    /// persistent cache.
+    pub fn new_with_backend<B: Backend + 'static>(
+        turbo_tasks: Arc<TurboTasks<B>>,
+        project_dir: String,
+        root_dir: String,
     ) -> NextDevServerBuilder {
         NextDevServerBuilder {
             turbo_tasks,
+            memory_turbo_tasks: None,
             project_dir,
             root_dir,
             entry_requests: vec![],
@@ -90,6 +113,9 @@ impl NextDevServerBuilder {
             show_all: false,
             log_detail: false,
             allow_retry: false,
+            request_trace: None,
+            watch_mode: WatchMode::Native,
+            mock_api: None,
         }
     }
 
@@ -138,6 +164,24 @@ impl NextDevServerBuilder {
         self
     }
 
+    pub fn request_trace(mut self, request_trace: PathBuf) -> NextDevServerBuilder {
+        self.request_trace = Some(request_trace);
+        self
+    }
+
+    /// How changes to the project and output directories are noticed.
+    pub fn watch_mode(mut self, watch_mode: WatchMode) -> NextDevServerBuilder {
+        self.watch_mode = watch_mode;
+        self
+    }
+
+    /// Serves fixtures from `dir`, relative to the project directory, as a
+    /// mock API under `/api/`, delaying every response by `delay_ms`.
+    pub fn mock_api(mut self, dir: String, delay_ms: u64) -> NextDevServerBuilder {
+        self.mock_api = Some((dir, delay_ms));
+        self
+    }
+
     /// Attempts to find an open port to bind.
     fn find_port(&self, host: IpAddr, port: u16, max_attempts: u16) -> Result<DevServerBuilder> {
         // max_attempts of 1 means we loop 0 times.
@@ -184,13 +228,28 @@ impl NextDevServerBuilder {
 
         let server = self.find_port(host, port, 10)?;
 
+        let mut request_loggers: Vec<Arc<dyn RequestLogger>> =
+            vec![Arc::new(ConsoleRequestLogger::default())];
+        if let Some(request_trace) = &self.request_trace {
+            request_loggers.push(Arc::new(
+                ChromeTraceRequestLogger::new(request_trace)
+                    .context("unable to create the request trace file")?,
+            ));
+        }
+
         let turbo_tasks = self.turbo_tasks;
+        let memory_turbo_tasks = Arc::new(self.memory_turbo_tasks);
         let project_dir = self.project_dir;
         let root_dir = self.root_dir;
         let eager_compile = self.eager_compile;
         let show_all = self.show_all;
         let log_detail = self.log_detail;
         let browserslist_query = self.browserslist_query;
+        let mock_api = self.mock_api;
+        let fs_options = DiskFileSystemOptions {
+            watch_mode: self.watch_mode,
+            ..Default::default()
+        };
         let log_options = LogOptions {
             current_dir: current_dir().unwrap(),
             show_all,
@@ -201,21 +260,43 @@ impl NextDevServerBuilder {
         let console_ui = Arc::new(ConsoleUi::new(log_options));
         let console_ui_to_dev_server = console_ui.clone();
         let server_addr = Arc::new(server.addr);
-        let tasks = turbo_tasks.clone();
         let source = move || {
             source(
                 root_dir.clone(),
                 project_dir.clone(),
+                Value::new(fs_options.clone()),
                 entry_requests.clone().into(),
                 eager_compile,
-                turbo_tasks.clone().into(),
+                memory_turbo_tasks.clone().into(),
                 console_ui.clone().into(),
                 browserslist_query.clone(),
                 server_addr.clone().into(),
+                mock_api.clone(),
             )
         };
 
-        Ok(server.serve(tasks, source, console_ui_to_dev_server))
+        if eager_compile {
+            // Start compiling in the background instead of waiting for the first request.
+            // Requests are executed with a higher priority, so they don't wait for it.
+            let source = source.clone();
+            tokio::spawn(run_once_with_priority(
+                turbo_tasks.clone(),
+                TaskPriority::Background,
+                async move {
+                    source()
+                        .get("", Value::new(ContentSourceData::default()))
+                        .await?;
+                    Ok(())
+                },
+            ));
+        }
+
+        Ok(server.serve(
+            turbo_tasks,
+            source,
+            console_ui_to_dev_server,
+            Arc::new(request_loggers),
+        ))
     }
 }
 
@@ -232,16 +313,26 @@ async fn handle_issues<T: Into<RawVc>>(source: T, console_ui: ConsoleUiVc) -> Re
 }
 
 #[turbo_tasks::function]
-async fn project_fs(project_dir: &str, console_ui: ConsoleUiVc) -> Result<FileSystemVc> {
-    let disk_fs = DiskFileSystemVc::new("project".to_string(), project_dir.to_string());
+async fn project_fs(
+    project_dir: &str,
+    options: Value<DiskFileSystemOptions>,
+    console_ui: ConsoleUiVc,
+) -> Result<FileSystemVc> {
+    let disk_fs =
+        DiskFileSystemVc::new_with_options("project".to_string(), project_dir.to_string(), options);
     handle_issues(disk_fs, console_ui).await?;
     disk_fs.await?.start_watching()?;
     Ok(disk_fs.into())
 }
 
 #[turbo_tasks::function]
-async fn output_fs(project_dir: &str, console_ui: ConsoleUiVc) -> Result<FileSystemVc> {
-    let disk_fs = DiskFileSystemVc::new("output".to_string(), project_dir.to_string());
+async fn output_fs(
+    project_dir: &str,
+    options: Value<DiskFileSystemOptions>,
+    console_ui: ConsoleUiVc,
+) -> Result<FileSystemVc> {
+    let disk_fs =
+        DiskFileSystemVc::new_with_options("output".to_string(), project_dir.to_string(), options);
     handle_issues(disk_fs, console_ui).await?;
     disk_fs.await?.start_watching()?;
     Ok(disk_fs.into())
@@ -252,16 +343,18 @@ async fn output_fs(project_dir: &str, console_ui: ConsoleUiVc) -> Result<FileSys
 async fn source(
     root_dir: String,
     project_dir: String,
+    fs_options: Value<DiskFileSystemOptions>,
     entry_requests: TransientInstance<Vec<EntryRequest>>,
     eager_compile: bool,
-    turbo_tasks: TransientInstance<TurboTasks<MemoryBackend>>,
+    memory_turbo_tasks: TransientInstance<Option<Arc<TurboTasks<MemoryBackend>>>>,
     console_ui: TransientInstance<ConsoleUi>,
     browserslist_query: String,
     server_addr: TransientInstance<SocketAddr>,
+    mock_api: Option<(String, u64)>,
 ) -> Result<ContentSourceVc> {
     let console_ui = (*console_ui).clone().cell();
-    let output_fs = output_fs(&project_dir, console_ui);
-    let fs = project_fs(&root_dir, console_ui);
+    let output_fs = output_fs(&project_dir, fs_options.clone(), console_ui);
+    let fs = project_fs(&root_dir, fs_options, console_ui);
     let project_relative = project_dir.strip_prefix(&root_dir).unwrap();
     let project_relative = project_relative
         .strip_prefix(MAIN_SEPARATOR)
@@ -321,11 +414,6 @@ async fn source(
         next_config,
         server_addr,
     );
-    let viz = turbo_tasks_viz::TurboTasksSource {
-        turbo_tasks: turbo_tasks.into(),
-    }
-    .cell()
-    .into();
     let static_source =
         StaticAssetsContentSourceVc::new(String::new(), project_path.join("public")).into();
     let manifest_source = DevManifestContentSource {
@@ -333,13 +421,15 @@ async fn source(
     }
     .cell()
     .into();
-    let main_source = CombinedContentSourceVc::new(vec![
-        manifest_source,
-        static_source,
-        app_source,
-        page_source,
-        web_source,
-    ]);
+    let mut sources = vec![manifest_source, static_source];
+    if let Some((dir, delay_ms)) = mock_api {
+        sources.push(
+            MockApiContentSourceVc::new("api/".to_string(), project_path.join(&dir), delay_ms)
+                .into(),
+        );
+    }
+    sources.extend([app_source, page_source, web_source]);
+    let main_source = CombinedContentSourceVc::new(sources);
     let introspect = IntrospectionSource {
         roots: HashSet::from([main_source.into()]),
     }
@@ -352,18 +442,22 @@ async fn source(
         CombinedContentSourceVc::new(vec![static_source, page_source]).into(),
     )
     .into();
+    let mut routes = vec![
+        ("__turbopack__/".to_string(), introspect),
+        (
+            "__nextjs_original-stack-frame".to_string(),
+            source_map_trace,
+        ),
+        // TODO: Load path from next.config.js
+        ("_next/image".to_string(), img_source),
+        ("__turbopack_sourcemap__/".to_string(), source_maps),
+    ];
+    if let Some(turbo_tasks) = &*memory_turbo_tasks {
+        let viz = turbo_tasks_viz::TurboTasksSourceVc::new(turbo_tasks.clone()).into();
+        routes.push(("__turbo_tasks__/".to_string(), viz));
+    }
     let source = RouterContentSource {
-        routes: vec![
-            ("__turbopack__/".to_string(), introspect),
-            ("__turbo_tasks__/".to_string(), viz),
-            (
-                "__nextjs_original-stack-frame".to_string(),
-                source_map_trace,
-            ),
-            // TODO: Load path from next.config.js
-            ("_next/image".to_string(), img_source),
-            ("__turbopack_sourcemap__/".to_string(), source_maps),
-        ],
+        routes,
         fallback: main_source,
     }
     .cell()
@@ -399,6 +493,12 @@ pub async fn start_server(options: &DevServerOptions) -> Result<()> {
         .context("project directory contains invalid characters")?
         .to_string();
 
+    if options.offline {
+        // The process env is what next-core reads `NEXT_OFFLINE` from. This
+        // happens before any tasks are started.
+        std::env::set_var("NEXT_OFFLINE", "1");
+    }
+
     let root_dir = if let Some(root) = options.root.as_ref() {
         root.canonicalize()
             .context("root directory can't be found")?
@@ -409,8 +509,76 @@ pub async fn start_server(options: &DevServerOptions) -> Result<()> {
         dir.clone()
     };
 
+    #[cfg(feature = "persistent_cache")]
+    if let Some(cache_dir) = &options.persistent_cache {
+        use turbo_tasks_memory::MemoryBackendWithPersistedGraph;
+        use turbo_tasks_rocksdb::RocksDbPersistedGraph;
+
+        let version = cache_version().context("unable to identify the build")?;
+        let persisted_graph = RocksDbPersistedGraph::new_versioned(cache_dir, &version)
+            .context("unable to open the persistent cache")?;
+        if persisted_graph.discarded_outdated_cache() {
+            println!(
+                "discarded the persistent cache at {}, as it has been written by a different build",
+                cache_dir.display()
+            );
+        }
+        let tt = TurboTasks::new(MemoryBackendWithPersistedGraph::new(persisted_graph));
+        let server = NextDevServerBuilder::new_with_backend(tt.clone(), dir, root_dir);
+        return tokio::select! {
+            result = run_server(options, start, tt.clone(), server) => result,
+            _ = tokio::signal::ctrl_c() => {
+                // Writes the task graph to disk before exiting.
+                tt.stop_and_wait().await;
+                Ok(())
+            }
+        };
+    }
+    #[cfg(not(feature = "persistent_cache"))]
+    if options.persistent_cache.is_some() {
+        anyhow::bail!("next-dev has been built without the persistent_cache feature");
+    }
+
     let tt = TurboTasks::new(MemoryBackend::new());
+    let server = NextDevServerBuilder::new(tt.clone(), dir, root_dir);
+    run_server(options, start, tt, server).await
+}
+
+/// Identifies the build writing the persistent cache. The persisted task graph
+/// refers to functions and value types by id and stores their serialized
+/// values, so it can only be restored by the exact same build. A changed value
+/// type layout doesn't change the registered names, which is why the
+/// executable itself is hashed.
+#[cfg(feature = "persistent_cache")]
+fn cache_version() -> Result<String> {
+    use std::{fs::File, io::Read};
+
+    use turbo_tasks_hash::{DeterministicHasher, Xxh3Hash64Hasher};
 
+    let mut executable = File::open(std::env::current_exe()?)?;
+    let mut hasher = Xxh3Hash64Hasher::new();
+    let mut buffer = vec![0; 1024 * 1024];
+    loop {
+        let read = executable.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.write_bytes(&buffer[..read]);
+    }
+    Ok(format!(
+        "{}-{:016x}-{:016x}",
+        env!("CARGO_PKG_VERSION"),
+        hasher.finish(),
+        turbo_tasks::registry::registry_hash()
+    ))
+}
+
+async fn run_server<B: Backend + 'static>(
+    options: &DevServerOptions,
+    start: Instant,
+    tt: Arc<TurboTasks<B>>,
+    server: NextDevServerBuilder,
+) -> Result<()> {
     let stats_type = match options.full_stats {
         true => StatsType::Full,
         false => StatsType::Essential,
@@ -419,8 +587,7 @@ pub async fn start_server(options: &DevServerOptions) -> Result<()> {
 
     let tt_clone = tt.clone();
 
-    #[allow(unused_mut)]
-    let mut server = NextDevServerBuilder::new(tt, dir, root_dir)
+    let mut server = server
         .entry_request(EntryRequest::Relative("src/index".into()))
         .eager_compile(options.eager_compile)
         .hostname(options.hostname)
@@ -438,6 +605,28 @@ pub async fn start_server(options: &DevServerOptions) -> Result<()> {
         server = server.allow_retry(options.allow_retry);
     }
 
+    if let Some(request_trace) = &options.request_trace {
+        server = server.request_trace(request_trace.clone());
+    }
+
+    if let Some(mock_api) = &options.mock_api {
+        server = server.mock_api(
+            mock_api.to_string_lossy().replace(MAIN_SEPARATOR, "/"),
+            options.mock_api_delay,
+        );
+    }
+
+    if let Some(poll_interval) = options.poll_interval {
+        if poll_interval == 0 {
+            anyhow::bail!("the poll interval must be at least 1 millisecond");
+        }
+        server = server.watch_mode(WatchMode::Polling {
+            interval: Duration::from_millis(poll_interval),
+            compare_contents: options.poll_compare_contents,
+            skip_dependencies: !options.poll_dependencies,
+        });
+    }
+
     let server = server.build().await?;
 
     {
@@ -521,7 +710,7 @@ pub async fn start_server(options: &DevServerOptions) -> Result<()> {
 #[cfg(feature = "profile")]
 // When profiling, exits the process when no new updates have been received for
 // a given timeout and there are no more tasks in progress.
-async fn profile_timeout<T>(tt: &TurboTasks<MemoryBackend>, future: impl Future<Output = T>) -> T {
+async fn profile_timeout<B: Backend, T>(tt: &TurboTasks<B>, future: impl Future<Output = T>) -> T {
     /// How long to wait in between updates before force-exiting the process
     /// during profiling.
     const PROFILE_EXIT_TIMEOUT: Duration = Duration::from_secs(5);
@@ -540,8 +729,8 @@ async fn profile_timeout<T>(tt: &TurboTasks<MemoryBackend>, future: impl Future<
 }
 
 #[cfg(not(feature = "profile"))]
-fn profile_timeout<T>(
-    _tt: &TurboTasks<MemoryBackend>,
+fn profile_timeout<B: Backend, T>(
+    _tt: &TurboTasks<B>,
     future: impl Future<Output = T>,
 ) -> impl Future<Output = T> {
     future
@@ -1,7 +1,7 @@
 use std::{sync::Arc, time::Duration};
 
 use anyhow::Result;
-use mime::TEXT_HTML_UTF_8;
+use mime::{APPLICATION_JSON, TEXT_HTML_UTF_8};
 use turbo_tasks::{get_invalidator, TurboTasks, TurboTasksBackendApi, Value};
 use turbo_tasks_fs::File;
 use turbo_tasks_memory::{
@@ -10,8 +10,9 @@ use turbo_tasks_memory::{
 };
 use turbopack_core::asset::AssetContentVc;
 use turbopack_dev_server::source::{
-    ContentSource, ContentSourceContent, ContentSourceData, ContentSourceDataFilter,
-    ContentSourceDataVary, ContentSourceResultVc, ContentSourceVc, NeededData,
+    query::QueryValue, ContentSource, ContentSourceContent, ContentSourceData,
+    ContentSourceDataFilter, ContentSourceDataVary, ContentSourceResultVc, ContentSourceVc,
+    NeededData,
 };
 
 #[turbo_tasks::value(serialization = "none", eq = "manual", cell = "new", into = "new")]
@@ -27,6 +28,7 @@ impl TurboTasksSourceVc {
 }
 
 const INVALIDATION_INTERVAL: Duration = Duration::from_secs(3);
+const MAX_LISTED_INVALIDATIONS: usize = 200;
 
 #[turbo_tasks::value_impl]
 impl ContentSource for TurboTasksSource {
@@ -71,6 +73,27 @@ impl ContentSource for TurboTasksSource {
                     viz::graph::visualize_stats_tree(tree, ReferenceType::Child, tt.stats_type());
                 viz::graph::wrap_html(&graph)
             }
+            "stats.json" => {
+                let mut stats = Stats::with_cell_sizes();
+                let b = tt.backend();
+                b.with_all_cached_tasks(|task| {
+                    stats.add_id(b, task);
+                });
+                let tree = stats.treeify(ReferenceType::Child);
+                let json = viz::json::create_json(&stats, &tree, tt.stats_type());
+                return Ok(ContentSourceResultVc::exact(
+                    ContentSourceContent::Static(
+                        AssetContentVc::from(
+                            File::from(json.to_string()).with_content_type(APPLICATION_JSON),
+                        )
+                        .into(),
+                    )
+                    .cell(),
+                ));
+            }
+            "flamegraph" => {
+                viz::flamegraph::wrap_html("stats.json", INVALIDATION_INTERVAL.as_millis() as u64)
+            }
             "table" => {
                 if let Some(query) = &data.query {
                     let mut stats = Stats::new();
@@ -99,6 +122,40 @@ impl ContentSource for TurboTasksSource {
                     ));
                 }
             }
+            "invalidations" => {
+                if let Some(query) = &data.query {
+                    let b = tt.backend();
+                    let task = match query.get("task") {
+                        Some(QueryValue::String(task)) => task.parse::<usize>().ok(),
+                        _ => None,
+                    };
+                    if matches!(task, Some(task) if !b.has_task(task.into())) {
+                        return Ok(ContentSourceResultVc::not_found());
+                    }
+                    let html = match task {
+                        Some(task) => viz::invalidations::create_invalidation_chain(b, task.into()),
+                        None => viz::invalidations::create_invalidations_list(
+                            b,
+                            MAX_LISTED_INVALIDATIONS,
+                        ),
+                    };
+                    viz::invalidations::wrap_html(&html)
+                } else {
+                    return Ok(ContentSourceResultVc::exact(
+                        ContentSourceContent::NeedData(NeededData {
+                            source: self_vc.into(),
+                            path: path.to_string(),
+                            vary: ContentSourceDataVary {
+                                query: Some(ContentSourceDataFilter::Subset(
+                                    ["task".to_string()].into(),
+                                )),
+                                ..Default::default()
+                            },
+                        })
+                        .cell(),
+                    ));
+                }
+            }
             "reset" => {
                 let b = tt.backend();
                 b.with_all_cached_tasks(|task| {
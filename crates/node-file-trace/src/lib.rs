@@ -29,12 +29,12 @@ use turbo_tasks_fs::{
 };
 use turbo_tasks_memory::{
     stats::{ReferenceType, Stats},
-    viz, MemoryBackend,
+    viz, CellSpillConfig, MemoryBackend,
 };
 use turbopack::{
-    emit_asset, emit_with_completion, module_options::ModuleOptionsContext, rebase::RebasedAssetVc,
-    resolve_options_context::ResolveOptionsContext, transition::TransitionsByNameVc,
-    ModuleAssetContextVc,
+    emit_asset, emit_atomically, emit_with_completion, module_options::ModuleOptionsContext,
+    rebase::RebasedAssetVc, resolve_options_context::ResolveOptionsContext,
+    transition::TransitionsByNameVc, ModuleAssetContextVc,
 };
 use turbopack_cli_utils::issue::{ConsoleUi, IssueSeverityCliOption, LogOptions};
 use turbopack_core::{
@@ -130,6 +130,12 @@ pub struct CommonArgs {
     #[cfg_attr(feature = "cli", clap(short, long))]
     #[cfg_attr(feature = "node-api", serde(default))]
     enable_mdx: bool,
+
+    /// Move large cached values that haven't been read for a while to this
+    /// directory to reduce memory usage
+    #[cfg_attr(feature = "cli", clap(long))]
+    #[cfg_attr(feature = "node-api", serde(default))]
+    spill_cells: Option<String>,
 }
 
 #[cfg_attr(feature = "cli", derive(Parser))]
@@ -164,6 +170,13 @@ pub enum Args {
         #[cfg_attr(feature = "cli", clap(short, long, default_value_t = String::from("dist")))]
         #[cfg_attr(feature = "node-api", serde(default = "default_output_directory"))]
         output_directory: String,
+
+        /// Write the output directory at once and remove the files in it that
+        /// are no longer part of the output. The output directory must not
+        /// contain anything else.
+        #[cfg_attr(feature = "cli", clap(long))]
+        #[cfg_attr(feature = "node-api", serde(default))]
+        clean: bool,
     },
 
     // Print total size of input and referenced files
@@ -341,6 +354,7 @@ pub async fn start(args: Arc<Args>) -> Result<Vec<String>> {
     register();
     let &CommonArgs {
         visualize_graph,
+        ref spill_cells,
         #[cfg(feature = "persistent_cache")]
             cache: CacheArgs {
             ref cache,
@@ -358,13 +372,12 @@ pub async fn start(args: Arc<Args>) -> Result<Vec<String>> {
             &args,
             || {
                 let start = Instant::now();
-                let backend = MemoryBackendWithPersistedGraph::new(
-                    RocksDbPersistedGraph::new(cache).unwrap(),
-                );
+                let backend =
+                    MemoryBackendWithPersistedGraph::new(RocksDbPersistedGraph::new(cache)?);
                 let tt = TurboTasks::new(backend);
                 let elapsed = start.elapsed();
                 println!("restored cache {}", FormatDuration(elapsed));
-                tt
+                Ok(tt)
             },
             |tt, _, duration| async move {
                 let mut start = Instant::now();
@@ -399,7 +412,21 @@ pub async fn start(args: Arc<Args>) -> Result<Vec<String>> {
 
     run(
         args.clone(),
-        || TurboTasks::new(MemoryBackend::new()),
+        || {
+            let backend = MemoryBackend::new();
+            let backend = match spill_cells {
+                Some(directory) => backend
+                    .with_cell_spilling(CellSpillConfig::new(directory))
+                    .with_context(|| {
+                        format!(
+                            "failed to create the directory for spilled cells in {}",
+                            directory
+                        )
+                    })?,
+                None => backend,
+            };
+            Ok(TurboTasks::new(backend))
+        },
         |tt, root_task, _| async move {
             if visualize_graph {
                 let mut stats = Stats::new();
@@ -422,7 +449,7 @@ pub async fn start(args: Arc<Args>) -> Result<Vec<String>> {
 
 async fn run<B: Backend + 'static, F: Future<Output = ()>>(
     args: Arc<Args>,
-    create_tt: impl Fn() -> Arc<TurboTasks<B>>,
+    create_tt: impl FnOnce() -> Result<Arc<TurboTasks<B>>>,
     final_finish: impl FnOnce(Arc<TurboTasks<B>>, TaskId, Duration) -> F,
 ) -> Result<Vec<String>> {
     let &CommonArgs {
@@ -471,7 +498,7 @@ async fn run<B: Backend + 'static, F: Future<Output = ()>>(
         matches!(&*args, Args::Annotate { .. }) || matches!(&*args, Args::Print { .. });
     let (sender, mut receiver) = channel(1);
     let dir = current_dir().unwrap();
-    let tt = create_tt();
+    let tt = create_tt()?;
     let console_ui = Arc::new(ConsoleUi::new(LogOptions {
         current_dir: dir.clone(),
         show_all,
@@ -569,6 +596,7 @@ async fn main_operation(
         }
         Args::Build {
             ref output_directory,
+            clean,
             common: _,
         } => {
             let output = process_context(&dir, Some(output_directory)).unwrap();
@@ -577,17 +605,22 @@ async fn main_operation(
             let out_fs = create_fs("output directory", &output, watch).await?;
             let input_dir = fs.root();
             let output_dir = out_fs.root();
-            let mut emits = Vec::new();
-            for module in input_to_modules(fs, input, process_cwd, exact, enable_mdx)
+            let rebased = input_to_modules(fs, input, process_cwd, exact, enable_mdx)
                 .await?
                 .iter()
-            {
-                let rebased = RebasedAssetVc::new(*module, input_dir, output_dir).into();
-                emits.push(emit_with_completion(rebased, output_dir));
-            }
-            // Wait for all files to be emitted
-            for emit in emits {
-                emit.await?;
+                .map(|module| RebasedAssetVc::new(*module, input_dir, output_dir).into())
+                .collect::<Vec<AssetVc>>();
+            if clean {
+                emit_atomically(AssetsVc::cell(rebased), output_dir).await?;
+            } else {
+                let emits = rebased
+                    .into_iter()
+                    .map(|asset| emit_with_completion(asset, output_dir))
+                    .collect::<Vec<_>>();
+                // Wait for all files to be emitted
+                for emit in emits {
+                    emit.await?;
+                }
             }
         }
         Args::Size { common: _ } => todo!(),
@@ -0,0 +1,170 @@
+use anyhow::Result;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use turbo_tasks::trace::TraceRawVcs;
+use turbo_tasks_fs::{FileContent, FileSystemPathVc};
+
+use crate::{
+    parse::{parse_dotenv, Expander},
+    EnvMapVc, ProcessEnv, ProcessEnvVc,
+};
+
+/// The dotenv files loaded for `mode`, from the highest to the lowest
+/// precedence, like Next.js and Vite load them. `.env.local` is skipped in the
+/// `test` mode, so tests have the same results everywhere.
+pub fn dotenv_files(mode: &str) -> Vec<String> {
+    let mut files = vec![format!(".env.{mode}.local")];
+    if mode != "test" {
+        files.push(".env.local".to_string());
+    }
+    files.push(format!(".env.{mode}"));
+    files.push(".env".to_string());
+    files
+}
+
+/// An env variable together with where it was defined.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TraceRawVcs)]
+pub struct EnvVar {
+    pub value: String,
+    /// The dotenv file which defined the variable, or `None` when it comes
+    /// from the prior env.
+    pub source: Option<FileSystemPathVc>,
+}
+
+#[turbo_tasks::value(transparent)]
+pub struct EnvVarMap(IndexMap<String, EnvVar>);
+
+/// A dotenv file which couldn't be parsed.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TraceRawVcs)]
+pub struct DotenvError {
+    pub path: FileSystemPathVc,
+    pub message: String,
+}
+
+#[turbo_tasks::value(transparent)]
+pub struct DotenvErrors(Vec<DotenvError>);
+
+#[turbo_tasks::value]
+struct LoadedDotenv {
+    vars: IndexMap<String, EnvVar>,
+    errors: Vec<DotenvError>,
+}
+
+/// Loads the layered dotenv files of a directory for a mode, see
+/// [dotenv_files]. Variables of the prior env and of files with higher
+/// precedence can't be overridden.
+///
+/// Values can reference other variables with `$NAME` or `${NAME}`, and
+/// provide a default with `${NAME:-default}` (when unset or empty) or
+/// `${NAME-default}` (when unset). References are expanded after all files
+/// have been loaded, so they can refer to variables of any file.
+///
+/// A file which can't be parsed is skipped, so the other files still apply.
+/// These files are reported by [LayeredDotenvProcessEnvVc::errors].
+#[turbo_tasks::value]
+pub struct LayeredDotenvProcessEnv {
+    prior: ProcessEnvVc,
+    directory: FileSystemPathVc,
+    mode: String,
+}
+
+#[turbo_tasks::value_impl]
+impl LayeredDotenvProcessEnvVc {
+    #[turbo_tasks::function]
+    pub fn new(prior: ProcessEnvVc, directory: FileSystemPathVc, mode: String) -> Self {
+        LayeredDotenvProcessEnv {
+            prior,
+            directory,
+            mode,
+        }
+        .cell()
+    }
+
+    /// Reads all env variables with the dotenv file which defined them.
+    #[turbo_tasks::function]
+    pub async fn read_all_with_sources(self) -> Result<EnvVarMapVc> {
+        Ok(EnvVarMapVc::cell(self.load().await?.vars.clone()))
+    }
+
+    /// The dotenv files which have been skipped, as they couldn't be parsed.
+    #[turbo_tasks::function]
+    pub async fn errors(self) -> Result<DotenvErrorsVc> {
+        Ok(DotenvErrorsVc::cell(self.load().await?.errors.clone()))
+    }
+
+    #[turbo_tasks::function]
+    async fn load(self) -> Result<LoadedDotenvVc> {
+        let this = self.await?;
+        let prior = this.prior.read_all().await?;
+
+        let mut definitions = IndexMap::new();
+        let mut errors = Vec::new();
+        for name in dotenv_files(&this.mode) {
+            let path = this.directory.join(&name);
+            let FileContent::Content(file) = &*path.read().await? else {
+                continue;
+            };
+            let entries = match file
+                .content()
+                .to_str()
+                .and_then(|content| parse_dotenv(&content))
+            {
+                Ok(entries) => entries,
+                Err(err) => {
+                    errors.push(DotenvError {
+                        path,
+                        message: err.to_string(),
+                    });
+                    continue;
+                }
+            };
+            for (key, value) in entries {
+                definitions.entry(key).or_insert((path, value));
+            }
+        }
+
+        let mut expander = Expander::new(
+            &prior,
+            definitions
+                .iter()
+                .map(|(key, (_, value))| (key.as_str(), value.as_slice()))
+                .collect(),
+        );
+        let mut vars = IndexMap::new();
+        for (key, value) in prior.iter() {
+            vars.insert(
+                key.clone(),
+                EnvVar {
+                    value: value.clone(),
+                    source: None,
+                },
+            );
+        }
+        for (key, (path, _)) in definitions.iter() {
+            if prior.contains_key(key) {
+                continue;
+            }
+            vars.insert(
+                key.clone(),
+                EnvVar {
+                    value: expander.resolve(key).unwrap_or_default(),
+                    source: Some(*path),
+                },
+            );
+        }
+        Ok(LoadedDotenv { vars, errors }.cell())
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl ProcessEnv for LayeredDotenvProcessEnv {
+    #[turbo_tasks::function]
+    async fn read_all(self_vc: LayeredDotenvProcessEnvVc) -> Result<EnvMapVc> {
+        let vars = self_vc.read_all_with_sources().await?;
+        Ok(EnvMapVc::cell(
+            vars.iter()
+                .map(|(key, var)| (key.clone(), var.value.clone()))
+                .collect(),
+        ))
+    }
+}
@@ -4,6 +4,8 @@ mod command_line;
 mod custom;
 mod dotenv;
 mod filter;
+mod layered;
+mod parse;
 
 use std::{env, sync::Mutex};
 
@@ -12,8 +14,14 @@ use indexmap::IndexMap;
 use turbo_tasks::primitives::OptionStringVc;
 
 pub use self::{
-    command_line::CommandLineProcessEnvVc, custom::CustomProcessEnvVc, dotenv::DotenvProcessEnvVc,
+    command_line::CommandLineProcessEnvVc,
+    custom::CustomProcessEnvVc,
+    dotenv::DotenvProcessEnvVc,
     filter::FilterProcessEnvVc,
+    layered::{
+        dotenv_files, DotenvError, DotenvErrors, DotenvErrorsVc, EnvVar, EnvVarMap, EnvVarMapVc,
+        LayeredDotenvProcessEnvVc,
+    },
 };
 
 #[turbo_tasks::value(transparent)]
@@ -0,0 +1,416 @@
+use std::{
+    collections::{HashMap, HashSet},
+    iter::Peekable,
+    str::Chars,
+};
+
+use anyhow::{bail, Result};
+use indexmap::IndexMap;
+
+/// A part of a value in a dotenv file.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum ValuePart {
+    Literal(String),
+    /// `$NAME`, `${NAME}`, `${NAME:-default}` or `${NAME-default}`.
+    Variable {
+        name: String,
+        default: Option<VariableDefault>,
+    },
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct VariableDefault {
+    /// With `:-` the default is also used when the variable is empty.
+    when_empty: bool,
+    value: Vec<ValuePart>,
+}
+
+/// Parses the variable definitions of a dotenv file. Later definitions of the
+/// same variable replace earlier ones.
+///
+/// Values can be quoted. Single quoted values are taken literally, double
+/// quoted values can contain escapes like `\n` and span multiple lines.
+/// Variable references are kept as [ValuePart::Variable] to be expanded later.
+///
+/// This doesn't use dotenvy, which expands references while parsing and only
+/// looks them up in the global env of the process, which needs to be mutated
+/// under a lock for that. It also has no defaults and can't refer to variables
+/// of files with a lower precedence, which are loaded afterwards.
+pub(crate) fn parse_dotenv(content: &str) -> Result<IndexMap<String, Vec<ValuePart>>> {
+    let mut parser = Parser {
+        chars: content.chars().peekable(),
+        line: 1,
+    };
+    let mut entries = IndexMap::new();
+    while let Some((key, value)) = parser.parse_entry()? {
+        entries.insert(key, value);
+    }
+    Ok(entries)
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        c
+    }
+
+    fn skip_spaces(&mut self) {
+        while matches!(self.chars.peek(), Some(' ' | '\t')) {
+            self.next();
+        }
+    }
+
+    fn skip_line(&mut self) {
+        while !matches!(self.next(), Some('\n') | None) {}
+    }
+
+    fn parse_key(&mut self) -> String {
+        let mut key = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if !(c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-')) {
+                break;
+            }
+            key.push(c);
+            self.next();
+        }
+        key
+    }
+
+    fn parse_entry(&mut self) -> Result<Option<(String, Vec<ValuePart>)>> {
+        loop {
+            match self.chars.peek() {
+                None => return Ok(None),
+                Some(' ' | '\t' | '\r' | '\n') => {
+                    self.next();
+                }
+                Some('#') => self.skip_line(),
+                Some(_) => break,
+            }
+        }
+
+        let line = self.line;
+        let mut key = self.parse_key();
+        if key == "export" && matches!(self.chars.peek(), Some(' ' | '\t')) {
+            self.skip_spaces();
+            key = self.parse_key();
+        }
+        if key.is_empty() {
+            bail!("line {line}: expected a variable name");
+        }
+        self.skip_spaces();
+        if self.next() != Some('=') {
+            bail!("line {line}: expected `=` after {key}");
+        }
+        self.skip_spaces();
+
+        let value = match self.chars.peek() {
+            Some('\'') => {
+                self.next();
+                let value = self.parse_quoted('\'', line)?;
+                self.skip_after_quoted(line)?;
+                vec![ValuePart::Literal(value)]
+            }
+            Some('"') => {
+                self.next();
+                let value = self.parse_quoted('"', line)?;
+                self.skip_after_quoted(line)?;
+                parse_value(&value, true, line)?
+            }
+            _ => {
+                let mut value = String::new();
+                while let Some(&c) = self.chars.peek() {
+                    if c == '\n' || (c == '#' && value.ends_with([' ', '\t'])) {
+                        break;
+                    }
+                    value.push(c);
+                    self.next();
+                }
+                parse_value(value.trim_end(), false, line)?
+            }
+        };
+        Ok(Some((key, value)))
+    }
+
+    /// Reads a quoted value up to the closing `quote`. Escapes are kept.
+    fn parse_quoted(&mut self, quote: char, line: usize) -> Result<String> {
+        let mut value = String::new();
+        loop {
+            match self.next() {
+                None => bail!("line {line}: unterminated quoted value"),
+                Some(c) if c == quote => return Ok(value),
+                Some('\\') if quote == '"' => {
+                    value.push('\\');
+                    if let Some(c) = self.next() {
+                        value.push(c);
+                    }
+                }
+                Some(c) => value.push(c),
+            }
+        }
+    }
+
+    fn skip_after_quoted(&mut self, line: usize) -> Result<()> {
+        self.skip_spaces();
+        match self.chars.peek() {
+            None | Some('\r' | '\n') => Ok(()),
+            Some('#') => {
+                self.skip_line();
+                Ok(())
+            }
+            Some(c) => bail!("line {line}: unexpected `{c}` after quoted value"),
+        }
+    }
+}
+
+/// Parses the variable references of a value. Double quoted values also
+/// support escapes, while unquoted values only support escaping `$`.
+fn parse_value(value: &str, escapes: bool, line: usize) -> Result<Vec<ValuePart>> {
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if escapes => match chars.next() {
+                Some('n') => literal.push('\n'),
+                Some('r') => literal.push('\r'),
+                Some('t') => literal.push('\t'),
+                Some(c) => literal.push(c),
+                None => literal.push('\\'),
+            },
+            '\\' if chars.peek() == Some(&'$') => {
+                literal.push('$');
+                chars.next();
+            }
+            '$' if chars.peek() == Some(&'{') => {
+                chars.next();
+                let mut reference = String::new();
+                let mut depth = 0;
+                loop {
+                    match chars.next() {
+                        None => bail!("line {line}: unterminated `${{`"),
+                        Some('}') if depth == 0 => break,
+                        Some(c) => {
+                            match c {
+                                '{' => depth += 1,
+                                '}' => depth -= 1,
+                                _ => {}
+                            }
+                            reference.push(c);
+                        }
+                    }
+                }
+                let name_end = reference
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(reference.len());
+                let (name, rest) = reference.split_at(name_end);
+                let default = if rest.is_empty() {
+                    None
+                } else if let Some(default) = rest.strip_prefix(":-") {
+                    Some(VariableDefault {
+                        when_empty: true,
+                        value: parse_value(default, escapes, line)?,
+                    })
+                } else if let Some(default) = rest.strip_prefix('-') {
+                    Some(VariableDefault {
+                        when_empty: false,
+                        value: parse_value(default, escapes, line)?,
+                    })
+                } else {
+                    bail!("line {line}: invalid variable reference `${{{reference}}}`");
+                };
+                if name.is_empty() {
+                    bail!("line {line}: invalid variable reference `${{{reference}}}`");
+                }
+                parts.push(ValuePart::Literal(std::mem::take(&mut literal)));
+                parts.push(ValuePart::Variable {
+                    name: name.to_string(),
+                    default,
+                });
+            }
+            '$' if matches!(chars.peek(), Some(c) if c.is_ascii_alphabetic() || *c == '_') => {
+                let mut name = String::new();
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || c == '_') {
+                        break;
+                    }
+                    name.push(c);
+                    chars.next();
+                }
+                parts.push(ValuePart::Literal(std::mem::take(&mut literal)));
+                parts.push(ValuePart::Variable {
+                    name,
+                    default: None,
+                });
+            }
+            c => literal.push(c),
+        }
+    }
+    parts.push(ValuePart::Literal(literal));
+    parts.retain(|part| !matches!(part, ValuePart::Literal(literal) if literal.is_empty()));
+    Ok(parts)
+}
+
+/// Expands the variable references of dotenv values. Variables of the `prior`
+/// env take precedence over the `defined` ones. Undefined variables and
+/// cyclic references expand to an empty string.
+pub(crate) struct Expander<'a> {
+    prior: &'a IndexMap<String, String>,
+    defined: IndexMap<&'a str, &'a [ValuePart]>,
+    expanded: HashMap<&'a str, String>,
+    expanding: HashSet<&'a str>,
+}
+
+impl<'a> Expander<'a> {
+    pub fn new(
+        prior: &'a IndexMap<String, String>,
+        defined: IndexMap<&'a str, &'a [ValuePart]>,
+    ) -> Self {
+        Expander {
+            prior,
+            defined,
+            expanded: HashMap::new(),
+            expanding: HashSet::new(),
+        }
+    }
+
+    /// The expanded value of a variable.
+    pub fn resolve(&mut self, name: &str) -> Option<String> {
+        if let Some(value) = self.prior.get(name) {
+            return Some(value.clone());
+        }
+        if let Some(value) = self.expanded.get(name) {
+            return Some(value.clone());
+        }
+        let (&name, &value) = self.defined.get_key_value(name)?;
+        if !self.expanding.insert(name) {
+            return None;
+        }
+        let expanded = self.expand(value);
+        self.expanding.remove(name);
+        self.expanded.insert(name, expanded.clone());
+        Some(expanded)
+    }
+
+    fn expand(&mut self, value: &'a [ValuePart]) -> String {
+        let mut expanded = String::new();
+        for part in value {
+            match part {
+                ValuePart::Literal(literal) => expanded.push_str(literal),
+                ValuePart::Variable { name, default } => {
+                    let value = self.resolve(name);
+                    match (value, default) {
+                        (Some(value), Some(default)) if default.when_empty && value.is_empty() => {
+                            expanded.push_str(&self.expand(&default.value))
+                        }
+                        (Some(value), _) => expanded.push_str(&value),
+                        (None, Some(default)) => expanded.push_str(&self.expand(&default.value)),
+                        (None, None) => {}
+                    }
+                }
+            }
+        }
+        expanded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+
+    use super::{parse_dotenv, Expander};
+
+    fn expand_all(content: &str, prior: &[(&str, &str)]) -> IndexMap<String, String> {
+        let prior = prior
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        let entries = parse_dotenv(content).unwrap();
+        let mut expander = Expander::new(
+            &prior,
+            entries
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.as_slice()))
+                .collect(),
+        );
+        entries
+            .keys()
+            .map(|key| (key.clone(), expander.resolve(key).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn parses_values() {
+        let vars = expand_all(
+            r#"
+# comment
+A=1
+export B = two words # comment
+C='single $A'
+D="double\n$A"
+E="multi
+line"
+F=
+A=3
+"#,
+            &[],
+        );
+        assert_eq!(vars["A"], "3");
+        assert_eq!(vars["B"], "two words");
+        assert_eq!(vars["C"], "single $A");
+        assert_eq!(vars["D"], "double\n3");
+        assert_eq!(vars["E"], "multi\nline");
+        assert_eq!(vars["F"], "");
+    }
+
+    #[test]
+    fn expands_variables() {
+        let vars = expand_all(
+            r#"
+URL=http://${HOST}:$PORT/
+HOST=localhost
+PORT=${CUSTOM_PORT:-3000}
+EMPTY=
+A=${EMPTY:-default}
+B=${EMPTY-default}
+C=${MISSING}
+D=\$HOST
+E=$FROM_PRIOR
+"#,
+            &[("FROM_PRIOR", "prior")],
+        );
+        assert_eq!(vars["URL"], "http://localhost:3000/");
+        assert_eq!(vars["A"], "default");
+        assert_eq!(vars["B"], "");
+        assert_eq!(vars["C"], "");
+        assert_eq!(vars["D"], "$HOST");
+        assert_eq!(vars["E"], "prior");
+    }
+
+    #[test]
+    fn prior_takes_precedence() {
+        let vars = expand_all("A=file\nB=$A", &[("A", "prior")]);
+        assert_eq!(vars["B"], "prior");
+    }
+
+    #[test]
+    fn cyclic_references_are_empty() {
+        let vars = expand_all("A=a$B\nB=b$A", &[]);
+        assert_eq!(vars["A"], "ab");
+    }
+
+    #[test]
+    fn reports_errors() {
+        assert!(parse_dotenv("A=\"unterminated").is_err());
+        assert!(parse_dotenv("A").is_err());
+        assert!(parse_dotenv("A=${B").is_err());
+        assert!(parse_dotenv("A='x' y").is_err());
+    }
+}
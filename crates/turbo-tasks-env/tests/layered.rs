@@ -0,0 +1,152 @@
+use std::fs;
+
+use anyhow::Result;
+use indexmap::IndexMap;
+use tempfile::TempDir;
+use turbo_tasks::TurboTasks;
+use turbo_tasks_env::{
+    CommandLineProcessEnvVc, CustomProcessEnvVc, EnvMapVc, LayeredDotenvProcessEnvVc,
+};
+use turbo_tasks_fs::{DiskFileSystemVc, FileSystem};
+use turbo_tasks_memory::MemoryBackend;
+
+/// The result of loading dotenv files.
+#[derive(Debug)]
+struct Loaded {
+    /// The value and the name of the defining file of the `LAYERED_*`
+    /// variables.
+    vars: IndexMap<String, (String, Option<String>)>,
+    /// The name of the skipped file and the error.
+    errors: Vec<(String, String)>,
+}
+
+/// Loads the dotenv `files` for `mode`, with a prior env extended by `prior`.
+async fn load(files: &[(&str, &str)], mode: &str, prior: &[(&str, &str)]) -> Result<Loaded> {
+    turbo_tasks_env::register();
+    turbo_tasks_fs::register();
+    let directory = TempDir::new()?;
+    for (name, content) in files {
+        fs::write(directory.path().join(name), content)?;
+    }
+    let root = directory.path().to_string_lossy().into_owned();
+    let mode = mode.to_string();
+    let prior = prior
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect::<IndexMap<_, _>>();
+
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let (vars, errors) = tt
+        .run_once(async move {
+            let directory = DiskFileSystemVc::new("root".to_string(), root).root();
+            let prior = CustomProcessEnvVc::new(
+                CommandLineProcessEnvVc::new().into(),
+                EnvMapVc::cell(prior),
+            );
+            let env = LayeredDotenvProcessEnvVc::new(prior.into(), directory, mode);
+            let mut vars = Vec::new();
+            for (key, var) in env.read_all_with_sources().await?.iter() {
+                if !key.starts_with("LAYERED_") {
+                    continue;
+                }
+                let source = match var.source {
+                    Some(source) => Some(source.await?.path.clone()),
+                    None => None,
+                };
+                vars.push((key.clone(), (var.value.clone(), source)));
+            }
+            let mut errors = Vec::new();
+            for error in env.errors().await?.iter() {
+                errors.push((error.path.await?.path.clone(), error.message.clone()));
+            }
+            Ok((vars, errors))
+        })
+        .await?;
+    Ok(Loaded {
+        vars: vars.into_iter().collect(),
+        errors,
+    })
+}
+
+fn var<'a>(loaded: &'a Loaded, key: &str) -> (&'a str, Option<&'a str>) {
+    let (value, source) = &loaded.vars[key];
+    (value, source.as_deref())
+}
+
+#[tokio::test]
+async fn files_with_higher_precedence_win() -> Result<()> {
+    let loaded = load(
+        &[
+            (".env.development.local", "LAYERED_A=mode-local"),
+            (".env.local", "LAYERED_A=local\nLAYERED_B=local"),
+            (".env.development", "LAYERED_B=mode\nLAYERED_C=mode"),
+            (".env.production", "LAYERED_C=other-mode"),
+            (
+                ".env",
+                "LAYERED_C=env\nLAYERED_D=env\nLAYERED_E=${LAYERED_A}-$LAYERED_D",
+            ),
+        ],
+        "development",
+        &[],
+    )
+    .await?;
+    assert_eq!(
+        var(&loaded, "LAYERED_A"),
+        ("mode-local", Some(".env.development.local"))
+    );
+    assert_eq!(var(&loaded, "LAYERED_B"), ("local", Some(".env.local")));
+    assert_eq!(
+        var(&loaded, "LAYERED_C"),
+        ("mode", Some(".env.development"))
+    );
+    assert_eq!(var(&loaded, "LAYERED_D"), ("env", Some(".env")));
+    // References are expanded with the values of all files
+    assert_eq!(var(&loaded, "LAYERED_E"), ("mode-local-env", Some(".env")));
+    assert!(loaded.errors.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_mode_skips_local_file() -> Result<()> {
+    let loaded = load(
+        &[(".env.local", "LAYERED_A=local"), (".env", "LAYERED_A=env")],
+        "test",
+        &[],
+    )
+    .await?;
+    assert_eq!(var(&loaded, "LAYERED_A"), ("env", Some(".env")));
+    Ok(())
+}
+
+#[tokio::test]
+async fn prior_env_is_not_overridden() -> Result<()> {
+    let loaded = load(
+        &[(".env", "LAYERED_PRIOR=file\nLAYERED_REF=$LAYERED_PRIOR")],
+        "development",
+        &[("LAYERED_PRIOR", "prior")],
+    )
+    .await?;
+    assert_eq!(var(&loaded, "LAYERED_PRIOR"), ("prior", None));
+    assert_eq!(var(&loaded, "LAYERED_REF"), ("prior", Some(".env")));
+    Ok(())
+}
+
+#[tokio::test]
+async fn broken_files_are_skipped() -> Result<()> {
+    let loaded = load(
+        &[
+            (".env.local", "LAYERED_A=local\nLAYERED_B"),
+            (".env", "LAYERED_A=env\nLAYERED_C=env"),
+        ],
+        "development",
+        &[],
+    )
+    .await?;
+    assert_eq!(var(&loaded, "LAYERED_A"), ("env", Some(".env")));
+    assert_eq!(var(&loaded, "LAYERED_C"), ("env", Some(".env")));
+    assert_eq!(loaded.errors.len(), 1);
+    let (path, message) = &loaded.errors[0];
+    assert_eq!(path, ".env.local");
+    assert!(message.contains("line 2"), "{message}");
+    Ok(())
+}
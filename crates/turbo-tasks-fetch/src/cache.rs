@@ -0,0 +1,188 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use reqwest::header::{HeaderMap, CACHE_CONTROL, ETAG, LAST_MODIFIED};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use turbo_tasks_hash::{encode_hex, Xxh3Hash64Hasher};
+
+use crate::request::response_headers;
+
+/// A cache of HTTP responses on disk, which is used by
+/// [fetch_cached](crate::fetch_cached).
+///
+/// Responses are reused while they are fresh according to their
+/// `Cache-Control: max-age` and revalidated with conditional requests
+/// afterwards. Responses with `Cache-Control: no-store` are never stored.
+#[turbo_tasks::value(shared)]
+#[derive(Debug)]
+pub struct HttpCache {
+    /// The directory the responses are stored in.
+    pub directory: String,
+    /// Serves all requests from the cache, even when the responses are stale,
+    /// and never sends requests. Requests that aren't cached fail.
+    pub offline: bool,
+}
+
+#[turbo_tasks::value_impl]
+impl HttpCacheVc {
+    #[turbo_tasks::function]
+    pub fn new(directory: String, offline: bool) -> Self {
+        HttpCache { directory, offline }.cell()
+    }
+}
+
+/// The metadata of a cached response, which is stored next to its body.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct CacheEntry {
+    pub status: u16,
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// When the response was received or last revalidated, in seconds since
+    /// the unix epoch.
+    pub stored_at: u64,
+    /// For how many seconds after `stored_at` the response is fresh.
+    pub max_age: u64,
+}
+
+impl CacheEntry {
+    pub fn is_fresh(&self) -> bool {
+        now() < self.stored_at.saturating_add(self.max_age)
+    }
+
+    /// Updates the freshness and validators of a cached response after it
+    /// has been revalidated with `headers`.
+    pub fn revalidated(&mut self, headers: &HeaderMap) {
+        let cache_control = CacheControl::from_headers(headers);
+        self.stored_at = now();
+        self.max_age = cache_control.max_age();
+        if let Some(etag) = header_value(headers, ETAG) {
+            self.etag = Some(etag);
+        }
+        if let Some(last_modified) = header_value(headers, LAST_MODIFIED) {
+            self.last_modified = Some(last_modified);
+        }
+    }
+}
+
+/// The `Cache-Control` directives relevant for a private cache.
+#[derive(Default)]
+pub(crate) struct CacheControl {
+    pub no_store: bool,
+    no_cache: bool,
+    max_age: Option<u64>,
+}
+
+impl CacheControl {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let mut cache_control = CacheControl::default();
+        for value in headers.get_all(CACHE_CONTROL) {
+            let Ok(value) = value.to_str() else {
+                continue;
+            };
+            for directive in value.split(',') {
+                let directive = directive.trim().to_ascii_lowercase();
+                match directive.split_once('=') {
+                    Some(("max-age", seconds)) => {
+                        cache_control.max_age = seconds.trim_matches('"').parse().ok();
+                    }
+                    None if directive == "no-store" => cache_control.no_store = true,
+                    None if directive == "no-cache" => cache_control.no_cache = true,
+                    _ => {}
+                }
+            }
+        }
+        cache_control
+    }
+
+    /// Without `max-age`, responses need to be revalidated on every use.
+    fn max_age(&self) -> u64 {
+        if self.no_cache {
+            0
+        } else {
+            self.max_age.unwrap_or(0)
+        }
+    }
+
+    pub fn to_entry(&self, status: u16, headers: &HeaderMap) -> CacheEntry {
+        CacheEntry {
+            status,
+            headers: response_headers(headers),
+            etag: header_value(headers, ETAG),
+            last_modified: header_value(headers, LAST_MODIFIED),
+            stored_at: now(),
+            max_age: self.max_age(),
+        }
+    }
+}
+
+fn header_value(headers: &HeaderMap, name: reqwest::header::HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string())
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+impl HttpCache {
+    /// The path of the cached response for a request, without extension.
+    fn entry_path(&self, url: &str, user_agent: Option<&String>) -> PathBuf {
+        let mut hasher = Xxh3Hash64Hasher::new();
+        hasher.write_ref(&url);
+        hasher.write_ref(&user_agent);
+        Path::new(&self.directory).join(encode_hex(hasher.finish()))
+    }
+
+    /// Reads the cached response for a request. A cache that can't be read is
+    /// treated like an empty cache.
+    pub(crate) async fn read(
+        &self,
+        url: &str,
+        user_agent: Option<&String>,
+    ) -> Option<(CacheEntry, Vec<u8>)> {
+        let path = self.entry_path(url, user_agent);
+        let meta = fs::read(path.with_extension("json")).await.ok()?;
+        let entry = serde_json::from_slice(&meta).ok()?;
+        let body = fs::read(path.with_extension("body")).await.ok()?;
+        Some((entry, body))
+    }
+
+    /// Stores a response. When `body` is `None`, only the metadata of a
+    /// revalidated response is updated.
+    pub(crate) async fn write(
+        &self,
+        url: &str,
+        user_agent: Option<&String>,
+        entry: &CacheEntry,
+        body: Option<&[u8]>,
+    ) -> Result<()> {
+        let path = self.entry_path(url, user_agent);
+        fs::create_dir_all(&self.directory).await?;
+        // The metadata is written last, so a cached response is only found
+        // when its body is complete
+        if let Some(body) = body {
+            write_atomically(&path.with_extension("body"), body).await?;
+        }
+        write_atomically(&path.with_extension("json"), &serde_json::to_vec(entry)?).await
+    }
+}
+
+/// Writes a file by renaming a temporary file, so concurrent readers never see
+/// partial contents.
+async fn write_atomically(path: &Path, content: &[u8]) -> Result<()> {
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(format!(".{}.tmp", std::process::id()));
+    fs::write(&temp_path, content).await?;
+    fs::rename(&temp_path, path).await?;
+    Ok(())
+}
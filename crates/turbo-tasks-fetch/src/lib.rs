@@ -1,10 +1,23 @@
 #![feature(min_specialization)]
 
+mod cache;
+mod request;
+
 use anyhow::Result;
+use reqwest::{
+    header::{IF_MODIFIED_SINCE, IF_NONE_MATCH},
+    StatusCode,
+};
 use turbo_tasks::primitives::{OptionStringVc, StringVc};
 use turbo_tasks_fs::FileSystemPathVc;
 use turbopack_core::issue::{Issue, IssueSeverityVc, IssueVc};
 
+use crate::{cache::CacheControl, request::response_headers};
+pub use crate::{
+    cache::{HttpCache, HttpCacheVc},
+    request::{FetchMethod, FetchRequest, FetchRequestVc},
+};
+
 pub fn register() {
     turbo_tasks::register();
     turbo_tasks_fs::register();
@@ -19,6 +32,9 @@ pub struct FetchResult(Result<HttpResponseVc, FetchErrorVc>);
 #[derive(Debug)]
 pub struct HttpResponse {
     pub status: u16,
+    /// The response headers with lowercase names, in the order they were
+    /// received.
+    pub headers: Vec<(String, String)>,
     pub body: HttpResponseBodyVc,
 }
 
@@ -35,30 +51,142 @@ impl HttpResponseBodyVc {
     }
 }
 
+impl HttpResponseVc {
+    fn new(status: u16, headers: Vec<(String, String)>, body: Vec<u8>) -> Self {
+        HttpResponse {
+            status,
+            headers,
+            body: HttpResponseBodyVc::cell(HttpResponseBody(body)),
+        }
+        .cell()
+    }
+}
+
+impl HttpResponse {
+    /// The value of the first header named `name`, ignoring case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+fn get_request(url: String, user_agent: Option<String>) -> FetchRequest {
+    let request = FetchRequest::get(url);
+    match user_agent {
+        Some(user_agent) => request.with_header("User-Agent", user_agent),
+        None => request,
+    }
+}
+
 #[turbo_tasks::function]
 pub async fn fetch(url: StringVc, user_agent: OptionStringVc) -> Result<FetchResultVc> {
-    let url = url.await?.clone();
-    let user_agent = &*user_agent.await?;
-    let client = reqwest::Client::new();
+    let request = get_request(url.await?.clone(), user_agent.await?.clone());
+    send(&request).await
+}
 
-    let mut builder = client.get(&url);
-    if let Some(user_agent) = user_agent {
-        builder = builder.header("User-Agent", user_agent);
-    }
+/// Sends a [FetchRequest], which allows any method, headers and body.
+#[turbo_tasks::function]
+pub async fn fetch_request(request: FetchRequestVc) -> Result<FetchResultVc> {
+    send(&*request.await?).await
+}
 
-    let response = builder.send().await.and_then(|r| r.error_for_status());
-    match response {
+async fn send(request: &FetchRequest) -> Result<FetchResultVc> {
+    match request.send().await {
         Ok(response) => {
             let status = response.status().as_u16();
+            let headers = response_headers(response.headers());
             let body = response.bytes().await?.to_vec();
 
-            Ok(FetchResultVc::cell(Ok(HttpResponse {
-                status,
-                body: HttpResponseBodyVc::cell(HttpResponseBody(body)),
-            }
-            .cell())))
+            Ok(FetchResultVc::cell(Ok(HttpResponseVc::new(
+                status, headers, body,
+            ))))
         }
         Err(err) => Ok(FetchResultVc::cell(Err(FetchError::from_reqwest_error(
+            &err,
+            &request.url,
+        )
+        .cell()))),
+    }
+}
+
+/// Like [fetch], but stores responses in `cache` and reuses them according to
+/// their `Cache-Control` and `ETag`/`Last-Modified` headers. When the request
+/// fails to connect, a stale cached response is used instead.
+#[turbo_tasks::function]
+pub async fn fetch_cached(
+    url: StringVc,
+    user_agent: OptionStringVc,
+    cache: HttpCacheVc,
+) -> Result<FetchResultVc> {
+    let url = url.await?.clone();
+    let user_agent = &*user_agent.await?;
+    let cache = cache.await?;
+
+    let cached = cache.read(&url, user_agent.as_ref()).await;
+    let cached = match cached {
+        Some((entry, body)) if cache.offline || entry.is_fresh() => {
+            return Ok(FetchResultVc::cell(Ok(HttpResponseVc::new(
+                entry.status,
+                entry.headers,
+                body,
+            ))));
+        }
+        None if cache.offline => {
+            return Ok(FetchResultVc::cell(Err(FetchError {
+                url: StringVc::cell(url),
+                kind: FetchErrorKind::Offline.into(),
+                detail: StringVc::cell("The response is not cached".to_string()),
+            }
+            .cell())));
+        }
+        cached => cached,
+    };
+
+    let mut request = get_request(url.clone(), user_agent.clone());
+    if let Some((entry, _)) = &cached {
+        // Revalidates the cached response
+        if let Some(etag) = &entry.etag {
+            request = request.with_header(IF_NONE_MATCH.as_str(), etag.clone());
+        }
+        if let Some(last_modified) = &entry.last_modified {
+            request = request.with_header(IF_MODIFIED_SINCE.as_str(), last_modified.clone());
+        }
+    }
+    let response = request.send().await;
+    match (response, cached) {
+        (Ok(response), Some((mut entry, body)))
+            if response.status() == StatusCode::NOT_MODIFIED =>
+        {
+            entry.revalidated(response.headers());
+            cache.write(&url, user_agent.as_ref(), &entry, None).await?;
+            Ok(FetchResultVc::cell(Ok(HttpResponseVc::new(
+                entry.status,
+                entry.headers,
+                body,
+            ))))
+        }
+        (Ok(response), _) => {
+            let status = response.status().as_u16();
+            let cache_control = CacheControl::from_headers(response.headers());
+            let entry = cache_control.to_entry(status, response.headers());
+            let headers = entry.headers.clone();
+            let body = response.bytes().await?.to_vec();
+            if !cache_control.no_store {
+                cache
+                    .write(&url, user_agent.as_ref(), &entry, Some(&body))
+                    .await?;
+            }
+
+            Ok(FetchResultVc::cell(Ok(HttpResponseVc::new(
+                status, headers, body,
+            ))))
+        }
+        (Err(err), Some((entry, body))) if err.is_connect() || err.is_timeout() => Ok(
+            FetchResultVc::cell(Ok(HttpResponseVc::new(entry.status, entry.headers, body))),
+        ),
+        (Err(err), _) => Ok(FetchResultVc::cell(Err(FetchError::from_reqwest_error(
             &err, &url,
         )
         .cell()))),
@@ -71,6 +199,8 @@ pub enum FetchErrorKind {
     Connect,
     Timeout,
     Status(u16),
+    /// The request isn't cached and requests can't be sent in offline mode.
+    Offline,
     Other,
 }
 
@@ -169,6 +299,10 @@ impl Issue for FetchIssue {
                 )
             }
             FetchErrorKind::Timeout => format!("Connection timed out when requesting {}", url),
+            FetchErrorKind::Offline => format!(
+                "{} is not available in offline mode because it has not been requested before.",
+                url
+            ),
             FetchErrorKind::Other => format!("There was an issue requesting {}", url),
         }))
     }
@@ -0,0 +1,99 @@
+use std::time::Duration;
+
+use reqwest::{header::HeaderMap, redirect::Policy, Method};
+use serde::{Deserialize, Serialize};
+use turbo_tasks::trace::TraceRawVcs;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, TraceRawVcs)]
+pub enum FetchMethod {
+    #[default]
+    Get,
+    Head,
+    Post,
+    Put,
+    Patch,
+    Delete,
+    Options,
+}
+
+impl FetchMethod {
+    fn to_reqwest(self) -> Method {
+        match self {
+            FetchMethod::Get => Method::GET,
+            FetchMethod::Head => Method::HEAD,
+            FetchMethod::Post => Method::POST,
+            FetchMethod::Put => Method::PUT,
+            FetchMethod::Patch => Method::PATCH,
+            FetchMethod::Delete => Method::DELETE,
+            FetchMethod::Options => Method::OPTIONS,
+        }
+    }
+}
+
+/// An HTTP request sent by [fetch_request](crate::fetch_request).
+#[turbo_tasks::value(shared)]
+#[derive(Debug, Clone)]
+pub struct FetchRequest {
+    pub url: String,
+    pub method: FetchMethod,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<Vec<u8>>,
+    /// How many redirects are followed. With `0`, redirect responses are
+    /// returned as they are.
+    pub max_redirects: usize,
+    /// Requests which take longer than this many milliseconds fail with
+    /// [FetchErrorKind::Timeout](crate::FetchErrorKind::Timeout).
+    pub timeout_ms: Option<u64>,
+}
+
+impl FetchRequest {
+    /// A GET request which follows up to 10 redirects, like browsers do.
+    pub fn get(url: String) -> Self {
+        FetchRequest {
+            url,
+            method: FetchMethod::Get,
+            headers: Vec::new(),
+            body: None,
+            max_redirects: 10,
+            timeout_ms: None,
+        }
+    }
+
+    pub fn with_header(mut self, name: &str, value: String) -> Self {
+        self.headers.push((name.to_string(), value));
+        self
+    }
+
+    /// Sends the request. Responses with an error status are returned as an
+    /// error.
+    pub(crate) async fn send(&self) -> reqwest::Result<reqwest::Response> {
+        let mut client = reqwest::Client::builder().redirect(if self.max_redirects == 0 {
+            Policy::none()
+        } else {
+            Policy::limited(self.max_redirects)
+        });
+        if let Some(timeout_ms) = self.timeout_ms {
+            client = client.timeout(Duration::from_millis(timeout_ms));
+        }
+        let client = client.build()?;
+
+        let mut builder = client.request(self.method.to_reqwest(), &self.url);
+        for (name, value) in self.headers.iter() {
+            builder = builder.header(name, value);
+        }
+        if let Some(body) = &self.body {
+            builder = builder.body(body.clone());
+        }
+
+        builder.send().await.and_then(|r| r.error_for_status())
+    }
+}
+
+/// The headers of a response, with lowercase names. Values which aren't valid
+/// UTF-8 are left out.
+pub(crate) fn response_headers(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect()
+}
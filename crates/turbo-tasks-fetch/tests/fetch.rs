@@ -1,7 +1,10 @@
 #![cfg(test)]
 
 use turbo_tasks::primitives::{OptionStringVc, StringVc};
-use turbo_tasks_fetch::{fetch, register, FetchErrorKind};
+use turbo_tasks_fetch::{
+    fetch, fetch_cached, fetch_request, register, FetchErrorKind, FetchMethod, FetchRequest,
+    HttpCache, HttpCacheVc,
+};
 use turbo_tasks_fs::{DiskFileSystemVc, FileSystemPathVc, FileSystemVc};
 use turbo_tasks_testing::{register, run};
 use turbopack_core::issue::{Issue, IssueSeverity};
@@ -141,6 +144,218 @@ async fn errors_on_404() {
     }
 }
 
+#[tokio::test]
+async fn sends_custom_request() {
+    run! {
+        register();
+
+        let server = httpmock::MockServer::start();
+        let resource_mock = server.mock(|when, then| {
+            when.method(httpmock::Method::POST)
+                .path("/api")
+                .header("Authorization", "Bearer token")
+                .body("requestbody");
+            then.status(201)
+                .header("Content-Type", "application/json")
+                .body("{}");
+        });
+
+        let request = FetchRequest {
+            method: FetchMethod::Post,
+            body: Some(b"requestbody".to_vec()),
+            ..FetchRequest::get(server.url("/api"))
+        }
+        .with_header("Authorization", "Bearer token".to_owned());
+        let result = &*fetch_request(request.cell()).await?;
+        resource_mock.assert();
+
+        let Ok(response) = result else {
+            panic!()
+        };
+        let response = response.await?;
+        assert_eq!(response.status, 201);
+        assert_eq!(response.header("content-type"), Some("application/json"));
+        assert_eq!(*response.body.to_string().await?, "{}");
+    }
+}
+
+#[tokio::test]
+async fn returns_redirects_when_not_followed() {
+    run! {
+        register();
+
+        let server = httpmock::MockServer::start();
+        server.mock(|when, then| {
+            when.path("/old");
+            then.status(301).header("Location", "/new");
+        });
+        let new_mock = server.mock(|when, then| {
+            when.path("/new");
+            then.status(200);
+        });
+
+        let request = FetchRequest {
+            max_redirects: 0,
+            ..FetchRequest::get(server.url("/old"))
+        };
+        let result = &*fetch_request(request.cell()).await?;
+        let Ok(response) = result else {
+            panic!()
+        };
+        let response = response.await?;
+        assert_eq!(response.status, 301);
+        assert_eq!(response.header("Location"), Some("/new"));
+        new_mock.assert_hits(0);
+    }
+}
+
+#[tokio::test]
+async fn errors_on_timeout() {
+    run! {
+        register();
+
+        let server = httpmock::MockServer::start();
+        server.mock(|when, then| {
+            when.path("/slow");
+            then.status(200).delay(std::time::Duration::from_secs(2));
+        });
+
+        let request = FetchRequest {
+            timeout_ms: Some(100),
+            ..FetchRequest::get(server.url("/slow"))
+        };
+        let result = &*fetch_request(request.cell()).await?;
+        let Err(err_vc) = result else {
+            panic!()
+        };
+        assert_eq!(*err_vc.await?.kind.await?, FetchErrorKind::Timeout);
+    }
+}
+
+#[tokio::test]
+async fn reuses_fresh_cached_response() {
+    run! {
+        register();
+
+        let server = httpmock::MockServer::start();
+        let resource_mock = server.mock(|when, then| {
+            when.path("/foo.woff");
+            then.status(200)
+                .header("Cache-Control", "public, max-age=3600")
+                .body("responsebody");
+        });
+
+        let cache_dir = tempfile::tempdir()?;
+        let url = StringVc::cell(server.url("/foo.woff"));
+        for _ in 0..2 {
+            // A new cache cell for every request, so turbo tasks doesn't reuse the result
+            let cache = http_cache(cache_dir.path(), false);
+            let result = &*fetch_cached(url, OptionStringVc::cell(None), cache).await?;
+            let Ok(response) = result else {
+                panic!()
+            };
+            assert_eq!(*response.await?.body.to_string().await?, "responsebody");
+        }
+
+        resource_mock.assert_hits(1);
+    }
+}
+
+#[tokio::test]
+async fn revalidates_stale_cached_response() {
+    run! {
+        register();
+
+        let server = httpmock::MockServer::start();
+        let resource_mock = server.mock(|when, then| {
+            when.path("/foo.woff").matches(|req| {
+                !req.headers
+                    .iter()
+                    .flatten()
+                    .any(|(name, _)| name.eq_ignore_ascii_case("If-None-Match"))
+            });
+            then.status(200)
+                .header("ETag", "\"abc\"")
+                .body("responsebody");
+        });
+        let not_modified_mock = server.mock(|when, then| {
+            when.path("/foo.woff").header("If-None-Match", "\"abc\"");
+            then.status(304);
+        });
+
+        let cache_dir = tempfile::tempdir()?;
+        let url = StringVc::cell(server.url("/foo.woff"));
+        for _ in 0..2 {
+            let cache = http_cache(cache_dir.path(), false);
+            let result = &*fetch_cached(url, OptionStringVc::cell(None), cache).await?;
+            let Ok(response) = result else {
+                panic!()
+            };
+            let response = response.await?;
+            assert_eq!(response.status, 200);
+            assert_eq!(*response.body.to_string().await?, "responsebody");
+        }
+
+        resource_mock.assert_hits(1);
+        not_modified_mock.assert_hits(1);
+    }
+}
+
+#[tokio::test]
+async fn offline_serves_cached_response() {
+    run! {
+        register();
+
+        let server = httpmock::MockServer::start();
+        let resource_mock = server.mock(|when, then| {
+            when.path("/foo.woff");
+            then.status(200)
+                .header("Cache-Control", "no-cache")
+                .body("responsebody");
+        });
+
+        let cache_dir = tempfile::tempdir()?;
+        let url = StringVc::cell(server.url("/foo.woff"));
+        let result = &*fetch_cached(url, OptionStringVc::cell(None), http_cache(cache_dir.path(), false)).await?;
+        assert!(result.is_ok());
+
+        let result = &*fetch_cached(url, OptionStringVc::cell(None), http_cache(cache_dir.path(), true)).await?;
+        let Ok(response) = result else {
+            panic!()
+        };
+        assert_eq!(*response.await?.body.to_string().await?, "responsebody");
+
+        resource_mock.assert_hits(1);
+    }
+}
+
+#[tokio::test]
+async fn offline_errors_without_cached_response() {
+    run! {
+        register();
+
+        let cache_dir = tempfile::tempdir()?;
+        let url = "https://example.com/foo.woff";
+        let cache = http_cache(cache_dir.path(), true);
+        let result = &*fetch_cached(StringVc::cell(url.to_owned()), OptionStringVc::cell(None), cache).await?;
+        let Err(err_vc) = result else {
+            panic!()
+        };
+        assert_eq!(*err_vc.await?.kind.await?, FetchErrorKind::Offline);
+
+        let issue = err_vc.to_issue(IssueSeverity::Error.into(), get_issue_context());
+        assert_eq!(*issue.description().await?, "https://example.com/foo.woff is not available in offline mode because it has not been requested before.");
+    }
+}
+
+fn http_cache(directory: &std::path::Path, offline: bool) -> HttpCacheVc {
+    HttpCache {
+        directory: directory.to_string_lossy().into_owned(),
+        offline,
+    }
+    .cell()
+}
+
 fn get_issue_context() -> FileSystemPathVc {
     std::convert::Into::<FileSystemVc>::into(DiskFileSystemVc::new(
         "root".to_owned(),
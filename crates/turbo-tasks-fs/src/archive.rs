@@ -0,0 +1,619 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    io::{Cursor, Read},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use flate2::read::GzDecoder;
+use turbo_tasks::{primitives::StringVc, CompletionVc, Value, ValueToString, ValueToStringVc};
+
+use crate::{
+    util::{join_path, normalize_path},
+    DirectoryContentVc, DirectoryEntry, File, FileContent, FileContentVc, FileMeta, FileMetaVc,
+    FileSystem, FileSystemPathVc, FileSystemVc, LinkContent, LinkContentVc, LinkType, Permissions,
+};
+
+/// Symlinks are followed up to this depth, like the `MAXSYMLINKS` of Linux.
+const MAX_SYMLINK_DEPTH: usize = 40;
+
+/// The format of the archive of an [ArchiveFileSystem].
+#[turbo_tasks::value(serialization = "auto_for_input")]
+#[derive(Clone, Copy, Debug, PartialOrd, Ord, Hash)]
+pub enum ArchiveFormat {
+    Tar,
+    /// A gzip compressed tar archive, like npm package tarballs.
+    TarGz,
+    Zip,
+}
+
+impl ArchiveFormat {
+    /// Detects the format by the extension of `file_name`.
+    pub fn from_file_name(file_name: &str) -> Option<Self> {
+        let file_name = file_name.to_ascii_lowercase();
+        if file_name.ends_with(".tar.gz") || file_name.ends_with(".tgz") {
+            Some(ArchiveFormat::TarGz)
+        } else if file_name.ends_with(".tar") {
+            Some(ArchiveFormat::Tar)
+        } else if file_name.ends_with(".zip") {
+            Some(ArchiveFormat::Zip)
+        } else {
+            None
+        }
+    }
+}
+
+enum ArchiveEntry {
+    File(File),
+    /// The names of the entries of the directory.
+    Directory(BTreeSet<String>),
+    Symlink(String),
+}
+
+/// The entries of an archive by their path relative to the root of the
+/// [ArchiveFileSystem].
+#[turbo_tasks::value(serialization = "none", eq = "manual", cell = "new")]
+struct ArchiveContents {
+    #[turbo_tasks(debug_ignore, trace_ignore)]
+    entries: HashMap<String, ArchiveEntry>,
+}
+
+impl ArchiveContents {
+    /// Looks up `path`, following symlinks in all of its components. Returns
+    /// the entry and its path after following them.
+    fn resolve(&self, path: &str) -> Option<(String, &ArchiveEntry)> {
+        let mut path = path.to_string();
+        let mut links = 0;
+        'resolve: loop {
+            let mut start = 0;
+            loop {
+                let end = path[start..]
+                    .find('/')
+                    .map_or(path.len(), |index| start + index);
+                let prefix = &path[..end];
+                match self.entries.get(prefix)? {
+                    ArchiveEntry::Symlink(target) => {
+                        links += 1;
+                        if links > MAX_SYMLINK_DEPTH {
+                            return None;
+                        }
+                        let resolved = resolve_link(prefix, target)?;
+                        path = match &path[end..] {
+                            "" => resolved,
+                            rest if resolved.is_empty() => rest[1..].to_string(),
+                            rest => resolved + rest,
+                        };
+                        continue 'resolve;
+                    }
+                    entry if end == path.len() => return Some((path, entry)),
+                    ArchiveEntry::Directory(_) => start = end + 1,
+                    ArchiveEntry::File(_) => return None,
+                }
+            }
+        }
+    }
+}
+
+/// The path of the target of the symlink at `path`. Absolute targets would
+/// point outside of the archive.
+fn resolve_link(path: &str, target: &str) -> Option<String> {
+    if target.starts_with('/') {
+        return None;
+    }
+    let parent = path.rsplit_once('/').map_or("", |(parent, _)| parent);
+    join_path(parent, target)
+}
+
+/// The most that is allocated upfront for an entry, as the size in the
+/// header of a broken or malicious archive can't be trusted.
+const MAX_ENTRY_PREALLOCATION: u64 = 1 << 20;
+
+/// Limits the decompressed size of an archive, as all of its entries are
+/// kept in memory and a small archive can decompress to a huge size.
+#[derive(Clone, Copy, Debug)]
+struct SizeLimits {
+    /// The maximal size of a single entry in bytes.
+    entry: u64,
+    /// The maximal size of all entries together in bytes.
+    total: u64,
+}
+
+impl Default for SizeLimits {
+    fn default() -> Self {
+        Self {
+            entry: 256 << 20,
+            total: 1 << 30,
+        }
+    }
+}
+
+fn permissions(mode: Option<u32>) -> Permissions {
+    match mode {
+        Some(mode) if mode & 0o111 != 0 => Permissions::Executable,
+        Some(mode) if mode & 0o222 == 0 => Permissions::Readable,
+        _ => Permissions::Writable,
+    }
+}
+
+/// Collects the entries of an archive below `root`.
+struct ArchiveContentsBuilder {
+    root: String,
+    entries: HashMap<String, ArchiveEntry>,
+    /// Hard links of tar archives, which refer to another entry.
+    hard_links: Vec<(String, String)>,
+    limits: SizeLimits,
+    /// The decompressed size of the entries read so far.
+    total_size: u64,
+}
+
+impl ArchiveContentsBuilder {
+    fn new(root: &str, limits: SizeLimits) -> Result<Self> {
+        let root = normalize_path(root).ok_or_else(|| anyhow!("invalid archive root {root}"))?;
+        let mut entries = HashMap::new();
+        entries.insert(String::new(), ArchiveEntry::Directory(BTreeSet::new()));
+        Ok(Self {
+            root,
+            entries,
+            hard_links: Vec::new(),
+            limits,
+            total_size: 0,
+        })
+    }
+
+    /// Reads the content of the entry `name`, which claims to be `size`
+    /// bytes, within the [SizeLimits].
+    fn read_entry(&mut self, name: &str, entry: impl Read, size: u64) -> Result<Vec<u8>> {
+        let limit = self.limits.entry.min(self.limits.total - self.total_size);
+        let mut content = Vec::with_capacity(size.min(limit).min(MAX_ENTRY_PREALLOCATION) as usize);
+        entry.take(limit + 1).read_to_end(&mut content)?;
+        let size = content.len() as u64;
+        if size > self.limits.entry {
+            bail!(
+                "{name} is larger than {} bytes when decompressed",
+                self.limits.entry
+            );
+        }
+        if size > limit {
+            bail!(
+                "the archive is larger than {} bytes when decompressed",
+                self.limits.total
+            );
+        }
+        self.total_size += size;
+        Ok(content)
+    }
+
+    /// The path of an archive entry relative to the root, or `None` when
+    /// it's not below the root.
+    fn relative_path(&self, name: &str) -> Option<String> {
+        let path = normalize_path(&name.replace('\\', "/"))?;
+        if self.root.is_empty() {
+            return Some(path);
+        }
+        match path.strip_prefix(&self.root)? {
+            "" => Some(String::new()),
+            relative => relative.strip_prefix('/').map(str::to_string),
+        }
+    }
+
+    fn add(&mut self, name: &str, entry: ArchiveEntry) {
+        let Some(path) = self.relative_path(name) else {
+            return;
+        };
+        if path.is_empty() {
+            return;
+        }
+        self.add_to_parent(&path);
+        // Directories might have been created implicitly by their entries
+        if !matches!(
+            (&entry, self.entries.get(&path)),
+            (ArchiveEntry::Directory(_), Some(ArchiveEntry::Directory(_)))
+        ) {
+            self.entries.insert(path, entry);
+        }
+    }
+
+    /// Adds `path` to the entries of its parent directory, creating missing
+    /// parent directories, as archives don't need to contain them.
+    fn add_to_parent(&mut self, path: &str) {
+        let (parent, name) = path.rsplit_once('/').unwrap_or(("", path));
+        if let Some(ArchiveEntry::Directory(names)) = self.entries.get_mut(parent) {
+            names.insert(name.to_string());
+            return;
+        }
+        self.entries.insert(
+            parent.to_string(),
+            ArchiveEntry::Directory(BTreeSet::from([name.to_string()])),
+        );
+        if !parent.is_empty() {
+            self.add_to_parent(parent);
+        }
+    }
+
+    fn add_tar(&mut self, reader: impl Read) -> Result<()> {
+        let mut archive = tar::Archive::new(reader);
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let name = entry.path()?.to_string_lossy().into_owned();
+            let entry_type = entry.header().entry_type();
+            let archive_entry = if entry_type.is_dir() {
+                ArchiveEntry::Directory(BTreeSet::new())
+            } else if entry_type.is_symlink() {
+                let target = entry
+                    .link_name()?
+                    .with_context(|| format!("symlink {name} has no target"))?;
+                ArchiveEntry::Symlink(target.to_string_lossy().replace('\\', "/"))
+            } else if entry_type.is_hard_link() {
+                if let (Some(path), Some(target)) = (
+                    self.relative_path(&name),
+                    entry
+                        .link_name()?
+                        .and_then(|target| self.relative_path(&target.to_string_lossy())),
+                ) {
+                    self.hard_links.push((path, target));
+                }
+                continue;
+            } else if entry_type.is_file() {
+                let size = entry.size();
+                let content = self.read_entry(&name, &mut entry, size)?;
+                let meta = FileMeta {
+                    permissions: permissions(entry.header().mode().ok()),
+                    content_type: None,
+                };
+                ArchiveEntry::File(File::new(meta, content))
+            } else {
+                // e.g. pax headers or device files
+                continue;
+            };
+            self.add(&name, archive_entry);
+        }
+        Ok(())
+    }
+
+    fn add_zip(&mut self, bytes: Vec<u8>) -> Result<()> {
+        let mut archive = zip::ZipArchive::new(Cursor::new(bytes))?;
+        for index in 0..archive.len() {
+            let mut entry = archive.by_index(index)?;
+            let name = entry.name().to_string();
+            let mode = entry.unix_mode();
+            let size = entry.size();
+            let content = self.read_entry(&name, &mut entry, size)?;
+            // Symlinks are stored as files containing the target, with the
+            // file type in the unix mode
+            let archive_entry = if entry.is_dir() {
+                ArchiveEntry::Directory(BTreeSet::new())
+            } else if mode.map_or(false, |mode| mode & 0o170000 == 0o120000) {
+                ArchiveEntry::Symlink(String::from_utf8(content)?.replace('\\', "/"))
+            } else {
+                let meta = FileMeta {
+                    permissions: permissions(mode),
+                    content_type: None,
+                };
+                ArchiveEntry::File(File::new(meta, content))
+            };
+            self.add(&name, archive_entry);
+        }
+        Ok(())
+    }
+
+    fn build(mut self) -> HashMap<String, ArchiveEntry> {
+        for (path, target) in std::mem::take(&mut self.hard_links) {
+            if let Some(ArchiveEntry::File(file)) = self.entries.get(&target) {
+                let file = file.clone();
+                self.add_to_parent(&path);
+                self.entries.insert(path, ArchiveEntry::File(file));
+            }
+        }
+        self.entries
+    }
+}
+
+fn parse_archive(
+    format: ArchiveFormat,
+    content: &FileContent,
+    root: &str,
+    limits: SizeLimits,
+) -> Result<HashMap<String, ArchiveEntry>> {
+    let FileContent::Content(file) = content else {
+        bail!("archive not found");
+    };
+    let mut builder = ArchiveContentsBuilder::new(root, limits)?;
+    match format {
+        ArchiveFormat::Tar => builder.add_tar(file.read())?,
+        ArchiveFormat::TarGz => builder.add_tar(GzDecoder::new(file.read()))?,
+        ArchiveFormat::Zip => {
+            let mut bytes = Vec::with_capacity(file.content().len());
+            file.read().read_to_end(&mut bytes)?;
+            builder.add_zip(bytes)?
+        }
+    }
+    Ok(builder.build())
+}
+
+/// A read-only [FileSystem] of the files in a tar, gzipped tar or zip archive,
+/// without extracting them. The archive is read into memory once and again
+/// when it changes.
+///
+/// It can be mounted into another file system with
+/// [crate::attach::AttachedFileSystem], e.g. an npm package tarball as
+/// `node_modules/<package>`.
+#[turbo_tasks::value]
+pub struct ArchiveFileSystem {
+    name: String,
+    archive: FileSystemPathVc,
+    format: ArchiveFormat,
+    /// The directory inside of the archive which is the root of the file
+    /// system.
+    root: String,
+}
+
+#[turbo_tasks::value_impl]
+impl ArchiveFileSystemVc {
+    /// Creates a file system of the entries of the `archive` file below
+    /// `root`, which is relative to the root of the archive.
+    #[turbo_tasks::function]
+    pub fn new(
+        name: String,
+        archive: FileSystemPathVc,
+        format: Value<ArchiveFormat>,
+        root: String,
+    ) -> Self {
+        ArchiveFileSystem {
+            name,
+            archive,
+            format: format.into_value(),
+            root,
+        }
+        .cell()
+    }
+
+    /// Creates a file system of an npm package tarball, which contains the
+    /// files of the package in a `package` directory.
+    #[turbo_tasks::function]
+    pub fn npm_package(name: String, archive: FileSystemPathVc) -> Self {
+        Self::new(
+            name,
+            archive,
+            Value::new(ArchiveFormat::TarGz),
+            "package".to_string(),
+        )
+    }
+
+    #[turbo_tasks::function]
+    async fn contents(self) -> Result<ArchiveContentsVc> {
+        let this = self.await?;
+        let content = this.archive.read().await?;
+        let entries = parse_archive(this.format, &content, &this.root, SizeLimits::default())
+            .with_context(|| format!("reading archive {}", this.name))?;
+        Ok(ArchiveContents { entries }.cell())
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl FileSystem for ArchiveFileSystem {
+    #[turbo_tasks::function]
+    async fn read(self_vc: ArchiveFileSystemVc, path: FileSystemPathVc) -> Result<FileContentVc> {
+        let contents = self_vc.contents().await?;
+        Ok(match contents.resolve(&path.await?.path) {
+            Some((_, ArchiveEntry::File(file))) => FileContent::Content(file.clone()).cell(),
+            _ => FileContent::NotFound.cell(),
+        })
+    }
+
+    #[turbo_tasks::function]
+    async fn read_link(
+        self_vc: ArchiveFileSystemVc,
+        path: FileSystemPathVc,
+    ) -> Result<LinkContentVc> {
+        let contents = self_vc.contents().await?;
+        let path = path.await?;
+        let Some(ArchiveEntry::Symlink(target)) = contents.entries.get(&path.path) else {
+            return Ok(LinkContent::NotFound.cell());
+        };
+        let Some(resolved) = resolve_link(&path.path, target) else {
+            return Ok(LinkContent::Invalid.cell());
+        };
+        let link_type = match contents.resolve(&resolved) {
+            Some((_, ArchiveEntry::Directory(_))) => LinkType::DIRECTORY,
+            _ => LinkType::UNSET,
+        };
+        Ok(LinkContent::Link {
+            target: target.clone(),
+            link_type,
+        }
+        .cell())
+    }
+
+    #[turbo_tasks::function]
+    async fn read_dir(
+        self_vc: ArchiveFileSystemVc,
+        path: FileSystemPathVc,
+    ) -> Result<DirectoryContentVc> {
+        let contents = self_vc.contents().await?;
+        let Some((directory, ArchiveEntry::Directory(names))) =
+            contents.resolve(&path.await?.path)
+        else {
+            return Ok(DirectoryContentVc::not_found());
+        };
+        let directory_path = self_vc.root().join(&directory);
+        let entries = names
+            .iter()
+            .filter_map(|name| {
+                let child = if directory.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{directory}/{name}")
+                };
+                let entry_path = directory_path.join(name);
+                let entry = match contents.entries.get(&child)? {
+                    ArchiveEntry::File(_) => DirectoryEntry::File(entry_path),
+                    ArchiveEntry::Directory(_) => DirectoryEntry::Directory(entry_path),
+                    ArchiveEntry::Symlink(_) => DirectoryEntry::Symlink(entry_path),
+                };
+                Some((name.clone(), entry))
+            })
+            .collect();
+        Ok(DirectoryContentVc::new(entries))
+    }
+
+    #[turbo_tasks::function]
+    fn write(&self, _path: FileSystemPathVc, _content: FileContentVc) -> Result<CompletionVc> {
+        bail!("Writing is not possible to the archive filesystem")
+    }
+
+    #[turbo_tasks::function]
+    fn write_link(&self, _path: FileSystemPathVc, _target: LinkContentVc) -> Result<CompletionVc> {
+        bail!("Writing is not possible to the archive filesystem")
+    }
+
+    #[turbo_tasks::function]
+    async fn metadata(self_vc: ArchiveFileSystemVc, path: FileSystemPathVc) -> Result<FileMetaVc> {
+        let contents = self_vc.contents().await?;
+        match contents.resolve(&path.await?.path) {
+            Some((_, ArchiveEntry::File(file))) => Ok(file.meta().clone().cell()),
+            Some(_) => Ok(FileMeta::default().cell()),
+            None => bail!("path not found, can't read metadata"),
+        }
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl ValueToString for ArchiveFileSystem {
+    #[turbo_tasks::function]
+    fn to_string(&self) -> StringVc {
+        StringVc::cell(self.name.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{write::GzEncoder, Compression};
+
+    use super::{parse_archive, ArchiveEntry, ArchiveFormat, SizeLimits};
+    use crate::{File, FileContent};
+
+    fn tgz(entries: &[(&str, &str)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        for (path, content) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, path, content.as_bytes())
+                .unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    fn zip(entries: &[(&str, &str)]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        for (path, content) in entries {
+            writer
+                .start_file(*path, zip::write::FileOptions::default())
+                .unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn file_content(entry: Option<&ArchiveEntry>) -> Option<String> {
+        match entry? {
+            ArchiveEntry::File(file) => Some(file.content().to_str().unwrap().into_owned()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn npm_package_tarball() {
+        let archive = FileContent::Content(File::from(tgz(&[
+            ("package/package.json", "{}"),
+            ("package/lib/index.js", "module.exports = 1"),
+        ])));
+        let entries = parse_archive(
+            ArchiveFormat::TarGz,
+            &archive,
+            "package",
+            SizeLimits::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            file_content(entries.get("package.json")).as_deref(),
+            Some("{}")
+        );
+        assert_eq!(
+            file_content(entries.get("lib/index.js")).as_deref(),
+            Some("module.exports = 1")
+        );
+        // Directories are created for the entries
+        let Some(ArchiveEntry::Directory(names)) = entries.get("") else {
+            panic!("root directory missing");
+        };
+        assert_eq!(names.iter().collect::<Vec<_>>(), ["lib", "package.json"]);
+        assert!(matches!(
+            entries.get("lib"),
+            Some(ArchiveEntry::Directory(_))
+        ));
+    }
+
+    #[test]
+    fn truncated_tar_with_lying_size() {
+        let mut header = tar::Header::new_gnu();
+        header.set_path("package/index.js").unwrap();
+        header.set_size(1 << 40);
+        header.set_mode(0o644);
+        header.set_cksum();
+        let mut bytes = header.as_bytes().to_vec();
+        bytes.extend_from_slice(b"export {}");
+        let archive = FileContent::Content(File::from(bytes));
+        assert!(parse_archive(
+            ArchiveFormat::Tar,
+            &archive,
+            "package",
+            SizeLimits::default()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn zip_outside_of_root() {
+        let archive = FileContent::Content(File::from(zip(&[
+            ("dist/index.js", "export {}"),
+            ("README.md", "# readme"),
+        ])));
+        let entries =
+            parse_archive(ArchiveFormat::Zip, &archive, "dist", SizeLimits::default()).unwrap();
+        assert_eq!(
+            file_content(entries.get("index.js")).as_deref(),
+            Some("export {}")
+        );
+        assert!(!entries.contains_key("README.md"));
+    }
+
+    #[test]
+    fn decompressed_size_limits() {
+        let limits = SizeLimits {
+            entry: 1000,
+            total: 1500,
+        };
+        let content = "a".repeat(800);
+        let archive = FileContent::Content(File::from(tgz(&[("package/a.js", &content)])));
+        assert!(parse_archive(ArchiveFormat::TarGz, &archive, "package", limits).is_ok());
+
+        let content = "a".repeat(1001);
+        let archive = FileContent::Content(File::from(tgz(&[("package/a.js", &content)])));
+        let error = parse_archive(ArchiveFormat::TarGz, &archive, "package", limits)
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("package/a.js is larger"));
+
+        let content = "a".repeat(800);
+        let archive =
+            FileContent::Content(File::from(zip(&[("a.js", &content), ("b.js", &content)])));
+        let error = parse_archive(ArchiveFormat::Zip, &archive, "", limits)
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("the archive is larger"));
+    }
+}
@@ -2,7 +2,10 @@ use std::mem::take;
 
 use anyhow::{bail, Result};
 use serde::{Deserialize, Serialize};
-use turbo_tasks::trace::TraceRawVcs;
+use turbo_tasks::{trace::TraceRawVcs, Value};
+
+/// Numeric ranges like `{1..5}` with more numbers than this are rejected.
+const MAX_RANGE_SIZE: u64 = 10000;
 
 #[derive(PartialEq, Eq, Debug, Clone, TraceRawVcs, Serialize, Deserialize)]
 enum GlobPart {
@@ -18,13 +21,17 @@ enum GlobPart {
     /// `/`: Matches the path separator
     PathSeparator,
 
-    /// `[abc]`: Matches any char of the list
-    FileChar(Vec<char>),
+    /// `[abc]`, `[a-z]`: Matches any char of the ranges (no path separator),
+    /// `[!abc]`: Matches any other char
+    FileChar {
+        ranges: Vec<(char, char)>,
+        negated: bool,
+    },
 
     /// `abc`: Matches literal filename
     File(String),
 
-    /// `{a,b,c}`: Matches any of the globs in the list
+    /// `{a,b,c}`, `{1..3}`, `@(a|b|c)`: Matches any of the globs in the list
     Alternatives(Vec<Glob>),
 }
 
@@ -36,24 +43,72 @@ enum GlobPart {
 // - **/*.js = AnyDirectories, PathSeparator, AnyFile, File(.js)
 // - {a/**,*}/file = Alternatives([File(a), PathSeparator, AnyDirectories],
 //   [AnyFile]), PathSeparator, File(file)
+// - file{1..3}.js = File(file), Alternatives([File(1)], [File(2)], [File(3)]),
+//   File(.js)
+// - ?(a|b).js = Alternatives([File(a)], [File(b)], []), File(.js)
 
 // Note: a/**/b does match a/b, so we need some special logic about path
 // separators
 
+/// Options for parsing a [Glob].
+#[turbo_tasks::value(serialization = "auto_for_input")]
+#[derive(Clone, Copy, Debug, Default, PartialOrd, Ord, Hash)]
+pub struct GlobOptions {
+    /// Matches paths regardless of their case, e.g. for case-insensitive file
+    /// systems.
+    pub case_insensitive: bool,
+}
+
 #[turbo_tasks::value]
 #[derive(Debug, Clone)]
 pub struct Glob {
     expression: Vec<GlobPart>,
+    /// Only set on the outermost glob, whose literals are lowercased.
+    case_insensitive: bool,
 }
 
 impl Glob {
     pub fn execute(&self, path: &str) -> bool {
+        if self.case_insensitive {
+            self.execute_exactly(&path.to_lowercase())
+        } else {
+            self.execute_exactly(path)
+        }
+    }
+
+    fn execute_exactly(&self, path: &str) -> bool {
         let match_partial = path.ends_with('/');
         self.iter_matches(path, true, match_partial)
             .next()
             .is_some()
     }
 
+    /// Whether the glob matches every path below the directory `dir`, e.g.
+    /// `dist/**` or `**/node_modules/**/*` for `packages/app/node_modules`.
+    /// This is conservative: more complex globs might match everything below
+    /// a directory, too.
+    fn matches_everything_below(&self, dir: &str) -> bool {
+        use GlobPart::{AnyDirectories, AnyFile, PathSeparator};
+        let prefix = match &self.expression[..] {
+            [AnyDirectories] => return true,
+            [prefix @ .., PathSeparator, AnyDirectories]
+            | [prefix @ .., PathSeparator, AnyDirectories, PathSeparator, AnyFile] => prefix,
+            _ => return false,
+        };
+        Glob {
+            expression: prefix.to_vec(),
+            case_insensitive: self.case_insensitive,
+        }
+        .execute(dir)
+    }
+
+    fn new(expression: Vec<GlobPart>) -> Self {
+        Glob {
+            expression,
+            case_insensitive: false,
+        }
+    }
+
     fn iter_matches<'a>(
         &'a self,
         path: &'a str,
@@ -67,20 +122,31 @@ impl Glob {
             is_path_separator_equivalent: previous_part_is_path_separator_equivalent,
             stack: Vec::new(),
             index: 0,
+            matched_empty: false,
         }
     }
 
     pub fn parse(input: &str) -> Result<Glob> {
+        Self::parse_with_options(input, GlobOptions::default())
+    }
+
+    pub fn parse_with_options(input: &str, options: GlobOptions) -> Result<Glob> {
         let mut current = input;
         let mut expression = Vec::new();
 
         while !current.is_empty() {
-            let (part, remainder) = GlobPart::parse(current, false)?;
+            let (part, remainder) = GlobPart::parse(current, &[])?;
             expression.push(part);
             current = remainder;
         }
 
-        Ok(Glob { expression })
+        if options.case_insensitive {
+            expression.iter_mut().for_each(GlobPart::make_lowercase);
+        }
+        Ok(Glob {
+            expression,
+            case_insensitive: options.case_insensitive,
+        })
     }
 }
 
@@ -91,6 +157,8 @@ struct GlobMatchesIterator<'a> {
     is_path_separator_equivalent: bool,
     stack: Vec<GlobPartMatchesIterator<'a>>,
     index: usize,
+    /// An empty expression matches once.
+    matched_empty: bool,
 }
 
 impl<'a> Iterator for GlobMatchesIterator<'a> {
@@ -131,6 +199,14 @@ impl<'a> Iterator for GlobMatchesIterator<'a> {
             } else {
                 // end of expression, matched successfully
 
+                if self.index == 0 {
+                    if self.matched_empty {
+                        return None;
+                    }
+                    self.matched_empty = true;
+                    return Some((self.current, self.is_path_separator_equivalent));
+                }
+
                 // backtrack for the next iteration
                 self.index -= 1;
 
@@ -162,7 +238,9 @@ impl GlobPart {
         }
     }
 
-    fn parse(input: &str, inside_of_braces: bool) -> Result<(GlobPart, &str)> {
+    /// Parses a part from the start of `input`. Literals end at any of
+    /// `terminators`, which separate alternatives.
+    fn parse<'a>(input: &'a str, terminators: &[char]) -> Result<(GlobPart, &'a str)> {
         debug_assert!(!input.is_empty());
         let two_chars = {
             let mut chars = input.chars();
@@ -172,37 +250,23 @@ impl GlobPart {
             ('/', _) => Ok((GlobPart::PathSeparator, &input[1..])),
             ('*', Some('*')) => Ok((GlobPart::AnyDirectories, &input[2..])),
             ('*', _) => Ok((GlobPart::AnyFile, &input[1..])),
+            ('@', Some('(')) => {
+                let (alternatives, remainder) = parse_alternatives(&input[2..], '|', ')')?;
+                Ok((GlobPart::Alternatives(alternatives), remainder))
+            }
+            ('?', Some('(')) => {
+                let (mut alternatives, remainder) = parse_alternatives(&input[2..], '|', ')')?;
+                alternatives.push(Glob::new(Vec::new()));
+                Ok((GlobPart::Alternatives(alternatives), remainder))
+            }
             ('?', _) => Ok((GlobPart::AnyFileChar, &input[1..])),
-            ('[', Some('[')) => todo!("glob char classes are not implemented yet"),
-            ('[', _) => todo!("glob char sequences are not implemented yet"),
+            ('[', _) => parse_char_class(&input[1..]),
             ('{', Some(_)) => {
-                let mut current = &input[1..];
-                let mut alternatives = Vec::new();
-                let mut expression = Vec::new();
-
-                loop {
-                    let (part, remainder) = GlobPart::parse(current, true)?;
-                    expression.push(part);
-                    current = remainder;
-                    match current.chars().next() {
-                        Some(',') => {
-                            alternatives.push(Glob {
-                                expression: take(&mut expression),
-                            });
-                            current = &current[1..];
-                        }
-                        Some('}') => {
-                            alternatives.push(Glob {
-                                expression: take(&mut expression),
-                            });
-                            current = &current[1..];
-                            break;
-                        }
-                        _ => bail!("Unterminated glob braces"),
-                    }
+                if let Some(range) = parse_numeric_range(&input[1..]) {
+                    return range;
                 }
-
-                Ok((GlobPart::Alternatives(alternatives), current))
+                let (alternatives, remainder) = parse_alternatives(&input[1..], ',', '}')?;
+                Ok((GlobPart::Alternatives(alternatives), remainder))
             }
             ('{', None) => {
                 bail!("Unterminated glob braces")
@@ -210,28 +274,159 @@ impl GlobPart {
             _ => {
                 let mut is_escaped = false;
                 let mut literal = String::new();
-                let mut index = 0;
-                for c in input.chars() {
+                let mut end = input.len();
+                let mut chars = input.char_indices().peekable();
+                while let Some((index, c)) = chars.next() {
                     if is_escaped {
                         is_escaped = false;
                     } else if c == '\\' {
                         is_escaped = true;
+                        continue;
                     } else if c == '/'
                         || c == '*'
                         || c == '?'
                         || c == '['
                         || c == '{'
-                        || (inside_of_braces && (c == ',' || c == '}'))
+                        || (c == '@' && matches!(chars.peek(), Some((_, '('))))
+                        || terminators.contains(&c)
                     {
+                        end = index;
                         break;
                     }
                     literal.push(c);
-                    index += 1;
                 }
-                Ok((GlobPart::File(literal), &input[index..]))
+                Ok((GlobPart::File(literal), &input[end..]))
+            }
+        }
+    }
+
+    fn make_lowercase(&mut self) {
+        match self {
+            GlobPart::FileChar { ranges, .. } => {
+                for (from, to) in ranges.iter_mut() {
+                    *from = lowercase_char(*from);
+                    *to = lowercase_char(*to);
+                }
+            }
+            GlobPart::File(name) => *name = name.to_lowercase(),
+            GlobPart::Alternatives(alternatives) => {
+                for alternative in alternatives.iter_mut() {
+                    alternative
+                        .expression
+                        .iter_mut()
+                        .for_each(GlobPart::make_lowercase);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn lowercase_char(c: char) -> char {
+    let mut lowercase = c.to_lowercase();
+    match (lowercase.next(), lowercase.next()) {
+        (Some(lowercase), None) => lowercase,
+        _ => c,
+    }
+}
+
+/// Parses alternatives separated by `separator` up to `end`, e.g. the
+/// `a,b}` of `{a,b}`.
+fn parse_alternatives(input: &str, separator: char, end: char) -> Result<(Vec<Glob>, &str)> {
+    let mut current = input;
+    let mut alternatives = Vec::new();
+    let mut expression = Vec::new();
+    loop {
+        match current.chars().next() {
+            Some(c) if c == separator => {
+                alternatives.push(Glob::new(take(&mut expression)));
+                current = &current[1..];
+            }
+            Some(c) if c == end => {
+                alternatives.push(Glob::new(take(&mut expression)));
+                return Ok((alternatives, &current[1..]));
+            }
+            Some(_) => {
+                let (part, remainder) = GlobPart::parse(current, &[separator, end])?;
+                expression.push(part);
+                current = remainder;
+            }
+            None => bail!(
+                "Unterminated glob {}",
+                if end == '}' { "braces" } else { "extglob" }
+            ),
+        }
+    }
+}
+
+/// Parses a numeric range like the `1..5}` of `{1..5}`. Returns `None` when
+/// the braces contain something else.
+fn parse_numeric_range(input: &str) -> Option<Result<(GlobPart, &str)>> {
+    let end = input.find('}')?;
+    let (start, stop) = input[..end].split_once("..")?;
+    let (from, to) = (start.parse::<i64>().ok()?, stop.parse::<i64>().ok()?);
+    if from.abs_diff(to) >= MAX_RANGE_SIZE {
+        return Some(Err(anyhow::anyhow!(
+            "glob range {{{start}..{stop}}} is too large"
+        )));
+    }
+    // `{01..10}` pads all numbers to the same width
+    let padded = |number: &str| {
+        let digits = number.trim_start_matches('-');
+        digits.len() > 1 && digits.starts_with('0')
+    };
+    let width = if padded(start) || padded(stop) {
+        start.len().max(stop.len())
+    } else {
+        0
+    };
+    let numbers: Box<dyn Iterator<Item = i64>> = if from <= to {
+        Box::new(from..=to)
+    } else {
+        Box::new((to..=from).rev())
+    };
+    let alternatives = numbers
+        .map(|number| Glob::new(vec![GlobPart::File(format!("{number:0width$}"))]))
+        .collect();
+    Some(Ok((
+        GlobPart::Alternatives(alternatives),
+        &input[end + 1..],
+    )))
+}
+
+/// Parses a char class like the `a-z]` of `[a-z]`.
+fn parse_char_class(input: &str) -> Result<(GlobPart, &str)> {
+    let (negated, input) = match input.strip_prefix(['!', '^']) {
+        Some(input) => (true, input),
+        None => (false, input),
+    };
+    let mut ranges = Vec::new();
+    let mut chars = input.char_indices().peekable();
+    let mut first = true;
+    while let Some((index, c)) = chars.next() {
+        let c = match c {
+            // `]` is a literal when it's the first char
+            ']' if !first => {
+                return Ok((GlobPart::FileChar { ranges, negated }, &input[index + 1..]));
+            }
+            '\\' => match chars.next() {
+                Some((_, escaped)) => escaped,
+                None => break,
+            },
+            c => c,
+        };
+        first = false;
+        let mut lookahead = chars.clone();
+        if let (Some((_, '-')), Some((_, to))) = (lookahead.next(), lookahead.next()) {
+            if to != ']' {
+                chars = lookahead;
+                ranges.push((c, to));
+                continue;
             }
         }
+        ranges.push((c, c));
     }
+    bail!("Unterminated glob char class")
 }
 
 struct GlobPartMatchesIterator<'a> {
@@ -271,22 +466,37 @@ impl<'a> Iterator for GlobPartMatchesIterator<'a> {
                 }
             }
             GlobPart::AnyFile => {
-                self.index += 1;
                 // TODO verify if `*` does match zero chars?
-                if let Some(slice) = self.path.get(0..self.index) {
-                    if slice.ends_with('/') {
-                        None
-                    } else {
-                        Some((
-                            &self.path[self.index..],
-                            self.previous_part_is_path_separator_equivalent && self.index == 1,
-                        ))
+                let first_char_len = self.path.chars().next().map_or(0, char::len_utf8);
+                loop {
+                    self.index += 1;
+                    if self.index > self.path.len() {
+                        return None;
                     }
-                } else {
+                    if self.path.is_char_boundary(self.index) {
+                        break;
+                    }
+                }
+                if self.path[..self.index].ends_with('/') {
                     None
+                } else {
+                    Some((
+                        &self.path[self.index..],
+                        self.previous_part_is_path_separator_equivalent
+                            && self.index == first_char_len,
+                    ))
+                }
+            }
+            GlobPart::AnyFileChar => {
+                if self.index > 0 {
+                    return None;
+                }
+                self.index = 1;
+                match self.path.chars().next() {
+                    Some(c) if c != '/' => Some((&self.path[c.len_utf8()..], false)),
+                    _ => None,
                 }
             }
-            GlobPart::AnyFileChar => todo!(),
             GlobPart::PathSeparator => {
                 if self.index == 0 {
                     self.index = 1;
@@ -301,16 +511,22 @@ impl<'a> Iterator for GlobPartMatchesIterator<'a> {
                     None
                 }
             }
-            GlobPart::FileChar(chars) => loop {
-                if let Some(c) = chars.get(self.index) {
-                    self.index += 1;
-                    if self.path.starts_with(*c) {
-                        return Some((&self.path[1..], false));
-                    }
-                } else {
+            GlobPart::FileChar { ranges, negated } => {
+                if self.index > 0 {
                     return None;
                 }
-            },
+                self.index = 1;
+                match self.path.chars().next() {
+                    Some(c)
+                        if c != '/'
+                            && ranges.iter().any(|(from, to)| (*from..=*to).contains(&c))
+                                != *negated =>
+                    {
+                        Some((&self.path[c.len_utf8()..], false))
+                    }
+                    _ => None,
+                }
+            }
             GlobPart::File(name) => {
                 if self.index == 0 && self.path.starts_with(name) {
                     self.index += 1;
@@ -355,13 +571,126 @@ impl GlobVc {
     pub fn new(glob: &str) -> Result<Self> {
         Ok(Self::cell(Glob::try_from(glob)?))
     }
+
+    #[turbo_tasks::function]
+    pub fn new_with_options(glob: &str, options: Value<GlobOptions>) -> Result<Self> {
+        Ok(Self::cell(Glob::parse_with_options(
+            glob,
+            options.into_value(),
+        )?))
+    }
+}
+
+/// A list of globs, where globs starting with `!` exclude paths, like in
+/// `.gitignore` files or `files` of `package.json`. A path matches when the
+/// last glob that matches it doesn't start with `!`, so later globs can
+/// include paths again.
+#[turbo_tasks::value]
+#[derive(Debug, Clone)]
+pub struct GlobList {
+    /// The globs with a flag whether they are negated.
+    globs: Vec<(bool, Glob)>,
+}
+
+impl GlobList {
+    pub fn parse(patterns: &[String], options: GlobOptions) -> Result<GlobList> {
+        let globs = patterns
+            .iter()
+            .map(|pattern| match pattern.strip_prefix('!') {
+                Some(pattern) => Ok((true, Glob::parse_with_options(pattern, options)?)),
+                None => Ok((false, Glob::parse_with_options(pattern, options)?)),
+            })
+            .collect::<Result<_>>()?;
+        Ok(GlobList { globs })
+    }
+
+    /// Matches paths that match any of `include` and none of `exclude`, like
+    /// the `include` and `exclude` of a `tsconfig.json`. An `exclude` pattern
+    /// that matches a directory also excludes everything below it.
+    pub fn include_exclude(
+        include: &[String],
+        exclude: &[String],
+        options: GlobOptions,
+    ) -> Result<GlobList> {
+        let mut globs = Vec::with_capacity(include.len() + exclude.len() * 2);
+        for pattern in include.iter() {
+            globs.push((false, Glob::parse_with_options(pattern, options)?));
+        }
+        for pattern in exclude.iter() {
+            globs.push((true, Glob::parse_with_options(pattern, options)?));
+            if !pattern.ends_with("**") {
+                let below = format!("{}/**", pattern.trim_end_matches('/'));
+                globs.push((true, Glob::parse_with_options(&below, options)?));
+            }
+        }
+        Ok(GlobList { globs })
+    }
+
+    pub fn execute(&self, path: &str) -> bool {
+        if let Some(dir) = path.strip_suffix('/') {
+            // A directory might contain included paths, even when it's
+            // excluded itself, since a later glob could include them again.
+            // Globs before the last one that excludes everything below the
+            // directory don't matter.
+            let start = self
+                .globs
+                .iter()
+                .rposition(|(negated, glob)| *negated && glob.matches_everything_below(dir))
+                .map_or(0, |index| index + 1);
+            return self.globs[start..]
+                .iter()
+                .any(|(negated, glob)| !negated && glob.execute(path));
+        }
+        self.globs
+            .iter()
+            .rev()
+            .find(|(_, glob)| glob.execute(path))
+            .map_or(false, |(negated, _)| !negated)
+    }
+}
+
+impl From<Glob> for GlobList {
+    fn from(glob: Glob) -> Self {
+        GlobList {
+            globs: vec![(false, glob)],
+        }
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl GlobListVc {
+    #[turbo_tasks::function]
+    pub fn new(patterns: Vec<String>, options: Value<GlobOptions>) -> Result<Self> {
+        Ok(Self::cell(GlobList::parse(
+            &patterns,
+            options.into_value(),
+        )?))
+    }
+
+    #[turbo_tasks::function]
+    pub fn include_exclude(
+        include: Vec<String>,
+        exclude: Vec<String>,
+        options: Value<GlobOptions>,
+    ) -> Result<Self> {
+        Ok(Self::cell(GlobList::include_exclude(
+            &include,
+            &exclude,
+            options.into_value(),
+        )?))
+    }
+
+    #[turbo_tasks::function]
+    pub async fn from_glob(glob: GlobVc) -> Result<Self> {
+        Ok(Self::cell(glob.await?.clone_value().into()))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use rstest::*;
 
-    use super::Glob;
+    use super::{Glob, GlobList, GlobOptions};
 
     #[rstest]
     #[case::file("file.js", "file.js")]
@@ -391,6 +720,20 @@ mod tests {
         "**/*/next/dist/server/next.js",
         "node_modules/next/dist/server/next.js"
     )]
+    #[case::any_char("file.?s", "file.js")]
+    #[case::char_class("file.[jt]s", "file.ts")]
+    #[case::char_range("file[0-9].js", "file5.js")]
+    #[case::char_class_negated("file.[!t]s", "file.js")]
+    #[case::range("file{1..12}.js", "file10.js")]
+    #[case::range_padded("file{01..12}.js", "file07.js")]
+    #[case::range_reversed("file{3..1}.js", "file2.js")]
+    #[case::braces_with_dirs("{a/b,c}/file.js", "a/b/file.js")]
+    #[case::braces_empty("file{,.min}.js", "file.js")]
+    #[case::extglob("@(src|lib)/*.js", "lib/file.js")]
+    #[case::extglob_optional("file?(.min).js", "file.min.js")]
+    #[case::extglob_optional("file?(.min).js", "file.js")]
+    #[case::escaped("file\\*.js", "file*.js")]
+    #[case::unicode("*.js", "ä.js")]
     fn glob_match(#[case] glob: &str, #[case] path: &str) {
         let glob = Glob::parse(glob).unwrap();
 
@@ -398,4 +741,110 @@ mod tests {
 
         assert!(glob.execute(path));
     }
+
+    #[rstest]
+    #[case::file("file.js", "file.ts")]
+    #[case::star_dir("*.js", "dir/file.js")]
+    #[case::any_char("file.?s", "file.s")]
+    #[case::char_class("file.[jt]s", "file.cs")]
+    #[case::char_class_negated("file.[!t]s", "file.ts")]
+    #[case::range("file{1..12}.js", "file13.js")]
+    #[case::range_padded("file{01..12}.js", "file7.js")]
+    #[case::extglob("@(src|lib)/*.js", "test/file.js")]
+    #[case::case_sensitive("*.JS", "file.js")]
+    fn glob_mismatch(#[case] glob: &str, #[case] path: &str) {
+        let glob = Glob::parse(glob).unwrap();
+
+        println!("{glob:?} {path}");
+
+        assert!(!glob.execute(path));
+    }
+
+    #[rstest]
+    #[case::literal("README.md", "readme.MD")]
+    #[case::char_range("[A-Z]*.js", "file.JS")]
+    #[case::braces("{SRC,lib}/*.js", "src/file.js")]
+    fn glob_match_case_insensitive(#[case] glob: &str, #[case] path: &str) {
+        let glob = Glob::parse_with_options(
+            glob,
+            GlobOptions {
+                case_insensitive: true,
+            },
+        )
+        .unwrap();
+
+        assert!(glob.execute(path));
+    }
+
+    #[rstest]
+    #[case::too_large_range("file{1..100000}.js")]
+    #[case::unterminated_braces("file{a,b")]
+    #[case::unterminated_extglob("@(a|b")]
+    #[case::unterminated_class("file[ab")]
+    fn glob_parse_error(#[case] glob: &str) {
+        assert!(Glob::parse(glob).is_err());
+    }
+
+    #[test]
+    fn glob_list() {
+        let list = GlobList::parse(
+            &[
+                "src/**/*.js".to_string(),
+                "!src/**/*.test.js".to_string(),
+                "src/fixtures/*.test.js".to_string(),
+            ],
+            GlobOptions::default(),
+        )
+        .unwrap();
+
+        assert!(list.execute("src/file.js"));
+        assert!(list.execute("src/dir/file.js"));
+        assert!(!list.execute("src/file.test.js"));
+        assert!(list.execute("src/fixtures/file.test.js"));
+        assert!(!list.execute("lib/file.js"));
+        assert!(list.execute("src/dir/"));
+        assert!(!list.execute("lib/"));
+    }
+
+    #[test]
+    fn glob_list_prunes_excluded_directories() {
+        let list = GlobList::parse(
+            &[
+                "**/*.js".to_string(),
+                "!**/node_modules/**".to_string(),
+                "!dist/**/*".to_string(),
+                "dist/keep/**".to_string(),
+            ],
+            GlobOptions::default(),
+        )
+        .unwrap();
+
+        assert!(list.execute("src/"));
+        assert!(!list.execute("node_modules/"));
+        assert!(!list.execute("src/node_modules/"));
+        assert!(!list.execute("src/node_modules/pkg/"));
+        assert!(list.execute("dist/"));
+        assert!(!list.execute("dist/other/"));
+        assert!(list.execute("dist/keep/"));
+    }
+
+    #[rstest]
+    #[case("node_modules")]
+    #[case("node_modules/")]
+    #[case("node_modules/**")]
+    #[case("**/node_modules")]
+    fn include_exclude_directories(#[case] exclude: &str) {
+        let list = GlobList::include_exclude(
+            &["**/*".to_string()],
+            &[exclude.to_string()],
+            GlobOptions::default(),
+        )
+        .unwrap();
+
+        assert!(list.execute("index.ts"));
+        assert!(list.execute("src/"));
+        assert!(!list.execute("node_modules/pkg/index.ts"));
+        assert!(!list.execute("node_modules/"));
+        assert!(!list.execute("node_modules/pkg/"));
+    }
 }
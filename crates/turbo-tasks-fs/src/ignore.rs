@@ -0,0 +1,319 @@
+use std::path::Path;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use turbo_tasks::trace::TraceRawVcs;
+
+use crate::{glob::Glob, util::sys_to_unix, FileContent, FileSystemPathVc};
+
+/// A pattern of a `.gitignore` file.
+#[derive(PartialEq, Eq, Debug, Clone, TraceRawVcs, Serialize, Deserialize)]
+struct GitIgnoreRule {
+    glob: Glob,
+    /// `!pattern`: Re-includes paths that have been ignored by previous rules
+    negated: bool,
+    /// `pattern/`: Only matches directories
+    directory_only: bool,
+}
+
+impl GitIgnoreRule {
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, pattern) = match line.strip_prefix('!') {
+            Some(pattern) => (true, pattern),
+            None => (false, line),
+        };
+        // `\#` and `\!` escape the special meaning of the first character
+        let pattern = pattern.strip_prefix('\\').unwrap_or(pattern);
+        let (directory_only, pattern) = match pattern.strip_suffix('/') {
+            Some(pattern) => (true, pattern),
+            None => (false, pattern),
+        };
+        // Patterns with a separator are relative to the directory of the
+        // `.gitignore` file, others match at any depth below it
+        let glob = if pattern.contains('/') {
+            pattern.trim_start_matches('/').to_string()
+        } else {
+            format!("**/{pattern}")
+        };
+        if glob.is_empty() {
+            return None;
+        }
+        Some(GitIgnoreRule {
+            glob: Glob::parse(&glob).ok()?,
+            negated,
+            directory_only,
+        })
+    }
+}
+
+/// The rules of a `.gitignore` file, which apply to the paths below its
+/// directory.
+#[derive(PartialEq, Eq, Debug, Clone, TraceRawVcs, Serialize, Deserialize)]
+pub struct GitIgnoreFile {
+    directory: String,
+    rules: Vec<GitIgnoreRule>,
+}
+
+impl GitIgnoreFile {
+    /// Parses the content of the `.gitignore` file in `directory`, which is
+    /// relative to the root of the file system.
+    pub fn parse(directory: &str, content: &str) -> Self {
+        GitIgnoreFile {
+            directory: directory.to_string(),
+            rules: content.lines().filter_map(GitIgnoreRule::parse).collect(),
+        }
+    }
+
+    /// Returns `Some(true)` when the last matching rule ignores `path`,
+    /// `Some(false)` when it re-includes `path` and `None` when no rule
+    /// matches.
+    fn matches(&self, path: &str, is_directory: bool) -> Option<bool> {
+        let path = if self.directory.is_empty() {
+            path
+        } else {
+            path.strip_prefix(&self.directory)?.strip_prefix('/')?
+        };
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| (is_directory || !rule.directory_only) && rule.glob.execute(path))
+            .map(|rule| !rule.negated)
+    }
+}
+
+#[turbo_tasks::value(transparent)]
+pub struct GitIgnoreFiles(Vec<GitIgnoreFile>);
+
+/// The `.gitignore` files that apply to the entries of `directory`, parents
+/// first.
+#[turbo_tasks::function]
+pub async fn gitignore_files(directory: FileSystemPathVc) -> Result<GitIgnoreFilesVc> {
+    let path = directory.await?;
+    let mut files = if path.is_root() {
+        Vec::new()
+    } else {
+        gitignore_files(directory.parent()).await?.clone_value()
+    };
+    if let FileContent::Content(file) = &*directory.join(".gitignore").read().await? {
+        files.push(GitIgnoreFile::parse(&path.path, &file.content().to_str()?));
+    }
+    Ok(GitIgnoreFilesVc::cell(files))
+}
+
+/// Decides which paths of a file system are ignored, by globs and optionally
+/// by `.gitignore` files. Paths are relative to the root of the file system.
+#[derive(Clone, Debug)]
+pub struct IgnoreMatcher {
+    globs: Vec<Glob>,
+    gitignore: bool,
+    /// Sorted by directory, so parents come before their children.
+    gitignore_files: Vec<GitIgnoreFile>,
+}
+
+impl IgnoreMatcher {
+    pub fn new(globs: Vec<Glob>, gitignore: bool) -> Self {
+        IgnoreMatcher {
+            globs,
+            gitignore,
+            gitignore_files: Vec::new(),
+        }
+    }
+
+    pub fn with_gitignore_files(mut self, files: Vec<GitIgnoreFile>) -> Self {
+        for file in files {
+            self.set_gitignore_file(file);
+        }
+        self
+    }
+
+    /// A path is ignored when it or any of its parent directories is ignored.
+    pub fn is_ignored(&self, path: &str, is_directory: bool) -> bool {
+        if path.is_empty() {
+            return false;
+        }
+        path.match_indices('/')
+            .any(|(index, _)| self.is_ignored_exactly(&path[..index], true))
+            || self.is_ignored_exactly(path, is_directory)
+    }
+
+    /// Like [IgnoreMatcher::is_ignored] for an absolute path below `root`.
+    pub fn is_ignored_path(&self, root: &Path, path: &Path, is_directory: bool) -> bool {
+        relative_path(root, path).map_or(false, |relative| self.is_ignored(&relative, is_directory))
+    }
+
+    /// Like [IgnoreMatcher::is_ignored_path] for a path that has changed on
+    /// disk. When the path no longer exists, it's unknown whether it was a
+    /// directory, so it's only ignored when it would be ignored either way.
+    pub fn is_ignored_changed_path(&self, root: &Path, path: &Path) -> bool {
+        match path.metadata() {
+            Ok(metadata) => self.is_ignored_path(root, path, metadata.is_dir()),
+            Err(_) => {
+                self.is_ignored_path(root, path, false) && self.is_ignored_path(root, path, true)
+            }
+        }
+    }
+
+    fn is_ignored_exactly(&self, path: &str, is_directory: bool) -> bool {
+        if self.globs.iter().any(|glob| glob.execute(path)) {
+            return true;
+        }
+        if !self.gitignore {
+            return false;
+        }
+        if is_directory && (path == ".git" || path.ends_with("/.git")) {
+            return true;
+        }
+        let mut ignored = false;
+        for file in self.gitignore_files.iter() {
+            if let Some(matches) = file.matches(path, is_directory) {
+                ignored = matches;
+            }
+        }
+        ignored
+    }
+
+    fn set_gitignore_file(&mut self, file: GitIgnoreFile) {
+        match self
+            .gitignore_files
+            .binary_search_by(|existing| existing.directory.cmp(&file.directory))
+        {
+            Ok(index) => self.gitignore_files[index] = file,
+            Err(index) => self.gitignore_files.insert(index, file),
+        }
+    }
+
+    /// Reads the `.gitignore` file of `directory` again, which might have
+    /// been created, changed or removed.
+    pub fn load_gitignore_file(&mut self, root: &Path, directory: &Path) {
+        if !self.gitignore {
+            return;
+        }
+        let Some(relative) = relative_path(root, directory) else {
+            return;
+        };
+        match std::fs::read_to_string(directory.join(".gitignore")) {
+            Ok(content) => self.set_gitignore_file(GitIgnoreFile::parse(&relative, &content)),
+            Err(_) => self
+                .gitignore_files
+                .retain(|existing| existing.directory != relative),
+        }
+    }
+
+    /// Calls `visit` for `directory` and all directories below it that are
+    /// not ignored, loading their `.gitignore` files on the way.
+    pub fn walk(
+        &mut self,
+        root: &Path,
+        directory: &Path,
+        visit: &mut dyn FnMut(&Path) -> Result<()>,
+    ) -> Result<()> {
+        self.load_gitignore_file(root, directory);
+        visit(directory)?;
+        // The directory might have been removed in the meantime
+        let Ok(entries) = std::fs::read_dir(directory) else {
+            return Ok(());
+        };
+        for entry in entries.flatten() {
+            if !entry
+                .file_type()
+                .map_or(false, |file_type| file_type.is_dir())
+            {
+                continue;
+            }
+            let path = entry.path();
+            if !self.is_ignored_path(root, &path, true) {
+                self.walk(root, &path, visit)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn relative_path(root: &Path, path: &Path) -> Option<String> {
+    Some(sys_to_unix(path.strip_prefix(root).ok()?.to_str()?).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::*;
+
+    use super::{GitIgnoreFile, IgnoreMatcher};
+    use crate::glob::Glob;
+
+    fn matcher(globs: &[&str], gitignore_files: &[(&str, &str)]) -> IgnoreMatcher {
+        IgnoreMatcher::new(
+            globs
+                .iter()
+                .map(|glob| Glob::parse(glob).unwrap())
+                .collect(),
+            true,
+        )
+        .with_gitignore_files(
+            gitignore_files
+                .iter()
+                .map(|(directory, content)| GitIgnoreFile::parse(directory, content))
+                .collect(),
+        )
+    }
+
+    #[rstest]
+    #[case::glob(&["node_modules/.cache"], &[], "node_modules/.cache/file.js", false, true)]
+    #[case::glob_other(&["node_modules/.cache"], &[], "node_modules/pkg/index.js", false, false)]
+    #[case::git_dir(&[], &[], ".git/HEAD", false, true)]
+    #[case::name(&[], &[("", "dist")], "packages/app/dist/index.js", false, true)]
+    #[case::star(&[], &[("", "*.log")], "logs/debug.log", false, true)]
+    #[case::anchored(&[], &[("", "/build")], "packages/build/index.js", false, false)]
+    #[case::anchored_root(&[], &[("", "/build")], "build/index.js", false, true)]
+    #[case::directory_only_file(&[], &[("", "out/")], "out", false, false)]
+    #[case::directory_only(&[], &[("", "out/")], "out", true, true)]
+    #[case::directory_only_child(&[], &[("", "out/")], "out/index.js", false, true)]
+    #[case::negated(&[], &[("", "*.log\n!keep.log")], "keep.log", false, false)]
+    #[case::comment(&[], &[("", "# dist\n")], "dist/index.js", false, false)]
+    #[case::nested(&[], &[("packages/app", ".next")], "packages/app/.next/trace", false, true)]
+    #[case::nested_other(
+        &[],
+        &[("packages/app", ".next")],
+        "packages/web/.next/trace",
+        false,
+        false
+    )]
+    #[case::nested_negated(
+        &[],
+        &[("", "*.env"), ("packages/app", "!.env")],
+        "packages/app/.env",
+        false,
+        false
+    )]
+    fn ignore_match(
+        #[case] globs: &[&str],
+        #[case] gitignore_files: &[(&str, &str)],
+        #[case] path: &str,
+        #[case] is_directory: bool,
+        #[case] ignored: bool,
+    ) {
+        let matcher = matcher(globs, gitignore_files);
+        assert_eq!(matcher.is_ignored(path, is_directory), ignored);
+    }
+
+    #[test]
+    fn removed_paths_are_ignored_as_files_and_directories() {
+        let root = tempfile::tempdir().unwrap();
+        let matcher = matcher(
+            &[],
+            &[(
+                "",
+                "out/
+dist",
+            )],
+        );
+        // A removed `out` might have been a directory, so it's not ignored
+        assert!(!matcher.is_ignored_changed_path(root.path(), &root.path().join("out")));
+        assert!(matcher.is_ignored_changed_path(root.path(), &root.path().join("dist")));
+        std::fs::create_dir(root.path().join("out")).unwrap();
+        assert!(matcher.is_ignored_changed_path(root.path(), &root.path().join("out")));
+    }
+}
@@ -5,11 +5,16 @@
 #![feature(io_error_more)]
 #![feature(main_separator_str)]
 
+pub mod archive;
 pub mod attach;
 pub mod embed;
 pub mod glob;
+pub mod ignore;
 mod invalidator_map;
 mod mutex_map;
+pub mod output;
+pub mod overlay;
+mod poll_watcher;
 mod read_glob;
 mod retry;
 pub mod rope;
@@ -17,6 +22,7 @@ pub mod util;
 
 use std::{
     collections::{HashMap, HashSet},
+    ffi::OsStr,
     fmt::{self, Debug, Display, Formatter},
     fs::FileType,
     io::{self, ErrorKind},
@@ -32,22 +38,23 @@ use std::{
 use anyhow::{anyhow, bail, Context, Result};
 use auto_hash_map::AutoMap;
 use bitflags::bitflags;
-use glob::GlobVc;
+use glob::{Glob, GlobListVc, GlobVc};
+use ignore::{gitignore_files, IgnoreMatcher};
 use invalidator_map::InvalidatorMap;
 use jsonc_parser::{parse_to_serde_value, ParseOptions};
 use mime::Mime;
 use notify::{watcher, DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
-use read_glob::read_glob;
+use poll_watcher::PollWatcher;
+use read_glob::{read_glob, read_glob_list};
 pub use read_glob::{ReadGlobResult, ReadGlobResultVc};
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
 use tokio::{fs, io::AsyncReadExt};
 use turbo_tasks::{
-    mark_stateful,
+    mark_session_dependent, mark_stateful,
     primitives::{BoolVc, StringReadRef, StringVc},
     spawn_thread,
     trace::TraceRawVcs,
-    CompletionVc, Invalidator, ValueToString, ValueToStringVc,
+    CompletionVc, Invalidator, Value, ValueToString, ValueToStringVc,
 };
 use turbo_tasks_hash::hash_xxh3_hash64;
 use util::{join_path, normalize_path, sys_to_unix, unix_to_sys};
@@ -74,10 +81,73 @@ pub trait FileSystem: ValueToString {
     fn metadata(&self, fs_path: FileSystemPathVc) -> FileMetaVc;
 }
 
+/// Options for a [DiskFileSystem].
+#[turbo_tasks::value(serialization = "auto_for_input")]
+#[derive(Clone, Debug, Default, PartialOrd, Ord, Hash)]
+pub struct DiskFileSystemOptions {
+    /// Globs of paths relative to the root that are neither watched nor
+    /// listed in directory contents. Paths inside of matching directories are
+    /// ignored too. Reading an ignored path still works, but changes to it
+    /// are not noticed.
+    pub ignore: Vec<String>,
+    /// Also ignores paths that are ignored by `.gitignore` files, and `.git`
+    /// directories.
+    pub gitignore: bool,
+    /// How changes are noticed after [DiskFileSystem::start_watching].
+    pub watch_mode: WatchMode,
+}
+
+/// How a [DiskFileSystem] notices changes.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    TraceRawVcs,
+)]
+pub enum WatchMode {
+    /// Events of the operating system, which is the fastest, but these are
+    /// not delivered for some file systems, e.g. network file systems or bind
+    /// mounts of containers.
+    #[default]
+    Native,
+    /// Scans the file system every `interval`. Files are compared by
+    /// modification time and size, and with `compare_contents` also by a hash
+    /// of their content. With `skip_dependencies`, `node_modules` and `.git`
+    /// directories are not scanned, so changes in there are not noticed, but
+    /// they are still read like any other directory.
+    Polling {
+        interval: Duration,
+        compare_contents: bool,
+        skip_dependencies: bool,
+    },
+}
+
+/// A running watcher of a [DiskFileSystem].
+enum DiskWatcher {
+    Native(RecommendedWatcher),
+    Polling(PollWatcher),
+}
+
+/// With ignore rules, directories are watched one by one to skip the ignored
+/// ones. This is only done on Linux, where a recursive watch needs a watch per
+/// directory anyway and the number of watches is limited.
+const WATCH_DIRECTORIES_INDIVIDUALLY: bool = cfg!(target_os = "linux");
+
 #[turbo_tasks::value(cell = "new", eq = "manual")]
 pub struct DiskFileSystem {
     pub name: String,
     pub root: String,
+    ignore: Vec<Glob>,
+    gitignore: bool,
+    watch_mode: WatchMode,
     #[turbo_tasks(debug_ignore, trace_ignore)]
     #[serde(skip)]
     mutex_map: MutexMap<PathBuf>,
@@ -87,13 +157,16 @@ pub struct DiskFileSystem {
     dir_invalidator_map: Arc<InvalidatorMap>,
     #[turbo_tasks(debug_ignore, trace_ignore)]
     #[serde(skip)]
-    watcher: Mutex<Option<RecommendedWatcher>>,
+    watcher: Arc<Mutex<Option<DiskWatcher>>>,
 }
 
 impl DiskFileSystem {
     /// registers the path as an invalidator for the current task,
     /// has to be called within a turbo-tasks function
     fn register_invalidator(&self, path: impl AsRef<Path>, file: bool) {
+        // The file might have changed while no process was watching it, so
+        // reads need to be repeated when restoring a persisted task graph.
+        mark_session_dependent();
         let invalidator = turbo_tasks::get_invalidator();
         if file {
             self.invalidator_map.insert(path_to_key(path), invalidator);
@@ -103,6 +176,39 @@ impl DiskFileSystem {
         }
     }
 
+    fn has_ignore_rules(&self) -> bool {
+        !self.ignore.is_empty() || self.gitignore
+    }
+
+    /// The matcher for the entries of `directory`, when there are ignore
+    /// rules. The `.gitignore` files are read as part of the current task.
+    async fn ignore_matcher(&self, directory: FileSystemPathVc) -> Result<Option<IgnoreMatcher>> {
+        if !self.has_ignore_rules() {
+            return Ok(None);
+        }
+        let matcher = IgnoreMatcher::new(self.ignore.clone(), self.gitignore);
+        Ok(Some(if self.gitignore {
+            matcher.with_gitignore_files(gitignore_files(directory).await?.clone_value())
+        } else {
+            matcher
+        }))
+    }
+
+    /// Invalidates the reads of `path` and all paths below it.
+    fn invalidate_path_and_children(&self, path: &Path) {
+        let key = path_to_key(path);
+        for invalidator_map in [&self.invalidator_map, &self.dir_invalidator_map] {
+            let mut invalidator_map = invalidator_map.lock().unwrap();
+            for (_, invalidators) in
+                invalidator_map.drain_filter(|path_key, _| path_key.starts_with(&key))
+            {
+                invalidators
+                    .into_iter()
+                    .for_each(|i| i.invalidate_with_reason(format!("{key} replaced")));
+            }
+        }
+    }
+
     pub fn invalidate(&self) {
         for (_, invalidators) in take(&mut *self.invalidator_map.lock().unwrap()).into_iter() {
             invalidators.into_iter().for_each(|i| i.invalidate());
@@ -112,22 +218,63 @@ impl DiskFileSystem {
         }
     }
 
+    /// Starts watching the file system for changes. When called within a
+    /// turbo-tasks function, the function is executed again in every session,
+    /// as the watcher isn't persisted.
     pub fn start_watching(&self) -> Result<()> {
+        mark_session_dependent();
         let mut watcher_guard = self.watcher.lock().unwrap();
         if watcher_guard.is_some() {
             return Ok(());
         }
         let invalidator_map = self.invalidator_map.clone();
         let dir_invalidator_map = self.dir_invalidator_map.clone();
+        let watcher_mutex = self.watcher.clone();
         let root = self.root.clone();
+        let mut ignore = self
+            .has_ignore_rules()
+            .then(|| IgnoreMatcher::new(self.ignore.clone(), self.gitignore));
         // Create a channel to receive the events.
         let (tx, rx) = channel();
-        // Create a watcher object, delivering debounced events.
-        // The notification back-end is selected based on the platform.
-        let mut watcher = watcher(tx, Duration::from_millis(1))?;
-        // Add a path to be watched. All files and directories at that path and
-        // below will be monitored for changes.
-        watcher.watch(&root, RecursiveMode::Recursive)?;
+        let disk_watcher = match self.watch_mode {
+            WatchMode::Native => {
+                // Create a watcher object, delivering debounced events.
+                // The notification back-end is selected based on the platform.
+                let mut watcher = watcher(tx, Duration::from_millis(1))?;
+                if let Some(ignore) = &mut ignore {
+                    let root = Path::new(&root);
+                    ignore.walk(root, root, &mut |directory| {
+                        watch_directory(&mut watcher, directory)
+                    })?;
+                }
+                if ignore.is_none() || !WATCH_DIRECTORIES_INDIVIDUALLY {
+                    // Add a path to be watched. All files and directories at that path and
+                    // below will be monitored for changes.
+                    watcher.watch(&root, RecursiveMode::Recursive)?;
+                }
+                DiskWatcher::Native(watcher)
+            }
+            WatchMode::Polling {
+                interval,
+                compare_contents,
+                skip_dependencies,
+            } => {
+                if let Some(ignore) = &mut ignore {
+                    // Loads the `.gitignore` files
+                    let root = Path::new(&root);
+                    ignore.walk(root, root, &mut |_| Ok(()))?;
+                }
+                // The poll watcher sends the same events as the native one
+                DiskWatcher::Polling(PollWatcher::start(
+                    PathBuf::from(&root),
+                    ignore.clone(),
+                    interval,
+                    compare_contents,
+                    skip_dependencies,
+                    tx,
+                ))
+            }
+        };
 
         // We need to invalidate all reads that happened before watching
         // Best is to start_watching before starting to read
@@ -138,9 +285,15 @@ impl DiskFileSystem {
             invalidators.into_iter().for_each(|i| i.invalidate());
         }
 
-        watcher_guard.replace(watcher);
+        watcher_guard.replace(disk_watcher);
 
         spawn_thread(move || {
+            let root_path = PathBuf::from(&root);
+            let is_ignored = |ignore: &Option<IgnoreMatcher>, path: &Path| {
+                ignore.as_ref().map_or(false, |ignore| {
+                    ignore.is_ignored_changed_path(&root_path, path)
+                })
+            };
             let mut batched_invalidate_path = HashSet::new();
             let mut batched_invalidate_path_dir = HashSet::new();
             let mut batched_invalidate_path_and_children = HashSet::new();
@@ -153,23 +306,40 @@ impl DiskFileSystem {
                 loop {
                     match event {
                         Ok(DebouncedEvent::Write(path)) => {
-                            batched_invalidate_path.insert(path);
+                            if !is_ignored(&ignore, &path) {
+                                if let Some(ignore) = &mut ignore {
+                                    update_ignore(ignore, &watcher_mutex, &root_path, &path);
+                                }
+                                batched_invalidate_path.insert(path);
+                            }
                         }
                         Ok(DebouncedEvent::Create(path)) | Ok(DebouncedEvent::Remove(path)) => {
-                            batched_invalidate_path_and_children.insert(path.clone());
-                            batched_invalidate_path_and_children_dir.insert(path.clone());
-                            if let Some(parent) = path.parent() {
-                                batched_invalidate_path_dir.insert(PathBuf::from(parent));
+                            if !is_ignored(&ignore, &path) {
+                                if let Some(ignore) = &mut ignore {
+                                    update_ignore(ignore, &watcher_mutex, &root_path, &path);
+                                }
+                                batched_invalidate_path_and_children.insert(path.clone());
+                                batched_invalidate_path_and_children_dir.insert(path.clone());
+                                if let Some(parent) = path.parent() {
+                                    batched_invalidate_path_dir.insert(PathBuf::from(parent));
+                                }
                             }
                         }
                         Ok(DebouncedEvent::Rename(source, destination)) => {
-                            batched_invalidate_path_and_children.insert(source.clone());
-                            if let Some(parent) = source.parent() {
-                                batched_invalidate_path_dir.insert(PathBuf::from(parent));
+                            if !is_ignored(&ignore, &source) {
+                                batched_invalidate_path_and_children.insert(source.clone());
+                                if let Some(parent) = source.parent() {
+                                    batched_invalidate_path_dir.insert(PathBuf::from(parent));
+                                }
                             }
-                            batched_invalidate_path_and_children.insert(destination.clone());
-                            if let Some(parent) = destination.parent() {
-                                batched_invalidate_path_dir.insert(PathBuf::from(parent));
+                            if !is_ignored(&ignore, &destination) {
+                                if let Some(ignore) = &mut ignore {
+                                    update_ignore(ignore, &watcher_mutex, &root_path, &destination);
+                                }
+                                batched_invalidate_path_and_children.insert(destination.clone());
+                                if let Some(parent) = destination.parent() {
+                                    batched_invalidate_path_dir.insert(PathBuf::from(parent));
+                                }
                             }
                         }
                         Ok(DebouncedEvent::Rescan) => {
@@ -215,7 +385,9 @@ impl DiskFileSystem {
                     for path in paths {
                         let key = path_to_key(path);
                         if let Some(invalidators) = invalidator_map.remove(&key) {
-                            invalidators.into_iter().for_each(|i| i.invalidate());
+                            invalidators
+                                .into_iter()
+                                .for_each(|i| i.invalidate_with_reason(format!("{key} changed")));
                         }
                     }
                 }
@@ -223,12 +395,14 @@ impl DiskFileSystem {
                     invalidator_map: &mut HashMap<String, HashSet<Invalidator>>,
                     paths: &mut HashSet<PathBuf>,
                 ) {
-                    for (_, invalidators) in invalidator_map.drain_filter(|key, _| {
+                    for (key, invalidators) in invalidator_map.drain_filter(|key, _| {
                         paths
                             .iter()
                             .any(|path_key| key.starts_with(&path_to_key(path_key)))
                     }) {
-                        invalidators.into_iter().for_each(|i| i.invalidate());
+                        invalidators
+                            .into_iter()
+                            .for_each(|i| i.invalidate_with_reason(format!("{key} changed")));
                     }
                     paths.clear()
                 }
@@ -274,6 +448,52 @@ impl DiskFileSystem {
     }
 }
 
+fn watch_directory(watcher: &mut RecommendedWatcher, directory: &Path) -> Result<()> {
+    if !WATCH_DIRECTORIES_INDIVIDUALLY {
+        return Ok(());
+    }
+    match watcher.watch(directory, RecursiveMode::NonRecursive) {
+        // The directory might have been removed in the meantime
+        Ok(()) | Err(notify::Error::PathNotFound) => Ok(()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Updates the ignore rules and the watched directories after `path` has
+/// been created, changed or removed.
+fn update_ignore(
+    ignore: &mut IgnoreMatcher,
+    watcher: &Mutex<Option<DiskWatcher>>,
+    root: &Path,
+    path: &Path,
+) {
+    let directory = if path.file_name() == Some(OsStr::new(".gitignore")) {
+        match path.parent() {
+            Some(parent) => parent,
+            None => return,
+        }
+    } else if path.is_dir() {
+        path
+    } else {
+        return;
+    };
+    // Walking loads the `.gitignore` files again and watches new directories
+    // and directories that are no longer ignored. The poll watcher scans all
+    // directories that aren't ignored anyway.
+    let mut watcher = watcher.lock().unwrap();
+    let result = ignore.walk(root, directory, &mut |directory| match watcher.as_mut() {
+        Some(DiskWatcher::Native(watcher)) => watch_directory(watcher, directory),
+        _ => Ok(()),
+    });
+    if let Err(err) = result {
+        tracing::warn!(
+            "failed to update the ignore rules and watched directories of {}: {:?}",
+            directory.display(),
+            err
+        );
+    }
+}
+
 pub fn path_to_key(path: impl AsRef<Path>) -> String {
     path.as_ref().to_string_lossy().to_string()
 }
@@ -281,18 +501,35 @@ pub fn path_to_key(path: impl AsRef<Path>) -> String {
 #[turbo_tasks::value_impl]
 impl DiskFileSystemVc {
     #[turbo_tasks::function]
-    pub async fn new(name: String, root: String) -> Result<Self> {
+    pub fn new(name: String, root: String) -> Self {
+        Self::new_with_options(name, root, Value::new(DiskFileSystemOptions::default()))
+    }
+
+    #[turbo_tasks::function]
+    pub async fn new_with_options(
+        name: String,
+        root: String,
+        options: Value<DiskFileSystemOptions>,
+    ) -> Result<Self> {
         mark_stateful();
         // create the directory for the filesystem on disk, if it doesn't exist
         fs::create_dir_all(&root).await?;
 
+        let ignore = options
+            .ignore
+            .iter()
+            .map(|glob| Glob::parse(glob).with_context(|| format!("parsing ignore glob {glob}")))
+            .collect::<Result<Vec<_>>>()?;
         let instance = DiskFileSystem {
             name,
             root,
+            ignore,
+            gitignore: options.gitignore,
+            watch_mode: options.watch_mode,
             mutex_map: Default::default(),
             invalidator_map: Arc::new(InvalidatorMap::new()),
             dir_invalidator_map: Arc::new(InvalidatorMap::new()),
-            watcher: Mutex::new(None),
+            watcher: Default::default(),
         };
 
         Ok(Self::cell(instance))
@@ -328,6 +565,7 @@ impl FileSystem for DiskFileSystem {
     async fn read_dir(&self, fs_path: FileSystemPathVc) -> Result<DirectoryContentVc> {
         let full_path = self.to_sys_path(fs_path).await?;
         self.register_invalidator(&full_path, false);
+        let ignore = self.ignore_matcher(fs_path).await?;
         let fs_path = fs_path.await?;
 
         // we use the sync std function here as it's a lot faster (600%) in
@@ -359,15 +597,27 @@ impl FileSystem for DiskFileSystem {
                 let file_name = path.file_name()?.to_str()?.to_string();
                 let path_to_root = sys_to_unix(path.strip_prefix(&self.root).ok()?.to_str()?);
 
+                let file_type = match e.file_type() {
+                    Ok(t) => t,
+                    Err(err) => return Some(Err(err.into())),
+                };
+                if let Some(ignore) = &ignore {
+                    if ignore.is_ignored(&path_to_root, file_type.is_dir()) {
+                        return None;
+                    }
+                }
+
                 let fs_path =
                     FileSystemPathVc::new_normalized(fs_path.fs, path_to_root.to_string());
 
-                let entry = match e.file_type() {
-                    Ok(t) if t.is_file() => DirectoryEntry::File(fs_path),
-                    Ok(t) if t.is_dir() => DirectoryEntry::Directory(fs_path),
-                    Ok(t) if t.is_symlink() => DirectoryEntry::Symlink(fs_path),
-                    Ok(_) => DirectoryEntry::Other(fs_path),
-                    Err(err) => return Some(Err(err.into())),
+                let entry = if file_type.is_file() {
+                    DirectoryEntry::File(fs_path)
+                } else if file_type.is_dir() {
+                    DirectoryEntry::Directory(fs_path)
+                } else if file_type.is_symlink() {
+                    DirectoryEntry::Symlink(fs_path)
+                } else {
+                    DirectoryEntry::Other(fs_path)
                 };
 
                 Some(anyhow::Ok((file_name, entry)))
@@ -858,6 +1108,17 @@ impl FileSystemPathVc {
         read_glob(self, glob, include_dot_files)
     }
 
+    /// Like [FileSystemPathVc::read_glob], but matches a list of globs where
+    /// globs starting with `!` exclude paths.
+    #[turbo_tasks::function]
+    pub async fn read_glob_list(
+        self,
+        globs: GlobListVc,
+        include_dot_files: bool,
+    ) -> ReadGlobResultVc {
+        read_glob_list(self, globs, include_dot_files)
+    }
+
     #[turbo_tasks::function]
     pub fn root(self) -> Self {
         self.fs().root()
@@ -1480,7 +1741,7 @@ impl FileContentVc {
 /// A file's content interpreted as a JSON value.
 #[turbo_tasks::value(shared, serialization = "none")]
 pub enum FileJsonContent {
-    Content(Value),
+    Content(serde_json::Value),
     Unparseable,
     NotFound,
 }
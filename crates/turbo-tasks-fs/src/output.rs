@@ -0,0 +1,461 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    io::{self, ErrorKind, Read},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use tokio::fs;
+use turbo_tasks::CompletionVc;
+use turbo_tasks_hash::hash_xxh3_hash64;
+
+use crate::{
+    retry::retry_blocking, util::unix_to_sys, DirectoryContent, DirectoryEntry, DiskFileSystem,
+    DiskFileSystemVc, FileContent, FileContentReadRef, FileContentVc, FileSystemPathVc,
+    LinkContent, LinkContentReadRef, LinkContentVc, LinkType, Permissions,
+};
+
+/// The content of an entry of [OutputFiles].
+#[turbo_tasks::value(shared)]
+#[derive(Clone, Copy, Debug)]
+pub enum OutputContent {
+    File(FileContentVc),
+    Link(LinkContentVc),
+}
+
+/// The complete contents of an output directory, see [write_output_files].
+#[turbo_tasks::value(transparent)]
+pub struct OutputFiles(Vec<(FileSystemPathVc, OutputContent)>);
+
+/// The changes [write_output_files] made to an output directory, by paths
+/// relative to it.
+#[turbo_tasks::value(shared)]
+#[derive(Debug, Default)]
+pub struct OutputDiff {
+    pub added: Vec<String>,
+    pub changed: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl OutputDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl OutputDiffVc {
+    /// A [CompletionVc] which only changes when files have been written.
+    #[turbo_tasks::function]
+    pub async fn completion(self) -> Result<CompletionVc> {
+        Ok(if self.await?.is_empty() {
+            CompletionVc::unchanged()
+        } else {
+            CompletionVc::new()
+        })
+    }
+}
+
+#[derive(PartialEq)]
+enum ResolvedContent {
+    File(FileContentReadRef),
+    Link(LinkContentReadRef),
+}
+
+impl ResolvedContent {
+    async fn new(content: OutputContent) -> Result<Self> {
+        Ok(match content {
+            OutputContent::File(file) => ResolvedContent::File(file.await?),
+            OutputContent::Link(link) => ResolvedContent::Link(link.await?),
+        })
+    }
+
+    fn exists(&self) -> bool {
+        match self {
+            ResolvedContent::File(file) => !matches!(**file, FileContent::NotFound),
+            ResolvedContent::Link(link) => !matches!(**link, LinkContent::NotFound),
+        }
+    }
+}
+
+/// The files and symlinks below `directory`, by their path relative to it.
+async fn read_output_directory(
+    directory: FileSystemPathVc,
+) -> Result<BTreeMap<String, ResolvedContent>> {
+    let mut entries = BTreeMap::new();
+    let mut queue = vec![(String::new(), directory)];
+    while let Some((prefix, directory)) = queue.pop() {
+        let DirectoryContent::Entries(directory_entries) = &*directory.read_dir().await? else {
+            continue;
+        };
+        for (name, entry) in directory_entries.iter() {
+            let path = format!("{prefix}{name}");
+            match *entry {
+                DirectoryEntry::File(file) => {
+                    entries.insert(path, ResolvedContent::File(file.read().await?));
+                }
+                DirectoryEntry::Symlink(link) => {
+                    entries.insert(path, ResolvedContent::Link(link.read_link().await?));
+                }
+                DirectoryEntry::Directory(directory) => {
+                    queue.push((format!("{path}/"), directory));
+                }
+                DirectoryEntry::Other(_) | DirectoryEntry::Error => {}
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// Replaces the contents of `directory` with `files`, which must be inside of
+/// it. Files that are not part of `files` are removed.
+///
+/// On a [DiskFileSystem], the files are written into a staging directory next
+/// to `directory` first, which then replaces `directory` at once, so readers
+/// never see a partially written output. Unchanged files are hard linked into
+/// the staging directory instead of being written again. The old output is
+/// compared by hashes of what is on disk, without tracking the reads, as the
+/// replacement would invalidate this function otherwise. Other file systems
+/// are written file by file.
+#[turbo_tasks::function]
+pub async fn write_output_files(
+    directory: FileSystemPathVc,
+    files: OutputFilesVc,
+) -> Result<OutputDiffVc> {
+    let directory_path = directory.await?;
+    let mut new_entries = BTreeMap::new();
+    for (path, content) in files.await?.iter() {
+        let path = path.await?;
+        let Some(relative) = directory_path.get_path_to(&path) else {
+            bail!(
+                "{} is not inside of the output directory {}",
+                path.path,
+                directory_path.path
+            );
+        };
+        let resolved = ResolvedContent::new(*content).await?;
+        if resolved.exists() {
+            new_entries.insert(relative.to_string(), (*content, resolved));
+        }
+    }
+    if let Some(disk_fs) = DiskFileSystemVc::resolve_from(directory.fs()).await? {
+        let diff = disk_fs
+            .await?
+            .replace_directory(directory, &new_entries)
+            .await
+            .with_context(|| format!("writing output directory {}", directory_path.path))?;
+        return Ok(diff.cell());
+    }
+
+    let old_entries = read_output_directory(directory).await?;
+    let diff = diff_entries(&old_entries, &new_entries, |old, (_, new)| old == new);
+    for path in diff.added.iter().chain(diff.changed.iter()) {
+        match new_entries[path].0 {
+            OutputContent::File(file) => {
+                directory.join(path).write(file).await?;
+            }
+            OutputContent::Link(link) => {
+                directory.join(path).write_link(link).await?;
+            }
+        }
+    }
+    for path in diff.removed.iter() {
+        match &old_entries[path] {
+            ResolvedContent::File(_) => {
+                directory
+                    .join(path)
+                    .write(FileContent::NotFound.cell())
+                    .await?;
+            }
+            ResolvedContent::Link(_) => {
+                directory
+                    .join(path)
+                    .write_link(LinkContent::NotFound.cell())
+                    .await?;
+            }
+        }
+    }
+    Ok(diff.cell())
+}
+
+/// Compares the entries of an output directory before and after writing.
+fn diff_entries<O, N>(
+    old_entries: &BTreeMap<String, O>,
+    new_entries: &BTreeMap<String, N>,
+    unchanged: impl Fn(&O, &N) -> bool,
+) -> OutputDiff {
+    let mut diff = OutputDiff::default();
+    for (path, content) in new_entries.iter() {
+        match old_entries.get(path) {
+            None => diff.added.push(path.clone()),
+            Some(old_content) if !unchanged(old_content, content) => {
+                diff.changed.push(path.clone())
+            }
+            Some(_) => {}
+        }
+    }
+    for path in old_entries.keys() {
+        if !new_entries.contains_key(path) {
+            diff.removed.push(path.clone());
+        }
+    }
+    diff
+}
+
+/// A file or symlink of an output directory on disk.
+#[derive(PartialEq, Eq)]
+enum DiskEntry {
+    File { hash: u64, permissions: Permissions },
+    Link(PathBuf),
+}
+
+/// The files and symlinks below `directory` on disk, by their path relative to
+/// it. Unlike [read_output_directory], this doesn't register the reads, which
+/// would invalidate the writing task with its own writes.
+fn read_disk_output(directory: &Path) -> io::Result<BTreeMap<String, DiskEntry>> {
+    let mut entries = BTreeMap::new();
+    let mut queue = vec![(String::new(), directory.to_path_buf())];
+    while let Some((prefix, directory)) = queue.pop() {
+        let dir_entries = match std::fs::read_dir(directory) {
+            Ok(dir_entries) => dir_entries,
+            Err(err) if err.kind() == ErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
+        };
+        for dir_entry in dir_entries {
+            let dir_entry = dir_entry?;
+            let path = format!("{prefix}{}", dir_entry.file_name().to_string_lossy());
+            let file_type = dir_entry.file_type()?;
+            if file_type.is_symlink() {
+                entries.insert(path, DiskEntry::Link(std::fs::read_link(dir_entry.path())?));
+            } else if file_type.is_dir() {
+                queue.push((format!("{path}/"), dir_entry.path()));
+            } else if file_type.is_file() {
+                let content = std::fs::read(dir_entry.path())?;
+                entries.insert(
+                    path,
+                    DiskEntry::File {
+                        hash: hash_xxh3_hash64(content.as_slice()),
+                        permissions: dir_entry.metadata()?.permissions().into(),
+                    },
+                );
+            }
+        }
+    }
+    Ok(entries)
+}
+
+impl DiskFileSystem {
+    /// The target of a symlink on disk.
+    fn link_target_path(&self, link_target: &str, link_type: LinkType) -> PathBuf {
+        if link_type.contains(LinkType::ABSOLUTE) {
+            Path::new(&self.root).join(unix_to_sys(link_target).as_ref())
+        } else {
+            PathBuf::from(unix_to_sys(link_target).as_ref())
+        }
+    }
+
+    /// How `content` is stored on disk.
+    fn disk_entry(&self, content: &ResolvedContent) -> Result<Option<DiskEntry>> {
+        Ok(match content {
+            ResolvedContent::File(file) => match &**file {
+                FileContent::Content(file) => {
+                    let mut content = Vec::with_capacity(file.content().len());
+                    file.read().read_to_end(&mut content)?;
+                    Some(DiskEntry::File {
+                        hash: hash_xxh3_hash64(content.as_slice()),
+                        permissions: file.meta.permissions,
+                    })
+                }
+                FileContent::NotFound => None,
+            },
+            ResolvedContent::Link(link) => match &**link {
+                LinkContent::Link {
+                    target: link_target,
+                    link_type,
+                } => Some(DiskEntry::Link(
+                    self.link_target_path(link_target, *link_type),
+                )),
+                _ => None,
+            },
+        })
+    }
+
+    async fn replace_directory(
+        &self,
+        directory: FileSystemPathVc,
+        entries: &BTreeMap<String, (OutputContent, ResolvedContent)>,
+    ) -> Result<OutputDiff> {
+        let target = self.to_sys_path(directory).await?;
+        // Held while staging, too, as concurrent replacements of the same
+        // directory would share the staging directory
+        let _lock = self.mutex_map.lock(target.clone()).await;
+        let old_entries = retry_blocking(&target, read_disk_output).await?;
+        let mut new_entries = BTreeMap::new();
+        for (path, (_, content)) in entries.iter() {
+            new_entries.insert(path.clone(), self.disk_entry(content)?);
+        }
+        let diff = diff_entries(&old_entries, &new_entries, |old, new| {
+            new.as_ref() == Some(old)
+        });
+        if diff.is_empty() {
+            return Ok(diff);
+        }
+
+        let name = target
+            .file_name()
+            .ok_or_else(|| anyhow!("can't replace the root of a filesystem"))?
+            .to_string_lossy();
+        let staging = target.with_file_name(format!(".{name}.staging-{}", std::process::id()));
+        remove_dir_if_exists(&staging).await?;
+
+        let written = diff
+            .added
+            .iter()
+            .chain(diff.changed.iter())
+            .collect::<HashSet<_>>();
+        for (path, (_, content)) in entries.iter() {
+            let full_path = staging.join(&*unix_to_sys(path));
+            if let Some(parent) = full_path.parent() {
+                fs::create_dir_all(parent).await?;
+            }
+            if !written.contains(path) {
+                let old_path = target.join(&*unix_to_sys(path));
+                let is_symlink = fs::symlink_metadata(&old_path)
+                    .await
+                    .map_or(false, |meta| meta.file_type().is_symlink());
+                if !is_symlink && fs::hard_link(&old_path, &full_path).await.is_ok() {
+                    continue;
+                }
+            }
+            match content {
+                ResolvedContent::File(file) => {
+                    let FileContent::Content(file) = &**file else {
+                        continue;
+                    };
+                    let mut f = fs::File::create(&full_path).await?;
+                    tokio::io::copy(&mut file.read(), &mut f).await?;
+                    #[cfg(target_family = "unix")]
+                    f.set_permissions(file.meta.permissions.into()).await?;
+                }
+                ResolvedContent::Link(link) => {
+                    let LinkContent::Link { target: link_target, link_type } = &**link else {
+                        continue;
+                    };
+                    let target_path = self.link_target_path(link_target, *link_type);
+                    create_symlink(&target_path, &full_path, *link_type)?;
+                }
+            }
+        }
+
+        if fs::symlink_metadata(&target).await.is_ok() {
+            exchange(&staging, &target)
+                .with_context(|| format!("replacing {}", target.display()))?;
+            // The staging directory contains the old output now
+            remove_dir_if_exists(&staging).await?;
+        } else {
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent).await?;
+            }
+            fs::rename(&staging, &target).await?;
+        }
+        // Without a watcher, the reads of the old output need to be invalidated
+        // here. With a watcher this happens twice, which is harmless.
+        self.invalidate_path_and_children(&target);
+        Ok(diff)
+    }
+}
+
+async fn remove_dir_if_exists(path: &Path) -> io::Result<()> {
+    match retry_blocking(path, |path| std::fs::remove_dir_all(path)).await {
+        Err(err) if err.kind() != ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+fn create_symlink(target: &Path, path: &Path, link_type: LinkType) -> io::Result<()> {
+    #[cfg(not(target_family = "windows"))]
+    {
+        let _ = link_type;
+        std::os::unix::fs::symlink(target, path)
+    }
+    #[cfg(target_family = "windows")]
+    {
+        if link_type.contains(LinkType::DIRECTORY) {
+            std::os::windows::fs::symlink_dir(target, path)
+        } else {
+            std::os::windows::fs::symlink_file(target, path)
+        }
+    }
+}
+
+/// Exchanges the directories `a` and `b`. This is atomic on Linux. Elsewhere
+/// `b` is moved out of the way first, so it doesn't exist for a moment.
+fn exchange(a: &Path, b: &Path) -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+        // Not exported by all versions of libc
+        const RENAME_EXCHANGE: libc::c_uint = 1 << 1;
+        let a_c = CString::new(a.as_os_str().as_bytes())?;
+        let b_c = CString::new(b.as_os_str().as_bytes())?;
+        // SAFETY: Both paths are valid null terminated strings.
+        let result = unsafe {
+            libc::syscall(
+                libc::SYS_renameat2,
+                libc::AT_FDCWD,
+                a_c.as_ptr(),
+                libc::AT_FDCWD,
+                b_c.as_ptr(),
+                RENAME_EXCHANGE,
+            )
+        };
+        if result == 0 {
+            return Ok(());
+        }
+        let err = io::Error::last_os_error();
+        // Old kernels and some file systems don't support exchanging
+        if !matches!(err.raw_os_error(), Some(libc::ENOSYS | libc::EINVAL)) {
+            return Err(err);
+        }
+    }
+    exchange_by_renaming(a, b)
+}
+
+/// Exchanges the directories `a` and `b` by moving `b` out of the way first.
+/// When `a` can't be moved, `b` is moved back.
+fn exchange_by_renaming(a: &Path, b: &Path) -> io::Result<()> {
+    let mut moved = b.as_os_str().to_owned();
+    moved.push(".old");
+    let moved = PathBuf::from(moved);
+    std::fs::rename(b, &moved)?;
+    if let Err(err) = std::fs::rename(a, b) {
+        std::fs::rename(&moved, b)?;
+        return Err(err);
+    }
+    std::fs::rename(&moved, a)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::exchange_by_renaming;
+
+    #[test]
+    fn failed_exchange_restores_the_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = dir.path().join("a");
+        let b = dir.path().join("b");
+        std::fs::create_dir(&a).unwrap();
+        std::fs::write(a.join("file"), "a").unwrap();
+        std::fs::create_dir(&b).unwrap();
+        std::fs::write(b.join("file"), "b").unwrap();
+        exchange_by_renaming(&a, &b).unwrap();
+        assert_eq!(std::fs::read_to_string(a.join("file")).unwrap(), "b");
+        assert_eq!(std::fs::read_to_string(b.join("file")).unwrap(), "a");
+
+        std::fs::remove_dir_all(&a).unwrap();
+        assert!(exchange_by_renaming(&a, &b).is_err());
+        assert_eq!(std::fs::read_to_string(b.join("file")).unwrap(), "a");
+        assert!(!dir.path().join("b.old").exists());
+    }
+}
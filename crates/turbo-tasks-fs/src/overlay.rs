@@ -0,0 +1,285 @@
+use std::collections::HashSet;
+
+use anyhow::{bail, Result};
+use auto_hash_map::AutoMap;
+use turbo_tasks::{
+    primitives::{BoolVc, StringVc},
+    CompletionVc, ValueToString, ValueToStringVc,
+};
+
+use crate::{
+    DirectoryContent, DirectoryContentVc, DirectoryEntry, File, FileContent, FileContentVc,
+    FileMetaVc, FileSystem, FileSystemEntryType, FileSystemPathVc, FileSystemVc, LinkContent,
+    LinkContentVc,
+};
+
+/// A file named `.wh.<name>` in a layer hides `<name>` in all layers below.
+const WHITEOUT_PREFIX: &str = ".wh.";
+/// A file named like this in a directory of a layer hides the contents of the
+/// directory in all layers below.
+const OPAQUE_MARKER: &str = ".wh..wh..opq";
+
+fn whiteout_path(path: &str) -> String {
+    match path.rsplit_once('/') {
+        Some((parent, name)) => format!("{parent}/{WHITEOUT_PREFIX}{name}"),
+        None => format!("{WHITEOUT_PREFIX}{path}"),
+    }
+}
+
+fn opaque_marker_path(directory: &str) -> String {
+    if directory.is_empty() {
+        OPAQUE_MARKER.to_string()
+    } else {
+        format!("{directory}/{OPAQUE_MARKER}")
+    }
+}
+
+fn is_whiteout(path: &str) -> bool {
+    let name = path.rsplit_once('/').map_or(path, |(_, name)| name);
+    name.starts_with(WHITEOUT_PREFIX)
+}
+
+/// `path` and its parent directories, e.g. `a/b/c`, `a/b` and `a`.
+fn path_and_ancestors(path: &str) -> impl Iterator<Item = &str> {
+    std::iter::successors((!path.is_empty()).then_some(path), |path| {
+        path.rsplit_once('/').map(|(parent, _)| parent)
+    })
+}
+
+async fn exists_in(layer: FileSystemVc, path: &str) -> Result<bool> {
+    Ok(*layer.root().join(path).get_type().await? != FileSystemEntryType::NotFound)
+}
+
+/// Whether `layer` hides `path` in the layers below it, by a whiteout of the
+/// path or one of its parents, or by an opaque parent directory.
+#[turbo_tasks::function]
+async fn hides_lower(layer: FileSystemVc, path: &str) -> Result<BoolVc> {
+    for current in path_and_ancestors(path) {
+        let parent = current.rsplit_once('/').map_or("", |(parent, _)| parent);
+        if exists_in(layer, &whiteout_path(current)).await?
+            || exists_in(layer, &opaque_marker_path(parent)).await?
+        {
+            return Ok(BoolVc::cell(true));
+        }
+    }
+    Ok(BoolVc::cell(false))
+}
+
+/// A [FileSystem] which stacks several [FileSystem]s. A path is read from the
+/// topmost layer that contains it, directory contents are merged and writes
+/// go to the topmost layer.
+///
+/// Like with overlay file systems of container images, deleting a path that
+/// exists in a lower layer creates a `.wh.<name>` whiteout file in the
+/// topmost layer, which hides the path in the lower layers. A `.wh..wh..opq`
+/// file hides the contents of its directory in the lower layers, which is
+/// created when a deleted directory is written to again.
+#[turbo_tasks::value]
+pub struct OverlayFileSystem {
+    /// The layers, topmost first.
+    layers: Vec<FileSystemVc>,
+}
+
+#[turbo_tasks::value_impl]
+impl OverlayFileSystemVc {
+    /// Creates an [OverlayFileSystem] of `layers`, topmost first. Writes go
+    /// to the first layer.
+    #[turbo_tasks::function]
+    pub fn new(layers: Vec<FileSystemVc>) -> Self {
+        OverlayFileSystem { layers }.cell()
+    }
+
+    /// Converts a path of one of the layers to the same path in this
+    /// [FileSystem].
+    #[turbo_tasks::function]
+    pub async fn convert_path(self, layer_path: FileSystemPathVc) -> Result<FileSystemPathVc> {
+        let layer_path = layer_path.await?;
+        if !self.await?.layers.contains(&layer_path.fs) {
+            bail!("path is not part of a layer of the overlay filesystem");
+        }
+        Ok(self.root().join(&layer_path.path))
+    }
+}
+
+/// The path in the topmost of `layers` that contains `path`, unless `path` is
+/// hidden by a whiteout.
+async fn find(layers: &[FileSystemVc], path: &str) -> Result<Option<FileSystemPathVc>> {
+    if is_whiteout(path) {
+        return Ok(None);
+    }
+    for layer in layers.iter() {
+        if exists_in(*layer, path).await? {
+            return Ok(Some(layer.root().join(path)));
+        }
+        if *hides_lower(*layer, path).await? {
+            break;
+        }
+    }
+    Ok(None)
+}
+
+impl OverlayFileSystem {
+    fn top(&self) -> Result<FileSystemVc> {
+        match self.layers.first() {
+            Some(top) => Ok(*top),
+            None => bail!("the overlay filesystem has no layers"),
+        }
+    }
+
+    /// Removes the whiteouts which hide `path` in the lower layers, before
+    /// `path` is written to the topmost layer. Parent directories that have
+    /// been deleted become opaque, so their old contents stay hidden.
+    async fn unhide(&self, top: FileSystemVc, path: &str) -> Result<()> {
+        let root = top.root();
+        for current in path_and_ancestors(path) {
+            let whiteout = whiteout_path(current);
+            if !exists_in(top, &whiteout).await? {
+                continue;
+            }
+            root.join(&whiteout)
+                .write(FileContent::NotFound.cell())
+                .await?;
+            if current != path {
+                root.join(&opaque_marker_path(current))
+                    .write(File::from("").into())
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl FileSystem for OverlayFileSystem {
+    #[turbo_tasks::function]
+    async fn read(&self, path: FileSystemPathVc) -> Result<FileContentVc> {
+        Ok(match find(&self.layers, &path.await?.path).await? {
+            Some(layer_path) => layer_path.read(),
+            None => FileContent::NotFound.cell(),
+        })
+    }
+
+    #[turbo_tasks::function]
+    async fn read_link(&self, path: FileSystemPathVc) -> Result<LinkContentVc> {
+        Ok(match find(&self.layers, &path.await?.path).await? {
+            Some(layer_path) => layer_path.read_link(),
+            None => LinkContent::NotFound.cell(),
+        })
+    }
+
+    #[turbo_tasks::function]
+    async fn read_dir(&self, path: FileSystemPathVc) -> Result<DirectoryContentVc> {
+        let path_str = &path.await?.path;
+        if is_whiteout(path_str) {
+            return Ok(DirectoryContentVc::not_found());
+        }
+        let mut found = false;
+        let mut entries = AutoMap::new();
+        // Names hidden by whiteouts of the layers above
+        let mut hidden = HashSet::new();
+        for layer in self.layers.iter() {
+            let layer_content = layer.root().join(path_str).read_dir().await?;
+            let mut opaque = false;
+            if let DirectoryContent::Entries(layer_entries) = &*layer_content {
+                found = true;
+                let mut whiteouts = Vec::new();
+                for (name, entry) in layer_entries.iter() {
+                    if name == OPAQUE_MARKER {
+                        opaque = true;
+                        continue;
+                    }
+                    if let Some(hidden_name) = name.strip_prefix(WHITEOUT_PREFIX) {
+                        whiteouts.push(hidden_name.to_string());
+                        continue;
+                    }
+                    if hidden.contains(name) || entries.contains_key(name) {
+                        continue;
+                    }
+                    let entry_path = path.join(name);
+                    let entry = match entry {
+                        DirectoryEntry::File(_) => DirectoryEntry::File(entry_path),
+                        DirectoryEntry::Directory(_) => DirectoryEntry::Directory(entry_path),
+                        DirectoryEntry::Symlink(_) => DirectoryEntry::Symlink(entry_path),
+                        DirectoryEntry::Other(_) => DirectoryEntry::Other(entry_path),
+                        DirectoryEntry::Error => DirectoryEntry::Error,
+                    };
+                    entries.insert(name.clone(), entry);
+                }
+                hidden.extend(whiteouts);
+            }
+            if opaque || *hides_lower(*layer, path_str).await? {
+                break;
+            }
+        }
+        Ok(if found {
+            DirectoryContentVc::new(entries)
+        } else {
+            DirectoryContentVc::not_found()
+        })
+    }
+
+    #[turbo_tasks::function]
+    async fn write(&self, path: FileSystemPathVc, content: FileContentVc) -> Result<CompletionVc> {
+        let path_str = &path.await?.path;
+        if is_whiteout(path_str) {
+            bail!("{path_str} is reserved for whiteouts of the overlay filesystem");
+        }
+        let top = self.top()?;
+        let top_path = top.root().join(path_str);
+        match &*content.await? {
+            FileContent::NotFound => {
+                let visible_below = !*hides_lower(top, path_str).await?
+                    && find(&self.layers[1..], path_str).await?.is_some();
+                top_path.write(content).await?;
+                // Hides the path in the lower layers
+                if visible_below {
+                    top.root()
+                        .join(&whiteout_path(path_str))
+                        .write(File::from("").into())
+                        .await?;
+                }
+            }
+            FileContent::Content(_) => {
+                self.unhide(top, path_str).await?;
+                top_path.write(content).await?;
+            }
+        }
+        Ok(CompletionVc::new())
+    }
+
+    #[turbo_tasks::function]
+    async fn write_link(
+        &self,
+        path: FileSystemPathVc,
+        target: LinkContentVc,
+    ) -> Result<CompletionVc> {
+        let path_str = &path.await?.path;
+        if is_whiteout(path_str) {
+            bail!("{path_str} is reserved for whiteouts of the overlay filesystem");
+        }
+        let top = self.top()?;
+        self.unhide(top, path_str).await?;
+        top.root().join(path_str).write_link(target).await?;
+        Ok(CompletionVc::new())
+    }
+
+    #[turbo_tasks::function]
+    async fn metadata(&self, path: FileSystemPathVc) -> Result<FileMetaVc> {
+        match find(&self.layers, &path.await?.path).await? {
+            Some(layer_path) => Ok(layer_path.metadata()),
+            None => bail!("path not found, can't read metadata"),
+        }
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl ValueToString for OverlayFileSystem {
+    #[turbo_tasks::function]
+    async fn to_string(&self) -> Result<StringVc> {
+        let mut names = Vec::with_capacity(self.layers.len());
+        for layer in self.layers.iter() {
+            names.push(layer.to_string().await?.clone_value());
+        }
+        Ok(StringVc::cell(format!("overlay({})", names.join(", "))))
+    }
+}
@@ -0,0 +1,303 @@
+use std::{
+    collections::HashMap,
+    fs::{self, Metadata},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{SendError, Sender},
+        Arc,
+    },
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use notify::DebouncedEvent;
+use turbo_tasks::spawn_thread;
+use turbo_tasks_hash::hash_xxh3_hash64;
+
+use crate::ignore::IgnoreMatcher;
+
+/// The state of a path as seen by a scan.
+#[derive(PartialEq, Eq)]
+struct PollEntry {
+    is_directory: bool,
+    modified: Option<SystemTime>,
+    len: u64,
+    /// Only computed when contents are compared.
+    hash: Option<u64>,
+}
+
+/// Directories skipped with `skip_dependencies`.
+const DEPENDENCY_DIRECTORIES: &[&str] = &["node_modules", ".git"];
+
+struct Scanner {
+    root: PathBuf,
+    ignore: Option<IgnoreMatcher>,
+    compare_contents: bool,
+    skip_dependencies: bool,
+}
+
+impl Scanner {
+    fn scan(&mut self) -> HashMap<PathBuf, PollEntry> {
+        let mut entries = HashMap::new();
+        let root = self.root.clone();
+        self.scan_directory(&root, &mut entries);
+        entries
+    }
+
+    fn scan_directory(&mut self, directory: &Path, entries: &mut HashMap<PathBuf, PollEntry>) {
+        if let Some(ignore) = &mut self.ignore {
+            ignore.load_gitignore_file(&self.root, directory);
+        }
+        // The directory might have been removed in the meantime
+        let Ok(dir_entries) = fs::read_dir(directory) else {
+            return;
+        };
+        for dir_entry in dir_entries.flatten() {
+            // Symlinks are not followed, like with native events
+            let Ok(metadata) = dir_entry.metadata() else {
+                continue;
+            };
+            let path = dir_entry.path();
+            let is_directory = metadata.is_dir();
+            if is_directory
+                && self.skip_dependencies
+                && DEPENDENCY_DIRECTORIES
+                    .iter()
+                    .any(|name| dir_entry.file_name() == *name)
+            {
+                continue;
+            }
+            if let Some(ignore) = &self.ignore {
+                if ignore.is_ignored_path(&self.root, &path, is_directory) {
+                    continue;
+                }
+            }
+            let entry = self.entry(&path, &metadata);
+            entries.insert(path.clone(), entry);
+            if is_directory {
+                self.scan_directory(&path, entries);
+            }
+        }
+    }
+
+    fn entry(&self, path: &Path, metadata: &Metadata) -> PollEntry {
+        let hash = if self.compare_contents && metadata.is_file() {
+            fs::read(path)
+                .ok()
+                .map(|content| hash_xxh3_hash64(content.as_slice()))
+        } else {
+            None
+        };
+        PollEntry {
+            is_directory: metadata.is_dir(),
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+            hash,
+        }
+    }
+}
+
+/// Reports the differences between two scans like the native watcher does.
+/// Fails when the receiver is gone.
+fn send_changes(
+    previous: &HashMap<PathBuf, PollEntry>,
+    current: &HashMap<PathBuf, PollEntry>,
+    tx: &Sender<DebouncedEvent>,
+) -> Result<(), SendError<DebouncedEvent>> {
+    for (path, entry) in current.iter() {
+        let event = match previous.get(path) {
+            None => DebouncedEvent::Create(path.clone()),
+            Some(previous) if previous.is_directory != entry.is_directory => {
+                DebouncedEvent::Create(path.clone())
+            }
+            // The modification time of a directory changes with its entries,
+            // which are reported on their own
+            Some(previous) if !entry.is_directory && previous != entry => {
+                DebouncedEvent::Write(path.clone())
+            }
+            Some(_) => continue,
+        };
+        tx.send(event)?;
+    }
+    for path in previous.keys() {
+        if !current.contains_key(path) {
+            tx.send(DebouncedEvent::Remove(path.clone()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Watches a directory by scanning it periodically, for file systems that
+/// don't deliver native events, like network file systems or bind mounts of
+/// containers. Scanning stops when this is dropped.
+pub struct PollWatcher {
+    stopped: Arc<AtomicBool>,
+}
+
+impl PollWatcher {
+    /// Scans `root` every `interval` and sends the changes to `tx`. Files are
+    /// compared by modification time and size, and with `compare_contents`
+    /// also by a hash of their content, which catches changes that keep both.
+    /// With `skip_dependencies`, `node_modules` and `.git` directories are not
+    /// scanned.
+    pub fn start(
+        root: PathBuf,
+        ignore: Option<IgnoreMatcher>,
+        interval: Duration,
+        compare_contents: bool,
+        skip_dependencies: bool,
+        tx: Sender<DebouncedEvent>,
+    ) -> Self {
+        let stopped = Arc::new(AtomicBool::new(false));
+        let mut scanner = Scanner {
+            root,
+            ignore,
+            compare_contents,
+            skip_dependencies,
+        };
+        let thread_stopped = stopped.clone();
+        spawn_thread(move || {
+            // The first scan happens right away, so changes after starting
+            // are noticed
+            let mut entries = scanner.scan();
+            loop {
+                thread::sleep(interval);
+                // Dropping the sender stops the thread receiving the events
+                if thread_stopped.load(Ordering::Acquire) {
+                    break;
+                }
+                let current = scanner.scan();
+                if send_changes(&entries, &current, &tx).is_err() {
+                    break;
+                }
+                entries = current;
+            }
+        });
+        Self { stopped }
+    }
+}
+
+impl Drop for PollWatcher {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        path::PathBuf,
+        sync::mpsc::channel,
+        time::{Duration, SystemTime},
+    };
+
+    use notify::DebouncedEvent;
+
+    use super::{send_changes, PollEntry};
+
+    fn file(modified: u64, len: u64, hash: Option<u64>) -> PollEntry {
+        PollEntry {
+            is_directory: false,
+            modified: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(modified)),
+            len,
+            hash,
+        }
+    }
+
+    fn directory(modified: u64) -> PollEntry {
+        PollEntry {
+            is_directory: true,
+            modified: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(modified)),
+            len: 0,
+            hash: None,
+        }
+    }
+
+    fn changes(previous: Vec<(&str, PollEntry)>, current: Vec<(&str, PollEntry)>) -> Vec<String> {
+        let to_map = |entries: Vec<(&str, PollEntry)>| {
+            entries
+                .into_iter()
+                .map(|(path, entry)| (PathBuf::from(path), entry))
+                .collect::<HashMap<_, _>>()
+        };
+        let (tx, rx) = channel();
+        send_changes(&to_map(previous), &to_map(current), &tx).unwrap();
+        drop(tx);
+        let mut events = rx
+            .into_iter()
+            .map(|event| match event {
+                DebouncedEvent::Create(path) => format!("create {}", path.display()),
+                DebouncedEvent::Write(path) => format!("write {}", path.display()),
+                DebouncedEvent::Remove(path) => format!("remove {}", path.display()),
+                event => panic!("unexpected event {event:?}"),
+            })
+            .collect::<Vec<_>>();
+        events.sort();
+        events
+    }
+
+    #[test]
+    fn unchanged_entries_send_nothing() {
+        assert!(changes(
+            vec![("a", file(1, 1, Some(1))), ("d", directory(1))],
+            vec![("a", file(1, 1, Some(1))), ("d", directory(1))],
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn new_and_removed_entries() {
+        assert_eq!(
+            changes(
+                vec![("a", file(1, 1, None)), ("d", directory(1))],
+                vec![("b", file(1, 1, None)), ("e", directory(1))],
+            ),
+            ["create b", "create e", "remove a", "remove d"]
+        );
+    }
+
+    #[test]
+    fn changed_files_are_written() {
+        assert_eq!(
+            changes(
+                vec![
+                    ("modified", file(1, 1, None)),
+                    ("len", file(1, 1, None)),
+                    ("hash", file(1, 1, Some(1))),
+                ],
+                vec![
+                    ("modified", file(2, 1, None)),
+                    ("len", file(1, 2, None)),
+                    ("hash", file(1, 1, Some(2))),
+                ],
+            ),
+            ["write hash", "write len", "write modified"]
+        );
+    }
+
+    #[test]
+    fn directory_modification_times_are_ignored() {
+        assert!(changes(vec![("d", directory(1))], vec![("d", directory(2))]).is_empty());
+    }
+
+    #[test]
+    fn type_changes_are_created() {
+        assert_eq!(
+            changes(
+                vec![("a", file(1, 1, None)), ("d", directory(1))],
+                vec![("a", directory(1)), ("d", file(1, 1, None))],
+            ),
+            ["create a", "create d"]
+        );
+    }
+
+    #[test]
+    fn fails_without_receiver() {
+        let (tx, rx) = channel();
+        drop(rx);
+        let current = HashMap::from([(PathBuf::from("a"), file(1, 1, None))]);
+        assert!(send_changes(&HashMap::new(), &current, &tx).is_err());
+    }
+}
@@ -2,7 +2,10 @@ use std::collections::HashMap;
 
 use anyhow::Result;
 
-use crate::{glob::GlobVc, DirectoryContent, DirectoryEntry, FileSystemPathVc};
+use crate::{
+    glob::{GlobListVc, GlobVc},
+    DirectoryContent, DirectoryEntry, FileSystemPathVc,
+};
 
 #[turbo_tasks::value]
 #[derive(Default, Debug)]
@@ -21,14 +24,36 @@ pub async fn read_glob(
     glob: GlobVc,
     include_dot_files: bool,
 ) -> Result<ReadGlobResultVc> {
-    read_glob_internal("", directory, glob, include_dot_files).await
+    read_glob_internal(
+        "",
+        directory,
+        GlobListVc::from_glob(glob),
+        include_dot_files,
+    )
+    .await
+}
+
+/// Reads matches of a list of glob patterns, where patterns starting with `!`
+/// exclude paths. The directory is only traversed once for all patterns.
+/// Directories excluded with a pattern like `!dist/**` are not read, unless
+/// later patterns might include paths in them.
+///
+/// DETERMINISM: Result is in random order. Either sort result or do not depend
+/// on the order.
+#[turbo_tasks::function]
+pub async fn read_glob_list(
+    directory: FileSystemPathVc,
+    globs: GlobListVc,
+    include_dot_files: bool,
+) -> Result<ReadGlobResultVc> {
+    read_glob_internal("", directory, globs, include_dot_files).await
 }
 
 #[turbo_tasks::function]
 async fn read_glob_inner(
     prefix: String,
     directory: FileSystemPathVc,
-    glob: GlobVc,
+    glob: GlobListVc,
     include_dot_files: bool,
 ) -> Result<ReadGlobResultVc> {
     read_glob_internal(&prefix, directory, glob, include_dot_files).await
@@ -37,7 +62,7 @@ async fn read_glob_inner(
 async fn read_glob_internal(
     prefix: &str,
     directory: FileSystemPathVc,
-    glob: GlobVc,
+    glob: GlobListVc,
     include_dot_files: bool,
 ) -> Result<ReadGlobResultVc> {
     let dir = directory.read_dir().await?;
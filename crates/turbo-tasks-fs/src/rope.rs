@@ -476,7 +476,10 @@ impl Iterator for RopeReader {
                 Some(StackElem::Shared(r, i)) => (r, i),
             };
 
-            let el = inner[index].clone();
+            // Empty ropes, e.g. `Rope::default()`, have no elements.
+            let Some(el) = inner.get(index).cloned() else {
+                continue;
+            };
             index += 1;
             if index < inner.len() {
                 self.stack.push(StackElem::Shared(inner, index));
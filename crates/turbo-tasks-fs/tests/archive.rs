@@ -0,0 +1,219 @@
+use std::fs;
+
+use anyhow::{bail, Result};
+use flate2::{write::GzEncoder, Compression};
+use tempfile::TempDir;
+use turbo_tasks::TurboTasks;
+use turbo_tasks_fs::{
+    archive::ArchiveFileSystemVc, attach::AttachedFileSystemVc, DirectoryContent, DirectoryEntry,
+    DiskFileSystemVc, FileContent, FileMeta, FileSystem, FileSystemPathVc, LinkContent, LinkType,
+};
+use turbo_tasks_memory::MemoryBackend;
+
+/// An npm package tarball with a file, an executable, a symlink to a file and
+/// to a directory and a hard link.
+fn package_tarball() -> Vec<u8> {
+    let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+    let mut append = |path: &str, entry_type: tar::EntryType, mode: u32, content: &str| {
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(entry_type);
+        header.set_mode(mode);
+        if entry_type == tar::EntryType::Regular {
+            header.set_size(content.len() as u64);
+            builder
+                .append_data(&mut header, path, content.as_bytes())
+                .unwrap();
+        } else {
+            header.set_size(0);
+            builder.append_link(&mut header, path, content).unwrap();
+        }
+    };
+    append(
+        "package/index.js",
+        tar::EntryType::Regular,
+        0o644,
+        "export {}",
+    );
+    append(
+        "package/bin/cli.js",
+        tar::EntryType::Regular,
+        0o755,
+        "#!/usr/bin/env node",
+    );
+    append(
+        "package/main.js",
+        tar::EntryType::Symlink,
+        0o777,
+        "index.js",
+    );
+    append("package/tools", tar::EntryType::Symlink, 0o777, "bin");
+    append(
+        "package/copy.js",
+        tar::EntryType::Link,
+        0o644,
+        "package/index.js",
+    );
+    builder.into_inner().unwrap().finish().unwrap()
+}
+
+/// Runs `f` with a disk file system containing `package.tgz` and the archive
+/// file system of that tarball.
+async fn run<F>(f: impl FnOnce(FileSystemPathVc, ArchiveFileSystemVc) -> F + Send + 'static)
+where
+    F: std::future::Future<Output = Result<()>> + Send + 'static,
+{
+    turbo_tasks_fs::register();
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("package.tgz"), package_tarball()).unwrap();
+    fs::write(dir.path().join("app.js"), "import 'pkg'").unwrap();
+    let path = dir.path().to_string_lossy().into_owned();
+    let tt = TurboTasks::new(MemoryBackend::new());
+    tt.run_once(async move {
+        let root = DiskFileSystemVc::new("project".to_string(), path).root();
+        let archive = ArchiveFileSystemVc::npm_package("pkg".to_string(), root.join("package.tgz"));
+        f(root, archive).await
+    })
+    .await
+    .unwrap();
+}
+
+async fn read(path: FileSystemPathVc) -> Result<Option<String>> {
+    Ok(match &*path.read().await? {
+        FileContent::Content(file) => Some(file.content().to_str()?.into_owned()),
+        FileContent::NotFound => None,
+    })
+}
+
+async fn list(path: FileSystemPathVc) -> Result<Vec<(String, &'static str)>> {
+    let DirectoryContent::Entries(entries) = &*path.read_dir().await? else {
+        bail!("directory not found");
+    };
+    let mut entries = entries
+        .iter()
+        .map(|(name, entry)| {
+            let kind = match entry {
+                DirectoryEntry::File(_) => "file",
+                DirectoryEntry::Directory(_) => "directory",
+                DirectoryEntry::Symlink(_) => "symlink",
+                _ => "other",
+            };
+            (name.clone(), kind)
+        })
+        .collect::<Vec<_>>();
+    entries.sort();
+    Ok(entries)
+}
+
+#[tokio::test]
+async fn reads_files_and_directories() {
+    run(|_, archive| async move {
+        let root = archive.root();
+        assert_eq!(
+            read(root.join("index.js")).await?.as_deref(),
+            Some("export {}")
+        );
+        assert_eq!(read(root.join("missing.js")).await?, None);
+        assert_eq!(read(root.join("bin")).await?, None);
+        assert_eq!(
+            list(root).await?,
+            [
+                ("bin".to_string(), "directory"),
+                ("copy.js".to_string(), "file"),
+                ("index.js".to_string(), "file"),
+                ("main.js".to_string(), "symlink"),
+                ("tools".to_string(), "symlink"),
+            ]
+        );
+        assert_eq!(
+            list(root.join("bin")).await?,
+            [("cli.js".to_string(), "file")]
+        );
+        assert!(matches!(
+            &*root.join("index.js/nested").read_dir().await?,
+            DirectoryContent::NotFound
+        ));
+
+        let cli = root.join("bin/cli.js");
+        let FileContent::Content(file) = &*cli.read().await? else {
+            bail!("cli.js not found");
+        };
+        assert_eq!(*cli.metadata().await?, *file.meta());
+        assert_ne!(*cli.metadata().await?, FileMeta::default());
+        assert_eq!(*root.join("bin").metadata().await?, FileMeta::default());
+        assert!(root.join("missing.js").metadata().await.is_err());
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn follows_symlinks_and_hard_links() {
+    run(|_, archive| async move {
+        let root = archive.root();
+        assert_eq!(
+            read(root.join("main.js")).await?.as_deref(),
+            Some("export {}")
+        );
+        assert_eq!(
+            read(root.join("copy.js")).await?.as_deref(),
+            Some("export {}")
+        );
+        assert_eq!(
+            read(root.join("tools/cli.js")).await?.as_deref(),
+            Some("#!/usr/bin/env node")
+        );
+        assert_eq!(
+            list(root.join("tools")).await?,
+            [("cli.js".to_string(), "file")]
+        );
+
+        let LinkContent::Link { target, link_type } = &*root.join("main.js").read_link().await?
+        else {
+            bail!("main.js is not a link");
+        };
+        assert_eq!(target, "index.js");
+        assert_eq!(*link_type, LinkType::UNSET);
+        let LinkContent::Link { target, link_type } = &*root.join("tools").read_link().await?
+        else {
+            bail!("tools is not a link");
+        };
+        assert_eq!(target, "bin");
+        assert_eq!(*link_type, LinkType::DIRECTORY);
+        assert!(matches!(
+            &*root.join("index.js").read_link().await?,
+            LinkContent::NotFound
+        ));
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn mounts_into_another_file_system() {
+    run(|project, archive| async move {
+        let attached = AttachedFileSystemVc::new(project.join("node_modules/pkg"), archive.into());
+        let root = attached.root();
+        assert_eq!(
+            read(root.join("app.js")).await?.as_deref(),
+            Some("import 'pkg'")
+        );
+        assert_eq!(
+            read(root.join("node_modules/pkg/index.js"))
+                .await?
+                .as_deref(),
+            Some("export {}")
+        );
+        assert_eq!(
+            read(root.join("node_modules/pkg/tools/cli.js"))
+                .await?
+                .as_deref(),
+            Some("#!/usr/bin/env node")
+        );
+        assert_eq!(
+            list(root.join("node_modules/pkg/bin")).await?,
+            [("cli.js".to_string(), "file")]
+        );
+        Ok(())
+    })
+    .await
+}
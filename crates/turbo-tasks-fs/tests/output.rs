@@ -0,0 +1,109 @@
+use std::fs;
+
+use anyhow::Result;
+use tempfile::TempDir;
+use turbo_tasks::TurboTasks;
+use turbo_tasks_fs::{
+    output::{write_output_files, OutputContent, OutputDiff, OutputFilesVc},
+    overlay::OverlayFileSystemVc,
+    DiskFileSystemVc, File, FileSystem, FileSystemPathVc, FileSystemVc,
+};
+use turbo_tasks_memory::MemoryBackend;
+
+/// An output directory `out` with a previous output on disk.
+fn previous_output() -> TempDir {
+    turbo_tasks_fs::register();
+    let root = TempDir::new().unwrap();
+    let out = root.path().join("out");
+    fs::create_dir_all(out.join("nested")).unwrap();
+    fs::write(out.join("same.txt"), "same").unwrap();
+    fs::write(out.join("changed.txt"), "old").unwrap();
+    fs::write(out.join("nested/stale.txt"), "stale").unwrap();
+    root
+}
+
+fn disk_fs(root: &str) -> FileSystemVc {
+    DiskFileSystemVc::new("root".to_string(), root.to_string()).into()
+}
+
+fn output_files(out: FileSystemPathVc) -> OutputFilesVc {
+    let file = |path: &str, content: &str| {
+        (
+            out.join(path),
+            OutputContent::File(File::from(content).into()),
+        )
+    };
+    OutputFilesVc::cell(vec![
+        file("same.txt", "same"),
+        file("changed.txt", "new"),
+        file("added/new.txt", "added"),
+    ])
+}
+
+fn assert_diff(diff: &OutputDiff) {
+    assert_eq!(diff.added, ["added/new.txt"]);
+    assert_eq!(diff.changed, ["changed.txt"]);
+    assert_eq!(diff.removed, ["nested/stale.txt"]);
+}
+
+fn assert_output(root: &TempDir) {
+    let out = root.path().join("out");
+    assert_eq!(fs::read_to_string(out.join("same.txt")).unwrap(), "same");
+    assert_eq!(fs::read_to_string(out.join("changed.txt")).unwrap(), "new");
+    assert_eq!(
+        fs::read_to_string(out.join("added/new.txt")).unwrap(),
+        "added"
+    );
+    assert!(!out.join("nested/stale.txt").exists());
+}
+
+#[tokio::test]
+async fn replaces_output_on_disk() -> Result<()> {
+    let root = previous_output();
+    let root_path = root.path().to_string_lossy().into_owned();
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let path = root_path.clone();
+    let diff = tt
+        .run_once(async move {
+            let out = disk_fs(&path).root().join("out");
+            let diff = write_output_files(out, output_files(out));
+            assert_diff(&*diff.await?);
+            Ok(diff)
+        })
+        .await?;
+    assert_output(&root);
+
+    // Reading the old output must not invalidate the write, which would
+    // replace the diff with an empty one
+    tt.run_once(async move {
+        assert_diff(&*diff.strongly_consistent().await?);
+        Ok(())
+    })
+    .await?;
+
+    // Writing the same files again changes nothing
+    let tt = TurboTasks::new(MemoryBackend::new());
+    tt.run_once(async move {
+        let out = disk_fs(&root_path).root().join("out");
+        assert!(write_output_files(out, output_files(out)).await?.is_empty());
+        Ok(())
+    })
+    .await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn writes_file_by_file_to_other_filesystems() -> Result<()> {
+    let root = previous_output();
+    let root_path = root.path().to_string_lossy().into_owned();
+    let tt = TurboTasks::new(MemoryBackend::new());
+    tt.run_once(async move {
+        let overlay = OverlayFileSystemVc::new(vec![disk_fs(&root_path)]);
+        let out = overlay.root().join("out");
+        assert_diff(&*write_output_files(out, output_files(out)).await?);
+        Ok(())
+    })
+    .await?;
+    assert_output(&root);
+    Ok(())
+}
@@ -0,0 +1,163 @@
+use std::{fs, future::Future};
+
+use anyhow::{bail, Result};
+use tempfile::TempDir;
+use turbo_tasks::TurboTasks;
+use turbo_tasks_fs::{
+    overlay::OverlayFileSystemVc, DirectoryContent, DiskFileSystemVc, File, FileContent,
+    FileSystem, FileSystemPathVc,
+};
+use turbo_tasks_memory::MemoryBackend;
+
+/// Two directories on disk used as the layers of an overlay.
+struct Layers {
+    top: TempDir,
+    bottom: TempDir,
+}
+
+impl Layers {
+    fn new(top: &[(&str, &str)], bottom: &[(&str, &str)]) -> Self {
+        turbo_tasks_fs::register();
+        let layers = Layers {
+            top: TempDir::new().unwrap(),
+            bottom: TempDir::new().unwrap(),
+        };
+        for (dir, files) in [(&layers.top, top), (&layers.bottom, bottom)] {
+            for (path, content) in files {
+                let path = dir.path().join(path);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, content).unwrap();
+            }
+        }
+        layers
+    }
+
+    /// Runs `f` with the root of the overlay. Every run uses new turbo-tasks,
+    /// as writes are only noticed by other reads when the layers are watched.
+    async fn run<F>(&self, f: impl FnOnce(FileSystemPathVc) -> F + Send + 'static) -> Result<()>
+    where
+        F: Future<Output = Result<()>> + Send + 'static,
+    {
+        let tt = TurboTasks::new(MemoryBackend::new());
+        let top = self.top.path().to_string_lossy().into_owned();
+        let bottom = self.bottom.path().to_string_lossy().into_owned();
+        tt.run_once(async move {
+            let overlay = OverlayFileSystemVc::new(vec![
+                DiskFileSystemVc::new("top".to_string(), top).into(),
+                DiskFileSystemVc::new("bottom".to_string(), bottom).into(),
+            ]);
+            f(overlay.root()).await
+        })
+        .await
+    }
+
+    fn top_contains(&self, path: &str) -> bool {
+        self.top.path().join(path).exists()
+    }
+}
+
+async fn read(root: FileSystemPathVc, path: &str) -> Result<Option<String>> {
+    Ok(match &*root.join(path).read().await? {
+        FileContent::Content(file) => Some(file.content().to_str()?.into_owned()),
+        FileContent::NotFound => None,
+    })
+}
+
+async fn list(root: FileSystemPathVc, path: &str) -> Result<Vec<String>> {
+    let DirectoryContent::Entries(entries) = &*root.join(path).read_dir().await? else {
+        bail!("{path} not found");
+    };
+    let mut names = entries
+        .iter()
+        .map(|(name, _)| name.clone())
+        .collect::<Vec<_>>();
+    names.sort();
+    Ok(names)
+}
+
+#[tokio::test]
+async fn reads_from_the_topmost_layer() -> Result<()> {
+    let layers = Layers::new(
+        &[("a.txt", "top")],
+        &[("a.txt", "bottom"), ("b.txt", "bottom")],
+    );
+    layers
+        .run(|root| async move {
+            assert_eq!(read(root, "a.txt").await?.as_deref(), Some("top"));
+            assert_eq!(read(root, "b.txt").await?.as_deref(), Some("bottom"));
+            assert_eq!(read(root, "c.txt").await?, None);
+            Ok(())
+        })
+        .await
+}
+
+#[tokio::test]
+async fn merges_directories() -> Result<()> {
+    let layers = Layers::new(
+        &[("a.txt", "top"), ("dir/x.txt", "top")],
+        &[("b.txt", "bottom"), ("dir/y.txt", "bottom")],
+    );
+    layers
+        .run(|root| async move {
+            assert_eq!(list(root, "").await?, ["a.txt", "b.txt", "dir"]);
+            assert_eq!(list(root, "dir").await?, ["x.txt", "y.txt"]);
+            Ok(())
+        })
+        .await
+}
+
+#[tokio::test]
+async fn delete_writes_a_whiteout() -> Result<()> {
+    let layers = Layers::new(&[("a.txt", "top")], &[("b.txt", "bottom")]);
+    layers
+        .run(|root| async move {
+            root.join("b.txt")
+                .write(FileContent::NotFound.cell())
+                .await?;
+            Ok(())
+        })
+        .await?;
+    assert!(layers.top_contains(".wh.b.txt"));
+    assert!(layers.bottom.path().join("b.txt").exists());
+
+    layers
+        .run(|root| async move {
+            assert_eq!(read(root, "b.txt").await?, None);
+            // Whiteouts are not listed
+            assert_eq!(list(root, "").await?, ["a.txt"]);
+            Ok(())
+        })
+        .await
+}
+
+#[tokio::test]
+async fn rewritten_deleted_directory_is_opaque() -> Result<()> {
+    let layers = Layers::new(&[], &[("dir/old.txt", "bottom")]);
+    layers
+        .run(|root| async move {
+            root.join("dir").write(FileContent::NotFound.cell()).await?;
+            Ok(())
+        })
+        .await?;
+    assert!(layers.top_contains(".wh.dir"));
+
+    layers
+        .run(|root| async move {
+            root.join("dir/new.txt")
+                .write(File::from("top").into())
+                .await?;
+            Ok(())
+        })
+        .await?;
+    assert!(!layers.top_contains(".wh.dir"));
+    assert!(layers.top_contains("dir/.wh..wh..opq"));
+
+    layers
+        .run(|root| async move {
+            assert_eq!(list(root, "dir").await?, ["new.txt"]);
+            assert_eq!(read(root, "dir/new.txt").await?.as_deref(), Some("top"));
+            assert_eq!(read(root, "dir/old.txt").await?, None);
+            Ok(())
+        })
+        .await
+}
@@ -0,0 +1,49 @@
+use std::fs;
+
+use anyhow::Result;
+use tempfile::TempDir;
+use turbo_tasks::{TurboTasks, Value};
+use turbo_tasks_fs::{
+    glob::{GlobListVc, GlobOptions},
+    DiskFileSystemVc, FileSystem,
+};
+use turbo_tasks_memory::MemoryBackend;
+
+#[tokio::test]
+async fn excluded_directories_are_not_read() -> Result<()> {
+    turbo_tasks_fs::register();
+    let dir = TempDir::new()?;
+    for path in [
+        "index.ts",
+        "src/app.ts",
+        "node_modules/pkg/index.ts",
+        "src/node_modules/pkg/index.ts",
+    ] {
+        let path = dir.path().join(path);
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(path, "")?;
+    }
+    let root = dir.path().to_string_lossy().into_owned();
+
+    let tt = TurboTasks::new(MemoryBackend::new());
+    tt.run_once(async move {
+        let fs = DiskFileSystemVc::new("root".to_string(), root);
+        let globs = GlobListVc::include_exclude(
+            vec!["**/*.ts".to_string()],
+            vec!["**/node_modules".to_string()],
+            Value::new(GlobOptions::default()),
+        );
+        let result = fs.root().read_glob_list(globs, false).await?;
+
+        let mut files = result.results.keys().cloned().collect::<Vec<_>>();
+        files.sort();
+        assert_eq!(files, ["index.ts"]);
+        assert!(!result.inner.contains_key("node_modules"));
+
+        let src = result.inner.get("src").unwrap().await?;
+        assert_eq!(src.results.keys().collect::<Vec<_>>(), ["src/app.ts"]);
+        assert!(src.inner.is_empty());
+        Ok(())
+    })
+    .await
+}
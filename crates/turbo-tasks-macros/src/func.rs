@@ -3,7 +3,7 @@ use proc_macro_error::abort;
 use quote::quote;
 use syn::{
     punctuated::Punctuated, spanned::Spanned, FnArg, Pat, PatIdent, PatType, Receiver, ReturnType,
-    Signature, Token, Type, TypePath, TypeReference,
+    Signature, Token, Type, TypePath, TypeReference, TypeSlice,
 };
 
 use crate::util::*;
@@ -93,6 +93,15 @@ pub fn gen_native_function_code(
                 });
             }
             FnArg::Typed(PatType { pat, ty, .. }) => {
+                let original_pat = pat;
+                // Patterns like `(a, b)` can't be bound to the raw input, so it's bound to a
+                // generated identifier and destructured by the original function.
+                let pat = if let Pat::Ident(_) = &**pat {
+                    quote! { #pat }
+                } else {
+                    let ident = Ident::new(&format!("__arg{index}"), pat.span());
+                    quote! { #ident }
+                };
                 input_extraction.push(quote! {
                     let #pat = __iter
                         .next()
@@ -106,14 +115,12 @@ pub fn gen_native_function_code(
                     elem,
                 }) = &**ty
                 {
-                    let ty = if let Type::Path(TypePath { qself: None, path }) = &**elem {
-                        if path.is_ident("str") {
+                    let ty = match &**elem {
+                        Type::Path(TypePath { qself: None, path }) if path.is_ident("str") => {
                             quote! { String }
-                        } else {
-                            quote! { #elem }
                         }
-                    } else {
-                        quote! { #elem }
+                        Type::Slice(TypeSlice { elem, .. }) => quote! { Vec<#elem> },
+                        _ => quote! { #elem },
                     };
                     input_convert.push(quote! {
                         let #pat: #ty = turbo_tasks::FromTaskInput::try_from(#pat)?;
@@ -135,7 +142,7 @@ pub fn gen_native_function_code(
                         #pat
                     });
                 }
-                let custom_self_type = if let Pat::Ident(PatIdent { ident, .. }) = &**pat {
+                let custom_self_type = if let Pat::Ident(PatIdent { ident, .. }) = &**original_pat {
                     ident == "self_vc"
                 } else {
                     false
@@ -146,7 +153,7 @@ pub fn gen_native_function_code(
                     });
                 } else {
                     input_raw_vc_arguments.push(quote! {
-                        #pat.into()
+                        #original_pat.into()
                     });
                 }
                 index += 1;
@@ -2,13 +2,14 @@
 
 use criterion::{criterion_group, criterion_main, Criterion};
 
+pub(crate) mod priority;
 pub(crate) mod scope_stress;
 pub(crate) mod stress;
 
 criterion_group!(
     name = turbo_tasks_memory_stress;
     config = Criterion::default();
-    targets = stress::fibonacci, scope_stress::scope_stress
+    targets = stress::fibonacci, scope_stress::scope_stress, priority::priority
 );
 criterion_main!(turbo_tasks_memory_stress);
 
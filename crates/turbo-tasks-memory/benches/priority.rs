@@ -0,0 +1,59 @@
+use anyhow::Result;
+use criterion::{BenchmarkId, Criterion};
+use turbo_tasks::{primitives::U32Vc, NothingVc, TaskPriority, TryJoinIterExt, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+
+use super::register;
+
+/// Compares executing the same tasks with the normal priority, which skips
+/// counting runnable executions, with executing them with a priority that
+/// requires counting them.
+pub fn priority(c: &mut Criterion) {
+    register();
+
+    let mut group = c.benchmark_group("turbo_tasks_memory_priority");
+    group.sample_size(20);
+
+    for priority in [TaskPriority::Normal, TaskPriority::Foreground] {
+        let size = 100;
+        group.throughput(criterion::Throughput::Elements(
+            /* tasks for chain from 0 to size - 1 = */
+            size as u64 * (size as u64 + 1) / 2 +
+            /* root task = */
+            1,
+        ));
+        group.bench_with_input(
+            BenchmarkId::new("chains", format_args!("{priority:?}")),
+            &priority,
+            |b, priority| {
+                let rt = tokio::runtime::Builder::new_multi_thread()
+                    .enable_all()
+                    .build()
+                    .unwrap();
+                let priority = *priority;
+
+                b.to_async(rt).iter_with_large_drop(move || {
+                    let tt = TurboTasks::new(MemoryBackend::new());
+                    async move {
+                        let task = tt.spawn_once_task_with_priority(priority, async move {
+                            (0..size).map(|i| chain(i, i)).try_join().await?;
+                            Ok(NothingVc::new().into())
+                        });
+                        tt.wait_task_completion(task, false).await.unwrap();
+                        tt
+                    }
+                })
+            },
+        );
+    }
+}
+
+/// Awaits a chain of `depth` tasks. The `key` parameter separates the cache
+/// entries of different chains.
+#[turbo_tasks::function]
+async fn chain(depth: u32, key: u32) -> Result<U32Vc> {
+    Ok(match depth {
+        0 => U32Vc::cell(0),
+        _ => U32Vc::cell(*chain(depth - 1, key).await? + 1),
+    })
+}
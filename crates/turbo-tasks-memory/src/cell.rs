@@ -1,10 +1,16 @@
-use std::{fmt::Debug, mem::take};
+use std::{fmt::Debug, mem::take, sync::Arc};
 
+use anyhow::Result;
 use auto_hash_map::AutoSet;
 use turbo_tasks::{
-    backend::CellContent,
+    backend::{CellContent, InvalidationReason},
     event::{Event, EventListener},
-    TaskId, TurboTasksBackendApi,
+    SharedReference, TaskId, TurboTasksBackendApi,
+};
+
+use crate::{
+    cell_spill::{SpillLoader, SpilledContent},
+    MemoryBackend,
 };
 
 #[derive(Debug)]
@@ -24,6 +30,41 @@ pub(crate) enum FullCell {
     },
 }
 
+#[derive(Debug)]
+pub(crate) struct SpilledCell {
+    content: SpilledContent,
+    updates: u32,
+    dependent_tasks: AutoSet<TaskId>,
+    /// Someone wanted to read the content and it is being loaded from disk.
+    loading: Option<Event>,
+}
+
+impl SpilledCell {
+    /// Waits for the content to be loaded. The first reader receives a
+    /// [SpillLoader] to load the content without holding the lock of the
+    /// task, see [Cell::install_loaded].
+    fn wait_for_load(
+        &mut self,
+        description: impl Fn() -> String + Sync + Send + 'static,
+        note: impl Fn() -> String + Sync + Send + 'static,
+    ) -> RecomputingCell {
+        let load = if self.loading.is_none() {
+            self.loading = Some(Event::new(move || {
+                (description)() + " -> SpilledCell::loading"
+            }));
+            Some(self.content.loader())
+        } else {
+            None
+        };
+        let listener = self.loading.as_ref().unwrap().listen_with_note(note);
+        RecomputingCell {
+            listener,
+            schedule: false,
+            load,
+        }
+    }
+}
+
 #[derive(Default, Debug)]
 pub(crate) enum Cell {
     /// No content has been set yet, or it was removed for memory pressure
@@ -45,15 +86,36 @@ pub(crate) enum Cell {
     // This is in a box so we don't need the updates counter for most cells that are only written
     // once.
     Full(Box<FullCell>),
+    /// The content was large and hasn't been read for a while, so it has been
+    /// moved to disk. It's loaded again on the next read.
+    Spilled(Box<SpilledCell>),
 }
 
 #[derive(Debug)]
 pub struct RecomputingCell {
     pub listener: EventListener,
     pub schedule: bool,
+    /// The spilled content needs to be loaded and installed with
+    /// [Cell::install_loaded].
+    pub(crate) load: Option<SpillLoader>,
 }
 
 impl Cell {
+    /// The value of the cell, if it holds one in memory.
+    pub fn value(&self) -> Option<&SharedReference> {
+        match self {
+            Cell::InitialValue {
+                content: CellContent(Some(value)),
+                ..
+            }
+            | Cell::Full(box FullCell::UpdatedValue {
+                content: CellContent(Some(value)),
+                ..
+            }) => Some(value),
+            _ => None,
+        }
+    }
+
     pub fn remove_dependent_task(&mut self, task: TaskId) {
         match self {
             Cell::Empty | Cell::Recomputing { .. } => {}
@@ -67,12 +129,124 @@ impl Cell {
                 | FullCell::UpdatedValue {
                     dependent_tasks, ..
                 }),
-            ) => {
+            )
+            | Cell::Spilled(box SpilledCell {
+                dependent_tasks, ..
+            }) => {
                 dependent_tasks.remove(&task);
             }
         }
     }
 
+    /// The content of the cell if spilling it would free memory, because
+    /// nobody else holds it.
+    pub fn spillable_content(&self) -> Option<&SharedReference> {
+        match self {
+            Cell::InitialValue {
+                content: CellContent(Some(shared_ref)),
+                ..
+            }
+            | Cell::Full(box FullCell::UpdatedValue {
+                content: CellContent(Some(shared_ref)),
+                ..
+            }) if shared_ref.0.is_some() && Arc::strong_count(&shared_ref.1) == 1 => {
+                Some(shared_ref)
+            }
+            _ => None,
+        }
+    }
+
+    /// Replaces the content with the spilled `content`, unless the content
+    /// has changed or is held by someone else than the caller since it has
+    /// been written. Returns true when the cell has been spilled.
+    pub fn replace_with_spilled(
+        &mut self,
+        written: &SharedReference,
+        content: SpilledContent,
+    ) -> bool {
+        let (shared_ref, updates, dependent_tasks) = match self {
+            Cell::InitialValue {
+                content: CellContent(Some(shared_ref)),
+                dependent_tasks,
+            } => (shared_ref, 1, dependent_tasks),
+            Cell::Full(box FullCell::UpdatedValue {
+                content: CellContent(Some(shared_ref)),
+                updates,
+                dependent_tasks,
+            }) => (shared_ref, *updates, dependent_tasks),
+            _ => return false,
+        };
+        let unchanged = std::ptr::eq(
+            Arc::as_ptr(&shared_ref.1).cast::<()>(),
+            Arc::as_ptr(&written.1).cast::<()>(),
+        );
+        if !unchanged || Arc::strong_count(&shared_ref.1) > 2 {
+            return false;
+        }
+        let dependent_tasks = take(dependent_tasks);
+        *self = Cell::Spilled(box SpilledCell {
+            content,
+            updates,
+            dependent_tasks,
+            loading: None,
+        });
+        true
+    }
+
+    /// Installs spilled content that has been loaded by `loader`, unless the
+    /// cell has changed in the meantime. Returns true when loading has failed
+    /// and the task needs to be invalidated to recompute the content.
+    pub fn install_loaded(
+        &mut self,
+        loader: &SpillLoader,
+        loaded: Result<CellContent>,
+        description: impl Fn() -> String + Sync + Send + 'static,
+    ) -> bool {
+        let Cell::Spilled(box SpilledCell {
+            content,
+            updates,
+            dependent_tasks,
+            loading,
+        }) = self else {
+            return false;
+        };
+        if !loader.loads(content) {
+            return false;
+        }
+        let updates = *updates;
+        let dependent_tasks = take(dependent_tasks);
+        let loading = loading.take();
+        let failed = match loaded {
+            Ok(content) => {
+                if updates == 1 {
+                    *self = Cell::InitialValue {
+                        content,
+                        dependent_tasks,
+                    };
+                } else {
+                    *self = Cell::Full(box FullCell::UpdatedValue {
+                        content,
+                        updates,
+                        dependent_tasks,
+                    });
+                }
+                false
+            }
+            Err(_) => {
+                // The spilled content couldn't be loaded, so it's recomputed.
+                // Readers retry and wait for the recomputation.
+                self.recompute(updates, dependent_tasks, description, || {
+                    "loading spilled content failed".to_string()
+                });
+                true
+            }
+        };
+        if let Some(event) = loading {
+            event.notify(usize::MAX);
+        }
+        failed
+    }
+
     fn recompute(
         &mut self,
         updates: u32,
@@ -106,6 +280,7 @@ impl Cell {
                 Err(RecomputingCell {
                     listener,
                     schedule: true,
+                    load: None,
                 })
             }
             Cell::Recomputing { event, .. }
@@ -114,8 +289,10 @@ impl Cell {
                 Err(RecomputingCell {
                     listener,
                     schedule: false,
+                    load: None,
                 })
             }
+            Cell::Spilled(cell) => Err(cell.wait_for_load(description, note)),
             Cell::InitialValue {
                 content,
                 dependent_tasks,
@@ -145,6 +322,7 @@ impl Cell {
                 Err(RecomputingCell {
                     listener,
                     schedule: true,
+                    load: None,
                 })
             }
             Cell::Recomputing { event, .. }
@@ -153,8 +331,10 @@ impl Cell {
                 Err(RecomputingCell {
                     listener,
                     schedule: false,
+                    load: None,
                 })
             }
+            Cell::Spilled(cell) => Err(cell.wait_for_load(description, note)),
             Cell::InitialValue { content, .. }
             | Cell::Full(box FullCell::UpdatedValue { content, .. }) => Ok(content.clone()),
         }
@@ -162,17 +342,27 @@ impl Cell {
 
     /// INVALIDATION: Be careful with this, it will not track dependencies, so
     /// using it could break cache invalidation.
-    pub fn read_own_content_untracked(&self) -> CellContent {
+    ///
+    /// Spilled content is returned as a [SpillLoader], which must be used
+    /// without holding the lock of the task.
+    pub fn read_own_content_untracked(&self) -> Result<CellContent, SpillLoader> {
         match self {
             Cell::Empty
             | Cell::Recomputing { .. }
-            | Cell::Full(box FullCell::Recomputing { .. }) => CellContent(None),
+            | Cell::Full(box FullCell::Recomputing { .. }) => Ok(CellContent(None)),
+            Cell::Spilled(box SpilledCell { content, .. }) => Err(content.loader()),
             Cell::InitialValue { content, .. }
-            | Cell::Full(box FullCell::UpdatedValue { content, .. }) => content.clone(),
+            | Cell::Full(box FullCell::UpdatedValue { content, .. }) => Ok(content.clone()),
         }
     }
 
-    pub fn assign(&mut self, content: CellContent, turbo_tasks: &dyn TurboTasksBackendApi) {
+    pub fn assign(
+        &mut self,
+        content: CellContent,
+        reason: InvalidationReason,
+        backend: &MemoryBackend,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
         match self {
             Cell::Empty => {
                 *self = Cell::InitialValue {
@@ -226,7 +416,11 @@ impl Cell {
             } => {
                 if content != *old_content {
                     if !dependent_tasks.is_empty() {
-                        turbo_tasks.schedule_notify_tasks_set(dependent_tasks);
+                        backend.schedule_notify_tasks_with_reason(
+                            dependent_tasks,
+                            reason,
+                            turbo_tasks,
+                        );
                         dependent_tasks.clear();
                     }
                     *self = Cell::Full(box FullCell::UpdatedValue {
@@ -236,6 +430,29 @@ impl Cell {
                     });
                 }
             }
+            Cell::Spilled(box SpilledCell {
+                updates,
+                dependent_tasks,
+                loading,
+                ..
+            }) => {
+                // Readers that wait for the content to be loaded read the new
+                // content instead
+                if let Some(event) = loading.take() {
+                    event.notify(usize::MAX);
+                }
+                // Spilled content is never equal to a new value, as values are
+                // compared by identity
+                if !dependent_tasks.is_empty() {
+                    backend.schedule_notify_tasks_with_reason(dependent_tasks, reason, turbo_tasks);
+                    dependent_tasks.clear();
+                }
+                *self = Cell::Full(box FullCell::UpdatedValue {
+                    content,
+                    updates: *updates + 1,
+                    dependent_tasks: take(dependent_tasks),
+                });
+            }
             Cell::Full(box FullCell::UpdatedValue {
                 content: cell_content,
                 updates,
@@ -243,7 +460,11 @@ impl Cell {
             }) => {
                 if content != *cell_content {
                     if !dependent_tasks.is_empty() {
-                        turbo_tasks.schedule_notify_tasks_set(dependent_tasks);
+                        backend.schedule_notify_tasks_with_reason(
+                            dependent_tasks,
+                            reason,
+                            turbo_tasks,
+                        );
                         dependent_tasks.clear();
                     }
                     *updates += 1;
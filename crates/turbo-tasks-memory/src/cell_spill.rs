@@ -0,0 +1,288 @@
+use std::{
+    fmt::Debug,
+    fs::{self, File},
+    hash::BuildHasherDefault,
+    io::{BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use bincode::Options;
+use dashmap::DashMap;
+use rustc_hash::FxHasher;
+use turbo_tasks::{backend::CellContent, CellId, SharedReference, TaskId};
+
+/// Configures moving large cell contents that haven't been read for a while
+/// from memory to disk. Spilled contents are read back when they are read
+/// again.
+#[derive(Clone, Debug)]
+pub struct CellSpillConfig {
+    /// The directory to store spilled contents in. Each backend creates its
+    /// own subdirectory, which is removed when the backend is dropped.
+    pub directory: PathBuf,
+    /// Contents smaller than this (in serialized bytes) stay in memory.
+    pub min_size: u64,
+    /// The minimal time between two spill passes. A cell is spilled when it
+    /// hasn't been read between two passes.
+    pub interval: Duration,
+}
+
+impl CellSpillConfig {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+            min_size: 64 * 1024,
+            interval: Duration::from_secs(30),
+        }
+    }
+}
+
+/// The files of spilled cell contents.
+struct SpillStore {
+    directory: PathBuf,
+    next_id: AtomicU64,
+    count: AtomicUsize,
+    size: AtomicU64,
+}
+
+impl SpillStore {
+    fn path(&self, id: u64) -> PathBuf {
+        self.directory.join(id.to_string())
+    }
+}
+
+impl Drop for SpillStore {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.directory);
+    }
+}
+
+/// A cell content that has been written to disk. The file is removed when
+/// this is dropped.
+pub(crate) struct SpilledContent {
+    store: Arc<SpillStore>,
+    id: u64,
+    size: u64,
+}
+
+impl SpilledContent {
+    /// A handle to load the content without holding the lock of the task
+    /// that owns it.
+    pub fn loader(&self) -> SpillLoader {
+        SpillLoader {
+            store: self.store.clone(),
+            id: self.id,
+        }
+    }
+}
+
+/// Loads a [SpilledContent]. Loading fails when the content has been dropped
+/// in the meantime.
+#[derive(Clone)]
+pub(crate) struct SpillLoader {
+    store: Arc<SpillStore>,
+    id: u64,
+}
+
+impl SpillLoader {
+    /// Whether this loads `content`.
+    pub fn loads(&self, content: &SpilledContent) -> bool {
+        Arc::ptr_eq(&self.store, &content.store) && self.id == content.id
+    }
+
+    /// Reads the content from disk. This blocks the thread.
+    pub fn load(&self) -> Result<CellContent> {
+        let reader = BufReader::new(File::open(self.store.path(self.id))?);
+        let shared_ref: SharedReference =
+            bincode::DefaultOptions::new().deserialize_from(reader)?;
+        Ok(CellContent(Some(shared_ref)))
+    }
+
+    /// Reads the content from disk on a blocking thread.
+    pub async fn load_blocking(self) -> Result<CellContent> {
+        tokio::task::spawn_blocking(move || self.load()).await?
+    }
+}
+
+impl Debug for SpillLoader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SpillLoader").field("id", &self.id).finish()
+    }
+}
+
+impl Drop for SpilledContent {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(self.store.path(self.id));
+        self.store.count.fetch_sub(1, Ordering::Relaxed);
+        self.store.size.fetch_sub(self.size, Ordering::Relaxed);
+    }
+}
+
+impl Debug for SpilledContent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SpilledContent")
+            .field("id", &self.id)
+            .field("size", &self.size)
+            .finish()
+    }
+}
+
+/// What the last passes have seen of a large cell content.
+struct Candidate {
+    /// The address of the content, to tell whether it has been replaced.
+    address: usize,
+    /// The serialized size of the content, computed once per content.
+    size: u64,
+    /// Whether the cell hasn't been read since a pass has seen it.
+    idle: bool,
+    /// The last pass that has seen the cell, to forget about removed cells.
+    pass: u64,
+}
+
+pub(crate) struct CellSpilling {
+    store: Arc<SpillStore>,
+    min_size: u64,
+    interval: Duration,
+    candidates: DashMap<(TaskId, CellId), Candidate, BuildHasherDefault<FxHasher>>,
+    current_pass: AtomicU64,
+    last_pass: Mutex<Instant>,
+    pass_scheduled: AtomicBool,
+}
+
+/// Distinguishes the stores of multiple backends in the same process.
+static NEXT_STORE_ID: AtomicUsize = AtomicUsize::new(0);
+
+impl CellSpilling {
+    pub fn new(config: CellSpillConfig) -> Result<Self> {
+        let directory = config.directory.join(format!(
+            "turbo-tasks-cells-{}-{}",
+            std::process::id(),
+            NEXT_STORE_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&directory)?;
+        Ok(Self {
+            store: Arc::new(SpillStore {
+                directory,
+                next_id: AtomicU64::new(0),
+                count: AtomicUsize::new(0),
+                size: AtomicU64::new(0),
+            }),
+            min_size: config.min_size,
+            interval: config.interval,
+            candidates: DashMap::default(),
+            current_pass: AtomicU64::new(0),
+            last_pass: Mutex::new(Instant::now()),
+            pass_scheduled: AtomicBool::new(false),
+        })
+    }
+
+    /// Returns true when a spill pass should be scheduled. The caller must
+    /// call [CellSpilling::pass_finished] after the pass.
+    pub fn start_pass_if_due(&self) -> bool {
+        self.last_pass.lock().unwrap().elapsed() >= self.interval
+            && self
+                .pass_scheduled
+                .compare_exchange(false, true, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok()
+    }
+
+    pub fn pass_finished(&self) {
+        *self.last_pass.lock().unwrap() = Instant::now();
+        self.pass_scheduled.store(false, Ordering::Release);
+    }
+
+    /// Starts a pass over all cells, see [CellSpilling::should_spill].
+    pub fn begin_pass(&self) {
+        self.current_pass.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Ends a pass and forgets about cells it hasn't seen.
+    pub fn end_pass(&self) {
+        let pass = self.current_pass.load(Ordering::Acquire);
+        self.candidates
+            .retain(|_, candidate| candidate.pass == pass);
+    }
+
+    pub fn mark_read(&self, task: TaskId, index: CellId) {
+        if let Some(mut candidate) = self.candidates.get_mut(&(task, index)) {
+            candidate.idle = false;
+        }
+    }
+
+    /// Number and total size of the cell contents that are currently on disk.
+    pub fn spilled(&self) -> (usize, u64) {
+        (
+            self.store.count.load(Ordering::Relaxed),
+            self.store.size.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Whether a content should be spilled because it's large and hasn't been
+    /// read since the previous pass has seen it. Otherwise the cell might
+    /// become a candidate for the next pass. Must not be called while holding
+    /// the lock of the task, as it might serialize the content.
+    pub fn should_spill(&self, task: TaskId, index: CellId, content: &SharedReference) -> bool {
+        let pass = self.current_pass.load(Ordering::Acquire);
+        let address = Arc::as_ptr(&content.1) as *const () as usize;
+        if let Some(mut candidate) = self.candidates.get_mut(&(task, index)) {
+            if candidate.address == address {
+                let spill = candidate.idle && candidate.size >= self.min_size;
+                candidate.idle = true;
+                candidate.pass = pass;
+                return spill;
+            }
+        }
+        let Ok(size) = bincode::DefaultOptions::new().serialized_size(content) else {
+            return false;
+        };
+        self.candidates.insert(
+            (task, index),
+            Candidate {
+                address,
+                size,
+                idle: true,
+                pass,
+            },
+        );
+        false
+    }
+
+    /// Whether the cell is still unread since [CellSpilling::should_spill]
+    /// decided to spill it.
+    pub fn is_idle(&self, task: TaskId, index: CellId) -> bool {
+        self.candidates
+            .get(&(task, index))
+            .map_or(false, |candidate| candidate.idle)
+    }
+
+    /// Writes `content` to disk on a blocking thread.
+    pub async fn write(&self, content: SharedReference) -> Option<SpilledContent> {
+        let store = self.store.clone();
+        let id = store.next_id.fetch_add(1, Ordering::Relaxed);
+        let path = store.path(id);
+        let size = tokio::task::spawn_blocking(move || match write(&path, &content) {
+            Ok(size) => Some(size),
+            Err(_) => {
+                let _ = fs::remove_file(&path);
+                None
+            }
+        })
+        .await
+        .ok()??;
+        store.count.fetch_add(1, Ordering::Relaxed);
+        store.size.fetch_add(size, Ordering::Relaxed);
+        Some(SpilledContent { store, id, size })
+    }
+}
+
+fn write(path: &Path, content: &SharedReference) -> Result<u64> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    bincode::DefaultOptions::new().serialize_into(&mut writer, content)?;
+    writer.flush()?;
+    Ok(writer.get_ref().metadata()?.len())
+}
@@ -7,6 +7,7 @@
 #![deny(unsafe_op_in_unsafe_fn)]
 
 mod cell;
+mod cell_spill;
 mod count_hash_set;
 mod map_guard;
 mod memory_backend;
@@ -17,5 +18,6 @@ pub mod stats;
 mod task;
 pub mod viz;
 
+pub use cell_spill::CellSpillConfig;
 pub use memory_backend::MemoryBackend;
 pub use memory_backend_with_pg::MemoryBackendWithPersistedGraph;
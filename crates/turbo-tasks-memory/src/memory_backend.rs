@@ -16,16 +16,17 @@ use rustc_hash::FxHasher;
 use tokio::task::futures::TaskLocalFuture;
 use turbo_tasks::{
     backend::{
-        Backend, BackendJobId, CellContent, PersistentTaskType, TaskExecutionSpec,
-        TransientTaskType,
+        Backend, BackendJobId, CellContent, InvalidationReason, PersistentTaskType,
+        TaskExecutionSpec, TransientTaskType,
     },
     event::EventListener,
     util::{IdFactory, NoMoveVec},
-    CellId, RawVc, TaskId, TraitTypeId, TurboTasksBackendApi,
+    CellId, RawVc, StatsType, TaskId, TraitTypeId, TurboTasksBackendApi,
 };
 
 use crate::{
     cell::RecomputingCell,
+    cell_spill::{CellSpillConfig, CellSpilling, SpillLoader},
     output::Output,
     scope::{TaskScope, TaskScopeId},
     task::{
@@ -42,6 +43,14 @@ pub struct MemoryBackend {
     backend_jobs: NoMoveVec<Job>,
     backend_job_id_factory: IdFactory<BackendJobId>,
     task_cache: DashMap<Arc<PersistentTaskType>, TaskId, BuildHasherDefault<FxHasher>>,
+    /// Reasons for scheduled notifications, taken when the notified task is
+    /// invalidated. Invalidation reasons are only recorded with
+    /// [StatsType::Full].
+    pending_invalidation_reasons: DashMap<TaskId, InvalidationReason, BuildHasherDefault<FxHasher>>,
+    /// The reason and time of the latest invalidation of each task.
+    invalidation_reasons:
+        DashMap<TaskId, (InvalidationReason, Instant), BuildHasherDefault<FxHasher>>,
+    cell_spilling: Option<CellSpilling>,
 }
 
 impl Default for MemoryBackend {
@@ -66,9 +75,95 @@ impl MemoryBackend {
             backend_jobs: NoMoveVec::new(),
             backend_job_id_factory: IdFactory::new(),
             task_cache: DashMap::default(),
+            pending_invalidation_reasons: DashMap::default(),
+            invalidation_reasons: DashMap::default(),
+            cell_spilling: None,
         }
     }
 
+    /// Enables moving large cell contents that haven't been read for a while
+    /// to disk, see [CellSpillConfig].
+    pub fn with_cell_spilling(mut self, config: CellSpillConfig) -> Result<Self> {
+        self.cell_spilling = Some(CellSpilling::new(config)?);
+        Ok(self)
+    }
+
+    /// Spills large cell contents that haven't been read since the last call
+    /// to disk, when spilling is enabled. This happens periodically during
+    /// execution, too. Returns the number of spilled cells.
+    ///
+    /// Contents are serialized and written without holding the locks of their
+    /// tasks, and only replaced when they are still unchanged and unread.
+    pub async fn spill_idle_cells(&self) -> usize {
+        let Some(spilling) = &self.cell_spilling else {
+            return 0;
+        };
+        let mut tasks = Vec::new();
+        self.with_all_cached_tasks(|task| tasks.push(task));
+        spilling.begin_pass();
+        let mut spilled = 0;
+        for task in tasks {
+            let cells = self.with_task(task, |task| task.spillable_cells());
+            for (index, content) in cells {
+                if !spilling.should_spill(task, index, &content) {
+                    continue;
+                }
+                let Some(spilled_content) = spilling.write(content.clone()).await else {
+                    continue;
+                };
+                if spilling.is_idle(task, index)
+                    && self.with_task(task, |task| {
+                        task.replace_with_spilled(index, &content, spilled_content)
+                    })
+                {
+                    spilled += 1;
+                }
+            }
+        }
+        spilling.end_pass();
+        spilled
+    }
+
+    /// The number and total size in bytes of the cell contents that are
+    /// currently spilled to disk.
+    pub fn spilled_cells(&self) -> (usize, u64) {
+        self.cell_spilling
+            .as_ref()
+            .map_or((0, 0), |spilling| spilling.spilled())
+    }
+
+    fn schedule_load_spilled_cell(
+        &self,
+        task: TaskId,
+        index: CellId,
+        loader: SpillLoader,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+        turbo_tasks.schedule_backend_foreground_job(
+            self.create_backend_job(Job::LoadSpilledCell(task, index, loader)),
+        );
+    }
+
+    /// Reads the content of a cell of the currently executing task. The own
+    /// content is only read to compare it with a new value, so spilled content
+    /// is loaded without holding the lock of the task, but not kept in memory.
+    fn read_own_cell(&self, task: TaskId, index: CellId) -> CellContent {
+        self.with_task(task, |task| {
+            task.with_cell(index, |cell| cell.read_own_content_untracked())
+        })
+        .unwrap_or_else(|loader| {
+            let multi_thread = tokio::runtime::Handle::try_current().map_or(false, |handle| {
+                handle.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread
+            });
+            let loaded = if multi_thread {
+                tokio::task::block_in_place(|| loader.load())
+            } else {
+                loader.load()
+            };
+            loaded.unwrap_or(CellContent(None))
+        })
+    }
+
     fn connect_task_child(
         &self,
         parent: TaskId,
@@ -108,6 +203,63 @@ impl MemoryBackend {
         }
     }
 
+    /// Calls `func` with the reason and time of the latest invalidation of
+    /// each task that has been invalidated.
+    pub fn with_all_invalidation_reasons(
+        &self,
+        mut func: impl FnMut(TaskId, &InvalidationReason, Instant),
+    ) {
+        for entry in self.invalidation_reasons.iter() {
+            let (reason, time) = entry.value();
+            func(*entry.key(), reason, *time);
+        }
+    }
+
+    /// Schedules notifications of `tasks` and remembers `reason` as the cause
+    /// of their invalidation. When a task is notified several times before
+    /// it's invalidated, the first reason wins.
+    pub(crate) fn schedule_notify_tasks_with_reason(
+        &self,
+        tasks: &AutoSet<TaskId>,
+        reason: InvalidationReason,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+        if turbo_tasks.stats_type() == StatsType::Full {
+            for task in tasks.iter() {
+                self.pending_invalidation_reasons
+                    .entry(*task)
+                    .or_insert_with(|| reason.clone());
+            }
+        }
+        turbo_tasks.schedule_notify_tasks_set(tasks);
+    }
+
+    fn invalidate_task_because(
+        &self,
+        task: TaskId,
+        reason: InvalidationReason,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+        if self.with_task(task, |task| task.invalidate(self, turbo_tasks))
+            && turbo_tasks.stats_type() == StatsType::Full
+        {
+            self.invalidation_reasons
+                .insert(task, (reason, Instant::now()));
+        }
+    }
+
+    /// Forgets the invalidation reasons of a task that is gone, so they don't
+    /// pile up or get attributed to a task reusing its id.
+    fn forget_invalidation_reasons(&self, task: TaskId) {
+        self.pending_invalidation_reasons.remove(&task);
+        self.invalidation_reasons.remove(&task);
+    }
+
+    /// Whether a task with this id exists, e.g. for ids coming from a user.
+    pub fn has_task(&self, id: TaskId) -> bool {
+        self.memory_tasks.get(*id).is_some()
+    }
+
     pub fn with_task<T>(&self, id: TaskId, func: impl FnOnce(&Task) -> T) -> T {
         func(self.memory_tasks.get(*id).unwrap())
     }
@@ -191,17 +343,47 @@ impl MemoryBackend {
 
 impl Backend for MemoryBackend {
     fn invalidate_task(&self, task: TaskId, turbo_tasks: &dyn TurboTasksBackendApi) {
-        self.with_task(task, |task| task.invalidate(self, turbo_tasks));
+        self.invalidate_task_because(
+            task,
+            InvalidationReason::Invalidator { description: None },
+            turbo_tasks,
+        );
+    }
+
+    fn invalidate_task_with_reason(
+        &self,
+        task: TaskId,
+        reason: Cow<'static, str>,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+        self.invalidate_task_because(
+            task,
+            InvalidationReason::Invalidator {
+                description: Some(reason),
+            },
+            turbo_tasks,
+        );
     }
 
     fn invalidate_tasks(&self, tasks: Vec<TaskId>, turbo_tasks: &dyn TurboTasksBackendApi) {
         for task in tasks.into_iter() {
-            self.with_task(task, |task| {
-                task.invalidate(self, turbo_tasks);
-            });
+            match self.pending_invalidation_reasons.remove(&task) {
+                Some((_, reason)) => self.invalidate_task_because(task, reason, turbo_tasks),
+                None => {
+                    self.with_task(task, |task| {
+                        task.invalidate(self, turbo_tasks);
+                    });
+                }
+            }
         }
     }
 
+    fn get_invalidation_reason(&self, task: TaskId) -> Option<InvalidationReason> {
+        self.invalidation_reasons
+            .get(&task)
+            .map(|entry| entry.value().0.clone())
+    }
+
     fn get_task_description(&self, task: TaskId) -> String {
         self.with_task(task, |task| task.get_description())
     }
@@ -231,7 +413,13 @@ impl Backend for MemoryBackend {
         turbo_tasks: &dyn TurboTasksBackendApi,
     ) {
         self.with_task(task, |task| {
-            task.execution_result(result, turbo_tasks);
+            task.execution_result(result, self, turbo_tasks);
+        })
+    }
+
+    fn task_execution_cancelled(&self, task: TaskId, turbo_tasks: &dyn TurboTasksBackendApi) {
+        self.with_task(task, |task| {
+            task.execution_cancelled(self, turbo_tasks);
         })
     }
 
@@ -242,9 +430,16 @@ impl Backend for MemoryBackend {
         instant: Instant,
         turbo_tasks: &dyn TurboTasksBackendApi,
     ) -> bool {
-        self.with_task(task, |task| {
+        let reexecute = self.with_task(task, |task| {
             task.execution_completed(duration, instant, self, turbo_tasks)
-        })
+        });
+        if let Some(spilling) = &self.cell_spilling {
+            if spilling.start_pass_if_due() {
+                turbo_tasks
+                    .schedule_backend_foreground_job(self.create_backend_job(Job::SpillCells));
+            }
+        }
+        reexecute
     }
 
     fn try_read_task_output(
@@ -292,11 +487,12 @@ impl Backend for MemoryBackend {
         turbo_tasks: &dyn TurboTasksBackendApi,
     ) -> Result<Result<CellContent, EventListener>> {
         if task_id == reader {
-            Ok(Ok(self.with_task(task_id, |task| {
-                task.with_cell(index, |cell| cell.read_own_content_untracked())
-            })))
+            Ok(Ok(self.read_own_cell(task_id, index)))
         } else {
             Task::add_dependency_to_current(TaskDependency::TaskCell(task_id, index));
+            if let Some(spilling) = &self.cell_spilling {
+                spilling.mark_read(task_id, index);
+            }
             self.with_task(task_id, |task| {
                 match task.with_cell_mut(index, |cell| {
                     cell.read_content(
@@ -306,10 +502,17 @@ impl Backend for MemoryBackend {
                     )
                 }) {
                     Ok(content) => Ok(Ok(content)),
-                    Err(RecomputingCell { listener, schedule }) => {
+                    Err(RecomputingCell {
+                        listener,
+                        schedule,
+                        load,
+                    }) => {
                         if schedule {
                             task.invalidate(self, turbo_tasks);
                         }
+                        if let Some(loader) = load {
+                            self.schedule_load_spilled_cell(task_id, index, loader, turbo_tasks);
+                        }
                         Ok(Err(listener))
                     }
                 }
@@ -323,9 +526,7 @@ impl Backend for MemoryBackend {
         index: CellId,
         _turbo_tasks: &dyn TurboTasksBackendApi,
     ) -> Result<CellContent> {
-        Ok(self.with_task(current_task, |task| {
-            task.with_cell(index, |cell| cell.read_own_content_untracked())
-        }))
+        Ok(self.read_own_cell(current_task, index))
     }
 
     fn try_read_task_cell_untracked(
@@ -334,6 +535,9 @@ impl Backend for MemoryBackend {
         index: CellId,
         turbo_tasks: &dyn TurboTasksBackendApi,
     ) -> Result<Result<CellContent, EventListener>> {
+        if let Some(spilling) = &self.cell_spilling {
+            spilling.mark_read(task_id, index);
+        }
         self.with_task(task_id, |task| {
             match task.with_cell_mut(index, |cell| {
                 cell.read_content_untracked(
@@ -342,10 +546,17 @@ impl Backend for MemoryBackend {
                 )
             }) {
                 Ok(content) => Ok(Ok(content)),
-                Err(RecomputingCell { listener, schedule }) => {
+                Err(RecomputingCell {
+                    listener,
+                    schedule,
+                    load,
+                }) => {
                     if schedule {
                         task.invalidate(self, turbo_tasks);
                     }
+                    if let Some(loader) = load {
+                        self.schedule_load_spilled_cell(task_id, index, loader, turbo_tasks);
+                    }
                     Ok(Err(listener))
                 }
             }
@@ -395,8 +606,11 @@ impl Backend for MemoryBackend {
         content: CellContent,
         turbo_tasks: &dyn TurboTasksBackendApi,
     ) {
+        let reason = InvalidationReason::CellChanged { task, cell: index };
         self.with_task(task, |task| {
-            task.with_cell_mut(index, |cell| cell.assign(content, turbo_tasks))
+            task.with_cell_mut(index, |cell| {
+                cell.assign(content, reason, self, turbo_tasks)
+            })
         })
     }
 
@@ -453,6 +667,7 @@ impl Backend for MemoryBackend {
                 }
                 Entry::Occupied(entry) => {
                     // Safety: We have a fresh task id that nobody knows about yet
+                    self.forget_invalidation_reasons(id);
                     unsafe {
                         self.memory_tasks.remove(*id);
                         turbo_tasks.reuse_task_id(id);
@@ -490,6 +705,13 @@ impl Backend for MemoryBackend {
         println!("new {scope} for {task}");
         id
     }
+
+    fn dispose_root_task(&self, task: TaskId, turbo_tasks: &dyn TurboTasksBackendApi) {
+        self.with_task(task, |task| {
+            task.remove_root_or_initial_scope(self, turbo_tasks);
+        });
+        self.forget_invalidation_reasons(task);
+    }
 }
 
 pub(crate) enum Job {
@@ -502,6 +724,11 @@ pub(crate) enum Job {
     /// Remove tasks from a scope. Scheduled by `run_remove_from_scope_queue` to
     /// split off work.
     RemoveFromScopeQueue(VecDeque<TaskId>, TaskScopeId),
+    /// Spill large cell contents to disk. Scheduled periodically when cell
+    /// spilling is enabled.
+    SpillCells,
+    /// Load a spilled cell content that has been read back into memory.
+    LoadSpilledCell(TaskId, CellId, SpillLoader),
 }
 
 impl Job {
@@ -534,6 +761,23 @@ impl Job {
             Job::RemoveFromScopeQueue(queue, id) => {
                 run_remove_from_scope_queue(queue, id, backend, turbo_tasks);
             }
+            Job::SpillCells => {
+                backend.spill_idle_cells().await;
+                if let Some(spilling) = &backend.cell_spilling {
+                    spilling.pass_finished();
+                }
+            }
+            Job::LoadSpilledCell(task_id, index, loader) => {
+                let loaded = loader.clone().load_blocking().await;
+                backend.with_task(task_id, |task| {
+                    let failed = task.with_cell_mut(index, |cell| {
+                        cell.install_loaded(&loader, loaded, move || format!("{task_id} {index}"))
+                    });
+                    if failed {
+                        task.invalidate(backend, turbo_tasks);
+                    }
+                });
+            }
         }
     }
 }
@@ -143,6 +143,8 @@ pub struct MemoryBackendWithPersistedGraph<P: PersistedGraph> {
     persist_queue1: ConcurrentQueue<TaskId>,
     persist_queue1_queued: DashSet<TaskId>,
     need_persisting: DashSet<TaskId>,
+    /// Tasks that need to be executed again when restored in a later session
+    session_dependent_tasks: DashSet<TaskId>,
     /// Task sorted by importance, sharded to avoid lock contention
     persist_queue_by_duration: [Mutex<BinaryHeap<(Duration, TaskId)>>; 64],
     persist_capacity: AtomicUsize,
@@ -171,6 +173,7 @@ impl<P: PersistedGraph> MemoryBackendWithPersistedGraph<P> {
             persist_queue1: ConcurrentQueue::unbounded(),
             persist_queue1_queued: DashSet::new(),
             need_persisting: DashSet::new(),
+            session_dependent_tasks: DashSet::new(),
             persist_queue_by_duration: [(); 64].map(|_| Mutex::new(BinaryHeap::new())),
             persist_capacity: AtomicUsize::new(num_cpus::get()),
             persist_job,
@@ -798,6 +801,9 @@ impl<P: PersistedGraph> MemoryBackendWithPersistedGraph<P> {
                                         let task_state =
                                             turbo_tasks::persisted_graph::PersistTaskState {
                                                 externally_active,
+                                                session_dependent: self
+                                                    .session_dependent_tasks
+                                                    .contains(&task),
                                             };
                                         if let Some(PersistResult {
                                             tasks_to_activate,
@@ -1011,6 +1017,14 @@ impl<P: PersistedGraph> Backend for MemoryBackendWithPersistedGraph<P> {
         format!("{:?}", task_info.task_type)
     }
 
+    fn mark_own_task_as_session_dependent(
+        &self,
+        task: TaskId,
+        _turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+        self.session_dependent_tasks.insert(task);
+    }
+
     type ExecutionScopeFuture<T: Future<Output = Result<()>> + Send + 'static> = T;
     fn execution_scope<T: Future<Output = Result<()>> + Send + 'static>(
         &self,
@@ -1038,6 +1052,9 @@ impl<P: PersistedGraph> Backend for MemoryBackendWithPersistedGraph<P> {
             println!("start {} {:?}", task, task_info.task_type);
         }
         mem_state.freshness = TaskFreshness::NeverExecuted;
+        // The execution marks the task again if it still depends on the
+        // session.
+        self.session_dependent_tasks.remove(&task);
         let deps = take(&mut mem_state.dependencies);
         let children = take(&mut mem_state.children);
         drop(state);
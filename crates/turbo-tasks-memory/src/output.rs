@@ -6,7 +6,11 @@ use std::{
 
 use anyhow::{anyhow, Error, Result};
 use auto_hash_map::AutoSet;
-use turbo_tasks::{util::SharedError, RawVc, TaskId, TurboTasksBackendApi};
+use turbo_tasks::{
+    backend::InvalidationReason, util::SharedError, RawVc, TaskId, TurboTasksBackendApi,
+};
+
+use crate::MemoryBackend;
 
 #[derive(Default, Debug)]
 pub struct Output {
@@ -59,7 +63,13 @@ impl Output {
         }
     }
 
-    pub fn link(&mut self, target: RawVc, turbo_tasks: &dyn TurboTasksBackendApi) {
+    pub fn link(
+        &mut self,
+        target: RawVc,
+        reason: InvalidationReason,
+        backend: &MemoryBackend,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
         let change;
         let mut _type_change = false;
         match &self.content {
@@ -84,38 +94,58 @@ impl Output {
             }
         };
         if let Some(target) = change {
-            self.assign(OutputContent::Link(target), turbo_tasks)
+            self.assign(OutputContent::Link(target), reason, backend, turbo_tasks)
         }
     }
 
-    pub fn error(&mut self, error: Error, turbo_tasks: &dyn TurboTasksBackendApi) {
+    pub fn error(
+        &mut self,
+        error: Error,
+        reason: InvalidationReason,
+        backend: &MemoryBackend,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
         self.content = OutputContent::Error(SharedError::new(error));
         self.updates += 1;
-        // notify
-        if !self.dependent_tasks.is_empty() {
-            turbo_tasks.schedule_notify_tasks_set(&take(&mut self.dependent_tasks));
-        }
+        self.notify(reason, backend, turbo_tasks);
     }
 
     pub fn panic(
         &mut self,
         message: Option<Cow<'static, str>>,
+        reason: InvalidationReason,
+        backend: &MemoryBackend,
         turbo_tasks: &dyn TurboTasksBackendApi,
     ) {
         self.content = OutputContent::Panic(message);
         self.updates += 1;
-        // notify
-        if !self.dependent_tasks.is_empty() {
-            turbo_tasks.schedule_notify_tasks_set(&take(&mut self.dependent_tasks));
-        }
+        self.notify(reason, backend, turbo_tasks);
     }
 
-    pub fn assign(&mut self, content: OutputContent, turbo_tasks: &dyn TurboTasksBackendApi) {
+    pub fn assign(
+        &mut self,
+        content: OutputContent,
+        reason: InvalidationReason,
+        backend: &MemoryBackend,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
         self.content = content;
         self.updates += 1;
-        // notify
+        self.notify(reason, backend, turbo_tasks);
+    }
+
+    fn notify(
+        &mut self,
+        reason: InvalidationReason,
+        backend: &MemoryBackend,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
         if !self.dependent_tasks.is_empty() {
-            turbo_tasks.schedule_notify_tasks_set(&take(&mut self.dependent_tasks));
+            backend.schedule_notify_tasks_with_reason(
+                &take(&mut self.dependent_tasks),
+                reason,
+                turbo_tasks,
+            );
         }
     }
 }
@@ -2,11 +2,12 @@ use std::{
     cmp::{self, max},
     collections::{hash_map::Entry, HashMap, HashSet, VecDeque},
     fmt::Display,
-    mem::take,
+    mem::{size_of_val, take},
     time::Duration,
 };
 
-use turbo_tasks::{registry, FunctionId, TaskId, TraitTypeId};
+use bincode::Options;
+use turbo_tasks::{registry, FunctionId, SharedReference, TaskId, TraitTypeId, ValueTypeId};
 
 use crate::{
     scope::TaskScopeId,
@@ -89,8 +90,28 @@ impl Default for ExportedTaskStats {
     }
 }
 
+/// The cells of a value type that hold a value in memory.
+#[derive(Default, Clone, Debug)]
+pub struct CellStats {
+    pub count: usize,
+    /// The estimated memory used by the values in bytes, see
+    /// [estimate_value_size]. Only collected by [Stats::with_cell_sizes].
+    pub size: Option<u64>,
+}
+
+/// Estimates the memory used by a cell value as its serialized size, which
+/// includes heap allocations like strings, vectors and ropes. Values that
+/// aren't serializable are estimated with their shallow size.
+pub fn estimate_value_size(value: &SharedReference) -> u64 {
+    bincode::DefaultOptions::new()
+        .serialized_size(value)
+        .unwrap_or_else(|_| size_of_val(&*value.1) as u64)
+}
+
 pub struct Stats {
     tasks: HashMap<StatsTaskType, ExportedTaskStats>,
+    cells: HashMap<ValueTypeId, CellStats>,
+    cell_sizes: bool,
 }
 
 impl Default for Stats {
@@ -103,6 +124,17 @@ impl Stats {
     pub fn new() -> Self {
         Self {
             tasks: Default::default(),
+            cells: Default::default(),
+            cell_sizes: false,
+        }
+    }
+
+    /// Like [Stats::new], but also estimates the memory used by cell values.
+    /// That serializes all values, so it's expensive for large graphs.
+    pub fn with_cell_sizes() -> Self {
+        Self {
+            cell_sizes: true,
+            ..Self::new()
         }
     }
 
@@ -130,6 +162,13 @@ impl Stats {
             active,
             unloaded,
         } = info;
+        for (ty, value) in task.get_stats_cells() {
+            let stats = self.cells.entry(ty).or_default();
+            stats.count += 1;
+            if self.cell_sizes {
+                *stats.size.get_or_insert(0) += estimate_value_size(&value);
+            }
+        }
         let stats = self.tasks.entry(ty).or_default();
         stats.count += 1;
         if active {
@@ -182,6 +221,11 @@ impl Stats {
         });
     }
 
+    /// Cell counts per value type of all added tasks.
+    pub fn cells(&self) -> &HashMap<ValueTypeId, CellStats> {
+        &self.cells
+    }
+
     pub fn merge_resolve(&mut self) {
         self.merge(|ty, _stats| match ty {
             StatsTaskType::Root(_) | StatsTaskType::Once(_) | StatsTaskType::Native(_) => false,
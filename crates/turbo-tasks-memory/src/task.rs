@@ -15,20 +15,21 @@ use std::{
     time::{Duration, Instant},
 };
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use auto_hash_map::{AutoMap, AutoSet};
 use parking_lot::{Mutex, RwLock};
 use stats::TaskStats;
 use tokio::task_local;
 use turbo_tasks::{
-    backend::{PersistentTaskType, TaskExecutionSpec},
+    backend::{InvalidationReason, PersistentTaskType, TaskExecutionSpec},
     event::{Event, EventListener},
-    get_invalidator, registry, CellId, Invalidator, RawVc, StatsType, TaskId, TraitTypeId,
-    TurboTasksBackendApi, ValueTypeId,
+    get_invalidator, registry, CellId, Invalidator, RawVc, SharedReference, StatsType, TaskId,
+    TraitTypeId, TurboTasksBackendApi, ValueTypeId,
 };
 
 use crate::{
     cell::Cell,
+    cell_spill::SpilledContent,
     count_hash_set::CountHashSet,
     memory_backend::Job,
     output::Output,
@@ -538,6 +539,7 @@ impl Task {
                 }
                 if let Some(collectibles) = state.collectibles.take() {
                     remove_collectible_from_scopes(
+                        self.id,
                         collectibles.emitted,
                         collectibles.unemitted,
                         &state.scopes,
@@ -606,14 +608,16 @@ impl Task {
     pub(crate) fn execution_result(
         &self,
         result: Result<Result<RawVc>, Option<Cow<'static, str>>>,
+        backend: &MemoryBackend,
         turbo_tasks: &dyn TurboTasksBackendApi,
     ) {
         let mut state = self.full_state_mut();
+        let reason = InvalidationReason::OutputChanged { task: self.id };
         match state.state_type {
             InProgress { .. } => match result {
-                Ok(Ok(result)) => state.output.link(result, turbo_tasks),
-                Ok(Err(err)) => state.output.error(err, turbo_tasks),
-                Err(message) => state.output.panic(message, turbo_tasks),
+                Ok(Ok(result)) => state.output.link(result, reason, backend, turbo_tasks),
+                Ok(Err(err)) => state.output.error(err, reason, backend, turbo_tasks),
+                Err(message) => state.output.panic(message, reason, backend, turbo_tasks),
             },
             InProgressDirty { .. } => {
                 // We don't want to assign the output cell here
@@ -629,6 +633,41 @@ impl Task {
         };
     }
 
+    /// Handles an execution that has been aborted by a cancellation. The
+    /// output is left untouched and the task is treated as dirty, so it will
+    /// be executed again when it's still needed. Once tasks can't be executed
+    /// again and fail instead.
+    pub(crate) fn execution_cancelled(
+        &self,
+        backend: &MemoryBackend,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+        let mut state = self.full_state_mut();
+        match state.state_type {
+            InProgress { ref mut event } => {
+                if let TaskType::Once(_) = self.ty {
+                    state.output.error(
+                        anyhow!("task execution was cancelled"),
+                        InvalidationReason::OutputChanged { task: self.id },
+                        backend,
+                        turbo_tasks,
+                    );
+                } else {
+                    state.state_type = InProgressDirty {
+                        event: event.take(),
+                    };
+                }
+            }
+            InProgressDirty { .. } => {}
+            Dirty { .. } | Scheduled { .. } | Done { .. } => {
+                panic!(
+                    "Task execution cancelled in unexpected state {}",
+                    Task::state_string(&state)
+                )
+            }
+        };
+    }
+
     #[must_use]
     pub(crate) fn execution_completed(
         &self,
@@ -748,20 +787,23 @@ impl Task {
         false
     }
 
-    fn make_dirty(&self, backend: &MemoryBackend, turbo_tasks: &dyn TurboTasksBackendApi) {
+    /// Marks the task as dirty. Returns `true` when the task wasn't dirty
+    /// before.
+    fn make_dirty(&self, backend: &MemoryBackend, turbo_tasks: &dyn TurboTasksBackendApi) -> bool {
         if let TaskType::Once(_) = self.ty {
             // once task won't become dirty
-            return;
+            return false;
         }
 
         if let TaskMetaStateWriteGuard::Full(mut state) = self.state_mut() {
             let id = self.id;
             let mut clear_dependencies = AutoSet::new();
 
-            match state.state_type {
+            let made_dirty = match state.state_type {
                 Dirty { .. } | Scheduled { .. } | InProgressDirty { .. } => {
                     // already dirty
                     drop(state);
+                    false
                 }
                 Done {
                     ref mut dependencies,
@@ -781,18 +823,27 @@ impl Task {
                         };
                         drop(state);
                     }
+                    true
                 }
                 InProgress { ref mut event } => {
                     state.state_type = InProgressDirty {
                         event: event.take(),
                     };
                     drop(state);
+                    // The result of the running execution will be thrown away, so there is
+                    // no point in continuing it. Self-invalidations don't cancel it, see
+                    // `TurboTasks::cancel_execution`.
+                    turbo_tasks.cancel_task_execution(self.id);
+                    true
                 }
-            }
+            };
 
             if !clear_dependencies.is_empty() {
                 self.clear_dependencies(clear_dependencies, backend);
             }
+            made_dirty
+        } else {
+            false
         }
     }
 
@@ -846,7 +897,11 @@ impl Task {
                 {
                     drop(state);
                     if !notify.is_empty() {
-                        turbo_tasks.schedule_notify_tasks_set(&notify);
+                        backend.schedule_notify_tasks_with_reason(
+                            &notify,
+                            InvalidationReason::CollectiblesChanged { task: self.id },
+                            turbo_tasks,
+                        );
                     }
                     if active {
                         backend.increase_scope_active(root, turbo_tasks);
@@ -968,7 +1023,11 @@ impl Task {
                         })
                         .for_each(|e| tasks.extend(e.notify));
                 };
-                turbo_tasks.schedule_notify_tasks_set(&tasks);
+                backend.schedule_notify_tasks_with_reason(
+                    &tasks,
+                    InvalidationReason::CollectiblesChanged { task: self.id },
+                    turbo_tasks,
+                );
             }
         });
         schedule_self
@@ -1037,7 +1096,11 @@ impl Task {
                         })
                         .for_each(|e| tasks.extend(e.notify));
                 };
-                turbo_tasks.schedule_notify_tasks_set(&tasks);
+                backend.schedule_notify_tasks_with_reason(
+                    &tasks,
+                    InvalidationReason::CollectiblesChanged { task: self.id },
+                    turbo_tasks,
+                );
             }
         });
     }
@@ -1062,7 +1125,11 @@ impl Task {
                     {
                         drop(state);
                         if !notify.is_empty() {
-                            turbo_tasks.schedule_notify_tasks_set(&notify);
+                            backend.schedule_notify_tasks_with_reason(
+                                &notify,
+                                InvalidationReason::CollectiblesChanged { task: self.id },
+                                turbo_tasks,
+                            );
                         }
                         if active {
                             backend.decrease_scope_active(root, turbo_tasks);
@@ -1219,7 +1286,11 @@ impl Task {
                 });
             }
             if !tasks.is_empty() {
-                turbo_tasks.schedule_notify_tasks_set(&tasks);
+                backend.schedule_notify_tasks_with_reason(
+                    &tasks,
+                    InvalidationReason::CollectiblesChanged { task: self.id },
+                    turbo_tasks,
+                );
             }
             backend.with_scope(root_scope, |root_scope| {
                 for parent in scopes_to_add_as_parent {
@@ -1307,12 +1378,13 @@ impl Task {
     }
 
     /// Called by the [Invalidator]. Invalidate the [Task]. When the task is
-    /// active it will be scheduled for execution.
+    /// active it will be scheduled for execution. Returns `true` when the
+    /// task wasn't dirty before.
     pub(crate) fn invalidate(
         &self,
         backend: &MemoryBackend,
         turbo_tasks: &dyn TurboTasksBackendApi,
-    ) {
+    ) -> bool {
         self.make_dirty(backend, turbo_tasks)
     }
 
@@ -1386,6 +1458,69 @@ impl Task {
         }
     }
 
+    /// The contents of the cells that could be spilled to disk. Only cells of
+    /// finished tasks are spilled.
+    pub(crate) fn spillable_cells(&self) -> Vec<(CellId, SharedReference)> {
+        let mut contents = Vec::new();
+        if let TaskMetaStateReadGuard::Full(state) = self.state() {
+            if !matches!(state.state_type, TaskStateType::Done { .. }) {
+                return contents;
+            }
+            for (&type_id, cells) in state.cells.iter() {
+                for (index, cell) in cells.iter().enumerate() {
+                    if let Some(content) = cell.spillable_content() {
+                        let index = CellId {
+                            type_id,
+                            index: index as u32,
+                        };
+                        contents.push((index, content.clone()));
+                    }
+                }
+            }
+        }
+        contents
+    }
+
+    /// Replaces the content of a cell with its spilled `content`, see
+    /// [Cell::replace_with_spilled]. Returns true when the cell has been
+    /// spilled.
+    pub(crate) fn replace_with_spilled(
+        &self,
+        index: CellId,
+        written: &SharedReference,
+        content: SpilledContent,
+    ) -> bool {
+        if let TaskMetaStateWriteGuard::Full(mut state) = self.state_mut() {
+            if !matches!(state.state_type, TaskStateType::Done { .. }) {
+                return false;
+            }
+            if let Some(cell) = state
+                .cells
+                .get_mut(&index.type_id)
+                .and_then(|cells| cells.get_mut(index.index as usize))
+            {
+                return cell.replace_with_spilled(written, content);
+            }
+        }
+        false
+    }
+
+    /// The value type and value of every cell of the task that holds a value
+    /// in memory. Values are cloned references, so they can be inspected
+    /// without holding the lock of the task.
+    pub fn get_stats_cells(&self) -> Vec<(ValueTypeId, SharedReference)> {
+        let mut cells = Vec::new();
+        if let TaskMetaStateReadGuard::Full(state) = self.state() {
+            for (ty, list) in state.cells.iter() {
+                cells.extend(
+                    list.iter()
+                        .filter_map(|cell| Some((*ty, cell.value()?.clone()))),
+                );
+            }
+        }
+        cells
+    }
+
     pub fn get_stats_info(&self, backend: &MemoryBackend) -> TaskStatsInfo {
         match self.state() {
             TaskMetaStateReadGuard::Full(state) => {
@@ -1705,7 +1840,11 @@ impl Task {
                 .flat_map(|e| e.notify)
                 .collect::<AutoSet<_>>();
             drop(state);
-            turbo_tasks.schedule_notify_tasks_set(&tasks);
+            backend.schedule_notify_tasks_with_reason(
+                &tasks,
+                InvalidationReason::CollectiblesChanged { task: self.id },
+                turbo_tasks,
+            );
         }
     }
 
@@ -1730,12 +1869,17 @@ impl Task {
                 })
                 .for_each(|e| tasks.extend(e.notify));
             drop(state);
-            turbo_tasks.schedule_notify_tasks_set(&tasks);
+            backend.schedule_notify_tasks_with_reason(
+                &tasks,
+                InvalidationReason::CollectiblesChanged { task: self.id },
+                turbo_tasks,
+            );
         }
     }
 }
 
 fn remove_collectible_from_scopes(
+    task: TaskId,
     emitted: AutoSet<(TraitTypeId, RawVc)>,
     unemitted: AutoSet<(TraitTypeId, RawVc)>,
     task_scopes: &TaskScopes,
@@ -1761,7 +1905,11 @@ fn remove_collectible_from_scopes(
                     })
                     .for_each(|e| tasks.extend(e.notify));
             };
-            turbo_tasks.schedule_notify_tasks_set(&tasks);
+            backend.schedule_notify_tasks_with_reason(
+                &tasks,
+                InvalidationReason::CollectiblesChanged { task },
+                turbo_tasks,
+            );
         })
     })
 }
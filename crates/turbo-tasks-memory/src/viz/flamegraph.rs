@@ -0,0 +1,166 @@
+/// Creates a page that periodically fetches the JSON created by
+/// [super::json::create_json] from `json_url` and renders it as a flamegraph
+/// of the task tree, next to tables of the functions and the cell counts and
+/// sizes.
+pub fn wrap_html(json_url: &str, refresh_interval_ms: u64) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+  <meta charset="utf-8">
+  <title>turbo-tasks flamegraph</title>
+  <style>{style}</style>
+</head>
+<body>
+  <p id="status">Loading...</p>
+  <div id="flamegraph"></div>
+  <div class="tables">
+    <table id="functions"></table>
+    <table id="cells"></table>
+  </div>
+  <script>
+    const JSON_URL = {json_url:?};
+    const REFRESH_INTERVAL = {refresh_interval_ms};
+    {script}
+  </script>
+</body>
+</html>"#,
+        style = STYLE,
+        script = SCRIPT,
+    )
+}
+
+const STYLE: &str = r#"
+body { margin: 0; padding: 0.8rem 1rem; font-family: monospace; }
+#flamegraph { position: relative; }
+.frame {
+  position: absolute; height: 18px; overflow: hidden; white-space: nowrap;
+  box-sizing: border-box; border: 1px solid #fff; padding: 0 3px;
+  font-size: 11px; line-height: 16px; cursor: pointer;
+}
+.tables { display: flex; gap: 2rem; align-items: flex-start; margin-top: 1rem; }
+table { border-spacing: 0; }
+th, td { padding: 2px 8px; text-align: right; }
+th:first-child, td:first-child { text-align: left; }
+th { background: gray; color: #fff; font-weight: normal; }
+"#;
+
+const SCRIPT: &str = r#"
+const FRAME_HEIGHT = 18;
+let zoomPath = [];
+
+function formatDuration(us) {
+  if (us >= 1000000) return (us / 1000000).toFixed(2) + "s";
+  if (us >= 1000) return (us / 1000).toFixed(1) + "ms";
+  return us + "µs";
+}
+
+function formatBytes(bytes) {
+  if (bytes == null) return "";
+  if (bytes >= 1048576) return (bytes / 1048576).toFixed(1) + "MiB";
+  if (bytes >= 1024) return (bytes / 1024).toFixed(1) + "KiB";
+  return bytes + "B";
+}
+
+function color(name) {
+  let hash = 0;
+  for (const c of name) hash = (hash * 31 + c.charCodeAt(0)) | 0;
+  return `hsl(${20 + (Math.abs(hash) % 40)}, 80%, ${60 + (Math.abs(hash) % 20)}%)`;
+}
+
+function findZoomed(tree) {
+  let node = tree;
+  for (const name of zoomPath) {
+    const child = node.children.find((child) => child.name === name);
+    if (!child) break;
+    node = child;
+  }
+  return node;
+}
+
+function renderFrames(container, node, path, left, width, depth, scale) {
+  if (node.totalDuration === 0 && depth > 0) return depth;
+  const frame = document.createElement("div");
+  frame.className = "frame";
+  frame.style.left = left + "%";
+  frame.style.width = width + "%";
+  frame.style.top = depth * FRAME_HEIGHT + "px";
+  frame.style.background = color(node.name);
+  frame.textContent = node.name;
+  frame.title = `${node.name}\ntotal ${formatDuration(node.totalDuration)}, self ${formatDuration(node.selfDuration)}` +
+    (node.executions != null ? `\n${node.executions} executions` : "") +
+    (node.count != null ? `, ${node.count} tasks` : "");
+  frame.onclick = () => {
+    // Clicking the top frame zooms out by one level
+    zoomPath = depth === 0 ? path.slice(0, -1) : path;
+    render();
+  };
+  container.appendChild(frame);
+  let maxDepth = depth + 1;
+  let childLeft = left;
+  for (const child of node.children) {
+    const childWidth = child.totalDuration * scale;
+    if (childWidth < 0.05) continue;
+    maxDepth = Math.max(maxDepth, renderFrames(container, child, [...path, child.name], childLeft, childWidth, depth + 1, scale));
+    childLeft += childWidth;
+  }
+  return maxDepth;
+}
+
+function renderTable(table, columns, rows) {
+  table.innerHTML = "";
+  const head = table.createTHead().insertRow();
+  for (const [title] of columns) {
+    const th = document.createElement("th");
+    th.textContent = title;
+    head.appendChild(th);
+  }
+  const body = table.createTBody();
+  for (const row of rows) {
+    const tr = body.insertRow();
+    for (const [, value] of columns) {
+      tr.insertCell().textContent = value(row);
+    }
+  }
+}
+
+let data;
+
+function render() {
+  const container = document.getElementById("flamegraph");
+  container.innerHTML = "";
+  const root = findZoomed(data.tree);
+  const scale = root.totalDuration > 0 ? 100 / root.totalDuration : 0;
+  const depth = renderFrames(container, root, zoomPath, 0, 100, 0, scale);
+  container.style.height = depth * FRAME_HEIGHT + "px";
+  renderTable(document.getElementById("functions"), [
+    ["function", (f) => f.name],
+    ["tasks", (f) => f.count],
+    ["executions", (f) => f.executions ?? "-"],
+    ["self duration", (f) => formatDuration(f.selfDuration)],
+    ["max duration", (f) => formatDuration(f.maxDuration)],
+  ], data.functions);
+  renderTable(document.getElementById("cells"), [
+    ["cell type", (c) => c.name],
+    ["cells", (c) => c.count],
+    ["estimated size", (c) => formatBytes(c.size)],
+  ], data.cells);
+}
+
+async function update() {
+  const status = document.getElementById("status");
+  try {
+    const response = await fetch(JSON_URL, { cache: "no-store" });
+    data = await response.json();
+    status.textContent = `Updated ${new Date().toLocaleTimeString()}` +
+      (data.fullStats ? "" : " (full stats collection is disabled, run with --full-stats to get execution counts and total durations)") +
+      (zoomPath.length > 0 ? ". Click the top frame to zoom out." : "");
+    render();
+  } catch (err) {
+    status.textContent = `Failed to update: ${err}`;
+  }
+  setTimeout(update, REFRESH_INTERVAL);
+}
+
+update();
+"#;
@@ -0,0 +1,111 @@
+use std::{collections::HashSet, time::Instant};
+
+use turbo_tasks::{backend::Backend, util::FormatDuration, TaskId};
+
+use super::*;
+use crate::MemoryBackend;
+
+/// Maximum number of steps shown for an invalidation chain.
+const MAX_CHAIN_LENGTH: usize = 100;
+
+pub fn wrap_html(html: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+  <meta charset="utf-8">
+  <title>turbo-tasks invalidations</title>
+  <style>
+    body {{ margin: 0; padding: 0.8rem 1rem; font-family: monospace; }}
+    li {{ margin: 0.3rem 0; }}
+    .reason {{ opacity: 0.6; }}
+  </style>
+</head>
+<body>
+  {html}
+</body>
+</html>"#
+    )
+}
+
+fn task_link(backend: &MemoryBackend, task: TaskId) -> String {
+    format!(
+        r#"<a href="?task={}">{}</a>"#,
+        *task,
+        escape_html(&backend.get_task_description(task))
+    )
+}
+
+/// Lists the most recently invalidated tasks, linking to their invalidation
+/// chains.
+pub fn create_invalidations_list(backend: &MemoryBackend, limit: usize) -> String {
+    let mut invalidations = Vec::new();
+    backend.with_all_invalidation_reasons(|task, reason, time| {
+        invalidations.push((time, task, reason.to_string()));
+    });
+    invalidations.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    let now = Instant::now();
+    let mut out = String::new();
+    out += "<h1>Recently invalidated tasks</h1>";
+    if invalidations.is_empty() {
+        out += "<p>No task has been invalidated yet. Invalidation reasons are only recorded with \
+                full stats.</p>";
+        return out;
+    }
+    out += "<ul>";
+    for (time, task, reason) in invalidations.into_iter().take(limit) {
+        write!(
+            out,
+            r#"<li>{} ago: {} <span class="reason">({})</span></li>"#,
+            FormatDuration(now - time),
+            task_link(backend, task),
+            escape_html(&reason)
+        )
+        .unwrap();
+    }
+    out += "</ul>";
+    out
+}
+
+/// Follows the latest invalidation reasons from `task` back to the change
+/// that started the chain, usually a file change reported by an invalidator.
+pub fn create_invalidation_chain(backend: &MemoryBackend, task: TaskId) -> String {
+    let mut out = String::new();
+    write!(
+        out,
+        r#"<p><a href="?">all invalidations</a></p><h1>Why did {} recompute?</h1><ol>"#,
+        escape_html(&backend.get_task_description(task))
+    )
+    .unwrap();
+    let mut visited = HashSet::new();
+    let mut current = Some(task);
+    while let Some(task) = current.take() {
+        if !visited.insert(task) {
+            out += "<li>... (cycle, the chain continues with an earlier invalidation)</li>";
+            break;
+        }
+        if visited.len() > MAX_CHAIN_LENGTH {
+            out += "<li>... (chain truncated)</li>";
+            break;
+        }
+        let Some(reason) = backend.get_invalidation_reason(task) else {
+            write!(
+                out,
+                "<li>{} has not been invalidated</li>",
+                task_link(backend, task)
+            )
+            .unwrap();
+            break;
+        };
+        write!(
+            out,
+            r#"<li>{} <span class="reason">because {}</span></li>"#,
+            task_link(backend, task),
+            escape_html(&reason.to_string())
+        )
+        .unwrap();
+        current = reason.source_task();
+    }
+    out += "</ol>";
+    out
+}
@@ -0,0 +1,138 @@
+use serde_json::{json, Value};
+use turbo_tasks::{registry, StatsType};
+
+use super::*;
+use crate::stats::Stats;
+
+/// The time spent in executions of the tasks of a type. Executions are
+/// measured by polling time, so this excludes time spent waiting for other
+/// tasks.
+fn self_duration(stats: &ExportedTaskStats) -> Duration {
+    stats.total_duration.unwrap_or(stats.total_current_duration)
+}
+
+fn task_type_json(ty: &StatsTaskType, stats: &ExportedTaskStats) -> Value {
+    json!({
+        "name": ty.to_string(),
+        "count": stats.count,
+        "activeCount": stats.active_count,
+        "executions": stats.executions,
+        "selfDuration": self_duration(stats).as_micros() as u64,
+        "maxDuration": stats.max_duration.as_micros() as u64,
+    })
+}
+
+/// Creates a flamegraph node. Its total duration spans the self duration and
+/// the total durations of its children.
+fn node_json(mut json: Value, mut children: Vec<Value>) -> Value {
+    children.sort_by_key(|child| std::cmp::Reverse(child["totalDuration"].as_u64()));
+    let children_duration = children
+        .iter()
+        .filter_map(|child| child["totalDuration"].as_u64())
+        .sum::<u64>();
+    let self_duration = json["selfDuration"].as_u64().unwrap_or_default();
+    json["totalDuration"] = json!(self_duration + children_duration);
+    json["children"] = Value::Array(children);
+    json
+}
+
+/// Converts a [GroupTree] into nested flamegraph nodes.
+fn tree_json(node: &GroupTree) -> Value {
+    let children = node
+        .children
+        .iter()
+        .map(tree_json)
+        .chain(
+            node.task_types
+                .iter()
+                .map(|(ty, stats)| node_json(task_type_json(ty, stats), Vec::new())),
+        )
+        .collect::<Vec<_>>();
+    let json = match &node.primary {
+        Some((ty, stats)) => task_type_json(ty, stats),
+        None => json!({ "name": "all tasks", "selfDuration": 0 }),
+    };
+    node_json(json, children)
+}
+
+fn collect_functions(node: &GroupTree, functions: &mut Vec<Value>) {
+    for (ty, stats) in node.primary.iter().chain(node.task_types.iter()) {
+        functions.push(task_type_json(ty, stats));
+    }
+    for child in node.children.iter() {
+        collect_functions(child, functions);
+    }
+}
+
+/// Creates a JSON snapshot of the statistics with execution counts and
+/// durations per function, a flamegraph tree and the number and estimated
+/// size of cells per value type. Durations are in microseconds.
+pub fn create_json(stats: &Stats, root: &GroupTree, stats_type: StatsType) -> Value {
+    let mut functions = Vec::new();
+    collect_functions(root, &mut functions);
+    functions.sort_by_key(|function| std::cmp::Reverse(function["selfDuration"].as_u64()));
+    let mut cells = stats
+        .cells()
+        .iter()
+        .map(|(ty, cell_stats)| {
+            json!({
+                "name": registry::get_value_type(*ty).name,
+                "count": cell_stats.count,
+                "size": cell_stats.size,
+            })
+        })
+        .collect::<Vec<_>>();
+    cells.sort_by_key(|cell| std::cmp::Reverse((cell["size"].as_u64(), cell["count"].as_u64())));
+    json!({
+        "fullStats": stats_type.is_full(),
+        "functions": functions,
+        "tree": tree_json(root),
+        "cells": cells,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use turbo_tasks::TaskId;
+
+    use super::*;
+
+    fn task_type(id: usize, self_millis: u64) -> (StatsTaskType, ExportedTaskStats) {
+        let stats = ExportedTaskStats {
+            count: 1,
+            total_duration: Some(Duration::from_millis(self_millis)),
+            ..Default::default()
+        };
+        (StatsTaskType::Root(TaskId::from(id)), stats)
+    }
+
+    fn totals(json: &Value) -> Vec<u64> {
+        let mut result = vec![json["totalDuration"].as_u64().unwrap()];
+        for child in json["children"].as_array().unwrap() {
+            result.extend(totals(child));
+        }
+        result
+    }
+
+    #[test]
+    fn total_durations_span_children() {
+        let tree = GroupTree {
+            primary: Some(task_type(1, 5)),
+            children: vec![GroupTree {
+                primary: Some(task_type(2, 3)),
+                children: Vec::new(),
+                task_types: vec![task_type(3, 4)],
+            }],
+            task_types: vec![task_type(4, 1), task_type(5, 0)],
+        };
+        let json = tree_json(&tree);
+
+        // Pre-order, children sorted by their total duration
+        assert_eq!(totals(&json), vec![13000, 7000, 4000, 1000, 0]);
+        assert_eq!(json["selfDuration"], 5000);
+        // Leaves are nodes without children
+        let leaf = &json["children"][0]["children"][0];
+        assert_eq!(leaf["selfDuration"], 4000);
+        assert_eq!(leaf["children"], json!([]));
+    }
+}
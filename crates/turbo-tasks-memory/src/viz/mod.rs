@@ -1,4 +1,7 @@
+pub mod flamegraph;
 pub mod graph;
+pub mod invalidations;
+pub mod json;
 pub mod table;
 
 use std::{
@@ -0,0 +1,155 @@
+#![feature(min_specialization)]
+
+use std::{
+    future::{pending, poll_fn, Future},
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering},
+        Arc,
+    },
+    task::Poll,
+    time::Duration,
+};
+
+use anyhow::Result;
+use turbo_tasks::{primitives::U32Vc, NothingVc, State, TaskId, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+static BLOCKING_STARTED: AtomicUsize = AtomicUsize::new(0);
+static BLOCKING_FINISHED: AtomicUsize = AtomicUsize::new(0);
+static NORMALIZE_FINISHED: AtomicUsize = AtomicUsize::new(0);
+
+#[tokio::test]
+async fn invalidation_cancels_running_execution() -> Result<()> {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let input = tt.run_once(async { Ok(InputVc::new(1)) }).await?;
+    let output = Arc::new(AtomicU32::new(0));
+    let root = spawn_root(&tt, output.clone(), move || blocking(input));
+
+    // The first execution never finishes on its own.
+    wait_until(|| BLOCKING_STARTED.load(Ordering::SeqCst) == 1).await;
+    tt.run_once(async move {
+        input.await?.value.set(2);
+        Ok(())
+    })
+    .await?;
+    tt.wait_task_completion(root, true).await?;
+
+    // The cancelled execution is rescheduled and executed again.
+    assert_eq!(BLOCKING_STARTED.load(Ordering::SeqCst), 2);
+    assert_eq!(BLOCKING_FINISHED.load(Ordering::SeqCst), 1);
+    assert_eq!(output.load(Ordering::SeqCst), 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn self_invalidation_does_not_cancel() -> Result<()> {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let input = tt.run_once(async { Ok(InputVc::new(1)) }).await?;
+    let output = Arc::new(AtomicU32::new(0));
+    let root = spawn_root(&tt, output.clone(), move || normalize(input));
+    tt.wait_task_completion(root, true).await?;
+
+    // The first execution invalidates itself and still runs to completion,
+    // but its output is thrown away in favor of the re-execution.
+    assert_eq!(NORMALIZE_FINISHED.load(Ordering::SeqCst), 2);
+    assert_eq!(output.load(Ordering::SeqCst), 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn dropping_run_once_skips_queued_task() -> Result<()> {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let started = Arc::new(AtomicBool::new(false));
+    let mut future = Box::pin(tt.run_once({
+        let started = started.clone();
+        async move {
+            started.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+    }));
+    // The first poll schedules the task, which can't start before this test
+    // yields on the single threaded runtime. The future is dropped by then.
+    let first_poll = poll_fn(|cx| Poll::Ready(future.as_mut().poll(cx))).await;
+    assert!(first_poll.is_pending());
+    drop(future);
+
+    tt.run_once(async { Ok(()) }).await?;
+    tt.wait_foreground_done().await;
+    assert!(!started.load(Ordering::SeqCst));
+    Ok(())
+}
+
+fn spawn_root(
+    tt: &Arc<TurboTasks<MemoryBackend>>,
+    output: Arc<AtomicU32>,
+    compute: impl Fn() -> U32Vc + Send + Sync + 'static,
+) -> TaskId {
+    let compute = Arc::new(compute);
+    tt.spawn_root_task(move || {
+        let compute = compute.clone();
+        let output = output.clone();
+        Box::pin(async move {
+            output.store(*compute().await?, Ordering::SeqCst);
+            Ok(NothingVc::new().into())
+        })
+    })
+}
+
+async fn wait_until(condition: impl Fn() -> bool) {
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while !condition() {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+    })
+    .await
+    .unwrap();
+}
+
+#[turbo_tasks::value(serialization = "none", eq = "manual", cell = "new")]
+struct Input {
+    value: State<u32>,
+}
+
+#[turbo_tasks::value_impl]
+impl InputVc {
+    #[turbo_tasks::function]
+    fn new(value: u32) -> Self {
+        Input {
+            value: State::new(value),
+        }
+        .cell()
+    }
+}
+
+/// Blocks forever for the value 1.
+#[turbo_tasks::function]
+async fn blocking(input: InputVc) -> Result<U32Vc> {
+    let value = *input.await?.value.get();
+    BLOCKING_STARTED.fetch_add(1, Ordering::SeqCst);
+    if value == 1 {
+        pending::<()>().await;
+    }
+    BLOCKING_FINISHED.fetch_add(1, Ordering::SeqCst);
+    Ok(U32Vc::cell(value))
+}
+
+/// Rounds the value up to the next even number by updating the state it has
+/// read itself.
+#[turbo_tasks::function]
+async fn normalize(input: InputVc) -> Result<U32Vc> {
+    let input = input.await?;
+    let value = *input.value.get();
+    if value % 2 == 1 {
+        input.value.set(value + 1);
+    }
+    // An await point at which a cancelled execution would be aborted.
+    tokio::task::yield_now().await;
+    NORMALIZE_FINISHED.fetch_add(1, Ordering::SeqCst);
+    Ok(U32Vc::cell(value))
+}
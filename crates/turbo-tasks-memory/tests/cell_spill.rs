@@ -0,0 +1,112 @@
+#![feature(min_specialization)]
+
+use std::time::Duration;
+
+use anyhow::Result;
+use turbo_tasks::{primitives::StringVc, TurboTasks};
+use turbo_tasks_memory::{CellSpillConfig, MemoryBackend};
+use turbo_tasks_testing::register;
+
+register!();
+
+#[tokio::test]
+async fn spills_idle_cells_and_loads_them_again() -> Result<()> {
+    lazy_static::initialize(&REGISTER);
+    let backend = MemoryBackend::new().with_cell_spilling(CellSpillConfig {
+        directory: std::env::temp_dir(),
+        min_size: 1024,
+        // Passes are triggered manually
+        interval: Duration::from_secs(3600),
+    })?;
+    let tt = TurboTasks::new(backend);
+    tt.run_once(async {
+        assert_eq!(big_string(1).await?.len(), 10000);
+        assert_eq!(small_string().await?.len(), 5);
+        Ok(())
+    })
+    .await?;
+
+    // The first pass only finds candidates
+    assert_eq!(tt.backend().spill_idle_cells().await, 0);
+    assert_eq!(tt.backend().spill_idle_cells().await, 1);
+    let (count, size) = tt.backend().spilled_cells();
+    assert_eq!(count, 1);
+    assert!(size >= 10000);
+
+    tt.run_once(async {
+        assert_eq!(*big_string(1).await?, "1".repeat(10000));
+        Ok(())
+    })
+    .await?;
+    assert_eq!(tt.backend().spilled_cells(), (0, 0));
+    Ok(())
+}
+
+#[tokio::test]
+async fn read_cells_are_not_spilled() -> Result<()> {
+    lazy_static::initialize(&REGISTER);
+    let backend = MemoryBackend::new().with_cell_spilling(CellSpillConfig {
+        directory: std::env::temp_dir(),
+        min_size: 1024,
+        interval: Duration::from_secs(3600),
+    })?;
+    let tt = TurboTasks::new(backend);
+    let read = || {
+        tt.run_once(async {
+            assert_eq!(big_string(2).await?.len(), 10000);
+            Ok(())
+        })
+    };
+    read().await?;
+
+    assert_eq!(tt.backend().spill_idle_cells().await, 0);
+    read().await?;
+    // The read makes the cell a candidate again instead of spilling it
+    assert_eq!(tt.backend().spill_idle_cells().await, 0);
+    assert_eq!(tt.backend().spill_idle_cells().await, 1);
+    assert_eq!(tt.backend().spilled_cells().0, 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn unloadable_spilled_cells_are_recomputed() -> Result<()> {
+    lazy_static::initialize(&REGISTER);
+    let directory = std::env::temp_dir().join(format!("cell-spill-test-{}", std::process::id()));
+    let backend = MemoryBackend::new().with_cell_spilling(CellSpillConfig {
+        directory: directory.clone(),
+        min_size: 1024,
+        interval: Duration::from_secs(3600),
+    })?;
+    let tt = TurboTasks::new(backend);
+    let read = || {
+        tt.run_once(async {
+            assert_eq!(*big_string(3).await?, "3".repeat(10000));
+            Ok(())
+        })
+    };
+    read().await?;
+    tt.backend().spill_idle_cells().await;
+    assert_eq!(tt.backend().spill_idle_cells().await, 1);
+
+    // Remove the spilled content behind the back of the backend
+    for store in std::fs::read_dir(&directory)? {
+        for file in std::fs::read_dir(store?.path())? {
+            std::fs::remove_file(file?.path())?;
+        }
+    }
+    read().await?;
+    assert_eq!(tt.backend().spilled_cells(), (0, 0));
+    drop(tt);
+    std::fs::remove_dir_all(&directory)?;
+    Ok(())
+}
+
+#[turbo_tasks::function]
+fn big_string(digit: u32) -> StringVc {
+    StringVc::cell(digit.to_string().repeat(10000))
+}
+
+#[turbo_tasks::function]
+fn small_string() -> StringVc {
+    StringVc::cell("small".to_string())
+}
@@ -0,0 +1,51 @@
+#![feature(min_specialization)]
+
+use anyhow::Result;
+use turbo_tasks::{
+    primitives::{String, StringVc},
+    TurboTasks, Typed,
+};
+use turbo_tasks_memory::{
+    stats::{CellStats, Stats},
+    MemoryBackend,
+};
+use turbo_tasks_testing::register;
+
+register!();
+
+fn string_cells(stats: &Stats) -> CellStats {
+    stats
+        .cells()
+        .get(&String::get_value_type_id())
+        .cloned()
+        .unwrap_or_default()
+}
+
+#[tokio::test]
+async fn cell_sizes_are_only_estimated_on_request() -> Result<()> {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    tt.run_once(async {
+        long_string().await?;
+        Ok(())
+    })
+    .await?;
+    let backend = tt.backend();
+
+    let mut stats = Stats::new();
+    backend.with_all_cached_tasks(|task| stats.add_id(backend, task));
+    let cells = string_cells(&stats);
+    assert!(cells.count >= 1);
+    assert_eq!(cells.size, None);
+
+    let mut stats = Stats::with_cell_sizes();
+    backend.with_all_cached_tasks(|task| stats.add_id(backend, task));
+    // The serialized size includes the heap allocated string.
+    assert!(string_cells(&stats).size.unwrap() >= 10_000);
+    Ok(())
+}
+
+#[turbo_tasks::function]
+fn long_string() -> StringVc {
+    StringVc::cell("x".repeat(10_000))
+}
@@ -0,0 +1,69 @@
+#![feature(min_specialization)]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::Result;
+use turbo_tasks::{primitives::U32Vc, NothingVc, State, TaskPriority, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+static ROOT_EXECUTIONS: AtomicUsize = AtomicUsize::new(0);
+
+#[tokio::test]
+async fn disposed_root_task_is_not_executed_again() -> Result<()> {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let input = tt.run_once(async { Ok(InputVc::new(1)) }).await?;
+    let root = tt.spawn_root_task_with_priority(TaskPriority::Foreground, move || {
+        Box::pin(async move {
+            double(input).await?;
+            ROOT_EXECUTIONS.fetch_add(1, Ordering::SeqCst);
+            Ok(NothingVc::new().into())
+        })
+    });
+    tt.wait_task_completion(root, true).await?;
+    assert_eq!(ROOT_EXECUTIONS.load(Ordering::SeqCst), 1);
+
+    tt.run_once(async move {
+        input.await?.value.set(2);
+        Ok(())
+    })
+    .await?;
+    tt.wait_task_completion(root, true).await?;
+    assert_eq!(ROOT_EXECUTIONS.load(Ordering::SeqCst), 2);
+
+    tt.dispose_root_task(root);
+    tt.wait_foreground_done().await;
+    tt.run_once(async move {
+        input.await?.value.set(3);
+        Ok(())
+    })
+    .await?;
+    tt.wait_foreground_done().await;
+    assert_eq!(ROOT_EXECUTIONS.load(Ordering::SeqCst), 2);
+    Ok(())
+}
+
+#[turbo_tasks::value(serialization = "none", eq = "manual", cell = "new")]
+struct Input {
+    value: State<u32>,
+}
+
+#[turbo_tasks::value_impl]
+impl InputVc {
+    #[turbo_tasks::function]
+    fn new(value: u32) -> Self {
+        Input {
+            value: State::new(value),
+        }
+        .cell()
+    }
+}
+
+#[turbo_tasks::function]
+async fn double(input: InputVc) -> Result<U32Vc> {
+    let value = *input.await?.value.get();
+    Ok(U32Vc::cell(value * 2))
+}
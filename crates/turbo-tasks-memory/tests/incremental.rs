@@ -0,0 +1,73 @@
+#![feature(min_specialization)]
+
+use anyhow::Result;
+use turbo_tasks::{
+    primitives::{BoolVc, U32Vc},
+    State,
+};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::{incremental::IncrementalTest, register};
+
+register!();
+
+#[tokio::test]
+async fn recomputes_only_affected_functions() -> Result<()> {
+    lazy_static::initialize(&REGISTER);
+    let mut test = IncrementalTest::start(
+        MemoryBackend::new(),
+        async { Ok(InputVc::new(1)) },
+        |input| async move { Ok(*is_even(double(input)).await?) },
+    )
+    .await?;
+    test.step()
+        .assert_executed("double", 1)
+        .assert_executed("is_even", 1);
+    assert!(*test.step().output());
+
+    test.update(|input| async move {
+        input.await?.value.set(2);
+        Ok(())
+    })
+    .await?
+    .assert_executed("double", 1)
+    .assert_executed("is_even", 1)
+    .assert_not_executed("root")
+    .assert_output_unchanged();
+
+    test.update(|input| async move {
+        input.await?.value.set(2);
+        Ok(())
+    })
+    .await?
+    .assert_only_executed(&[])
+    .assert_output_unchanged();
+
+    Ok(())
+}
+
+#[turbo_tasks::value(serialization = "none", eq = "manual", cell = "new")]
+struct Input {
+    value: State<u32>,
+}
+
+#[turbo_tasks::value_impl]
+impl InputVc {
+    #[turbo_tasks::function]
+    fn new(value: u32) -> Self {
+        Input {
+            value: State::new(value),
+        }
+        .cell()
+    }
+}
+
+#[turbo_tasks::function]
+async fn double(input: InputVc) -> Result<U32Vc> {
+    let value = *input.await?.value.get();
+    Ok(U32Vc::cell(value * 2))
+}
+
+#[turbo_tasks::function]
+async fn is_even(value: U32Vc) -> Result<BoolVc> {
+    Ok(BoolVc::cell(*value.await? % 2 == 0))
+}
@@ -0,0 +1,66 @@
+#![feature(min_specialization)]
+
+use anyhow::Result;
+use turbo_tasks::{
+    backend::InvalidationReason, primitives::U32Vc, NothingVc, State, StatsType, TurboTasks,
+    TurboTasksApi, TurboTasksBackendApi,
+};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+#[tokio::test]
+async fn reasons_are_only_recorded_with_full_stats() -> Result<()> {
+    lazy_static::initialize(&REGISTER);
+    for stats_type in [StatsType::Essential, StatsType::Full] {
+        let tt = TurboTasks::new(MemoryBackend::new());
+        tt.set_stats_type(stats_type);
+        let input = tt.run_once(async { Ok(InputVc::new(1)) }).await?;
+        let root = tt.spawn_root_task(move || {
+            Box::pin(async move {
+                read(input).await?;
+                Ok(NothingVc::new().into())
+            })
+        });
+        tt.wait_task_completion(root, true).await?;
+
+        tt.run_once(async move {
+            input.await?.value.set(2);
+            Ok(())
+        })
+        .await?;
+        tt.wait_task_completion(root, true).await?;
+
+        let reason = tt.invalidation_reason(root);
+        match stats_type {
+            StatsType::Essential => assert!(reason.is_none()),
+            StatsType::Full => assert!(matches!(
+                reason,
+                Some(InvalidationReason::CellChanged { .. })
+            )),
+        }
+    }
+    Ok(())
+}
+
+#[turbo_tasks::value(serialization = "none", eq = "manual", cell = "new")]
+struct Input {
+    value: State<u32>,
+}
+
+#[turbo_tasks::value_impl]
+impl InputVc {
+    #[turbo_tasks::function]
+    fn new(value: u32) -> Self {
+        Input {
+            value: State::new(value),
+        }
+        .cell()
+    }
+}
+
+#[turbo_tasks::function]
+async fn read(input: InputVc) -> Result<U32Vc> {
+    Ok(U32Vc::cell(*input.await?.value.get()))
+}
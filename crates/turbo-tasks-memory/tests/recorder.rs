@@ -0,0 +1,94 @@
+#![feature(min_specialization)]
+
+use std::{
+    io::{self, Write},
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Result;
+use turbo_tasks::{
+    primitives::U32Vc,
+    recorder::{RecordingBackend, TraceEvent, TraceReader},
+    TurboTasks,
+};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+/// A trace output which can be read while recording.
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn records_memory_backend() -> Result<()> {
+    lazy_static::initialize(&REGISTER);
+    let buffer = SharedBuffer::default();
+    let backend = RecordingBackend::with_writer(MemoryBackend::new(), Box::new(buffer.clone()))?;
+    let tt = TurboTasks::new(backend);
+    tt.run_once(async {
+        assert_eq!(*double(21).await?, 42);
+        Ok(())
+    })
+    .await?;
+
+    let trace = buffer.0.lock().unwrap().clone();
+    let events = TraceReader::new(&trace[..])?
+        .map(|record| Ok(record?.event))
+        .collect::<Result<Vec<_>>>()?;
+    let position = |predicate: &dyn Fn(&TraceEvent) -> bool| {
+        events
+            .iter()
+            .position(predicate)
+            .expect("event hasn't been recorded")
+    };
+
+    let created = position(&|event| match event {
+        TraceEvent::TaskCreated { description, .. } => description.contains("double"),
+        _ => false,
+    });
+    let TraceEvent::TaskCreated { task: double_task, .. } = events[created] else {
+        unreachable!()
+    };
+    let started = position(&|event| *event == TraceEvent::ExecutionStarted { task: double_task });
+    let updated = position(
+        &|event| matches!(event, TraceEvent::CellUpdated { task, .. } if *task == double_task),
+    );
+    let completed =
+        position(&|event| *event == TraceEvent::ExecutionCompleted { task: double_task });
+    let read = position(&|event| match event {
+        TraceEvent::CellRead { task, reader, .. } => *task == double_task && reader.is_some(),
+        _ => false,
+    });
+    assert!(created < started);
+    assert!(started < updated && updated < completed);
+    assert!(updated < read);
+
+    // The value type of the cell is named before the first event referring to
+    // it.
+    let TraceEvent::CellUpdated { cell, .. } = events[updated] else {
+        unreachable!()
+    };
+    let named = position(&|event| match event {
+        TraceEvent::ValueType { id, name } => *id == cell.type_id && name.contains("U32"),
+        _ => false,
+    });
+    assert!(named < updated);
+    Ok(())
+}
+
+#[turbo_tasks::function]
+fn double(value: u32) -> U32Vc {
+    U32Vc::cell(value * 2)
+}
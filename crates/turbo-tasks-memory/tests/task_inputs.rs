@@ -0,0 +1,90 @@
+#![feature(min_specialization)]
+
+use turbo_tasks::{primitives::StringVc, TaskInput};
+use turbo_tasks_testing::{register, run};
+
+register!();
+
+#[tokio::test]
+async fn float_arguments() {
+    run! {
+        assert_eq!(*scale(1.5, 2.0).await?, "3");
+        assert_eq!(*scale(f64::NAN, 1.0).await?, "NaN");
+    }
+}
+
+#[tokio::test]
+async fn byte_arguments() {
+    run! {
+        assert_eq!(*byte_sum(vec![1, 2, 3]).await?, "6");
+        assert_eq!(*byte_slice_len(&[1, 2, 3, 4]).await?, "4");
+    }
+}
+
+#[test]
+fn byte_and_option_encoding() {
+    // Bytes are stored as a buffer, not as a list of numbers
+    assert_eq!(TaskInput::from(vec![1u8, 2]), TaskInput::Bytes(vec![1, 2]));
+    assert_eq!(TaskInput::from(&[1u8, 2][..]), TaskInput::Bytes(vec![1, 2]));
+
+    assert_eq!(TaskInput::from(None::<u32>), TaskInput::Option(None));
+    assert_eq!(
+        TaskInput::from(Some(None::<u32>)),
+        TaskInput::Option(Some(Box::new(TaskInput::Option(None))))
+    );
+    assert_ne!(
+        TaskInput::from(Some(None::<u32>)),
+        TaskInput::from(None::<Option<u32>>)
+    );
+}
+
+#[tokio::test]
+async fn tuple_arguments() {
+    run! {
+        assert_eq!(*tuple((7, "seven".to_string())).await?, "7 seven");
+        assert_eq!(*destructured_tuple((1, 2, 3)).await?, "6");
+    }
+}
+
+#[tokio::test]
+async fn option_arguments() {
+    run! {
+        assert_eq!(*option(None).await?, "none");
+        assert_eq!(*option(Some(Some(42))).await?, "some 42");
+        assert_eq!(*option(Some(None)).await?, "some none");
+    }
+}
+
+#[turbo_tasks::function]
+fn scale(value: f64, factor: f32) -> StringVc {
+    StringVc::cell((value * factor as f64).to_string())
+}
+
+#[turbo_tasks::function]
+fn byte_sum(bytes: Vec<u8>) -> StringVc {
+    StringVc::cell(bytes.iter().map(|b| *b as u32).sum::<u32>().to_string())
+}
+
+#[turbo_tasks::function]
+fn byte_slice_len(bytes: &[u8]) -> StringVc {
+    StringVc::cell(bytes.len().to_string())
+}
+
+#[turbo_tasks::function]
+fn tuple(value: (u32, String)) -> StringVc {
+    StringVc::cell(format!("{} {}", value.0, value.1))
+}
+
+#[turbo_tasks::function]
+fn destructured_tuple((a, b, c): (u32, u32, u32)) -> StringVc {
+    StringVc::cell((a + b + c).to_string())
+}
+
+#[turbo_tasks::function]
+fn option(value: Option<Option<u32>>) -> StringVc {
+    StringVc::cell(match value {
+        None => "none".to_string(),
+        Some(None) => "some none".to_string(),
+        Some(Some(value)) => format!("some {value}"),
+    })
+}
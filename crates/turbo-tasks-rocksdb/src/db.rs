@@ -212,6 +212,7 @@ table!(externally_active_tasks, (()) => [usize]);
 table!(potential_active_external_tasks, (()) => [usize]);
 table!(potential_dirty_active_tasks, (()) => [usize]);
 table!(pending_active_update, (()) => [usize]);
+table!(session_dependent_tasks, (()) => [usize]);
 
 database!(
     last_task_id,
@@ -225,5 +226,6 @@ database!(
     externally_active_tasks,
     potential_active_external_tasks,
     potential_dirty_active_tasks,
-    pending_active_update
+    pending_active_update,
+    session_dependent_tasks
 );
@@ -1,6 +1,7 @@
 use std::{
     collections::HashSet,
     fmt::Debug,
+    fs,
     path::Path,
     sync::atomic::{AtomicU8, AtomicUsize, Ordering},
 };
@@ -88,6 +89,8 @@ pub struct RocksDbPersistedGraph {
     task_id_forward_mapping: HashMap<TaskId, usize>,
     task_id_backward_mapping: HashMap<usize, TaskId>,
     last_task_id: AtomicUsize,
+    /// An outdated cache has been discarded when opening the graph.
+    discarded_outdated: bool,
     #[cfg(feature = "unsafe_once_map")]
     cache_once: turbo_tasks::util::OnceConcurrentlyMap<[u8], Result<usize, SharedError>>,
     #[cfg(not(feature = "unsafe_once_map"))]
@@ -97,15 +100,79 @@ pub struct RocksDbPersistedGraph {
     active_cache: InfiniteVec<AtomicU8>,
 }
 
+/// The version of the on-disk format. Caches written with a different version
+/// are discarded.
+///
+/// 2: Task inputs store options as `TaskInput::Option`.
+const FORMAT_VERSION: u32 = 2;
+
+/// The file in the cache directory storing the version of the cache.
+const VERSION_FILE: &str = "TURBO_TASKS_CACHE_VERSION";
+
+/// The prefix of the content of the [VERSION_FILE]. Directories are only
+/// discarded when their version file starts with it, so a misconfigured path
+/// never deletes anything that hasn't been written by this crate.
+const VERSION_MAGIC: &str = "turbo-tasks-rocksdb ";
+
+/// Makes all session dependent tasks dirty. They will be picked up by
+/// `get_dirty_active_tasks` at startup.
+fn invalidate_session_dependent_tasks(db: &Database) -> Result<()> {
+    let b = &mut db.batch();
+    for db_task in db.session_dependent_tasks.get_all(&())? {
+        db.state.merge(b, &db_task, &TaskStateChange::MakeDirty)?;
+        db.potential_dirty_active_tasks.insert(b, &(), &db_task)?;
+    }
+    b.write()?;
+    Ok(())
+}
+
 impl RocksDbPersistedGraph {
     pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::new_versioned(path, "")
+    }
+
+    /// Opens the persisted graph in `path`, discarding it when it was written
+    /// by a different `version` of the application or with a different
+    /// format.
+    ///
+    /// Tasks which were marked as session dependent, e.g. file system reads,
+    /// are made dirty, so they are executed again once they are active. Only
+    /// tasks depending on changed results will be invalidated by that.
+    pub fn new_versioned<P: AsRef<Path>>(path: P, version: &str) -> Result<Self> {
+        let path = path.as_ref();
+        let version = format!("{VERSION_MAGIC}{FORMAT_VERSION}:{version}");
+        let version_file = path.join(VERSION_FILE);
+        let mut discarded_outdated = false;
+        match fs::read_to_string(&version_file) {
+            Ok(existing_version) if existing_version == version => {}
+            Ok(existing_version) if existing_version.starts_with(VERSION_MAGIC) => {
+                fs::remove_dir_all(path)?;
+                discarded_outdated = true;
+            }
+            Ok(_) => {
+                return Err(anyhow!(
+                    "{} has not been written by turbo-tasks",
+                    version_file.display()
+                ));
+            }
+            Err(_) if path.exists() && path.read_dir()?.next().is_some() => {
+                return Err(anyhow!(
+                    "{} is not empty and doesn't contain a cache",
+                    path.display()
+                ));
+            }
+            Err(_) => {}
+        }
         let db = Database::open(path)?;
+        fs::write(&version_file, &version)?;
+        invalidate_session_dependent_tasks(&db)?;
         let last_id = db.last_task_id.get()?.unwrap_or_default();
         Ok(Self {
             database: db,
             task_id_forward_mapping: HashMap::new(),
             task_id_backward_mapping: HashMap::new(),
             last_task_id: AtomicUsize::new(last_id),
+            discarded_outdated,
             #[cfg(feature = "unsafe_once_map")]
             cache_once: turbo_tasks::util::OnceConcurrentlyMap::new(),
             #[cfg(not(feature = "unsafe_once_map"))]
@@ -115,6 +182,11 @@ impl RocksDbPersistedGraph {
         })
     }
 
+    /// Whether an outdated cache has been discarded when opening the graph.
+    pub fn discarded_outdated_cache(&self) -> bool {
+        self.discarded_outdated
+    }
+
     fn with_task_id_mapping<T>(&self, api: &dyn PersistedGraphApi, func: impl FnOnce() -> T) -> T {
         with_task_id_mapping(&PgApiMapping::new(self, api), func)
     }
@@ -420,6 +492,11 @@ impl PersistedGraph for RocksDbPersistedGraph {
             }
             db.dependencies.write(b, &db_task, &data.dependencies)?;
             db.pending_active_update.remove(b, &(), &db_task)?;
+            if state.session_dependent {
+                db.session_dependent_tasks.insert(b, &(), &db_task)?;
+            } else {
+                db.session_dependent_tasks.remove(b, &(), &db_task)?;
+            }
             b.write()?;
             let ty = db.task_type.get(&db_task)?.unwrap();
             match ty {
@@ -897,3 +974,81 @@ impl<'a> IdMapping<TaskId> for PgApiReadOnlyMapping<'a> {
         new_id
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use anyhow::Result;
+    use tempfile::TempDir;
+
+    use super::RocksDbPersistedGraph;
+    use crate::db::TaskStateChange;
+
+    /// Writes the children of a task, so tests can tell whether the cache has
+    /// been kept.
+    fn write_children(graph: &RocksDbPersistedGraph, task: usize) -> Result<()> {
+        let db = &graph.database;
+        let b = &mut db.batch();
+        db.children.write(b, &task, &vec![task + 1])?;
+        b.write()?;
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_a_cache_of_the_same_version() -> Result<()> {
+        let dir = TempDir::new()?;
+        write_children(&RocksDbPersistedGraph::new_versioned(dir.path(), "1")?, 1)?;
+
+        let graph = RocksDbPersistedGraph::new_versioned(dir.path(), "1")?;
+        assert!(!graph.discarded_outdated_cache());
+        assert_eq!(graph.database.children.get(&1)?, Some(vec![2]));
+        Ok(())
+    }
+
+    #[test]
+    fn discards_an_outdated_cache() -> Result<()> {
+        let dir = TempDir::new()?;
+        write_children(&RocksDbPersistedGraph::new_versioned(dir.path(), "1")?, 1)?;
+
+        let graph = RocksDbPersistedGraph::new_versioned(dir.path(), "2")?;
+        assert!(graph.discarded_outdated_cache());
+        assert_eq!(graph.database.children.get(&1)?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn refuses_to_discard_a_foreign_directory() -> Result<()> {
+        let dir = TempDir::new()?;
+        fs::write(dir.path().join("file"), "content")?;
+
+        assert!(RocksDbPersistedGraph::new_versioned(dir.path(), "1").is_err());
+        assert!(dir.path().join("file").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn invalidates_session_dependent_tasks() -> Result<()> {
+        let dir = TempDir::new()?;
+        {
+            let graph = RocksDbPersistedGraph::new_versioned(dir.path(), "1")?;
+            let db = &graph.database;
+            let b = &mut db.batch();
+            for task in [1, 2] {
+                db.state.merge(b, &task, &TaskStateChange::Persist(false))?;
+            }
+            db.session_dependent_tasks.insert(b, &(), &1)?;
+            b.write()?;
+        }
+
+        let graph = RocksDbPersistedGraph::new_versioned(dir.path(), "1")?;
+        let db = &graph.database;
+        let is_clean = |task: usize| -> Result<bool> {
+            Ok(db.state.get(&task)?.unwrap().internal.unwrap().clean)
+        };
+        assert!(!is_clean(1)?);
+        assert!(is_clean(2)?);
+        assert_eq!(db.potential_dirty_active_tasks.get_all(&())?, vec![1]);
+        Ok(())
+    }
+}
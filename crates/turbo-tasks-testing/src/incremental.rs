@@ -0,0 +1,275 @@
+//! A harness for testing that turbo-tasks functions recompute exactly when
+//! expected.
+//!
+//! ```ignore
+//! let mut test = IncrementalTest::start(
+//!     MemoryBackend::new(),
+//!     async { Ok(InputVc::new(1)) },
+//!     |input| async move { Ok(*double(input).await?) },
+//! )
+//! .await?;
+//! test.step().assert_executed("double", 1);
+//! test.update(|input| async move {
+//!     input.await?.value.set(2);
+//!     Ok(())
+//! })
+//! .await?
+//! .assert_executed("double", 1)
+//! .assert_output_changed();
+//! ```
+
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    future::Future,
+    io::{self, Write},
+    mem::take,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{anyhow, Result};
+use turbo_tasks::{
+    backend::Backend,
+    recorder::{RecordingBackend, TraceEvent, TraceReader},
+    trace::TraceRawVcs,
+    NothingVc, TaskId, TurboTasks,
+};
+
+/// A trace writer that keeps the trace in memory.
+#[derive(Clone, Default)]
+struct TraceBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for TraceBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Runs a computation in a root task and reports which functions have been
+/// executed after each update of its inputs.
+pub struct IncrementalTest<B: Backend + 'static, I, T> {
+    turbo_tasks: Arc<TurboTasks<RecordingBackend<B>>>,
+    trace: TraceBuffer,
+    seen_records: usize,
+    task_names: HashMap<usize, String>,
+    inputs: I,
+    root: TaskId,
+    output: Arc<Mutex<Option<Result<T, String>>>>,
+    step: Step<T>,
+}
+
+impl<B, I, T> IncrementalTest<B, I, T>
+where
+    B: Backend + 'static,
+    I: TraceRawVcs + Clone + Send + Sync + 'static,
+    T: Clone + PartialEq + Debug + Send + 'static,
+{
+    /// Creates the inputs in a once task, then spawns a root task that calls
+    /// `compute` with them and waits until it has settled.
+    pub async fn start<F, Fut>(
+        backend: B,
+        inputs: impl Future<Output = Result<I>> + Send + 'static,
+        compute: F,
+    ) -> Result<Self>
+    where
+        F: Fn(I) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<T>> + Send + 'static,
+    {
+        let trace = TraceBuffer::default();
+        let backend = RecordingBackend::with_writer(backend, Box::new(trace.clone()))?;
+        let turbo_tasks = TurboTasks::new(backend);
+        let inputs = turbo_tasks.run_once(inputs).await?;
+        let output = Arc::new(Mutex::new(None));
+        let root = {
+            let inputs = inputs.clone();
+            let output = output.clone();
+            turbo_tasks.spawn_root_task(move || {
+                let result = compute(inputs.clone());
+                let output = output.clone();
+                Box::pin(async move {
+                    let result = result.await;
+                    *output.lock().unwrap() = Some(result.map_err(|err| format!("{err:?}")));
+                    Ok(NothingVc::new().into())
+                })
+            })
+        };
+        let mut test = Self {
+            turbo_tasks,
+            trace,
+            seen_records: 0,
+            task_names: HashMap::new(),
+            inputs,
+            root,
+            output,
+            step: Step {
+                output: None,
+                previous_output: None,
+                executions: HashMap::new(),
+            },
+        };
+        test.settle().await?;
+        Ok(test)
+    }
+
+    pub fn turbo_tasks(&self) -> &Arc<TurboTasks<RecordingBackend<B>>> {
+        &self.turbo_tasks
+    }
+
+    /// The result of the latest step, which is the initial computation right
+    /// after [IncrementalTest::start].
+    pub fn step(&self) -> &Step<T> {
+        &self.step
+    }
+
+    /// Mutates the inputs in a once task, e.g. by setting a
+    /// [turbo_tasks::State] or writing a file, and waits until the root
+    /// task has settled again.
+    pub async fn update<F, Fut>(&mut self, mutate: F) -> Result<&Step<T>>
+    where
+        F: FnOnce(I) -> Fut,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.turbo_tasks
+            .run_once(mutate(self.inputs.clone()))
+            .await?;
+        self.settle().await?;
+        Ok(&self.step)
+    }
+
+    async fn settle(&mut self) -> Result<()> {
+        self.turbo_tasks
+            .wait_task_completion(self.root, true)
+            .await?;
+        self.turbo_tasks.backend().flush()?;
+        let trace = self.trace.0.lock().unwrap().clone();
+        let mut executions = HashMap::new();
+        for record in TraceReader::new(&trace[..])?.skip(self.seen_records) {
+            self.seen_records += 1;
+            match record?.event {
+                TraceEvent::TaskCreated {
+                    task, description, ..
+                } => {
+                    // Descriptions are prefixed with the task id, e.g. "[42] my_function"
+                    let name = description
+                        .strip_prefix('[')
+                        .and_then(|description| description.split_once("] "))
+                        .map_or_else(|| description.clone(), |(_, name)| name.to_string());
+                    self.task_names.insert(task, name);
+                }
+                TraceEvent::ExecutionStarted { task } => {
+                    let name = self
+                        .task_names
+                        .get(&task)
+                        .cloned()
+                        .unwrap_or_else(|| format!("unknown task {task}"));
+                    // The once tasks mutating the inputs are not part of the
+                    // computation
+                    if name == "once" {
+                        continue;
+                    }
+                    *executions.entry(name).or_default() += 1;
+                }
+                _ => {}
+            }
+        }
+        let output = self
+            .output
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or_else(|| anyhow!("the root task hasn't produced an output"))?
+            .map_err(|err| anyhow!("the computation failed: {err}"))?;
+        self.step = Step {
+            previous_output: take(&mut self.step.output),
+            output: Some(output),
+            executions,
+        };
+        Ok(())
+    }
+}
+
+/// The outcome of a step of an [IncrementalTest]. The assertions panic with
+/// a description of all executions of the step when they fail.
+#[derive(Debug)]
+pub struct Step<T> {
+    output: Option<T>,
+    previous_output: Option<T>,
+    executions: HashMap<String, usize>,
+}
+
+impl<T: PartialEq + Debug> Step<T> {
+    /// The output of the computation after the step.
+    pub fn output(&self) -> &T {
+        self.output.as_ref().unwrap()
+    }
+
+    /// The number of executions of tasks of `function` during the step.
+    /// Functions are named like in task descriptions, e.g. `my_function` or
+    /// `[resolve] my_function` for the task resolving the arguments.
+    pub fn executions(&self, function: &str) -> usize {
+        self.executions.get(function).copied().unwrap_or_default()
+    }
+
+    /// All functions executed during the step and their execution counts,
+    /// sorted by name.
+    pub fn executed_functions(&self) -> Vec<(&str, usize)> {
+        let mut functions = self
+            .executions
+            .iter()
+            .map(|(name, count)| (name.as_str(), *count))
+            .collect::<Vec<_>>();
+        functions.sort();
+        functions
+    }
+
+    pub fn assert_executed(&self, function: &str, times: usize) -> &Self {
+        assert_eq!(
+            self.executions(function),
+            times,
+            "expected {function} to be executed {times} times, executed functions: {:?}",
+            self.executed_functions()
+        );
+        self
+    }
+
+    pub fn assert_not_executed(&self, function: &str) -> &Self {
+        self.assert_executed(function, 0)
+    }
+
+    /// Asserts that no other functions than `functions` have been executed.
+    pub fn assert_only_executed(&self, functions: &[&str]) -> &Self {
+        let unexpected = self
+            .executed_functions()
+            .into_iter()
+            .filter(|(name, _)| *name != "root" && !functions.contains(name))
+            .collect::<Vec<_>>();
+        assert!(
+            unexpected.is_empty(),
+            "expected only {functions:?} to be executed, but also executed: {unexpected:?}"
+        );
+        self
+    }
+
+    pub fn assert_output_unchanged(&self) -> &Self {
+        assert_eq!(
+            self.previous_output.as_ref(),
+            self.output.as_ref(),
+            "expected the output to stay equal"
+        );
+        self
+    }
+
+    pub fn assert_output_changed(&self) -> &Self {
+        assert_ne!(
+            self.previous_output.as_ref(),
+            self.output.as_ref(),
+            "expected the output to change"
+        );
+        self
+    }
+}
@@ -2,6 +2,7 @@
 
 #![feature(box_syntax)]
 
+pub mod incremental;
 mod macros;
 pub mod retry;
 
@@ -16,11 +17,11 @@ use std::{
 use anyhow::Result;
 use auto_hash_map::AutoSet;
 use turbo_tasks::{
-    backend::CellContent,
+    backend::{CellContent, InvalidationReason},
     event::{Event, EventListener},
     registry,
     test_helpers::{current_task_for_testing, with_turbo_tasks_for_testing},
-    CellId, RawVc, TaskId, TraitTypeId, TurboTasksApi, TurboTasksCallApi,
+    CellId, RawVc, TaskId, TaskPriority, TraitTypeId, TurboTasksApi, TurboTasksCallApi,
 };
 
 enum Task {
@@ -91,6 +92,14 @@ impl TurboTasksCallApi for VcStorage {
         unreachable!()
     }
 
+    fn run_once_with_priority(
+        &self,
+        _priority: TaskPriority,
+        _future: std::pin::Pin<Box<dyn Future<Output = Result<()>> + Send + 'static>>,
+    ) -> TaskId {
+        unreachable!()
+    }
+
     fn run_once_process(
         &self,
         _future: std::pin::Pin<Box<dyn Future<Output = Result<()>> + Send + 'static>>,
@@ -104,6 +113,22 @@ impl TurboTasksApi for VcStorage {
         unreachable!()
     }
 
+    fn invalidate_with_reason(&self, _task: TaskId, _reason: Cow<'static, str>) {
+        unreachable!()
+    }
+
+    fn invalidation_reason(&self, _task: TaskId) -> Option<InvalidationReason> {
+        None
+    }
+
+    fn cancel_task(&self, _task: TaskId) {
+        // ignore
+    }
+
+    fn cancel_once_task(&self, _task: TaskId) {
+        // ignore
+    }
+
     fn notify_scheduled_tasks(&self) {
         // ignore
     }
@@ -203,6 +228,10 @@ impl TurboTasksApi for VcStorage {
         let cell = map.entry((task, index)).or_default();
         *cell = content;
     }
+
+    fn mark_own_task_as_session_dependent(&self, _task: TaskId) {
+        // ignore
+    }
 }
 
 impl VcStorage {
@@ -0,0 +1,292 @@
+//! Inspects and replays task graph traces recorded with a
+//! `turbo_tasks::recorder::RecordingBackend`.
+//!
+//! Usage: `cargo run -p turbo-tasks --example trace -- <command> <trace>`
+
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    process::exit,
+    time::Duration,
+};
+
+use anyhow::{bail, Result};
+use turbo_tasks::{
+    recorder::{TraceCell, TraceEvent, TraceReader, TraceRecord, TraceVc},
+    util::FormatDuration,
+};
+
+const USAGE: &str = "Usage: trace <dump|stats|replay> <trace>
+
+Commands:
+  dump    Prints all events of the trace
+  stats   Prints event counts and the most executed tasks and read cells
+  replay  Replays the trace and reports cell updates which didn't invalidate
+          the tasks that read the cell";
+
+fn main() -> Result<()> {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let [command, path] = &args[..] else {
+        eprintln!("{USAGE}");
+        exit(1);
+    };
+    let records = TraceReader::open(path)?.collect::<Result<Vec<_>>>()?;
+    let names = Names::new(&records);
+    match command.as_str() {
+        "dump" => dump(&records, &names),
+        "stats" => stats(&records, &names),
+        "replay" => replay(&records, &names),
+        _ => bail!("unknown command {command}\n\n{USAGE}"),
+    }
+    Ok(())
+}
+
+/// Names of tasks, value types and traits, as recorded in the trace.
+#[derive(Default)]
+struct Names {
+    tasks: HashMap<usize, String>,
+    value_types: HashMap<usize, String>,
+    trait_types: HashMap<usize, String>,
+}
+
+impl Names {
+    fn new(records: &[TraceRecord]) -> Self {
+        let mut names = Names::default();
+        for record in records {
+            match &record.event {
+                TraceEvent::TaskCreated {
+                    task, description, ..
+                } => {
+                    names.tasks.insert(*task, description.clone());
+                }
+                TraceEvent::ValueType { id, name } => {
+                    names.value_types.insert(*id, name.clone());
+                }
+                TraceEvent::TraitType { id, name } => {
+                    names.trait_types.insert(*id, name.clone());
+                }
+                _ => {}
+            }
+        }
+        names
+    }
+
+    fn task(&self, task: usize) -> String {
+        match self.tasks.get(&task) {
+            Some(description) => description.clone(),
+            None => format!("[{task}] unknown task"),
+        }
+    }
+
+    fn cell(&self, task: usize, cell: TraceCell) -> String {
+        let ty = self
+            .value_types
+            .get(&cell.type_id)
+            .map_or("unknown type", |name| name.as_str());
+        format!("{ty} cell {} of {}", cell.index, self.task(task))
+    }
+
+    fn trait_type(&self, trait_type: usize) -> &str {
+        self.trait_types
+            .get(&trait_type)
+            .map_or("unknown trait", |name| name.as_str())
+    }
+
+    fn vc(&self, vc: TraceVc) -> String {
+        match vc {
+            TraceVc::TaskOutput(task) => format!("output of {}", self.task(task)),
+            TraceVc::TaskCell(task, cell) => self.cell(task, cell),
+        }
+    }
+
+    fn reader(&self, reader: Option<usize>) -> String {
+        match reader {
+            Some(reader) => self.task(reader),
+            None => "untracked".to_string(),
+        }
+    }
+
+    fn event(&self, event: &TraceEvent) -> Option<String> {
+        Some(match event {
+            TraceEvent::ValueType { .. } | TraceEvent::TraitType { .. } => return None,
+            TraceEvent::TaskCreated { task, parent, .. } => match parent {
+                Some(parent) => format!("created {} from {}", self.task(*task), self.task(*parent)),
+                None => format!("created {}", self.task(*task)),
+            },
+            TraceEvent::ExecutionStarted { task } => format!("started {}", self.task(*task)),
+            TraceEvent::ExecutionCompleted { task } => {
+                format!("completed {}", self.task(*task))
+            }
+            TraceEvent::OutputRead { task, reader } => format!(
+                "{} read output of {}",
+                self.reader(*reader),
+                self.task(*task)
+            ),
+            TraceEvent::CellRead { task, cell, reader } => {
+                format!("{} read {}", self.reader(*reader), self.cell(*task, *cell))
+            }
+            TraceEvent::CellUpdated { task, cell } => {
+                format!("updated {}", self.cell(*task, *cell))
+            }
+            TraceEvent::CollectiblesRead {
+                task,
+                trait_type,
+                reader,
+            } => format!(
+                "{} read {} collectibles of {}",
+                self.task(*reader),
+                self.trait_type(*trait_type),
+                self.task(*task)
+            ),
+            TraceEvent::CollectibleEmitted {
+                task,
+                trait_type,
+                collectible,
+            } => format!(
+                "{} emitted {} {}",
+                self.task(*task),
+                self.trait_type(*trait_type),
+                self.vc(*collectible)
+            ),
+            TraceEvent::CollectibleUnemitted {
+                task,
+                trait_type,
+                collectible,
+            } => format!(
+                "{} unemitted {} {}",
+                self.task(*task),
+                self.trait_type(*trait_type),
+                self.vc(*collectible)
+            ),
+            TraceEvent::Invalidated { task } => format!("invalidated {}", self.task(*task)),
+        })
+    }
+}
+
+fn dump(records: &[TraceRecord], names: &Names) {
+    for record in records {
+        if let Some(event) = names.event(&record.event) {
+            println!("{:>12} {event}", format_time(record.time));
+        }
+    }
+}
+
+fn format_time(time: Duration) -> String {
+    format!("{}", FormatDuration(time))
+}
+
+fn stats(records: &[TraceRecord], names: &Names) {
+    let mut counts = HashMap::<&str, usize>::new();
+    let mut executions = HashMap::<usize, usize>::new();
+    let mut cell_reads = HashMap::<(usize, TraceCell), usize>::new();
+    for record in records {
+        let kind = match &record.event {
+            TraceEvent::ValueType { .. } | TraceEvent::TraitType { .. } => continue,
+            TraceEvent::TaskCreated { .. } => "task created",
+            TraceEvent::ExecutionStarted { task } => {
+                *executions.entry(*task).or_default() += 1;
+                "execution started"
+            }
+            TraceEvent::ExecutionCompleted { .. } => "execution completed",
+            TraceEvent::OutputRead { .. } => "output read",
+            TraceEvent::CellRead { task, cell, .. } => {
+                *cell_reads.entry((*task, *cell)).or_default() += 1;
+                "cell read"
+            }
+            TraceEvent::CellUpdated { .. } => "cell updated",
+            TraceEvent::CollectiblesRead { .. } => "collectibles read",
+            TraceEvent::CollectibleEmitted { .. } => "collectible emitted",
+            TraceEvent::CollectibleUnemitted { .. } => "collectible unemitted",
+            TraceEvent::Invalidated { .. } => "invalidated",
+        };
+        *counts.entry(kind).or_default() += 1;
+    }
+
+    let duration = records.last().map_or(Duration::ZERO, |record| record.time);
+    println!("{} events in {}", records.len(), format_time(duration));
+    let mut counts = counts.into_iter().collect::<Vec<_>>();
+    counts.sort_by(|(a_kind, a), (b_kind, b)| b.cmp(a).then(a_kind.cmp(b_kind)));
+    for (kind, count) in counts {
+        println!("{count:>10} {kind}");
+    }
+
+    println!("\nmost executed tasks:");
+    let mut executions = executions.into_iter().collect::<Vec<_>>();
+    executions.sort_by(|(a_task, a), (b_task, b)| b.cmp(a).then(a_task.cmp(b_task)));
+    for (task, count) in executions.into_iter().take(20) {
+        println!("{count:>10} {}", names.task(task));
+    }
+
+    println!("\nmost read cells:");
+    let mut cell_reads = cell_reads.into_iter().collect::<Vec<_>>();
+    cell_reads.sort_by(|(a_cell, a), (b_cell, b)| b.cmp(a).then(a_cell.cmp(b_cell)));
+    for ((task, cell), count) in cell_reads.into_iter().take(20) {
+        println!("{count:>10} {}", names.cell(task, cell));
+    }
+}
+
+/// Replays the trace, tracking which tasks read which cells during their
+/// latest execution. When a cell is updated, all of these tasks need to be
+/// invalidated or executed again afterwards, otherwise the update didn't
+/// propagate.
+fn replay(records: &[TraceRecord], names: &Names) {
+    // cell -> tasks that read the cell during their latest execution
+    let mut dependents = HashMap::<(usize, TraceCell), HashSet<usize>>::new();
+    // task -> cells read during its latest execution
+    let mut dependencies = HashMap::<usize, HashSet<(usize, TraceCell)>>::new();
+    // task -> update it hasn't been invalidated for yet
+    let mut pending = HashMap::<usize, (Duration, usize, TraceCell)>::new();
+
+    for record in records {
+        match &record.event {
+            TraceEvent::ExecutionStarted { task } => {
+                pending.remove(task);
+                for cell in dependencies.remove(task).into_iter().flatten() {
+                    if let Some(tasks) = dependents.get_mut(&cell) {
+                        tasks.remove(task);
+                    }
+                }
+            }
+            TraceEvent::Invalidated { task } => {
+                pending.remove(task);
+            }
+            &TraceEvent::CellRead {
+                task,
+                cell,
+                reader: Some(reader),
+            } => {
+                dependents.entry((task, cell)).or_default().insert(reader);
+                dependencies.entry(reader).or_default().insert((task, cell));
+            }
+            &TraceEvent::CellUpdated { task, cell } => {
+                for &reader in dependents.get(&(task, cell)).into_iter().flatten() {
+                    // A task updating a cell it has read itself is invalidated by its own
+                    // execution.
+                    if reader != task {
+                        pending.entry(reader).or_insert((record.time, task, cell));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if pending.is_empty() {
+        println!("all cell updates have been propagated to the tasks reading them");
+        return;
+    }
+    let mut pending = pending.into_iter().collect::<Vec<_>>();
+    pending.sort_by_key(|(task, (time, ..))| (*time, *task));
+    for (reader, (time, task, cell)) in pending.iter() {
+        println!(
+            "{:>12} update of {} didn't invalidate {}",
+            format_time(*time),
+            names.cell(*task, *cell),
+            names.task(*reader)
+        );
+    }
+    println!(
+        "\n{} tasks haven't been invalidated after a cell they read was updated",
+        pending.len()
+    );
+}
@@ -59,6 +59,54 @@ impl Debug for TransientTaskType {
     }
 }
 
+/// The change that caused a task to be invalidated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvalidationReason {
+    /// A cell the task has read changed.
+    CellChanged { task: TaskId, cell: CellId },
+    /// The output of a task the task has read changed.
+    OutputChanged { task: TaskId },
+    /// Collectibles the task has read changed, because `task` emitted or
+    /// unemitted a collectible or has been connected or disconnected.
+    CollectiblesChanged { task: TaskId },
+    /// An [Invalidator](crate::Invalidator) of the task has been called, e.g.
+    /// because a file it has read changed.
+    Invalidator {
+        description: Option<Cow<'static, str>>,
+    },
+}
+
+impl InvalidationReason {
+    /// The task whose change caused the invalidation, if any. The reason that
+    /// task has been executed again continues the invalidation chain.
+    pub fn source_task(&self) -> Option<TaskId> {
+        match self {
+            InvalidationReason::CellChanged { task, .. }
+            | InvalidationReason::OutputChanged { task }
+            | InvalidationReason::CollectiblesChanged { task } => Some(*task),
+            InvalidationReason::Invalidator { .. } => None,
+        }
+    }
+}
+
+impl Display for InvalidationReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidationReason::CellChanged { task, cell } => {
+                write!(f, "{cell} of {task} changed")
+            }
+            InvalidationReason::OutputChanged { task } => write!(f, "output of {task} changed"),
+            InvalidationReason::CollectiblesChanged { task } => {
+                write!(f, "collectibles of {task} changed")
+            }
+            InvalidationReason::Invalidator {
+                description: Some(description),
+            } => write!(f, "invalidated: {description}"),
+            InvalidationReason::Invalidator { description: None } => write!(f, "invalidated"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum PersistentTaskType {
     /// A normal task execution a native (rust) function
@@ -171,8 +219,38 @@ pub trait Backend: Sync + Send {
 
     fn invalidate_tasks(&self, tasks: Vec<TaskId>, turbo_tasks: &dyn TurboTasksBackendApi);
 
+    /// Like [Backend::invalidate_task], with a description of the external
+    /// change, which backends can report as [InvalidationReason].
+    #[allow(unused_variables)]
+    fn invalidate_task_with_reason(
+        &self,
+        task: TaskId,
+        reason: Cow<'static, str>,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+        self.invalidate_task(task, turbo_tasks)
+    }
+
+    /// The reason for the latest invalidation of a task, if the backend
+    /// tracks it.
+    #[allow(unused_variables)]
+    fn get_invalidation_reason(&self, task: TaskId) -> Option<InvalidationReason> {
+        None
+    }
+
     fn get_task_description(&self, task: TaskId) -> String;
 
+    /// Marks a task as depending on state outside of turbo-tasks that doesn't
+    /// survive the session, e.g. file system reads. Backends that persist
+    /// the task graph need to re-execute such tasks when restoring it.
+    #[allow(unused_variables)]
+    fn mark_own_task_as_session_dependent(
+        &self,
+        task: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+    }
+
     type ExecutionScopeFuture<T: Future<Output = Result<()>> + Send + 'static>: Future<Output = Result<()>>
         + Send
         + 'static;
@@ -196,6 +274,17 @@ pub trait Backend: Sync + Send {
         turbo_tasks: &dyn TurboTasksBackendApi,
     );
 
+    /// Called instead of [Backend::task_execution_result] when the execution
+    /// has been aborted by a cancellation. By default the task fails with an
+    /// error.
+    fn task_execution_cancelled(&self, task: TaskId, turbo_tasks: &dyn TurboTasksBackendApi) {
+        self.task_execution_result(
+            task,
+            Ok(Err(anyhow!("task execution was cancelled"))),
+            turbo_tasks,
+        );
+    }
+
     fn task_execution_completed(
         &self,
         task: TaskId,
@@ -302,6 +391,11 @@ pub trait Backend: Sync + Send {
         task_type: TransientTaskType,
         turbo_tasks: &dyn TurboTasksBackendApi,
     ) -> TaskId;
+
+    /// Disposes a root task created by [Backend::create_transient_task]. It
+    /// no longer keeps the tasks it depends on active.
+    #[allow(unused_variables)]
+    fn dispose_root_task(&self, task: TaskId, turbo_tasks: &dyn TurboTasksBackendApi) {}
 }
 
 impl PersistentTaskType {
@@ -0,0 +1,66 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use futures::task::AtomicWaker;
+use pin_project_lite::pin_project;
+
+/// Cancellation state of a single task execution.
+#[derive(Default)]
+pub struct ExecutionCancellation {
+    cancelled: AtomicBool,
+    waker: AtomicWaker,
+}
+
+impl ExecutionCancellation {
+    /// Requests cancellation. The execution is aborted the next time it is
+    /// polled, which happens at the latest when it reaches an await point.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Release);
+        self.waker.wake();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Acquire)
+    }
+}
+
+pin_project! {
+    /// A future that resolves to `None` instead of polling the inner future
+    /// once the execution has been cancelled.
+    pub struct CancellableFuture<F> {
+        #[pin]
+        future: F,
+        cancellation: Arc<ExecutionCancellation>,
+    }
+}
+
+impl<F: Future> CancellableFuture<F> {
+    pub fn new(future: F, cancellation: Arc<ExecutionCancellation>) -> Self {
+        Self {
+            future,
+            cancellation,
+        }
+    }
+}
+
+impl<F: Future> Future for CancellableFuture<F> {
+    type Output = Option<F::Output>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        // Register before checking the flag so a concurrent cancel can't be
+        // missed.
+        this.cancellation.waker.register(cx.waker());
+        if this.cancellation.is_cancelled() {
+            return Poll::Ready(None);
+        }
+        this.future.poll(cx).map(Some)
+    }
+}
@@ -34,6 +34,7 @@
 #![feature(new_uninit)]
 
 pub mod backend;
+mod cancellation;
 mod collectibles;
 mod completion;
 pub mod debug;
@@ -50,8 +51,10 @@ mod nothing;
 mod once_map;
 pub mod persisted_graph;
 pub mod primitives;
+mod priority;
 mod raw_vc;
 mod read_ref;
+pub mod recorder;
 pub mod registry;
 pub mod small_duration;
 mod state;
@@ -72,12 +75,13 @@ pub use id::{
 };
 pub use join_iter_ext::{JoinIterExt, TryJoinIterExt};
 pub use manager::{
-    dynamic_call, emit, get_invalidator, mark_stateful, run_once, spawn_blocking, spawn_thread,
-    trait_call, turbo_tasks, Invalidator, StatsType, TaskIdProvider, TurboTasks, TurboTasksApi,
-    TurboTasksBackendApi, TurboTasksCallApi,
+    dynamic_call, emit, get_invalidator, mark_session_dependent, mark_stateful, run_once,
+    run_once_with_priority, spawn_blocking, spawn_thread, trait_call, turbo_tasks, Invalidator,
+    StatsType, TaskIdProvider, TurboTasks, TurboTasksApi, TurboTasksBackendApi, TurboTasksCallApi,
 };
 pub use native_function::{NativeFunction, NativeFunctionVc};
 pub use nothing::{Nothing, NothingVc};
+pub use priority::TaskPriority;
 pub use raw_vc::{CellId, CollectiblesFuture, RawVc, ReadRawVcFuture, ResolveTypeError};
 pub use read_ref::ReadRef;
 pub use state::State;
@@ -16,16 +16,19 @@ use std::{
 
 use anyhow::{anyhow, Result};
 use auto_hash_map::AutoSet;
+use dashmap::{DashMap, DashSet};
 use futures::FutureExt;
 use nohash_hasher::BuildNoHashHasher;
 use serde::{de::Visitor, Deserialize, Serialize};
 use tokio::{runtime::Handle, select, task_local};
 
 use crate::{
-    backend::{Backend, CellContent, PersistentTaskType, TransientTaskType},
+    backend::{Backend, CellContent, InvalidationReason, PersistentTaskType, TransientTaskType},
+    cancellation::{CancellableFuture, ExecutionCancellation},
     event::{Event, EventListener},
     id::{BackendJobId, FunctionId, TraitTypeId},
     id_factory::IdFactory,
+    priority::{PriorityFuture, PriorityScheduler, TaskPriority},
     raw_vc::{CellId, RawVc},
     registry,
     task_input::{SharedReference, TaskInput},
@@ -49,6 +52,11 @@ pub trait TurboTasksCallApi: Sync + Send {
         &self,
         future: Pin<Box<dyn Future<Output = Result<()>> + Send + 'static>>,
     ) -> TaskId;
+    fn run_once_with_priority(
+        &self,
+        priority: TaskPriority,
+        future: Pin<Box<dyn Future<Output = Result<()>> + Send + 'static>>,
+    ) -> TaskId;
     fn run_once_process(
         &self,
         future: Pin<Box<dyn Future<Output = Result<()>> + Send + 'static>>,
@@ -57,6 +65,24 @@ pub trait TurboTasksCallApi: Sync + Send {
 
 pub trait TurboTasksApi: TurboTasksCallApi + Sync + Send {
     fn invalidate(&self, task: TaskId);
+    /// Like [TurboTasksApi::invalidate], with a description of the external
+    /// change that caused the invalidation.
+    fn invalidate_with_reason(&self, task: TaskId, reason: Cow<'static, str>);
+
+    /// The reason for the latest invalidation of a task, if the backend
+    /// tracks it. Following [InvalidationReason::source_task] leads to the
+    /// external change that caused a chain of invalidations.
+    fn invalidation_reason(&self, task: TaskId) -> Option<InvalidationReason>;
+
+    /// Cancels the running execution of a task, e.g. when its result is no
+    /// longer needed. The execution is aborted at its next await point and
+    /// the backend decides if and when the task is executed again.
+    fn cancel_task(&self, task: TaskId);
+
+    /// Cancels a once task whose result is no longer needed. Unlike
+    /// [TurboTasksApi::cancel_task] this also applies when the task hasn't
+    /// started yet, it's skipped then.
+    fn cancel_once_task(&self, task: TaskId);
 
     /// Eagerly notifies all tasks that were scheduled for notifications via
     /// `schedule_notify_tasks_set()`
@@ -110,6 +136,8 @@ pub trait TurboTasksApi: TurboTasksCallApi + Sync + Send {
 
     fn read_current_task_cell(&self, index: CellId) -> Result<CellContent>;
     fn update_current_task_cell(&self, index: CellId, content: CellContent);
+
+    fn mark_own_task_as_session_dependent(&self, task: TaskId);
 }
 
 /// The type of stats reporting.
@@ -146,6 +174,9 @@ pub trait TurboTasksBackendApi: TaskIdProvider + TurboTasksCallApi + Sync + Send
     fn pin(&self) -> Arc<dyn TurboTasksBackendApi>;
 
     fn schedule(&self, task: TaskId);
+    /// Aborts the running execution of a task at its next await point, e.g.
+    /// because it has been invalidated while in progress.
+    fn cancel_task_execution(&self, task: TaskId);
     fn schedule_backend_background_job(&self, id: BackendJobId);
     fn schedule_backend_foreground_job(&self, id: BackendJobId);
 
@@ -208,6 +239,13 @@ pub struct TurboTasks<B: Backend + 'static> {
     currently_scheduled_foreground_jobs: AtomicUsize,
     currently_scheduled_background_jobs: AtomicUsize,
     scheduled_tasks: AtomicUsize,
+    running_executions: DashMap<TaskId, Arc<ExecutionCancellation>, BuildNoHashHasher<TaskId>>,
+    /// Once tasks which have been cancelled before they started executing.
+    cancelled_once_tasks: DashSet<TaskId, BuildNoHashHasher<TaskId>>,
+    priority_scheduler: Arc<PriorityScheduler>,
+    /// Priorities of root tasks spawned with an explicit priority. They are
+    /// needed again whenever the root task is re-executed.
+    root_task_priorities: DashMap<TaskId, TaskPriority, BuildNoHashHasher<TaskId>>,
     start: Mutex<Option<Instant>>,
     aggregated_update: Mutex<Option<(Duration, usize)>>,
     event: Event,
@@ -229,6 +267,9 @@ task_local! {
 
     static CURRENT_TASK_ID: TaskId;
 
+    /// The priority of the current task, inherited by tasks scheduled from it
+    static CURRENT_PRIORITY: TaskPriority;
+
     /// Affected [Task]s, that are tracked during task execution
     /// These tasks will be invalidated when the execution finishes
     /// or before reading a cell value
@@ -253,6 +294,10 @@ impl<B: Backend> TurboTasks<B> {
             currently_scheduled_background_jobs: AtomicUsize::new(0),
             currently_scheduled_foreground_jobs: AtomicUsize::new(0),
             scheduled_tasks: AtomicUsize::new(0),
+            running_executions: Default::default(),
+            cancelled_once_tasks: Default::default(),
+            priority_scheduler: Default::default(),
+            root_task_priorities: Default::default(),
             start: Default::default(),
             aggregated_update: Default::default(),
             event: Event::new(|| "TurboTasks::event".to_string()),
@@ -285,6 +330,32 @@ impl<B: Backend> TurboTasks<B> {
         id
     }
 
+    /// Creates a new root task with a priority, which is inherited by all
+    /// tasks it schedules.
+    pub fn spawn_root_task_with_priority(
+        &self,
+        priority: TaskPriority,
+        functor: impl Fn() -> Pin<Box<dyn Future<Output = Result<RawVc>> + Send>>
+            + Sync
+            + Send
+            + 'static,
+    ) -> TaskId {
+        let id = self
+            .backend
+            .create_transient_task(TransientTaskType::Root(Box::new(functor)), self);
+        self.root_task_priorities.insert(id, priority);
+        self.schedule_with_priority(id, priority);
+        id
+    }
+
+    /// Disposes a root task that is no longer needed. It's not executed again
+    /// when its dependencies change.
+    pub fn dispose_root_task(&self, task_id: TaskId) {
+        self.root_task_priorities.remove(&task_id);
+        self.priority_scheduler.reset(task_id);
+        self.backend.dispose_root_task(task_id, self);
+    }
+
     // TODO make sure that all dependencies settle before reading them
     /// Creates a new root task, that is only executed once.
     /// Dependencies will not invalidate the task.
@@ -300,6 +371,21 @@ impl<B: Backend> TurboTasks<B> {
         id
     }
 
+    /// Creates a new root task with a priority, that is only executed once.
+    /// The priority is inherited by all tasks it schedules.
+    #[track_caller]
+    pub fn spawn_once_task_with_priority(
+        &self,
+        priority: TaskPriority,
+        future: impl Future<Output = Result<RawVc>> + Send + 'static,
+    ) -> TaskId {
+        let id = self
+            .backend
+            .create_transient_task(TransientTaskType::Once(Box::pin(future)), self);
+        self.schedule_with_priority(id, priority);
+        id
+    }
+
     pub async fn run_once<T: TraceRawVcs + Send + 'static>(
         &self,
         future: impl Future<Output = Result<T>> + Send + 'static,
@@ -311,10 +397,12 @@ impl<B: Backend> TurboTasks<B> {
                 .map_err(|_| anyhow!("unable to send result"))?;
             Ok(CompletionVc::new().into())
         });
+        let guard = CancelOnDrop::new(self, task_id);
         // INVALIDATION: A Once task will never invalidate, therefore we don't need to
         // track a dependency
         let raw_result = read_task_output_untracked(self, task_id, false).await?;
         raw_result.into_read_untracked::<Completion>(self).await?;
+        guard.disarm();
 
         Ok(rx.await?)
     }
@@ -372,8 +460,45 @@ impl<B: Backend> TurboTasks<B> {
         ))
     }
 
+    /// Schedules a task with the priority it was spawned with, or the priority
+    /// of the task scheduling it.
     #[track_caller]
     pub(crate) fn schedule(&self, task_id: TaskId) {
+        let priority = self
+            .root_task_priorities
+            .get(&task_id)
+            .map(|priority| *priority)
+            .or_else(|| self.current_priority())
+            .unwrap_or_default();
+        self.schedule_with_priority(task_id, priority);
+    }
+
+    /// The effective priority of the current task, if any.
+    fn current_priority(&self) -> Option<TaskPriority> {
+        let task = CURRENT_TASK_ID.try_with(|id| *id).ok()?;
+        let priority = CURRENT_PRIORITY.try_with(|priority| *priority).ok()?;
+        Some(self.priority_scheduler.effective_priority(task, priority))
+    }
+
+    /// Raises the priority of a task the current task has to wait for to the
+    /// priority of the current task.
+    fn raise_awaited<T>(
+        &self,
+        task: TaskId,
+        result: Result<Result<T, EventListener>>,
+    ) -> Result<Result<T, EventListener>> {
+        if let Ok(Err(_)) = &result {
+            if let Some(priority) = self.current_priority() {
+                if priority > TaskPriority::Background {
+                    self.priority_scheduler.raise(task, priority);
+                }
+            }
+        }
+        result
+    }
+
+    #[track_caller]
+    fn schedule_with_priority(&self, task_id: TaskId, priority: TaskPriority) {
         self.begin_primary_job();
         self.scheduled_tasks.fetch_add(1, Ordering::AcqRel);
 
@@ -382,20 +507,34 @@ impl<B: Backend> TurboTasks<B> {
 
         let this = self.pin();
         let future = async move {
+            this.priority_scheduler
+                .wait_for_turn(task_id, priority)
+                .await;
             loop {
                 if this.stopped.load(Ordering::Acquire) {
                     break;
                 }
+                // Registered before starting the execution so cancellations
+                // can't get lost in between
+                let cancellation = this.register_execution(task_id);
+                if this.cancelled_once_tasks.remove(&task_id).is_some() {
+                    // The execution is aborted before it's polled at all.
+                    cancellation.cancel();
+                }
                 if let Some(execution) = this.backend.try_start_task_execution(task_id, &*this) {
                     // Setup thread locals
                     let (result, duration, instant) = CELL_COUNTERS
                         .scope(Default::default(), async {
                             let (result, duration, instant) =
-                                TimedFuture::new(AssertUnwindSafe(execution.future).catch_unwind())
-                                    .await;
+                                TimedFuture::new(CancellableFuture::new(
+                                    AssertUnwindSafe(execution.future).catch_unwind(),
+                                    cancellation,
+                                ))
+                                .await;
                             (result, duration, instant)
                         })
                         .await;
+                    this.unregister_execution(task_id);
                     if cfg!(feature = "log_function_stats") && duration.as_millis() > 1000 {
                         println!(
                             "{} took {}",
@@ -403,14 +542,18 @@ impl<B: Backend> TurboTasks<B> {
                             FormatDuration(duration)
                         )
                     }
-                    let result = result.map_err(|any| match any.downcast::<String>() {
-                        Ok(owned) => Some(Cow::Owned(*owned)),
-                        Err(any) => match any.downcast::<&'static str>() {
-                            Ok(str) => Some(Cow::Borrowed(*str)),
-                            Err(_) => None,
-                        },
-                    });
-                    this.backend.task_execution_result(task_id, result, &*this);
+                    if let Some(result) = result {
+                        let result = result.map_err(|any| match any.downcast::<String>() {
+                            Ok(owned) => Some(Cow::Owned(*owned)),
+                            Err(any) => match any.downcast::<&'static str>() {
+                                Ok(str) => Some(Cow::Borrowed(*str)),
+                                Err(_) => None,
+                            },
+                        });
+                        this.backend.task_execution_result(task_id, result, &*this);
+                    } else {
+                        this.backend.task_execution_cancelled(task_id, &*this);
+                    }
                     this.notify_scheduled_tasks_internal();
                     let reexecute = this
                         .backend
@@ -419,22 +562,33 @@ impl<B: Backend> TurboTasks<B> {
                         break;
                     }
                 } else {
+                    this.unregister_execution(task_id);
                     break;
                 }
             }
+            this.cancelled_once_tasks.remove(&task_id);
+            this.priority_scheduler.reset(task_id);
             this.finish_primary_job();
             anyhow::Ok(())
         };
 
-        let future = TURBO_TASKS.scope(
-            self.pin(),
-            CURRENT_TASK_ID.scope(
-                task_id,
-                TASKS_TO_NOTIFY.scope(
-                    Default::default(),
-                    self.backend.execution_scope(task_id, future),
+        let future = PriorityFuture::new(
+            TURBO_TASKS.scope(
+                self.pin(),
+                CURRENT_TASK_ID.scope(
+                    task_id,
+                    CURRENT_PRIORITY.scope(
+                        priority,
+                        TASKS_TO_NOTIFY.scope(
+                            Default::default(),
+                            self.backend.execution_scope(task_id, future),
+                        ),
+                    ),
                 ),
             ),
+            self.priority_scheduler.clone(),
+            task_id,
+            priority,
         );
 
         #[cfg(feature = "tokio_tracing")]
@@ -446,6 +600,29 @@ impl<B: Backend> TurboTasks<B> {
         tokio::task::spawn(future);
     }
 
+    fn register_execution(&self, task_id: TaskId) -> Arc<ExecutionCancellation> {
+        let cancellation = Arc::new(ExecutionCancellation::default());
+        self.running_executions
+            .insert(task_id, cancellation.clone());
+        cancellation
+    }
+
+    fn unregister_execution(&self, task_id: TaskId) {
+        self.running_executions.remove(&task_id);
+    }
+
+    fn cancel_execution(&self, task_id: TaskId) {
+        // A task invalidating itself, e.g. by updating a state it has read,
+        // would be cancelled on every execution and never finish. It's
+        // executed again after completion instead.
+        if CURRENT_TASK_ID.try_with(|id| *id).ok() == Some(task_id) {
+            return;
+        }
+        if let Some(cancellation) = self.running_executions.get(&task_id) {
+            cancellation.cancel();
+        }
+    }
+
     fn begin_primary_job(&self) {
         if self
             .currently_scheduled_tasks
@@ -712,6 +889,18 @@ impl<B: Backend> TurboTasksCallApi for TurboTasks<B> {
         })
     }
 
+    #[track_caller]
+    fn run_once_with_priority(
+        &self,
+        priority: TaskPriority,
+        future: Pin<Box<dyn Future<Output = Result<()>> + Send + 'static>>,
+    ) -> TaskId {
+        self.spawn_once_task_with_priority(priority, async move {
+            future.await?;
+            Ok(CompletionVc::new().into())
+        })
+    }
+
     #[track_caller]
     fn run_once_process(
         &self,
@@ -732,6 +921,25 @@ impl<B: Backend> TurboTasksApi for TurboTasks<B> {
         self.backend.invalidate_task(task, self);
     }
 
+    fn invalidate_with_reason(&self, task: TaskId, reason: Cow<'static, str>) {
+        self.backend.invalidate_task_with_reason(task, reason, self);
+    }
+
+    fn invalidation_reason(&self, task: TaskId) -> Option<InvalidationReason> {
+        self.backend.get_invalidation_reason(task)
+    }
+
+    fn cancel_task(&self, task: TaskId) {
+        self.cancel_execution(task);
+    }
+
+    fn cancel_once_task(&self, task: TaskId) {
+        // Inserted before looking for a running execution, so a concurrently
+        // starting execution either sees the entry or is cancelled.
+        self.cancelled_once_tasks.insert(task);
+        self.cancel_execution(task);
+    }
+
     fn notify_scheduled_tasks(&self) {
         let _ = TASKS_TO_NOTIFY.try_with(|tasks| {
             let tasks = tasks.take();
@@ -747,12 +955,13 @@ impl<B: Backend> TurboTasksApi for TurboTasks<B> {
         task: TaskId,
         strongly_consistent: bool,
     ) -> Result<Result<RawVc, EventListener>> {
-        self.backend.try_read_task_output(
+        let result = self.backend.try_read_task_output(
             task,
             current_task("reading Vcs"),
             strongly_consistent,
             self,
-        )
+        );
+        self.raise_awaited(task, result)
     }
 
     fn try_read_task_output_untracked(
@@ -760,8 +969,10 @@ impl<B: Backend> TurboTasksApi for TurboTasks<B> {
         task: TaskId,
         strongly_consistent: bool,
     ) -> Result<Result<RawVc, EventListener>> {
-        self.backend
-            .try_read_task_output_untracked(task, strongly_consistent, self)
+        let result = self
+            .backend
+            .try_read_task_output_untracked(task, strongly_consistent, self);
+        self.raise_awaited(task, result)
     }
 
     fn try_read_task_cell(
@@ -769,8 +980,10 @@ impl<B: Backend> TurboTasksApi for TurboTasks<B> {
         task: TaskId,
         index: CellId,
     ) -> Result<Result<CellContent, EventListener>> {
-        self.backend
-            .try_read_task_cell(task, index, current_task("reading Vcs"), self)
+        let result =
+            self.backend
+                .try_read_task_cell(task, index, current_task("reading Vcs"), self);
+        self.raise_awaited(task, result)
     }
 
     fn try_read_task_cell_untracked(
@@ -778,7 +991,8 @@ impl<B: Backend> TurboTasksApi for TurboTasks<B> {
         task: TaskId,
         index: CellId,
     ) -> Result<Result<CellContent, EventListener>> {
-        self.backend.try_read_task_cell_untracked(task, index, self)
+        let result = self.backend.try_read_task_cell_untracked(task, index, self);
+        self.raise_awaited(task, result)
     }
 
     fn try_read_own_task_cell_untracked(
@@ -845,6 +1059,10 @@ impl<B: Backend> TurboTasksApi for TurboTasks<B> {
             self,
         );
     }
+
+    fn mark_own_task_as_session_dependent(&self, task: TaskId) {
+        self.backend.mark_own_task_as_session_dependent(task, self);
+    }
 }
 
 impl<B: Backend> TurboTasksBackendApi for TurboTasks<B> {
@@ -913,6 +1131,10 @@ impl<B: Backend> TurboTasksBackendApi for TurboTasks<B> {
         self.schedule(task)
     }
 
+    fn cancel_task_execution(&self, task: TaskId) {
+        self.cancel_execution(task)
+    }
+
     fn stats_type(&self) -> StatsType {
         match self.enable_full_stats.load(Ordering::Acquire) {
             true => StatsType::Full,
@@ -984,6 +1206,20 @@ impl Invalidator {
             turbo_tasks.invalidate(task);
         }
     }
+
+    /// Invalidates the task with a description of the external change, e.g.
+    /// the path of a changed file, which is reported as [InvalidationReason].
+    pub fn invalidate_with_reason(self, reason: impl Into<Cow<'static, str>>) {
+        let Invalidator {
+            task,
+            turbo_tasks,
+            handle,
+        } = self;
+        let _ = handle.enter();
+        if let Some(turbo_tasks) = turbo_tasks.upgrade() {
+            turbo_tasks.invalidate_with_reason(task, reason.into());
+        }
+    }
 }
 
 impl TraceRawVcs for Invalidator {
@@ -1033,24 +1269,76 @@ impl<'de> Deserialize<'de> for Invalidator {
 pub async fn run_once<T: Send + 'static>(
     tt: Arc<dyn TurboTasksApi>,
     future: impl Future<Output = Result<T>> + Send + 'static,
+) -> Result<T> {
+    run_once_internal(tt, None, future).await
+}
+
+/// Like [run_once], but the task and all tasks it schedules are executed with
+/// the given priority.
+pub async fn run_once_with_priority<T: Send + 'static>(
+    tt: Arc<dyn TurboTasksApi>,
+    priority: TaskPriority,
+    future: impl Future<Output = Result<T>> + Send + 'static,
+) -> Result<T> {
+    run_once_internal(tt, Some(priority), future).await
+}
+
+async fn run_once_internal<T: Send + 'static>(
+    tt: Arc<dyn TurboTasksApi>,
+    priority: Option<TaskPriority>,
+    future: impl Future<Output = Result<T>> + Send + 'static,
 ) -> Result<T> {
     let (tx, rx) = tokio::sync::oneshot::channel();
 
-    let task_id = tt.run_once(Box::pin(async move {
+    let future = Box::pin(async move {
         let result = future.await?;
         tx.send(result)
             .map_err(|_| anyhow!("unable to send result"))?;
         Ok(())
-    }));
+    });
+    let task_id = match priority {
+        Some(priority) => tt.run_once_with_priority(priority, future),
+        None => tt.run_once(future),
+    };
 
+    let guard = CancelOnDrop::new(&*tt, task_id);
     // INVALIDATION: A Once task will never invalidate, therefore we don't need to
     // track a dependency
     let raw_result = read_task_output_untracked(&*tt, task_id, false).await?;
     raw_result.into_read_untracked::<Completion>(&*tt).await?;
+    guard.disarm();
 
     Ok(rx.await?)
 }
 
+/// Cancels a once task when the future waiting for its result is dropped
+/// before it completed, as nobody can receive the result anymore.
+struct CancelOnDrop<'a, T: TurboTasksApi + ?Sized> {
+    turbo_tasks: &'a T,
+    task: Option<TaskId>,
+}
+
+impl<'a, T: TurboTasksApi + ?Sized> CancelOnDrop<'a, T> {
+    fn new(turbo_tasks: &'a T, task: TaskId) -> Self {
+        Self {
+            turbo_tasks,
+            task: Some(task),
+        }
+    }
+
+    fn disarm(mut self) {
+        self.task = None;
+    }
+}
+
+impl<T: TurboTasksApi + ?Sized> Drop for CancelOnDrop<'_, T> {
+    fn drop(&mut self) {
+        if let Some(task) = self.task {
+            self.turbo_tasks.cancel_once_task(task);
+        }
+    }
+}
+
 /// see [TurboTasks] `dynamic_call`
 pub fn dynamic_call(func: FunctionId, inputs: Vec<TaskInput>) -> RawVc {
     with_turbo_tasks(|tt| tt.dynamic_call(func, inputs))
@@ -1103,6 +1391,20 @@ pub fn get_invalidator() -> Invalidator {
     }
 }
 
+/// Marks the current task as depending on state that doesn't survive the
+/// session, e.g. the file system. When the task graph is restored from a
+/// persistent cache, such tasks are executed again, and only tasks depending
+/// on changed results are invalidated.
+///
+/// Does nothing outside of a task, as no task result can depend on the state
+/// then.
+pub fn mark_session_dependent() {
+    let Ok(task) = CURRENT_TASK_ID.try_with(|id| *id) else {
+        return;
+    };
+    with_turbo_tasks(|tt| tt.mark_own_task_as_session_dependent(task));
+}
+
 /// Marks the current task as stateful. This prevents the tasks from being
 /// dropped without persisting the state.
 pub fn mark_stateful() {
@@ -32,6 +32,9 @@ pub struct ReadTaskState {
 
 pub struct PersistTaskState {
     pub externally_active: bool,
+    /// The task depends on state that doesn't survive the session and needs
+    /// to be executed again when the graph is restored.
+    pub session_dependent: bool,
 }
 
 /*
@@ -0,0 +1,377 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use dashmap::{mapref::entry::Entry, DashMap};
+use nohash_hasher::BuildNoHashHasher;
+use pin_project_lite::pin_project;
+
+use crate::{event::Event, TaskId};
+
+/// The priority of a task. Executions of a lower priority are only started
+/// when no runnable work of a higher priority is left.
+///
+/// Root and once tasks can be spawned with a priority, all other tasks inherit
+/// the priority of the task that scheduled them. A task is raised to the
+/// priority of a task waiting for its output, so work of a higher priority
+/// never waits for work that is held back by other work of that priority.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TaskPriority {
+    /// Speculative work nobody is waiting for yet, e.g. eager compilation.
+    Background,
+    #[default]
+    Normal,
+    /// Work somebody is actively waiting for, e.g. the page a browser
+    /// requested.
+    Foreground,
+}
+
+impl TaskPriority {
+    const COUNT: usize = 3;
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Tracks the number of runnable executions per priority.
+///
+/// An execution is runnable unless it is waiting for something, e.g. the
+/// output of another task. Only counting runnable executions ensures that
+/// executions of a higher priority waiting for work of a lower priority don't
+/// block that work.
+pub struct PriorityScheduler {
+    runnable: [AtomicUsize; TaskPriority::COUNT],
+    /// Tasks raised above the priority they have been scheduled with, because
+    /// a task of a higher priority is waiting for them.
+    raised: DashMap<TaskId, TaskPriority, BuildNoHashHasher<TaskId>>,
+    /// The number of raised tasks and of executions with a priority other
+    /// than [TaskPriority::Normal]. As long as it's zero, all work has the
+    /// same priority and executions don't need to be counted.
+    prioritized: AtomicUsize,
+    event: Event,
+}
+
+impl PriorityScheduler {
+    pub fn new() -> Self {
+        Self {
+            runnable: Default::default(),
+            raised: Default::default(),
+            prioritized: AtomicUsize::new(0),
+            event: Event::new(|| "PriorityScheduler::event".to_string()),
+        }
+    }
+
+    /// Raises the priority of a task to at least `priority` until
+    /// [PriorityScheduler::reset] is called for it.
+    pub fn raise(&self, task: TaskId, priority: TaskPriority) {
+        match self.raised.entry(task) {
+            Entry::Occupied(entry) if *entry.get() >= priority => return,
+            Entry::Occupied(mut entry) => {
+                entry.insert(priority);
+            }
+            Entry::Vacant(entry) => {
+                self.prioritized.fetch_add(1, Ordering::AcqRel);
+                entry.insert(priority);
+            }
+        }
+        // The task might be waiting for its turn with a lower priority
+        self.event.notify(usize::MAX);
+    }
+
+    /// Forgets about a raised priority of a task, once its execution has
+    /// finished or it has been disposed.
+    pub fn reset(&self, task: TaskId) {
+        if self.prioritized.load(Ordering::Acquire) == 0 {
+            return;
+        }
+        if self.raised.remove(&task).is_some() {
+            self.prioritized.fetch_sub(1, Ordering::AcqRel);
+        }
+    }
+
+    /// The priority of a task scheduled with `priority`, considering whether
+    /// it has been raised.
+    pub fn effective_priority(&self, task: TaskId, priority: TaskPriority) -> TaskPriority {
+        if self.prioritized.load(Ordering::Acquire) == 0 {
+            return priority;
+        }
+        match self.raised.get(&task) {
+            Some(raised) => priority.max(*raised),
+            None => priority,
+        }
+    }
+
+    fn is_prioritized(&self) -> bool {
+        self.prioritized.load(Ordering::Acquire) > 0
+    }
+
+    fn has_higher_priority_work(&self, priority: TaskPriority) -> bool {
+        self.runnable[priority.index() + 1..]
+            .iter()
+            .any(|count| count.load(Ordering::Acquire) > 0)
+    }
+
+    /// Waits until no executions of a higher priority than the effective
+    /// priority of the task are runnable.
+    pub async fn wait_for_turn(&self, task: TaskId, priority: TaskPriority) {
+        loop {
+            if !self.has_higher_priority_work(self.effective_priority(task, priority)) {
+                return;
+            }
+            let listener = self.event.listen();
+            if !self.has_higher_priority_work(self.effective_priority(task, priority)) {
+                return;
+            }
+            listener.await;
+        }
+    }
+
+    fn increment(&self, priority: TaskPriority) {
+        self.runnable[priority.index()].fetch_add(1, Ordering::AcqRel);
+    }
+
+    fn decrement(&self, priority: TaskPriority) {
+        if self.runnable[priority.index()].fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.event.notify(usize::MAX);
+        }
+    }
+}
+
+impl Default for PriorityScheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pin_project! {
+    /// Counts the wrapped execution of a task as runnable in the
+    /// [PriorityScheduler] while it is not waiting, with the effective
+    /// priority of the task.
+    ///
+    /// Executions aren't counted while all work has the normal priority. An
+    /// execution which is being polled when work of another priority shows up
+    /// is counted from its next poll on.
+    pub struct PriorityFuture<F> {
+        #[pin]
+        future: F,
+        scheduler: Arc<PriorityScheduler>,
+        task: TaskId,
+        priority: TaskPriority,
+        // The priority the execution is currently counted with, if any
+        counted: Option<TaskPriority>,
+    }
+
+    impl<F> PinnedDrop for PriorityFuture<F> {
+        fn drop(this: Pin<&mut Self>) {
+            let this = this.project();
+            if let Some(counted) = this.counted.take() {
+                this.scheduler.decrement(counted);
+            }
+            if *this.priority != TaskPriority::Normal {
+                this.scheduler.prioritized.fetch_sub(1, Ordering::AcqRel);
+            }
+        }
+    }
+}
+
+impl<F: Future> PriorityFuture<F> {
+    pub fn new(
+        future: F,
+        scheduler: Arc<PriorityScheduler>,
+        task: TaskId,
+        priority: TaskPriority,
+    ) -> Self {
+        if priority != TaskPriority::Normal {
+            scheduler.prioritized.fetch_add(1, Ordering::AcqRel);
+        }
+        let counted = scheduler.is_prioritized().then(|| {
+            let counted = scheduler.effective_priority(task, priority);
+            scheduler.increment(counted);
+            counted
+        });
+        Self {
+            future,
+            scheduler,
+            task,
+            priority,
+            counted,
+        }
+    }
+}
+
+impl<F: Future> Future for PriorityFuture<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        if this.counted.is_none() {
+            if !this.scheduler.is_prioritized() {
+                return this.future.poll(cx);
+            }
+            let priority = this
+                .scheduler
+                .effective_priority(*this.task, *this.priority);
+            this.scheduler.increment(priority);
+            *this.counted = Some(priority);
+        }
+        let result = this.future.poll(cx);
+        // Waiting for something else or done, either way the execution doesn't
+        // hold back other work anymore
+        if let Some(counted) = this.counted.take() {
+            this.scheduler.decrement(counted);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::{pending, poll_fn};
+
+    use futures::{executor::block_on, poll, FutureExt};
+
+    use super::*;
+
+    fn task(id: usize) -> TaskId {
+        TaskId::from(id)
+    }
+
+    #[test]
+    fn lower_priorities_wait_for_runnable_work() {
+        let scheduler = Arc::new(PriorityScheduler::new());
+        // Not polled yet, so it's runnable
+        let foreground = PriorityFuture::new(
+            pending::<()>(),
+            scheduler.clone(),
+            task(1),
+            TaskPriority::Foreground,
+        );
+
+        assert!(scheduler
+            .wait_for_turn(task(2), TaskPriority::Background)
+            .now_or_never()
+            .is_none());
+        assert!(scheduler
+            .wait_for_turn(task(3), TaskPriority::Normal)
+            .now_or_never()
+            .is_none());
+        assert!(scheduler
+            .wait_for_turn(task(4), TaskPriority::Foreground)
+            .now_or_never()
+            .is_some());
+
+        drop(foreground);
+        assert!(scheduler
+            .wait_for_turn(task(2), TaskPriority::Background)
+            .now_or_never()
+            .is_some());
+    }
+
+    #[test]
+    fn waiting_work_does_not_block_lower_priorities() {
+        let scheduler = Arc::new(PriorityScheduler::new());
+        let mut foreground = Box::pin(PriorityFuture::new(
+            pending::<()>(),
+            scheduler.clone(),
+            task(1),
+            TaskPriority::Foreground,
+        ));
+        let mut background = Box::pin(scheduler.wait_for_turn(task(2), TaskPriority::Background));
+        block_on(async {
+            assert!(poll!(&mut background).is_pending());
+            // The foreground execution now waits, e.g. for the output of the
+            // background task, which must not starve.
+            assert!(poll!(&mut foreground).is_pending());
+            assert!(poll!(&mut background).is_ready());
+        });
+    }
+
+    #[test]
+    fn raised_tasks_take_their_turn() {
+        let scheduler = Arc::new(PriorityScheduler::new());
+        let _foreground = PriorityFuture::new(
+            pending::<()>(),
+            scheduler.clone(),
+            task(1),
+            TaskPriority::Foreground,
+        );
+        let mut background = Box::pin(scheduler.wait_for_turn(task(2), TaskPriority::Background));
+        block_on(async {
+            assert!(poll!(&mut background).is_pending());
+            scheduler.raise(task(2), TaskPriority::Normal);
+            assert!(poll!(&mut background).is_pending());
+            scheduler.raise(task(2), TaskPriority::Foreground);
+            assert!(poll!(&mut background).is_ready());
+        });
+    }
+
+    #[test]
+    fn raise_never_lowers() {
+        let scheduler = PriorityScheduler::new();
+        scheduler.raise(task(1), TaskPriority::Foreground);
+        scheduler.raise(task(1), TaskPriority::Normal);
+        assert_eq!(
+            scheduler.effective_priority(task(1), TaskPriority::Background),
+            TaskPriority::Foreground
+        );
+
+        scheduler.reset(task(1));
+        assert_eq!(
+            scheduler.effective_priority(task(1), TaskPriority::Background),
+            TaskPriority::Background
+        );
+        assert!(scheduler.raised.is_empty());
+        assert!(!scheduler.is_prioritized());
+    }
+
+    #[test]
+    fn normal_executions_are_only_counted_with_other_priorities() {
+        let scheduler = Arc::new(PriorityScheduler::new());
+        let counted = |scheduler: &PriorityScheduler| {
+            scheduler.runnable[TaskPriority::Normal.index()].load(Ordering::Acquire)
+        };
+        let normal = PriorityFuture::new(
+            pending::<()>(),
+            scheduler.clone(),
+            task(1),
+            TaskPriority::Normal,
+        );
+        assert_eq!(counted(&scheduler), 0);
+        drop(normal);
+
+        let _background = PriorityFuture::new(
+            pending::<()>(),
+            scheduler.clone(),
+            task(2),
+            TaskPriority::Background,
+        );
+        let _normal = PriorityFuture::new(
+            pending::<()>(),
+            scheduler.clone(),
+            task(1),
+            TaskPriority::Normal,
+        );
+        assert_eq!(counted(&scheduler), 1);
+    }
+
+    #[test]
+    fn executions_are_counted_with_the_raised_priority() {
+        let scheduler = Arc::new(PriorityScheduler::new());
+        scheduler.raise(task(1), TaskPriority::Foreground);
+        let execution = PriorityFuture::new(
+            poll_fn(|_| Poll::Ready(scheduler.has_higher_priority_work(TaskPriority::Normal))),
+            scheduler.clone(),
+            task(1),
+            TaskPriority::Background,
+        );
+        assert_eq!(execution.now_or_never(), Some(true));
+        assert!(!scheduler.has_higher_priority_work(TaskPriority::Background));
+    }
+}
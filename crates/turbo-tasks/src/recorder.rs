@@ -0,0 +1,889 @@
+//! Recording of task graph activity into a compact binary trace.
+//!
+//! Wrap any [Backend] in a [RecordingBackend] to log task creations,
+//! executions, reads, cell updates, collectible emits and invalidations. The
+//! trace can be inspected and replayed offline with the `trace` example of
+//! this crate, or read with a [TraceReader].
+
+use std::{
+    borrow::Cow,
+    collections::HashSet,
+    fs::File,
+    future::Future,
+    io::{self, BufReader, BufWriter, ErrorKind, Read, Write},
+    path::Path,
+    pin::Pin,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Result};
+use auto_hash_map::AutoSet;
+use dashmap::DashSet;
+use nohash_hasher::BuildNoHashHasher;
+use parking_lot::Mutex;
+
+use crate::{
+    backend::{
+        Backend, BackendJobId, CellContent, InvalidationReason, PersistentTaskType,
+        TaskExecutionSpec, TransientTaskType,
+    },
+    event::EventListener,
+    registry, CellId, RawVc, TaskId, TaskIdProvider, TraitTypeId, TurboTasksBackendApi,
+    ValueTypeId,
+};
+
+const MAGIC: &[u8; 8] = b"TTTRACE\0";
+const FORMAT_VERSION: u64 = 1;
+
+/// A cell as it is stored in a trace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TraceCell {
+    pub type_id: usize,
+    pub index: u32,
+}
+
+impl From<CellId> for TraceCell {
+    fn from(cell: CellId) -> Self {
+        TraceCell {
+            type_id: *cell.type_id,
+            index: cell.index,
+        }
+    }
+}
+
+/// A [RawVc] as it is stored in a trace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TraceVc {
+    TaskOutput(usize),
+    TaskCell(usize, TraceCell),
+}
+
+impl From<RawVc> for TraceVc {
+    fn from(vc: RawVc) -> Self {
+        match vc {
+            RawVc::TaskOutput(task) => TraceVc::TaskOutput(*task),
+            RawVc::TaskCell(task, cell) => TraceVc::TaskCell(*task, cell.into()),
+        }
+    }
+}
+
+/// A single event of a trace. Ids are the raw ids of the recording session,
+/// names of value types and traits are recorded before their first use.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraceEvent {
+    ValueType {
+        id: usize,
+        name: String,
+    },
+    TraitType {
+        id: usize,
+        name: String,
+    },
+    TaskCreated {
+        task: usize,
+        parent: Option<usize>,
+        description: String,
+    },
+    ExecutionStarted {
+        task: usize,
+    },
+    ExecutionCompleted {
+        task: usize,
+    },
+    /// `reader` is `None` for untracked reads.
+    OutputRead {
+        task: usize,
+        reader: Option<usize>,
+    },
+    /// `reader` is `None` for untracked reads.
+    CellRead {
+        task: usize,
+        cell: TraceCell,
+        reader: Option<usize>,
+    },
+    CellUpdated {
+        task: usize,
+        cell: TraceCell,
+    },
+    CollectiblesRead {
+        task: usize,
+        trait_type: usize,
+        reader: usize,
+    },
+    CollectibleEmitted {
+        task: usize,
+        trait_type: usize,
+        collectible: TraceVc,
+    },
+    CollectibleUnemitted {
+        task: usize,
+        trait_type: usize,
+        collectible: TraceVc,
+    },
+    Invalidated {
+        task: usize,
+    },
+}
+
+impl TraceEvent {
+    /// The value type and trait the event refers to, which have to be named in
+    /// the trace before the event.
+    fn referenced_types(&self) -> (Option<usize>, Option<usize>) {
+        match self {
+            TraceEvent::CellRead { cell, .. } | TraceEvent::CellUpdated { cell, .. } => {
+                (Some(cell.type_id), None)
+            }
+            TraceEvent::CollectiblesRead { trait_type, .. } => (None, Some(*trait_type)),
+            TraceEvent::CollectibleEmitted {
+                trait_type,
+                collectible,
+                ..
+            }
+            | TraceEvent::CollectibleUnemitted {
+                trait_type,
+                collectible,
+                ..
+            } => {
+                let value_type = match collectible {
+                    TraceVc::TaskOutput(_) => None,
+                    TraceVc::TaskCell(_, cell) => Some(cell.type_id),
+                };
+                (value_type, Some(*trait_type))
+            }
+            _ => (None, None),
+        }
+    }
+}
+
+/// An event with the time since the start of the recording.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceRecord {
+    pub time: Duration,
+    pub event: TraceEvent,
+}
+
+impl TraceRecord {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        fn task(buf: &mut Vec<u8>, task: usize) {
+            write_varint(buf, task as u64);
+        }
+        fn optional_task(buf: &mut Vec<u8>, task: Option<usize>) {
+            write_varint(buf, task.map_or(0, |task| task as u64 + 1));
+        }
+        fn cell(buf: &mut Vec<u8>, cell: TraceCell) {
+            write_varint(buf, cell.type_id as u64);
+            write_varint(buf, cell.index as u64);
+        }
+        fn vc(buf: &mut Vec<u8>, vc: TraceVc) {
+            match vc {
+                TraceVc::TaskOutput(t) => {
+                    buf.push(0);
+                    task(buf, t);
+                }
+                TraceVc::TaskCell(t, c) => {
+                    buf.push(1);
+                    task(buf, t);
+                    cell(buf, c);
+                }
+            }
+        }
+
+        let tag = match &self.event {
+            TraceEvent::ValueType { .. } => 0,
+            TraceEvent::TraitType { .. } => 1,
+            TraceEvent::TaskCreated { .. } => 2,
+            TraceEvent::ExecutionStarted { .. } => 3,
+            TraceEvent::ExecutionCompleted { .. } => 4,
+            TraceEvent::OutputRead { .. } => 5,
+            TraceEvent::CellRead { .. } => 6,
+            TraceEvent::CellUpdated { .. } => 7,
+            TraceEvent::CollectiblesRead { .. } => 8,
+            TraceEvent::CollectibleEmitted { .. } => 9,
+            TraceEvent::CollectibleUnemitted { .. } => 10,
+            TraceEvent::Invalidated { .. } => 11,
+        };
+        buf.push(tag);
+        write_varint(buf, self.time.as_micros() as u64);
+        match &self.event {
+            TraceEvent::ValueType { id, name } | TraceEvent::TraitType { id, name } => {
+                write_varint(buf, *id as u64);
+                write_str(buf, name);
+            }
+            TraceEvent::TaskCreated {
+                task: t,
+                parent,
+                description,
+            } => {
+                task(buf, *t);
+                optional_task(buf, *parent);
+                write_str(buf, description);
+            }
+            TraceEvent::ExecutionStarted { task: t }
+            | TraceEvent::ExecutionCompleted { task: t }
+            | TraceEvent::Invalidated { task: t } => task(buf, *t),
+            TraceEvent::OutputRead { task: t, reader } => {
+                task(buf, *t);
+                optional_task(buf, *reader);
+            }
+            TraceEvent::CellRead {
+                task: t,
+                cell: c,
+                reader,
+            } => {
+                task(buf, *t);
+                cell(buf, *c);
+                optional_task(buf, *reader);
+            }
+            TraceEvent::CellUpdated { task: t, cell: c } => {
+                task(buf, *t);
+                cell(buf, *c);
+            }
+            TraceEvent::CollectiblesRead {
+                task: t,
+                trait_type,
+                reader,
+            } => {
+                task(buf, *t);
+                write_varint(buf, *trait_type as u64);
+                task(buf, *reader);
+            }
+            TraceEvent::CollectibleEmitted {
+                task: t,
+                trait_type,
+                collectible,
+            }
+            | TraceEvent::CollectibleUnemitted {
+                task: t,
+                trait_type,
+                collectible,
+            } => {
+                task(buf, *t);
+                write_varint(buf, *trait_type as u64);
+                vc(buf, *collectible);
+            }
+        }
+    }
+
+    fn decode(tag: u8, reader: &mut impl Read) -> Result<Self> {
+        fn task(reader: &mut impl Read) -> Result<usize> {
+            Ok(read_varint(reader)? as usize)
+        }
+        fn optional_task(reader: &mut impl Read) -> Result<Option<usize>> {
+            Ok(match read_varint(reader)? {
+                0 => None,
+                n => Some(n as usize - 1),
+            })
+        }
+        fn cell(reader: &mut impl Read) -> Result<TraceCell> {
+            Ok(TraceCell {
+                type_id: read_varint(reader)? as usize,
+                index: read_varint(reader)? as u32,
+            })
+        }
+        fn vc(reader: &mut impl Read) -> Result<TraceVc> {
+            Ok(match read_u8(reader)? {
+                0 => TraceVc::TaskOutput(task(reader)?),
+                1 => TraceVc::TaskCell(task(reader)?, cell(reader)?),
+                tag => bail!("invalid vc tag {tag} in trace"),
+            })
+        }
+
+        let time = Duration::from_micros(read_varint(reader)?);
+        let event = match tag {
+            0 => TraceEvent::ValueType {
+                id: read_varint(reader)? as usize,
+                name: read_str(reader)?,
+            },
+            1 => TraceEvent::TraitType {
+                id: read_varint(reader)? as usize,
+                name: read_str(reader)?,
+            },
+            2 => TraceEvent::TaskCreated {
+                task: task(reader)?,
+                parent: optional_task(reader)?,
+                description: read_str(reader)?,
+            },
+            3 => TraceEvent::ExecutionStarted {
+                task: task(reader)?,
+            },
+            4 => TraceEvent::ExecutionCompleted {
+                task: task(reader)?,
+            },
+            5 => TraceEvent::OutputRead {
+                task: task(reader)?,
+                reader: optional_task(reader)?,
+            },
+            6 => TraceEvent::CellRead {
+                task: task(reader)?,
+                cell: cell(reader)?,
+                reader: optional_task(reader)?,
+            },
+            7 => TraceEvent::CellUpdated {
+                task: task(reader)?,
+                cell: cell(reader)?,
+            },
+            8 => TraceEvent::CollectiblesRead {
+                task: task(reader)?,
+                trait_type: read_varint(reader)? as usize,
+                reader: task(reader)?,
+            },
+            9 => TraceEvent::CollectibleEmitted {
+                task: task(reader)?,
+                trait_type: read_varint(reader)? as usize,
+                collectible: vc(reader)?,
+            },
+            10 => TraceEvent::CollectibleUnemitted {
+                task: task(reader)?,
+                trait_type: read_varint(reader)? as usize,
+                collectible: vc(reader)?,
+            },
+            11 => TraceEvent::Invalidated {
+                task: task(reader)?,
+            },
+            tag => bail!("invalid event tag {tag} in trace"),
+        };
+        Ok(TraceRecord { time, event })
+    }
+}
+
+fn write_varint(buf: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            buf.push(byte);
+            return;
+        }
+        buf.push(byte | 0x80);
+    }
+}
+
+fn write_str(buf: &mut Vec<u8>, str: &str) {
+    write_varint(buf, str.len() as u64);
+    buf.extend_from_slice(str.as_bytes());
+}
+
+fn read_u8(reader: &mut impl Read) -> Result<u8> {
+    let mut byte = [0];
+    reader.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+fn read_varint(reader: &mut impl Read) -> Result<u64> {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = read_u8(reader)?;
+        if shift >= 64 {
+            bail!("varint in trace is too long");
+        }
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+        shift += 7;
+    }
+}
+
+/// The maximum length of a string in a trace. Longer strings can only come
+/// from a corrupted trace, which must not allocate arbitrary amounts of
+/// memory.
+const MAX_STR_LEN: u64 = 1024 * 1024;
+
+fn read_str(reader: &mut impl Read) -> Result<String> {
+    let len = read_varint(reader)?;
+    if len > MAX_STR_LEN {
+        bail!("string of {len} bytes in trace is too long");
+    }
+    let mut bytes = vec![0; len as usize];
+    reader.read_exact(&mut bytes)?;
+    Ok(String::from_utf8(bytes)?)
+}
+
+/// Reads the records of a trace written by a [RecordingBackend].
+pub struct TraceReader<R: Read> {
+    reader: R,
+}
+
+impl TraceReader<BufReader<File>> {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> TraceReader<R> {
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut magic = [0; MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            bail!("not a task graph trace");
+        }
+        let version = read_varint(&mut reader)?;
+        if version != FORMAT_VERSION {
+            bail!("unsupported trace format version {version}, expected {FORMAT_VERSION}");
+        }
+        Ok(Self { reader })
+    }
+}
+
+impl<R: Read> Iterator for TraceReader<R> {
+    type Item = Result<TraceRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut tag = [0];
+        match self.reader.read(&mut tag) {
+            Ok(0) => None,
+            Ok(_) => Some(TraceRecord::decode(tag[0], &mut self.reader)),
+            Err(err) if err.kind() == ErrorKind::Interrupted => self.next(),
+            Err(err) => Some(Err(err.into())),
+        }
+    }
+}
+
+/// A [Backend] that records all task graph activity of the wrapped backend.
+///
+/// Recording is opt-in and has a cost, so it's meant for reproducing bugs
+/// like invalidations that don't propagate.
+pub struct RecordingBackend<B: Backend> {
+    backend: B,
+    trace: Mutex<TraceWriter>,
+    failed: AtomicBool,
+    start: Instant,
+    tasks: DashSet<TaskId, BuildNoHashHasher<TaskId>>,
+}
+
+/// The output of a [RecordingBackend]. The names of value types and traits
+/// are recorded under the same lock as the events, so they are always written
+/// before the first event referring to them.
+struct TraceWriter {
+    writer: Box<dyn Write + Send>,
+    value_types: HashSet<usize, BuildNoHashHasher<usize>>,
+    trait_types: HashSet<usize, BuildNoHashHasher<usize>>,
+    buf: Vec<u8>,
+}
+
+impl<B: Backend> RecordingBackend<B> {
+    /// Records into a new trace file at `path`.
+    pub fn new(backend: B, path: impl AsRef<Path>) -> Result<Self> {
+        Self::with_writer(backend, Box::new(BufWriter::new(File::create(path)?)))
+    }
+
+    pub fn with_writer(backend: B, mut writer: Box<dyn Write + Send>) -> Result<Self> {
+        let mut header = MAGIC.to_vec();
+        write_varint(&mut header, FORMAT_VERSION);
+        writer.write_all(&header)?;
+        Ok(Self {
+            backend,
+            trace: Mutex::new(TraceWriter {
+                writer,
+                value_types: Default::default(),
+                trait_types: Default::default(),
+                buf: Vec::new(),
+            }),
+            failed: AtomicBool::new(false),
+            start: Instant::now(),
+            tasks: Default::default(),
+        })
+    }
+
+    /// Writes buffered records to the trace.
+    pub fn flush(&self) -> io::Result<()> {
+        self.trace.lock().writer.flush()
+    }
+
+    fn record(&self, event: TraceEvent) {
+        if self.failed.load(Ordering::Relaxed) {
+            return;
+        }
+        let mut trace = self.trace.lock();
+        let TraceWriter {
+            writer,
+            value_types,
+            trait_types,
+            buf,
+        } = &mut *trace;
+        let time = self.start.elapsed();
+        buf.clear();
+        let (value_type, trait_type) = event.referenced_types();
+        if let Some(id) = value_type.filter(|id| value_types.insert(*id)) {
+            TraceRecord {
+                time,
+                event: TraceEvent::ValueType {
+                    id,
+                    name: registry::get_value_type(ValueTypeId::from(id)).name.clone(),
+                },
+            }
+            .encode(buf);
+        }
+        if let Some(id) = trait_type.filter(|id| trait_types.insert(*id)) {
+            TraceRecord {
+                time,
+                event: TraceEvent::TraitType {
+                    id,
+                    name: registry::get_trait(TraitTypeId::from(id)).name.clone(),
+                },
+            }
+            .encode(buf);
+        }
+        TraceRecord { time, event }.encode(buf);
+        if let Err(err) = writer.write_all(buf) {
+            if !self.failed.swap(true, Ordering::Relaxed) {
+                eprintln!("failed to write the task graph trace, recording stopped: {err}");
+            }
+        }
+    }
+
+    fn record_task(&self, task: TaskId, parent: Option<TaskId>) {
+        if self.tasks.insert(task) {
+            self.record(TraceEvent::TaskCreated {
+                task: *task,
+                parent: parent.map(|parent| *parent),
+                description: self.backend.get_task_description(task),
+            });
+        }
+    }
+}
+
+impl<B: Backend> Backend for RecordingBackend<B> {
+    fn initialize(&mut self, task_id_provider: &dyn TaskIdProvider) {
+        self.backend.initialize(task_id_provider)
+    }
+
+    fn startup(&self, turbo_tasks: &dyn TurboTasksBackendApi) {
+        self.backend.startup(turbo_tasks)
+    }
+
+    fn stop(&self, turbo_tasks: &dyn TurboTasksBackendApi) {
+        self.backend.stop(turbo_tasks);
+        if let Err(err) = self.flush() {
+            eprintln!("failed to flush the task graph trace: {err}");
+        }
+    }
+
+    fn invalidate_task(&self, task: TaskId, turbo_tasks: &dyn TurboTasksBackendApi) {
+        self.record(TraceEvent::Invalidated { task: *task });
+        self.backend.invalidate_task(task, turbo_tasks)
+    }
+
+    fn invalidate_tasks(&self, tasks: Vec<TaskId>, turbo_tasks: &dyn TurboTasksBackendApi) {
+        for task in tasks.iter() {
+            self.record(TraceEvent::Invalidated { task: **task });
+        }
+        self.backend.invalidate_tasks(tasks, turbo_tasks)
+    }
+
+    fn invalidate_task_with_reason(
+        &self,
+        task: TaskId,
+        reason: Cow<'static, str>,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+        self.record(TraceEvent::Invalidated { task: *task });
+        self.backend
+            .invalidate_task_with_reason(task, reason, turbo_tasks)
+    }
+
+    fn get_invalidation_reason(&self, task: TaskId) -> Option<InvalidationReason> {
+        self.backend.get_invalidation_reason(task)
+    }
+
+    fn get_task_description(&self, task: TaskId) -> String {
+        self.backend.get_task_description(task)
+    }
+
+    fn mark_own_task_as_session_dependent(
+        &self,
+        task: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+        self.backend
+            .mark_own_task_as_session_dependent(task, turbo_tasks)
+    }
+
+    type ExecutionScopeFuture<T: Future<Output = Result<()>> + Send + 'static> =
+        B::ExecutionScopeFuture<T>;
+
+    fn execution_scope<T: Future<Output = Result<()>> + Send + 'static>(
+        &self,
+        task: TaskId,
+        future: T,
+    ) -> Self::ExecutionScopeFuture<T> {
+        self.backend.execution_scope(task, future)
+    }
+
+    fn try_start_task_execution(
+        &self,
+        task: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> Option<TaskExecutionSpec> {
+        let execution = self.backend.try_start_task_execution(task, turbo_tasks);
+        if execution.is_some() {
+            self.record(TraceEvent::ExecutionStarted { task: *task });
+        }
+        execution
+    }
+
+    fn task_execution_result(
+        &self,
+        task: TaskId,
+        result: Result<Result<RawVc>, Option<Cow<'static, str>>>,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+        self.backend
+            .task_execution_result(task, result, turbo_tasks)
+    }
+
+    fn task_execution_cancelled(&self, task: TaskId, turbo_tasks: &dyn TurboTasksBackendApi) {
+        self.backend.task_execution_cancelled(task, turbo_tasks)
+    }
+
+    fn task_execution_completed(
+        &self,
+        task: TaskId,
+        duration: Duration,
+        instant: Instant,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> bool {
+        self.record(TraceEvent::ExecutionCompleted { task: *task });
+        self.backend
+            .task_execution_completed(task, duration, instant, turbo_tasks)
+    }
+
+    fn run_backend_job<'a>(
+        &'a self,
+        id: BackendJobId,
+        turbo_tasks: &'a dyn TurboTasksBackendApi,
+    ) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>> {
+        self.backend.run_backend_job(id, turbo_tasks)
+    }
+
+    fn try_read_task_output(
+        &self,
+        task: TaskId,
+        reader: TaskId,
+        strongly_consistent: bool,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> Result<Result<RawVc, EventListener>> {
+        self.record(TraceEvent::OutputRead {
+            task: *task,
+            reader: Some(*reader),
+        });
+        self.backend
+            .try_read_task_output(task, reader, strongly_consistent, turbo_tasks)
+    }
+
+    fn try_read_task_output_untracked(
+        &self,
+        task: TaskId,
+        strongly_consistent: bool,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> Result<Result<RawVc, EventListener>> {
+        self.record(TraceEvent::OutputRead {
+            task: *task,
+            reader: None,
+        });
+        self.backend
+            .try_read_task_output_untracked(task, strongly_consistent, turbo_tasks)
+    }
+
+    fn try_read_task_cell(
+        &self,
+        task: TaskId,
+        index: CellId,
+        reader: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> Result<Result<CellContent, EventListener>> {
+        self.record(TraceEvent::CellRead {
+            task: *task,
+            cell: index.into(),
+            reader: Some(*reader),
+        });
+        self.backend
+            .try_read_task_cell(task, index, reader, turbo_tasks)
+    }
+
+    fn try_read_task_cell_untracked(
+        &self,
+        task: TaskId,
+        index: CellId,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> Result<Result<CellContent, EventListener>> {
+        self.record(TraceEvent::CellRead {
+            task: *task,
+            cell: index.into(),
+            reader: None,
+        });
+        self.backend
+            .try_read_task_cell_untracked(task, index, turbo_tasks)
+    }
+
+    fn try_read_own_task_cell_untracked(
+        &self,
+        current_task: TaskId,
+        index: CellId,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> Result<CellContent> {
+        self.backend
+            .try_read_own_task_cell_untracked(current_task, index, turbo_tasks)
+    }
+
+    fn try_read_task_collectibles(
+        &self,
+        task: TaskId,
+        trait_id: TraitTypeId,
+        reader: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> Result<Result<AutoSet<RawVc>, EventListener>> {
+        self.record(TraceEvent::CollectiblesRead {
+            task: *task,
+            trait_type: *trait_id,
+            reader: *reader,
+        });
+        self.backend
+            .try_read_task_collectibles(task, trait_id, reader, turbo_tasks)
+    }
+
+    fn emit_collectible(
+        &self,
+        trait_type: TraitTypeId,
+        collectible: RawVc,
+        task: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+        self.record(TraceEvent::CollectibleEmitted {
+            task: *task,
+            trait_type: *trait_type,
+            collectible: collectible.into(),
+        });
+        self.backend
+            .emit_collectible(trait_type, collectible, task, turbo_tasks)
+    }
+
+    fn unemit_collectible(
+        &self,
+        trait_type: TraitTypeId,
+        collectible: RawVc,
+        task: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+        self.record(TraceEvent::CollectibleUnemitted {
+            task: *task,
+            trait_type: *trait_type,
+            collectible: collectible.into(),
+        });
+        self.backend
+            .unemit_collectible(trait_type, collectible, task, turbo_tasks)
+    }
+
+    fn update_task_cell(
+        &self,
+        task: TaskId,
+        index: CellId,
+        content: CellContent,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+        self.record(TraceEvent::CellUpdated {
+            task: *task,
+            cell: index.into(),
+        });
+        self.backend
+            .update_task_cell(task, index, content, turbo_tasks)
+    }
+
+    fn get_or_create_persistent_task(
+        &self,
+        task_type: PersistentTaskType,
+        parent_task: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> TaskId {
+        let task = self
+            .backend
+            .get_or_create_persistent_task(task_type, parent_task, turbo_tasks);
+        self.record_task(task, Some(parent_task));
+        task
+    }
+
+    fn create_transient_task(
+        &self,
+        task_type: TransientTaskType,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> TaskId {
+        let task = self.backend.create_transient_task(task_type, turbo_tasks);
+        self.record_task(task, None);
+        task
+    }
+
+    fn dispose_root_task(&self, task: TaskId, turbo_tasks: &dyn TurboTasksBackendApi) {
+        self.backend.dispose_root_task(task, turbo_tasks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{write_varint, TraceCell, TraceEvent, TraceReader, TraceRecord, TraceVc};
+
+    #[test]
+    fn roundtrip() {
+        let records = vec![
+            TraceRecord {
+                time: Duration::from_micros(1),
+                event: TraceEvent::TaskCreated {
+                    task: 1,
+                    parent: None,
+                    description: "[1] root".to_string(),
+                },
+            },
+            TraceRecord {
+                time: Duration::from_micros(300),
+                event: TraceEvent::CellRead {
+                    task: 2,
+                    cell: TraceCell {
+                        type_id: 42,
+                        index: 3,
+                    },
+                    reader: Some(1),
+                },
+            },
+            TraceRecord {
+                time: Duration::from_secs(100),
+                event: TraceEvent::CollectibleEmitted {
+                    task: usize::MAX,
+                    trait_type: 7,
+                    collectible: TraceVc::TaskCell(
+                        5,
+                        TraceCell {
+                            type_id: 0,
+                            index: u32::MAX,
+                        },
+                    ),
+                },
+            },
+        ];
+        let mut buf = super::MAGIC.to_vec();
+        write_varint(&mut buf, super::FORMAT_VERSION);
+        for record in &records {
+            record.encode(&mut buf);
+        }
+        let decoded = TraceReader::new(&buf[..])
+            .unwrap()
+            .collect::<anyhow::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(decoded, records);
+    }
+
+    #[test]
+    fn rejects_oversized_strings() {
+        let mut buf = super::MAGIC.to_vec();
+        write_varint(&mut buf, super::FORMAT_VERSION);
+        // A value type event whose name claims to be longer than the trace.
+        buf.push(0);
+        write_varint(&mut buf, 0);
+        write_varint(&mut buf, 0);
+        write_varint(&mut buf, u64::MAX);
+        let mut reader = TraceReader::new(&buf[..]).unwrap();
+        assert!(reader.next().unwrap().is_err());
+    }
+}
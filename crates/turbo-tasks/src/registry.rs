@@ -2,6 +2,7 @@ use std::{fmt::Debug, hash::Hash, ops::Deref};
 
 use dashmap::{mapref::entry::Entry, DashMap};
 use once_cell::sync::Lazy;
+use turbo_tasks_hash::Xxh3Hash64Hasher;
 
 use crate::{
     id::{FunctionId, TraitTypeId, ValueTypeId},
@@ -145,3 +146,32 @@ pub fn get_trait(id: TraitTypeId) -> &'static TraitType {
 pub fn get_trait_type_global_name(id: TraitTypeId) -> &'static str {
     &TRAIT_TYPES.get(*id).unwrap().1
 }
+
+/// A hash of the global names of all registered functions, value types and
+/// traits. It changes when a build adds, removes or renames any of them, which
+/// changes the ids of the others. Has to be called after everything has been
+/// registered.
+///
+/// It doesn't change with the layout of a value type, so data storing
+/// serialized values, e.g. a persisted task graph, has to be versioned by the
+/// build as well.
+pub fn registry_hash() -> u64 {
+    fn sorted_names<K>(map: &DashMap<String, K>) -> Vec<String> {
+        let mut names = map
+            .iter()
+            .map(|entry| entry.key().clone())
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        names
+    }
+
+    let mut hasher = Xxh3Hash64Hasher::new();
+    for names in [
+        sorted_names(&FUNCTIONS_BY_NAME),
+        sorted_names(&VALUE_TYPES_BY_NAME),
+        sorted_names(&TRAIT_TYPES_BY_NAME),
+    ] {
+        hasher.write_ref(&names);
+    }
+    hasher.finish()
+}
@@ -337,6 +337,25 @@ pub enum TaskInput {
     SharedValue(SharedValue),
     TransientSharedValue(TransientSharedValue),
     SharedReference(SharedReference),
+    /// The bits of an [f32], so inputs are compared and hashed by their binary
+    /// representation.
+    F32(u32),
+    /// The bits of an [f64], so inputs are compared and hashed by their binary
+    /// representation.
+    F64(u64),
+    Bytes(Vec<u8>),
+    Tuple(Vec<TaskInput>),
+    /// An [Option], so `Some(None)` can be told apart from `None`. Options
+    /// used to be stored as [TaskInput::Nothing] or the bare value, which
+    /// changed the hashes of inputs and the persisted format.
+    Option(Option<Box<TaskInput>>),
+}
+
+fn resolve_all(
+    list: Vec<TaskInput>,
+) -> Pin<Box<dyn Future<Output = Result<Vec<TaskInput>>> + Send>> {
+    use crate::TryJoinIterExt;
+    Box::pin(list.into_iter().map(|i| i.resolve()).try_join())
 }
 
 impl TaskInput {
@@ -368,15 +387,18 @@ impl TaskInput {
                     if list.iter().all(|i| i.is_resolved()) {
                         return Ok(TaskInput::List(list));
                     }
-                    fn resolve_all(
-                        list: Vec<TaskInput>,
-                    ) -> Pin<Box<dyn Future<Output = Result<Vec<TaskInput>>> + Send>>
-                    {
-                        use crate::TryJoinIterExt;
-                        Box::pin(list.into_iter().map(|i| i.resolve()).try_join())
-                    }
                     return Ok(TaskInput::List(resolve_all(list).await?));
                 }
+                TaskInput::Tuple(list) => {
+                    if list.iter().all(|i| i.is_resolved()) {
+                        return Ok(TaskInput::Tuple(list));
+                    }
+                    return Ok(TaskInput::Tuple(resolve_all(list).await?));
+                }
+                TaskInput::Option(Some(value)) if !value.is_resolved() => {
+                    let value = resolve_all(vec![*value]).await?.pop().unwrap();
+                    return Ok(TaskInput::Option(Some(Box::new(value))));
+                }
                 _ => return Ok(current),
             }
         }
@@ -460,7 +482,8 @@ impl TaskInput {
     pub fn is_resolved(&self) -> bool {
         match self {
             TaskInput::TaskOutput(_) => false,
-            TaskInput::List(list) => list.iter().all(|i| i.is_resolved()),
+            TaskInput::List(list) | TaskInput::Tuple(list) => list.iter().all(|i| i.is_resolved()),
+            TaskInput::Option(Some(value)) => value.is_resolved(),
             _ => true,
         }
     }
@@ -522,6 +545,19 @@ impl Display for TaskInput {
             TaskInput::SharedReference(data) => {
                 write!(f, "shared reference with {}", data)
             }
+            TaskInput::F32(bits) => write!(f, "f32 {}", f32::from_bits(*bits)),
+            TaskInput::F64(bits) => write!(f, "f64 {}", f64::from_bits(*bits)),
+            TaskInput::Bytes(bytes) => write!(f, "{} bytes", bytes.len()),
+            TaskInput::Tuple(list) => write!(
+                f,
+                "tuple ({})",
+                list.iter()
+                    .map(|i| i.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            TaskInput::Option(None) => write!(f, "none"),
+            TaskInput::Option(Some(value)) => write!(f, "some {}", value),
         }
     }
 }
@@ -568,23 +604,90 @@ impl From<usize> for TaskInput {
     }
 }
 
+impl From<f32> for TaskInput {
+    fn from(v: f32) -> Self {
+        TaskInput::F32(v.to_bits())
+    }
+}
+
+impl From<f64> for TaskInput {
+    fn from(v: f64) -> Self {
+        TaskInput::F64(v.to_bits())
+    }
+}
+
+// These only compile next to the generic `From<&[T]>` impl because `u8`
+// doesn't implement `Into<TaskInput>`. Don't add `From<u8> for TaskInput`,
+// bytes are meant to be stored as a buffer and not as a list.
+impl From<Vec<u8>> for TaskInput {
+    fn from(bytes: Vec<u8>) -> Self {
+        TaskInput::Bytes(bytes)
+    }
+}
+
+impl From<&[u8]> for TaskInput {
+    fn from(bytes: &[u8]) -> Self {
+        TaskInput::Bytes(bytes.to_vec())
+    }
+}
+
+impl<T: Into<TaskInput> + Clone> From<&[T]> for TaskInput {
+    fn from(s: &[T]) -> Self {
+        TaskInput::List(s.iter().map(|i| i.clone().into()).collect())
+    }
+}
+
 impl<T> From<Option<T>> for TaskInput
 where
     TaskInput: From<T>,
 {
     fn from(v: Option<T>) -> Self {
-        match v {
-            None => TaskInput::Nothing,
-            Some(v) => {
-                let result = v.into();
-                // Option<Option<T>> leads to problems with using Some(None)
-                debug_assert!(result != TaskInput::Nothing);
-                result
+        TaskInput::Option(v.map(|v| {
+            let input = v.into();
+            // `FromTaskInput` still reads a bare `Nothing` as `None`, which
+            // would be ambiguous for a `Some` holding it
+            debug_assert!(input != TaskInput::Nothing);
+            Box::new(input)
+        }))
+    }
+}
+
+macro_rules! tuple_task_input {
+    ($len:literal; $($ty:ident $value:ident $index:tt),+) => {
+        impl<$($ty: Into<TaskInput>),+> From<($($ty,)+)> for TaskInput {
+            fn from(v: ($($ty,)+)) -> Self {
+                TaskInput::Tuple(vec![$(v.$index.into()),+])
             }
         }
-    }
+
+        impl<'a, $($ty: FromTaskInput<'a, Error = anyhow::Error>),+> FromTaskInput<'a>
+            for ($($ty,)+)
+        {
+            type Error = anyhow::Error;
+
+            fn try_from(value: &'a TaskInput) -> Result<Self, Self::Error> {
+                match value {
+                    TaskInput::Tuple(list) => {
+                        let [$($value),+] = &list[..] else {
+                            return Err(anyhow!(
+                                "invalid task input type, expected tuple of {} elements got {}",
+                                $len,
+                                list.len()
+                            ));
+                        };
+                        Ok(($(FromTaskInput::try_from($value)?,)+))
+                    }
+                    _ => Err(anyhow!("invalid task input type, expected tuple")),
+                }
+            }
+        }
+    };
 }
 
+tuple_task_input!(2; A a 0, B b 1);
+tuple_task_input!(3; A a 0, B b 1, C c 2);
+tuple_task_input!(4; A a 0, B b 1, C c 2, D d 3);
+
 impl<T: Any + Debug + Clone + Hash + Eq + Ord + Typed + TypedForInput + Send + Sync + 'static>
     From<Value<T>> for TaskInput
 where
@@ -722,6 +825,50 @@ impl FromTaskInput<'_> for usize {
     }
 }
 
+impl FromTaskInput<'_> for f32 {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &TaskInput) -> Result<Self, Self::Error> {
+        match value {
+            TaskInput::F32(bits) => Ok(f32::from_bits(*bits)),
+            _ => Err(anyhow!("invalid task input type, expected f32")),
+        }
+    }
+}
+
+impl FromTaskInput<'_> for f64 {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &TaskInput) -> Result<Self, Self::Error> {
+        match value {
+            TaskInput::F64(bits) => Ok(f64::from_bits(*bits)),
+            _ => Err(anyhow!("invalid task input type, expected f64")),
+        }
+    }
+}
+
+impl FromTaskInput<'_> for Vec<u8> {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &TaskInput) -> Result<Self, Self::Error> {
+        match value {
+            TaskInput::Bytes(bytes) => Ok(bytes.clone()),
+            _ => Err(anyhow!("invalid task input type, expected bytes")),
+        }
+    }
+}
+
+impl<'a> FromTaskInput<'a> for &'a [u8] {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &'a TaskInput) -> Result<Self, Self::Error> {
+        match value {
+            TaskInput::Bytes(bytes) => Ok(bytes),
+            _ => Err(anyhow!("invalid task input type, expected bytes")),
+        }
+    }
+}
+
 impl<'a, T> FromTaskInput<'a> for Option<T>
 where
     T: FromTaskInput<'a>,
@@ -730,7 +877,8 @@ where
 
     fn try_from(value: &'a TaskInput) -> Result<Self, Self::Error> {
         match value {
-            TaskInput::Nothing => Ok(None),
+            TaskInput::Option(None) | TaskInput::Nothing => Ok(None),
+            TaskInput::Option(Some(value)) => Ok(Some(FromTaskInput::try_from(value)?)),
             _ => Ok(Some(FromTaskInput::try_from(value)?)),
         }
     }
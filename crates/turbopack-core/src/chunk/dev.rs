@@ -19,6 +19,11 @@ impl DevChunkingContextBuilder {
         self
     }
 
+    pub fn error_overlay(mut self) -> Self {
+        self.context.enable_error_overlay = true;
+        self
+    }
+
     pub fn layer(mut self, layer: &str) -> Self {
         self.context.layer = (!layer.is_empty()).then(|| layer.to_string());
         self
@@ -56,6 +61,8 @@ pub struct DevChunkingContext {
     layer: Option<String>,
     /// Enable HMR for this chunking
     enable_hot_module_replacement: bool,
+    /// Enable the built-in error overlay for this chunking
+    enable_error_overlay: bool,
     /// The environment chunks will be evaluated in.
     environment: EnvironmentVc,
 }
@@ -77,6 +84,7 @@ impl DevChunkingContextVc {
                 asset_root_path,
                 layer: None,
                 enable_hot_module_replacement: false,
+                enable_error_overlay: false,
                 environment,
             },
         }
@@ -191,6 +199,11 @@ impl ChunkingContext for DevChunkingContext {
         BoolVc::cell(self.enable_hot_module_replacement)
     }
 
+    #[turbo_tasks::function]
+    fn is_error_overlay_enabled(&self) -> BoolVc {
+        BoolVc::cell(self.enable_error_overlay)
+    }
+
     #[turbo_tasks::function]
     fn layer(&self) -> StringVc {
         StringVc::cell(self.layer.clone().unwrap_or_default())
@@ -62,6 +62,13 @@ pub trait ChunkingContext {
         BoolVc::cell(false)
     }
 
+    /// Whether the runtime should show issues and runtime errors in a
+    /// built-in overlay. Frameworks that bring their own overlay keep this
+    /// disabled.
+    fn is_error_overlay_enabled(&self) -> BoolVc {
+        BoolVc::cell(false)
+    }
+
     fn layer(&self) -> StringVc {
         StringVc::cell("".to_string())
     }
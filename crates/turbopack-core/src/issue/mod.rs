@@ -14,9 +14,11 @@ use std::{
 use anyhow::Result;
 use auto_hash_map::AutoSet;
 use futures::FutureExt;
+use serde::Serialize;
 use turbo_tasks::{
     emit,
     primitives::{BoolVc, StringVc, U64Vc},
+    trace::TraceRawVcs,
     CollectiblesSource, ReadRef, TryJoinIterExt, ValueToString, ValueToStringVc,
 };
 use turbo_tasks_fs::{
@@ -529,6 +531,17 @@ pub struct PlainIssueSource {
     pub end: SourcePos,
 }
 
+/// The number of lines to include before and after the issue range in a
+/// code frame.
+const CODE_FRAME_CONTEXT_LINES: usize = 3;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, TraceRawVcs)]
+pub struct CodeFrameLine {
+    /// The zero-based line number, matching [SourcePos::line].
+    pub line: usize,
+    pub content: String,
+}
+
 #[turbo_tasks::value_impl]
 impl IssueSourceVc {
     #[turbo_tasks::function]
@@ -543,6 +556,36 @@ impl IssueSourceVc {
     }
 }
 
+impl PlainIssueSource {
+    /// The lines surrounding the issue, so clients can render a code frame
+    /// without reading the asset. This is only computed on request, as most
+    /// consumers of issues never display one.
+    pub fn code_frame(&self) -> Vec<CodeFrameLine> {
+        // Sources without a known position span the whole asset.
+        if self.end == SourcePos::max() {
+            return Vec::new();
+        }
+        let FileContent::Content(file) = &*self.asset.content else {
+            return Vec::new();
+        };
+        let Ok(content) = file.content().to_str() else {
+            return Vec::new();
+        };
+        let start = self.start.line.saturating_sub(CODE_FRAME_CONTEXT_LINES);
+        let end = self.end.line.saturating_add(CODE_FRAME_CONTEXT_LINES);
+        content
+            .split('\n')
+            .enumerate()
+            .take(end.saturating_add(1))
+            .skip(start)
+            .map(|(line, content)| CodeFrameLine {
+                line,
+                content: content.to_string(),
+            })
+            .collect()
+    }
+}
+
 #[turbo_tasks::value(serialization = "none")]
 #[derive(Clone, Debug)]
 pub struct PlainAsset {
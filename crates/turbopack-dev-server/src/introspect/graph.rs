@@ -0,0 +1,276 @@
+use std::{collections::HashMap, fmt::Write};
+
+use anyhow::Result;
+use turbo_tasks::TryJoinIterExt;
+use turbopack_core::introspect::IntrospectableVc;
+
+use super::HtmlStringEscaped;
+
+/// The maximum number of nodes visited when walking the introspection graph.
+/// Walks stop early and mark the graph as truncated when this is exceeded.
+pub const MAX_GRAPH_NODES: usize = 50_000;
+
+/// An introspectable and the names and indices of its children in an
+/// [IntrospectionGraph].
+pub struct IntrospectionNode {
+    pub introspectable: IntrospectableVc,
+    pub ty: String,
+    pub title: String,
+    /// The edge to the parent this node has been reached from first, which
+    /// is on a shortest path from the root.
+    pub parent: Option<(String, usize)>,
+}
+
+pub struct IntrospectionEdge {
+    pub from: usize,
+    pub to: usize,
+    pub name: String,
+}
+
+/// The part of the introspection tree reachable from a root, with each
+/// introspectable included once.
+pub struct IntrospectionGraph {
+    pub nodes: Vec<IntrospectionNode>,
+    pub edges: Vec<IntrospectionEdge>,
+    pub truncated: bool,
+}
+
+impl IntrospectionGraph {
+    /// Walks the graph breadth-first from `root`. The children of all nodes
+    /// of a level are read concurrently.
+    pub async fn walk(root: IntrospectableVc, max_nodes: usize) -> Result<Self> {
+        let mut graph = IntrospectionGraph {
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            truncated: false,
+        };
+        let mut indices = HashMap::new();
+        graph.add_nodes(&mut indices, vec![(root, None)]).await?;
+        let mut level = vec![0];
+
+        while !level.is_empty() {
+            let level_children = level
+                .iter()
+                .map(|&index| {
+                    let introspectable = graph.nodes[index].introspectable;
+                    async move {
+                        let children = introspectable
+                            .children()
+                            .await?
+                            .iter()
+                            .map(
+                                |&(name, child)| async move { Ok(((*name.await?).clone(), child)) },
+                            )
+                            .try_join()
+                            .await?;
+                        anyhow::Ok((index, children))
+                    }
+                })
+                .try_join()
+                .await?;
+
+            let mut new_nodes = Vec::new();
+            for (index, mut children) in level_children {
+                // Children are a set, sort them to get a stable output.
+                children.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+                for (name, child) in children {
+                    let to = match indices.get(&child) {
+                        Some(&to) => to,
+                        None => {
+                            if graph.nodes.len() + new_nodes.len() >= max_nodes {
+                                graph.truncated = true;
+                                continue;
+                            }
+                            let to = graph.nodes.len() + new_nodes.len();
+                            indices.insert(child, to);
+                            new_nodes.push((child, Some((name.clone(), index))));
+                            to
+                        }
+                    };
+                    graph.edges.push(IntrospectionEdge {
+                        from: index,
+                        to,
+                        name,
+                    });
+                }
+            }
+            level = graph.add_nodes(&mut indices, new_nodes).await?;
+        }
+        Ok(graph)
+    }
+
+    /// Adds nodes in order, reading their types and titles concurrently.
+    /// Returns the indices of the added nodes.
+    async fn add_nodes(
+        &mut self,
+        indices: &mut HashMap<IntrospectableVc, usize>,
+        nodes: Vec<(IntrospectableVc, Option<(String, usize)>)>,
+    ) -> Result<Vec<usize>> {
+        let nodes = nodes
+            .into_iter()
+            .map(|(introspectable, parent)| async move {
+                Ok(IntrospectionNode {
+                    introspectable,
+                    ty: (*introspectable.ty().await?).clone(),
+                    title: (*introspectable.title().await?).clone(),
+                    parent,
+                })
+            })
+            .try_join()
+            .await?;
+        let first = self.nodes.len();
+        for node in nodes {
+            indices.insert(node.introspectable, self.nodes.len());
+            self.nodes.push(node);
+        }
+        Ok((first..self.nodes.len()).collect())
+    }
+
+    /// The names and nodes on the shortest path from the root to the node,
+    /// excluding the root.
+    pub fn path_to(&self, mut index: usize) -> Vec<(&str, &IntrospectionNode)> {
+        let mut path = Vec::new();
+        while let Some((name, parent)) = &self.nodes[index].parent {
+            path.push((name.as_str(), &self.nodes[index]));
+            index = *parent;
+        }
+        path.reverse();
+        path
+    }
+
+    /// Exports the graph in the Graphviz DOT format.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph introspection {\n");
+        for (index, node) in self.nodes.iter().enumerate() {
+            writeln!(
+                dot,
+                "  n{index} [label=\"[{ty}] {title}\"];",
+                ty = DotEscaped(&node.ty),
+                title = DotEscaped(&node.title)
+            )
+            .unwrap();
+        }
+        for edge in &self.edges {
+            writeln!(
+                dot,
+                "  n{} -> n{} [label=\"{}\"];",
+                edge.from,
+                edge.to,
+                DotEscaped(&edge.name)
+            )
+            .unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Exports the graph in the GraphML format.
+    pub fn to_graphml(&self) -> String {
+        let mut graphml = String::from(GRAPHML_HEADER);
+        for (index, node) in self.nodes.iter().enumerate() {
+            writeln!(
+                graphml,
+                "    <node id=\"n{index}\"><data key=\"ty\">{ty}</data><data \
+                 key=\"title\">{title}</data></node>",
+                ty = HtmlStringEscaped(&node.ty),
+                title = HtmlStringEscaped(&node.title)
+            )
+            .unwrap();
+        }
+        for edge in &self.edges {
+            writeln!(
+                graphml,
+                "    <edge source=\"n{}\" target=\"n{}\"><data key=\"name\">{}</data></edge>",
+                edge.from,
+                edge.to,
+                HtmlStringEscaped(&edge.name)
+            )
+            .unwrap();
+        }
+        graphml.push_str("  </graph>\n</graphml>\n");
+        graphml
+    }
+}
+
+const GRAPHML_HEADER: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+  <key id="ty" for="node" attr.name="type" attr.type="string"/>
+  <key id="title" for="node" attr.name="title" attr.type="string"/>
+  <key id="name" for="edge" attr.name="name" attr.type="string"/>
+  <graph id="introspection" edgedefault="directed">
+"#;
+
+struct DotEscaped<'a>(&'a str);
+
+impl std::fmt::Display for DotEscaped<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                '\n' => f.write_str("\\n")?,
+                c => f.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use turbo_tasks::{RawVc, TaskId};
+
+    use super::*;
+
+    fn graph() -> IntrospectionGraph {
+        // The introspectables are never read by the exports.
+        let node = |id: usize, ty: &str, title: &str| IntrospectionNode {
+            introspectable: RawVc::TaskOutput(TaskId::from(id)).into(),
+            ty: ty.to_string(),
+            title: title.to_string(),
+            parent: None,
+        };
+        IntrospectionGraph {
+            nodes: vec![
+                node(1, "source", "say \"hi\""),
+                node(2, "asset", "<a> & <b>"),
+            ],
+            edges: vec![IntrospectionEdge {
+                from: 0,
+                to: 1,
+                name: "a\\b\n\"c\" & <d>".to_string(),
+            }],
+            truncated: false,
+        }
+    }
+
+    #[test]
+    fn dot_escaping() {
+        assert_eq!(
+            graph().to_dot(),
+            r#"digraph introspection {
+  n0 [label="[source] say \"hi\""];
+  n1 [label="[asset] <a> & <b>"];
+  n0 -> n1 [label="a\\b\n\"c\" & <d>"];
+}
+"#
+        );
+    }
+
+    #[test]
+    fn graphml_escaping() {
+        let graphml = graph().to_graphml();
+        assert!(graphml.starts_with(GRAPHML_HEADER));
+        assert_eq!(
+            &graphml[GRAPHML_HEADER.len()..],
+            r#"    <node id="n0"><data key="ty">source</data><data key="title">say &quot;hi&quot;</data></node>
+    <node id="n1"><data key="ty">asset</data><data key="title">&lt;a&gt; &amp; &lt;b&gt;</data></node>
+    <edge source="n0" target="n1"><data key="name">a\b
+&quot;c&quot; &amp; &lt;d&gt;</data></edge>
+  </graph>
+</graphml>
+"#
+        );
+    }
+}
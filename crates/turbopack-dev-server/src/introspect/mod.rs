@@ -1,7 +1,11 @@
+mod graph;
+
 use std::{collections::HashSet, fmt::Display};
 
 use anyhow::Result;
-use turbo_tasks::{primitives::StringVc, TryJoinIterExt};
+use mime::Mime;
+use serde_json::json;
+use turbo_tasks::{primitives::StringVc, TryJoinIterExt, Value};
 use turbo_tasks_fs::{File, FileContent};
 use turbopack_core::{
     asset::AssetContent,
@@ -9,8 +13,11 @@ use turbopack_core::{
 };
 use turbopack_ecmascript::utils::FormatIter;
 
+use self::graph::{IntrospectionGraph, MAX_GRAPH_NODES};
 use crate::source::{
-    ContentSource, ContentSourceContent, ContentSourceData, ContentSourceResultVc, ContentSourceVc,
+    query::QueryValue, ContentSource, ContentSourceContent, ContentSourceData,
+    ContentSourceDataFilter, ContentSourceDataVary, ContentSourceResultVc, ContentSourceVc,
+    NeededData,
 };
 
 #[turbo_tasks::value(shared)]
@@ -70,85 +77,327 @@ impl<T: Display> Display for HtmlStringEscaped<T> {
     }
 }
 
+/// The format a page of the introspection UI is served in.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Html,
+    Json,
+}
+
+fn serve(body: String, content_type: Mime) -> ContentSourceResultVc {
+    ContentSourceResultVc::exact(
+        ContentSourceContent::Static(
+            AssetContent::File(
+                FileContent::Content(File::from(body).with_content_type(content_type)).cell(),
+            )
+            .cell()
+            .into(),
+        )
+        .cell(),
+    )
+}
+
+fn serve_json(value: &serde_json::Value) -> Result<ContentSourceResultVc> {
+    Ok(serve(
+        serde_json::to_string_pretty(value)?,
+        mime::APPLICATION_JSON,
+    ))
+}
+
+fn query_string<'a>(data: &'a ContentSourceData, name: &str) -> Option<&'a str> {
+    match data.query.as_ref()?.get(name)? {
+        QueryValue::String(value) if !value.is_empty() => Some(value),
+        _ => None,
+    }
+}
+
 #[turbo_tasks::value_impl]
-impl ContentSource for IntrospectionSource {
+impl IntrospectionSourceVc {
+    /// The introspectable a path in the introspection UI refers to. The empty
+    /// path refers to the root.
     #[turbo_tasks::function]
-    async fn get(
-        self_vc: IntrospectionSourceVc,
-        path: &str,
-        _data: turbo_tasks::Value<ContentSourceData>,
-    ) -> Result<ContentSourceResultVc> {
-        let introspectable = if path.is_empty() {
-            let roots = &self_vc.await?.roots;
+    async fn introspectable(self, path: &str) -> Result<IntrospectableVc> {
+        Ok(if path.is_empty() {
+            let roots = &self.await?.roots;
             if roots.len() == 1 {
                 *roots.iter().next().unwrap()
             } else {
-                self_vc.as_introspectable()
+                self.as_introspectable()
             }
         } else {
             serde_json::from_str(path)?
-        };
-        let ty = introspectable.ty().await?;
-        let title = introspectable.title().await?;
-        let details = introspectable.details().await?;
-        let children = introspectable.children().await?;
-        let has_children = !children.is_empty();
-        let mut children = children
-            .iter()
-            .map(|&(name, child)| async move {
-                let name = name.await?;
-                let ty = child.ty().await?;
-                let title = child.title().await?;
-                let path = serde_json::to_string(&child)?;
-                Ok(format!(
-                    "<li>{name} <!-- {title} --><a href=\"./{path}\">[{ty}] {title}</a></li>",
-                    name = HtmlEscaped(name),
-                    title = HtmlEscaped(title),
-                    path = HtmlStringEscaped(urlencoding::encode(&path)),
-                    ty = HtmlEscaped(ty),
-                ))
-            })
-            .try_join()
-            .await?;
-        children.sort();
-        let details = if details.is_empty() {
-            String::new()
-        } else if has_children {
-            format!(
-                "<details><summary><h3 style=\"display: \
-                 inline;\">Details</h3></summary><pre>{details}</pre></details>",
-                details = HtmlEscaped(details)
-            )
-        } else {
-            format!(
-                "<h3>Details</h3><pre>{details}</pre>",
-                details = HtmlEscaped(details)
-            )
-        };
-        let html = format!(
-            "<!DOCTYPE html>
+        })
+    }
+}
+
+async fn html_page(introspectable: IntrospectableVc, path: &str) -> Result<ContentSourceResultVc> {
+    let ty = introspectable.ty().await?;
+    let title = introspectable.title().await?;
+    let details = introspectable.details().await?;
+    let children = introspectable.children().await?;
+    let has_children = !children.is_empty();
+    let mut children = children
+        .iter()
+        .map(|&(name, child)| async move {
+            let name = name.await?;
+            let ty = child.ty().await?;
+            let title = child.title().await?;
+            let path = serde_json::to_string(&child)?;
+            Ok(format!(
+                "<li>{name} <!-- {title} --><a href=\"./{path}\">[{ty}] {title}</a></li>",
+                name = HtmlEscaped(name),
+                title = HtmlEscaped(title),
+                path = HtmlStringEscaped(urlencoding::encode(&path)),
+                ty = HtmlEscaped(ty),
+            ))
+        })
+        .try_join()
+        .await?;
+    children.sort();
+    let details = if details.is_empty() {
+        String::new()
+    } else if has_children {
+        format!(
+            "<details><summary><h3 style=\"display: \
+             inline;\">Details</h3></summary><pre>{details}</pre></details>",
+            details = HtmlEscaped(details)
+        )
+    } else {
+        format!(
+            "<h3>Details</h3><pre>{details}</pre>",
+            details = HtmlEscaped(details)
+        )
+    };
+    let path = HtmlStringEscaped(urlencoding::encode(path));
+    let html = format!(
+        "<!DOCTYPE html>
 <html><head><title>{title}</title></head>
 <body>
+  {search_form}
   <h2>{ty}</h2>
   <h1>{title}</h1>
+  <p>
+    <a href=\"./json/{path}\">JSON</a> |
+    <a href=\"./graph.dot/{path}\">DOT</a> |
+    <a href=\"./graph.graphml/{path}\">GraphML</a>
+  </p>
   {details}
   <ul>{children}</ul>
 </body>
 </html>",
-            title = HtmlEscaped(title),
-            ty = HtmlEscaped(ty),
-            children = FormatIter(|| children.iter())
-        );
-        Ok(ContentSourceResultVc::exact(
-            ContentSourceContent::Static(
-                AssetContent::File(
-                    FileContent::Content(File::from(html).with_content_type(mime::TEXT_HTML_UTF_8))
-                        .cell(),
+        search_form = SEARCH_FORM,
+        title = HtmlEscaped(title),
+        ty = HtmlEscaped(ty),
+        children = FormatIter(|| children.iter())
+    );
+    Ok(serve(html, mime::TEXT_HTML_UTF_8))
+}
+
+async fn json_page(introspectable: IntrospectableVc) -> Result<ContentSourceResultVc> {
+    let children = introspectable
+        .children()
+        .await?
+        .iter()
+        .map(|&(name, child)| async move {
+            Ok(json!({
+                "name": &*name.await?,
+                "ty": &*child.ty().await?,
+                "title": &*child.title().await?,
+                "path": serde_json::to_string(&child)?,
+            }))
+        })
+        .try_join()
+        .await?;
+    serve_json(&json!({
+        "ty": &*introspectable.ty().await?,
+        "title": &*introspectable.title().await?,
+        "details": &*introspectable.details().await?,
+        "path": serde_json::to_string(&introspectable)?,
+        "children": children,
+    }))
+}
+
+const SEARCH_FORM: &str = "<form action=\"./search\">
+    <input name=\"q\" placeholder=\"path or title\">
+    <input name=\"type\" placeholder=\"type\">
+    <button>Search</button>
+  </form>";
+
+/// The maximum number of results returned by a search.
+const MAX_SEARCH_RESULTS: usize = 100;
+
+/// Searches all introspectables reachable from `root` by a case-insensitive
+/// substring of their title, which is the path for assets, and/or by their
+/// exact type. Every result includes the shortest path from the root, which
+/// explains why it is included.
+async fn search(
+    root: IntrospectableVc,
+    query: Option<&str>,
+    ty: Option<&str>,
+    format: Format,
+) -> Result<ContentSourceResultVc> {
+    let graph = IntrospectionGraph::walk(root, MAX_GRAPH_NODES).await?;
+    let query = query.map(|query| query.to_lowercase());
+    let results = graph
+        .nodes
+        .iter()
+        .enumerate()
+        .filter(|(_, node)| {
+            query
+                .as_ref()
+                .map_or(true, |query| node.title.to_lowercase().contains(query))
+                && ty.map_or(true, |ty| node.ty == ty)
+        })
+        .take(MAX_SEARCH_RESULTS)
+        .collect::<Vec<_>>();
+
+    if format == Format::Json {
+        let results = results
+            .into_iter()
+            .map(|(index, node)| {
+                let via = graph
+                    .path_to(index)
+                    .into_iter()
+                    .map(|(name, node)| json!({ "name": name, "ty": node.ty, "title": node.title }))
+                    .collect::<Vec<_>>();
+                Ok(json!({
+                    "ty": node.ty,
+                    "title": node.title,
+                    "path": serde_json::to_string(&node.introspectable)?,
+                    "via": via,
+                }))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        return serve_json(&json!({
+            "results": results,
+            "truncated": graph.truncated,
+        }));
+    }
+
+    let mut items = Vec::new();
+    for (index, node) in results {
+        let path = serde_json::to_string(&node.introspectable)?;
+        let via = graph
+            .path_to(index)
+            .into_iter()
+            .map(|(name, node)| {
+                format!(
+                    "{name} [{ty}] {title}",
+                    name = HtmlEscaped(name),
+                    ty = HtmlEscaped(&node.ty),
+                    title = HtmlEscaped(&node.title)
                 )
-                .cell()
-                .into(),
+            })
+            .collect::<Vec<_>>();
+        items.push(format!(
+            "<li><a href=\"./{path}\">[{ty}] {title}</a><br><small>{via}</small></li>",
+            path = HtmlStringEscaped(urlencoding::encode(&path)),
+            ty = HtmlEscaped(&node.ty),
+            title = HtmlEscaped(&node.title),
+            via = via.join(" &rarr; "),
+        ));
+    }
+    let truncated = if graph.truncated {
+        "<p>Only the first nodes of the graph have been searched.</p>"
+    } else {
+        ""
+    };
+    let html = format!(
+        "<!DOCTYPE html>
+<html><head><title>Search</title></head>
+<body>
+  {search_form}
+  <h1>{count} results</h1>
+  {truncated}
+  <ul>{items}</ul>
+</body>
+</html>",
+        search_form = SEARCH_FORM,
+        count = items.len(),
+        items = FormatIter(|| items.iter())
+    );
+    Ok(serve(html, mime::TEXT_HTML_UTF_8))
+}
+
+/// The introspection UI.
+///
+/// * `<path>` shows an introspectable as HTML, where the path is the serialized
+///   introspectable or empty for the root.
+/// * `json/<path>` returns the same as JSON.
+/// * `search?q=<title>&type=<type>` searches everything reachable from the
+///   root, `json/search` returns the results as JSON.
+/// * `graph.dot/<path>` and `graph.graphml/<path>` export everything reachable
+///   from an introspectable as a graph.
+#[turbo_tasks::value_impl]
+impl ContentSource for IntrospectionSource {
+    #[turbo_tasks::function]
+    async fn get(
+        self_vc: IntrospectionSourceVc,
+        path: &str,
+        data: Value<ContentSourceData>,
+    ) -> Result<ContentSourceResultVc> {
+        let (format, subpath) = match path.strip_prefix("json") {
+            Some("") => (Format::Json, ""),
+            Some(rest) if rest.starts_with('/') => (Format::Json, &rest[1..]),
+            _ => (Format::Html, path),
+        };
+
+        if subpath == "search" {
+            if data.query.is_none() {
+                return Ok(ContentSourceResultVc::exact(
+                    ContentSourceContent::NeedData(NeededData {
+                        source: self_vc.into(),
+                        path: path.to_string(),
+                        vary: ContentSourceDataVary {
+                            query: Some(ContentSourceDataFilter::Subset(
+                                ["q".to_string(), "type".to_string()].into(),
+                            )),
+                            ..Default::default()
+                        },
+                    })
+                    .cell(),
+                ));
+            }
+            return search(
+                self_vc.introspectable(""),
+                query_string(&data, "q"),
+                query_string(&data, "type"),
+                format,
             )
-            .cell(),
-        ))
+            .await;
+        }
+
+        if format == Format::Html {
+            for (prefix, export) in [
+                ("graph.dot", GraphExport::Dot),
+                ("graph.graphml", GraphExport::GraphMl),
+            ] {
+                let subpath = match path.strip_prefix(prefix) {
+                    Some("") => "",
+                    Some(rest) if rest.starts_with('/') => &rest[1..],
+                    _ => continue,
+                };
+                let graph =
+                    IntrospectionGraph::walk(self_vc.introspectable(subpath), MAX_GRAPH_NODES)
+                        .await?;
+                return Ok(match export {
+                    GraphExport::Dot => serve(graph.to_dot(), "text/vnd.graphviz".parse()?),
+                    GraphExport::GraphMl => {
+                        serve(graph.to_graphml(), "application/graphml+xml".parse()?)
+                    }
+                });
+            }
+        }
+
+        let introspectable = self_vc.introspectable(subpath);
+        match format {
+            Format::Html => html_page(introspectable, subpath).await,
+            Format::Json => json_page(introspectable).await,
+        }
     }
 }
+
+enum GraphExport {
+    Dot,
+    GraphMl,
+}
@@ -4,6 +4,7 @@
 
 pub mod html;
 pub mod introspect;
+pub mod request_log;
 pub mod source;
 pub mod update;
 
@@ -30,22 +31,32 @@ use hyper::{
 };
 use mime_guess::mime;
 use source::{Body, Bytes};
+use tracing::Instrument;
 use turbo_tasks::{
-    run_once, trace::TraceRawVcs, util::FormatDuration, RawVc, TransientValue, TurboTasksApi, Value,
+    run_once_with_priority, trace::TraceRawVcs, RawVc, TaskPriority, TransientValue, TurboTasksApi,
+    Value,
 };
 use turbo_tasks_fs::{FileContent, FileContentReadRef};
 use turbopack_cli_utils::issue::{ConsoleUi, ConsoleUiVc};
 use turbopack_core::asset::AssetContent;
 
 use self::{
+    request_log::{
+        next_request_id, RequestLog, RequestLogger, RequestPhaseKind, RequestTimings, TimedBody,
+    },
     source::{
-        query::Query, ContentSourceContent, ContentSourceDataVary, ContentSourceResultVc,
-        ContentSourceVc, ProxyResultReadRef,
+        query::Query, ContentSourceContent, ContentSourceContentVc, ContentSourceDataVary,
+        ContentSourceResultVc, ContentSourceVc, ProxyResultReadRef,
+    },
+    update::{
+        protocol::ResourceIdentifier, sse::is_event_stream_request, SseSessions, UpdateServer,
     },
-    update::{protocol::ResourceIdentifier, UpdateServer},
 };
 use crate::source::{ContentSourceData, HeaderValue};
 
+/// The path on which clients connect to receive updates.
+const HMR_PATH: &str = "/turbopack-hmr";
+
 pub trait SourceProvider: Send + Clone + 'static {
     /// must call a turbo-tasks function internally
     fn get_source(&self) -> ContentSourceVc;
@@ -106,6 +117,10 @@ enum GetFromSourceResult {
         path: String,
         vary: ContentSourceDataVary,
     },
+    Delayed {
+        result: GetFromSourceResultReadRef,
+        delay_ms: u64,
+    },
     NotFound,
 }
 
@@ -115,8 +130,12 @@ async fn get_from_source(
     path: &str,
     data: Value<ContentSourceData>,
 ) -> Result<GetFromSourceResultVc> {
-    let content = source.get(path, data).await?.content.await?;
-    Ok(match &*content {
+    Ok(get_from_content(source.get(path, data).await?.content))
+}
+
+#[turbo_tasks::function]
+async fn get_from_content(content: ContentSourceContentVc) -> Result<GetFromSourceResultVc> {
+    Ok(match &*content.await? {
         ContentSourceContent::Static(content_vc) => {
             if let AssetContent::File(file) = &*content_vc.content().await? {
                 GetFromSourceResult::Static(file.await?)
@@ -130,6 +149,10 @@ async fn get_from_source(
             path: data.path.clone(),
             vary: data.vary.clone(),
         },
+        ContentSourceContent::Delayed { content, delay_ms } => GetFromSourceResult::Delayed {
+            result: get_from_content(*content).await?,
+            delay_ms: *delay_ms,
+        },
         ContentSourceContent::NotFound => GetFromSourceResult::NotFound,
     }
     .cell())
@@ -141,18 +164,37 @@ async fn process_request_with_content_source(
     mut asset_path: Cow<'_, str>,
     mut request: Request<hyper::Body>,
     console_ui: ConsoleUiVc,
+    timings: &mut RequestTimings,
+    request_span: &tracing::Span,
 ) -> Result<Response<hyper::Body>> {
     let mut data = ContentSourceData::default();
+    let mut phase = RequestPhaseKind::GetContent;
+    let mut phase_start = Instant::now();
     loop {
         let content_source_result = get_from_source(resolved_source, &asset_path, Value::new(data));
-        handle_issues(
-            content_source_result,
-            path,
-            "get content from source",
-            console_ui,
-        )
+        let result = async {
+            handle_issues(
+                content_source_result,
+                path,
+                "get content from source",
+                console_ui,
+            )
+            .await?;
+            content_source_result.strongly_consistent().await
+        }
+        .instrument(phase.span(request_span))
         .await?;
-        match &*content_source_result.strongly_consistent().await? {
+        timings.record(phase, phase_start);
+        let mut result = &*result;
+        while let GetFromSourceResult::Delayed {
+            result: delayed,
+            delay_ms,
+        } = result
+        {
+            tokio::time::sleep(Duration::from_millis(*delay_ms)).await;
+            result = delayed;
+        }
+        match result {
             GetFromSourceResult::Static(file) => {
                 if let FileContent::Content(content) = &**file {
                     let content_type = content.content_type().map_or_else(
@@ -198,11 +240,14 @@ async fn process_request_with_content_source(
                 return Ok(response.body(hyper::Body::wrap_stream(proxy_result.body.read()))?);
             }
             GetFromSourceResult::NeedData { source, path, vary } => {
+                phase = RequestPhaseKind::NeedData;
+                phase_start = Instant::now();
                 resolved_source = *source;
                 asset_path = Cow::Owned(path.to_string());
                 data = request_to_data(&mut request, vary).await?;
                 continue;
             }
+            GetFromSourceResult::Delayed { .. } => unreachable!("delays are awaited above"),
             GetFromSourceResult::NotFound => {}
         }
         return Ok(Response::builder().status(404).body(hyper::Body::empty())?);
@@ -233,31 +278,70 @@ impl DevServerBuilder {
         turbo_tasks: Arc<dyn TurboTasksApi>,
         source_provider: impl SourceProvider + Clone + Send + Sync,
         console_ui: Arc<ConsoleUi>,
+        request_logger: Arc<dyn RequestLogger>,
     ) -> DevServer {
+        let sse_sessions = SseSessions::default();
         let make_svc = make_service_fn(move |_| {
             let tt = turbo_tasks.clone();
             let source_provider = source_provider.clone();
             let console_ui = console_ui.clone();
+            let sse_sessions = sse_sessions.clone();
+            let request_logger = request_logger.clone();
             async move {
                 let handler = move |request: Request<hyper::Body>| {
                     let console_ui = console_ui.clone();
                     let start = Instant::now();
                     let tt = tt.clone();
                     let source_provider = source_provider.clone();
+                    let sse_sessions = sse_sessions.clone();
+                    let request_logger = request_logger.clone();
+                    let request_id = next_request_id();
+                    let method = request.method().to_string();
+                    let request_path = request.uri().path().to_string();
+                    let span = tracing::info_span!(
+                        "request",
+                        id = request_id,
+                        method = %method,
+                        path = %request_path,
+                        status = tracing::field::Empty,
+                    );
+                    let request_span = span.clone();
                     let future = async move {
+                        // Fallback for clients which can't use WebSockets, e.g. because a proxy
+                        // strips the upgrade headers.
+                        if request.uri().path() == HMR_PATH && is_event_stream_request(&request) {
+                            let (client, response) = sse_sessions.connect()?;
+                            let update_server = UpdateServer::new(source_provider);
+                            update_server.run_sse(&*tt, client);
+                            // The body streams until the client disconnects, so the whole
+                            // session is logged as the stream body phase.
+                            return Ok((response, RequestTimings::default()));
+                        }
+                        if request.method() == hyper::Method::POST {
+                            if let Some(session) = request
+                                .uri()
+                                .path()
+                                .strip_prefix(HMR_PATH)
+                                .and_then(|rest| rest.strip_prefix('/'))
+                            {
+                                let session = session.to_string();
+                                let response = sse_sessions.handle_post(&session, request).await?;
+                                return Ok((response, RequestTimings::default()));
+                            }
+                        }
+
                         if hyper_tungstenite::is_upgrade_request(&request) {
                             let uri = request.uri();
                             let path = uri.path();
 
-                            if path == "/turbopack-hmr" {
+                            if path == HMR_PATH {
                                 let (response, websocket) =
                                     hyper_tungstenite::upgrade(request, None)?;
                                 let update_server = UpdateServer::new(source_provider);
                                 update_server.run(&*tt, websocket);
-                                return Ok(response);
+                                return Ok((response, RequestTimings::default()));
                             }
 
-                            println!("[404] {} (WebSocket)", path);
                             if path == "/_next/webpack-hmr" {
                                 // Special-case requests to webpack-hmr as these are made by Next.js
                                 // clients built without turbopack, which may be making requests in
@@ -269,55 +353,92 @@ impl DevServerBuilder {
                                 );
                             }
 
-                            return Ok(Response::builder()
-                                .status(404)
-                                .body(hyper::Body::empty())?);
+                            return Ok((
+                                Response::builder().status(404).body(hyper::Body::empty())?,
+                                RequestTimings::default(),
+                            ));
                         }
 
-                        run_once(tt, async move {
+                        // The browser is waiting for the response, so it's compiled before
+                        // any background work. Source maps are only requested by devtools
+                        // and can wait.
+                        let priority = if request.uri().path().ends_with(".map") {
+                            TaskPriority::Background
+                        } else {
+                            TaskPriority::Foreground
+                        };
+                        // When the client disconnects, hyper drops this future, which
+                        // cancels the once task handling the request.
+                        run_once_with_priority(tt, priority, async move {
                             let console_ui = (*console_ui).clone().cell();
                             let uri = request.uri();
                             let path = uri.path();
                             // Remove leading slash.
                             let path = &path[1..].to_string();
                             let asset_path = urlencoding::decode(path)?;
-                            let source = source_provider.get_source();
-                            handle_issues(source, path, "get source", console_ui).await?;
-                            let resolved_source = source.resolve_strongly_consistent().await?;
+                            let mut timings = RequestTimings::default();
+                            let phase = RequestPhaseKind::GetSource;
+                            let phase_start = Instant::now();
+                            let resolved_source = async {
+                                let source = source_provider.get_source();
+                                handle_issues(source, path, "get source", console_ui).await?;
+                                source.resolve_strongly_consistent().await
+                            }
+                            .instrument(phase.span(&request_span))
+                            .await?;
+                            timings.record(phase, phase_start);
                             let response = process_request_with_content_source(
                                 path,
                                 resolved_source,
                                 asset_path,
                                 request,
                                 console_ui,
+                                &mut timings,
+                                &request_span,
                             )
                             .await?;
-                            let status = response.status().as_u16();
-                            let is_error = response.status().is_client_error()
-                                || response.status().is_server_error();
-                            let elapsed = start.elapsed();
-                            if is_error
-                                || (cfg!(feature = "log_request_stats")
-                                    && elapsed > Duration::from_secs(1))
-                            {
-                                println!(
-                                    "[{status}] /{path} ({duration})",
-                                    duration = FormatDuration(elapsed)
-                                );
-                            }
-                            Ok(response)
+                            Ok((response, timings))
                         })
                         .await
-                    };
+                    }
+                    .instrument(span.clone());
                     async move {
                         match future.await {
-                            Ok(r) => Ok::<_, hyper::http::Error>(r),
+                            Ok((response, timings)) => {
+                                let status = response.status().as_u16();
+                                span.record("status", status);
+                                let (parts, body) = response.into_parts();
+                                let body = TimedBody::new(body, &span, move |stream_start| {
+                                    let mut timings = timings;
+                                    timings.record(RequestPhaseKind::StreamBody, stream_start);
+                                    request_logger.log(&RequestLog {
+                                        id: request_id,
+                                        method: &method,
+                                        path: &request_path,
+                                        status,
+                                        start,
+                                        duration: start.elapsed(),
+                                        phases: timings.phases(),
+                                        error: None,
+                                    });
+                                });
+                                Ok::<_, hyper::http::Error>(Response::from_parts(
+                                    parts,
+                                    hyper::Body::wrap_stream(body),
+                                ))
+                            }
                             Err(e) => {
-                                println!(
-                                    "[500] error: {:?} ({})",
-                                    e,
-                                    FormatDuration(start.elapsed())
-                                );
+                                span.record("status", 500);
+                                request_logger.log(&RequestLog {
+                                    id: request_id,
+                                    method: &method,
+                                    path: &request_path,
+                                    status: 500,
+                                    start,
+                                    duration: start.elapsed(),
+                                    phases: &[],
+                                    error: Some(&e),
+                                });
                                 Ok(Response::builder()
                                     .status(500)
                                     .body(hyper::Body::from(format!("{:?}", e,)))?)
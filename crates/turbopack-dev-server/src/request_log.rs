@@ -0,0 +1,465 @@
+use std::{
+    fmt::Write as _,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use futures::Stream;
+use hyper::body::Bytes;
+use parking_lot::Mutex;
+use serde_json::json;
+use turbo_tasks::util::FormatDuration;
+
+/// A phase of handling a request in the dev server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestPhaseKind {
+    /// Getting and resolving the content source.
+    GetSource,
+    /// Getting the content from the content source.
+    GetContent,
+    /// Reading request data a content source asked for, and getting the
+    /// content again with that data.
+    NeedData,
+    /// Streaming the response body to the client.
+    StreamBody,
+}
+
+impl RequestPhaseKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RequestPhaseKind::GetSource => "get source",
+            RequestPhaseKind::GetContent => "get content",
+            RequestPhaseKind::NeedData => "need data",
+            RequestPhaseKind::StreamBody => "stream body",
+        }
+    }
+
+    /// Creates a `tracing` span covering this phase. The parent is explicit,
+    /// as phases run in tasks which don't inherit the request's span.
+    pub fn span(&self, request_span: &tracing::Span) -> tracing::Span {
+        match self {
+            RequestPhaseKind::GetSource => {
+                tracing::info_span!(parent: request_span, "get source")
+            }
+            RequestPhaseKind::GetContent => {
+                tracing::info_span!(parent: request_span, "get content")
+            }
+            RequestPhaseKind::NeedData => tracing::info_span!(parent: request_span, "need data"),
+            RequestPhaseKind::StreamBody => {
+                tracing::info_span!(parent: request_span, "stream body")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RequestPhase {
+    pub kind: RequestPhaseKind,
+    pub start: Instant,
+    pub duration: Duration,
+}
+
+/// Collects the phases of a single request while it is handled.
+#[derive(Debug, Default)]
+pub struct RequestTimings {
+    phases: Vec<RequestPhase>,
+}
+
+impl RequestTimings {
+    /// Records a phase that started at `start` and ended now.
+    pub fn record(&mut self, kind: RequestPhaseKind, start: Instant) {
+        self.phases.push(RequestPhase {
+            kind,
+            start,
+            duration: start.elapsed(),
+        });
+    }
+
+    pub fn phases(&self) -> &[RequestPhase] {
+        &self.phases
+    }
+}
+
+/// A request handled by the dev server, passed to a [RequestLogger] once the
+/// response body has been sent.
+pub struct RequestLog<'a> {
+    /// A process-unique id of the request.
+    pub id: u64,
+    pub method: &'a str,
+    pub path: &'a str,
+    pub status: u16,
+    pub start: Instant,
+    pub duration: Duration,
+    pub phases: &'a [RequestPhase],
+    pub error: Option<&'a anyhow::Error>,
+}
+
+impl<'a> RequestLog<'a> {
+    /// The total duration of all phases of the given kind.
+    pub fn phase_duration(&self, kind: RequestPhaseKind) -> Duration {
+        self.phases
+            .iter()
+            .filter(|phase| phase.kind == kind)
+            .map(|phase| phase.duration)
+            .sum()
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.error.is_some() || self.status >= 400
+    }
+}
+
+/// Receives every request handled by the dev server.
+pub trait RequestLogger: Send + Sync + 'static {
+    fn log(&self, request: &RequestLog<'_>);
+}
+
+impl RequestLogger for Vec<Arc<dyn RequestLogger>> {
+    fn log(&self, request: &RequestLog<'_>) {
+        for logger in self {
+            logger.log(request);
+        }
+    }
+}
+
+/// Prints failed and slow requests to stdout.
+pub struct ConsoleRequestLogger {
+    /// Successful requests taking longer than this are printed too, together
+    /// with a breakdown of their phases.
+    pub slow_request_threshold: Option<Duration>,
+}
+
+impl Default for ConsoleRequestLogger {
+    fn default() -> Self {
+        Self {
+            slow_request_threshold: cfg!(feature = "log_request_stats")
+                .then(|| Duration::from_secs(1)),
+        }
+    }
+}
+
+impl RequestLogger for ConsoleRequestLogger {
+    fn log(&self, request: &RequestLog<'_>) {
+        let duration = FormatDuration(request.duration);
+        if let Some(error) = request.error {
+            println!("[{}] error: {:?} ({})", request.status, error, duration);
+            return;
+        }
+        let is_slow = self
+            .slow_request_threshold
+            .map_or(false, |threshold| request.duration > threshold);
+        if is_slow {
+            println!(
+                "[{}] {} ({}: {})",
+                request.status,
+                request.path,
+                duration,
+                format_phases(request)
+            );
+        } else if request.is_error() {
+            println!("[{}] {} ({})", request.status, request.path, duration);
+        }
+    }
+}
+
+fn format_phases(request: &RequestLog<'_>) -> String {
+    let mut formatted = String::new();
+    for kind in [
+        RequestPhaseKind::GetSource,
+        RequestPhaseKind::GetContent,
+        RequestPhaseKind::NeedData,
+        RequestPhaseKind::StreamBody,
+    ] {
+        let count = request.phases.iter().filter(|p| p.kind == kind).count();
+        if count == 0 {
+            continue;
+        }
+        if !formatted.is_empty() {
+            formatted.push_str(", ");
+        }
+        let duration = FormatDuration(request.phase_duration(kind));
+        if count > 1 {
+            write!(formatted, "{} {}x {}", kind.as_str(), count, duration).unwrap();
+        } else {
+            write!(formatted, "{} {}", kind.as_str(), duration).unwrap();
+        }
+    }
+    formatted
+}
+
+/// Writes every request and its phases as complete events in the Chrome
+/// trace event format, which can be loaded in `chrome://tracing` or Perfetto.
+///
+/// The trace is written incrementally and never closes the JSON array, which
+/// both viewers accept.
+pub struct ChromeTraceRequestLogger {
+    start: Instant,
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl ChromeTraceRequestLogger {
+    pub fn new(path: &Path) -> Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(b"[\n")?;
+        writer.flush()?;
+        Ok(Self {
+            start: Instant::now(),
+            writer: Mutex::new(writer),
+        })
+    }
+
+    fn timestamp(&self, instant: Instant) -> u64 {
+        instant
+            .checked_duration_since(self.start)
+            .unwrap_or_default()
+            .as_micros() as u64
+    }
+}
+
+impl RequestLogger for ChromeTraceRequestLogger {
+    fn log(&self, request: &RequestLog<'_>) {
+        // Each request gets its own row, so concurrent requests don't overlap.
+        let mut events = vec![json!({
+            "name": format!("{} {}", request.method, request.path),
+            "cat": "request",
+            "ph": "X",
+            "ts": self.timestamp(request.start),
+            "dur": request.duration.as_micros() as u64,
+            "pid": 1,
+            "tid": request.id,
+            "args": { "status": request.status },
+        })];
+        events.extend(request.phases.iter().map(|phase| {
+            json!({
+                "name": phase.kind.as_str(),
+                "cat": "phase",
+                "ph": "X",
+                "ts": self.timestamp(phase.start),
+                "dur": phase.duration.as_micros() as u64,
+                "pid": 1,
+                "tid": request.id,
+            })
+        }));
+
+        let mut writer = self.writer.lock();
+        for event in events {
+            let _ = writeln!(writer, "{event},");
+        }
+        let _ = writer.flush();
+    }
+}
+
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+
+pub(crate) fn next_request_id() -> u64 {
+    NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Wraps a response body to invoke a callback with the time it took to
+/// stream it, once it has been fully sent or dropped by the client. Polling
+/// the body is traced in a "stream body" span below the request's span.
+pub(crate) struct TimedBody {
+    body: hyper::Body,
+    start: Instant,
+    span: tracing::Span,
+    on_done: Option<Box<dyn FnOnce(Instant) + Send>>,
+}
+
+impl TimedBody {
+    pub fn new(
+        body: hyper::Body,
+        request_span: &tracing::Span,
+        on_done: impl FnOnce(Instant) + Send + 'static,
+    ) -> Self {
+        Self {
+            body,
+            start: Instant::now(),
+            span: RequestPhaseKind::StreamBody.span(request_span),
+            on_done: Some(Box::new(on_done)),
+        }
+    }
+
+    fn done(&mut self) {
+        if let Some(on_done) = self.on_done.take() {
+            on_done(self.start);
+        }
+    }
+}
+
+impl Stream for TimedBody {
+    type Item = Result<Bytes, hyper::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let guard = this.span.enter();
+        let poll = Pin::new(&mut this.body).poll_next(cx);
+        drop(guard);
+        if let Poll::Ready(None | Some(Err(_))) = &poll {
+            this.done();
+        }
+        poll
+    }
+}
+
+impl Drop for TimedBody {
+    fn drop(&mut self) {
+        self.done();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn phase(kind: RequestPhaseKind, start: Instant, millis: u64) -> RequestPhase {
+        RequestPhase {
+            kind,
+            start,
+            duration: Duration::from_millis(millis),
+        }
+    }
+
+    fn request(start: Instant, phases: &[RequestPhase]) -> RequestLog<'_> {
+        RequestLog {
+            id: 7,
+            method: "GET",
+            path: "/index.js",
+            status: 200,
+            start,
+            duration: Duration::from_millis(60),
+            phases,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn format_phases_sums_repeated_phases() {
+        let start = Instant::now();
+        let phases = [
+            phase(RequestPhaseKind::GetSource, start, 20),
+            phase(RequestPhaseKind::GetContent, start, 12),
+            phase(RequestPhaseKind::NeedData, start, 1),
+            phase(RequestPhaseKind::GetContent, start, 15),
+        ];
+        assert_eq!(
+            format_phases(&request(start, &phases)),
+            "get source 20ms, get content 2x 27ms, need data 1ms"
+        );
+        assert_eq!(format_phases(&request(start, &[])), "");
+    }
+
+    #[test]
+    fn chrome_trace_events() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("trace.json");
+        let logger = ChromeTraceRequestLogger::new(&path)?;
+        let start = logger.start + Duration::from_millis(5);
+        let phases = [
+            phase(RequestPhaseKind::GetSource, start, 10),
+            phase(
+                RequestPhaseKind::StreamBody,
+                start + Duration::from_millis(10),
+                50,
+            ),
+        ];
+        logger.log(&request(start, &phases));
+
+        // The array is never closed, and every event ends with a comma.
+        let trace = std::fs::read_to_string(&path)?;
+        let trace = format!("{}]", trace.trim_end().trim_end_matches(','));
+        let events: serde_json::Value = serde_json::from_str(&trace)?;
+        assert_eq!(
+            events,
+            json!([
+                {
+                    "name": "GET /index.js",
+                    "cat": "request",
+                    "ph": "X",
+                    "ts": 5000,
+                    "dur": 60000,
+                    "pid": 1,
+                    "tid": 7,
+                    "args": { "status": 200 },
+                },
+                {
+                    "name": "get source",
+                    "cat": "phase",
+                    "ph": "X",
+                    "ts": 5000,
+                    "dur": 10000,
+                    "pid": 1,
+                    "tid": 7,
+                },
+                {
+                    "name": "stream body",
+                    "cat": "phase",
+                    "ph": "X",
+                    "ts": 15000,
+                    "dur": 50000,
+                    "pid": 1,
+                    "tid": 7,
+                },
+            ])
+        );
+        Ok(())
+    }
+
+    /// The name of a span and of its parent.
+    type SpanParent = (&'static str, Option<&'static str>);
+
+    /// Records the name of the parent of each new span.
+    #[derive(Clone, Default)]
+    struct SpanParents(Arc<Mutex<Vec<SpanParent>>>);
+
+    impl<S> tracing_subscriber::Layer<S> for SpanParents
+    where
+        S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+    {
+        fn on_new_span(
+            &self,
+            _attrs: &tracing::span::Attributes<'_>,
+            id: &tracing::span::Id,
+            ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            let span = ctx.span(id).unwrap();
+            let parent = span.parent().map(|parent| parent.name());
+            self.0.lock().push((span.name(), parent));
+        }
+    }
+
+    #[test]
+    fn phase_spans_are_children_of_the_request() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let parents = SpanParents::default();
+        let subscriber = tracing_subscriber::registry().with(parents.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            let request = tracing::info_span!("request");
+            // Phases run in tasks which are entered in spans of their own,
+            // not in the request's span.
+            let _task = tracing::info_span!("task").entered();
+            let _get_source = RequestPhaseKind::GetSource.span(&request);
+            let _need_data = RequestPhaseKind::NeedData.span(&request);
+            let _body = TimedBody::new(hyper::Body::empty(), &request, |_| {});
+        });
+        assert_eq!(
+            *parents.0.lock(),
+            [
+                ("request", None),
+                ("task", None),
+                ("get source", Some("request")),
+                ("need data", Some("request")),
+                ("stream body", Some("request")),
+            ]
+        );
+    }
+}
@@ -74,6 +74,13 @@ pub enum ContentSourceContent {
     Static(VersionedContentVc),
     HttpProxy(ProxyResultVc),
     NeedData(NeededData),
+    /// Content which the dev server waits for before sending it, e.g. to
+    /// simulate a slow API. Unlike a delay in [ContentSource::get], this
+    /// applies to every request, even when the content is reused.
+    Delayed {
+        content: ContentSourceContentVc,
+        delay_ms: u64,
+    },
 }
 
 /// Needed data content signals that the content source requires more
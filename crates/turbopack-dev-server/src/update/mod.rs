@@ -1,5 +1,7 @@
 pub mod protocol;
 pub mod server;
+pub mod sse;
 pub mod stream;
 
 pub(super) use server::UpdateServer;
+pub(super) use sse::SseSessions;
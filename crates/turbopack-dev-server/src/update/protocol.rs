@@ -4,7 +4,7 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use turbopack_cli_utils::issue::{format_issue, LogOptions};
 use turbopack_core::{
-    issue::{IssueSeverity, PlainIssue},
+    issue::{CodeFrameLine, IssueSeverity, PlainIssue},
     source_pos::SourcePos,
 };
 
@@ -106,6 +106,8 @@ pub struct IssueSource<'a> {
     pub asset: Asset<'a>,
     pub start: SourcePos,
     pub end: SourcePos,
+    /// See [turbopack_core::issue::PlainIssueSource::code_frame].
+    pub code_frame: Vec<CodeFrameLine>,
 }
 
 #[derive(Serialize)]
@@ -133,6 +135,7 @@ impl<'a> From<&'a PlainIssue> for Issue<'a> {
             },
             start: source.start,
             end: source.end,
+            code_frame: source.code_frame(),
         });
 
         Issue {
@@ -15,10 +15,19 @@ use turbopack_core::version::Update;
 
 use super::{
     protocol::{ClientMessage, ClientUpdateInstruction, Issue, ResourceIdentifier},
+    sse::SseClient,
     stream::UpdateStream,
 };
 use crate::{update::stream::UpdateStreamItem, SourceProvider};
 
+/// A connection to a client that speaks the update protocol.
+pub(crate) trait UpdateClient = Stream<Item = Result<ClientMessage>>
+    + FusedStream
+    + for<'a> Sink<ClientUpdateInstruction<'a>, Error = Error>
+    + Unpin
+    + Send
+    + 'static;
+
 /// A server that listens for updates and sends them to connected clients.
 pub(crate) struct UpdateServer<P: SourceProvider> {
     source_provider: P,
@@ -30,19 +39,31 @@ impl<P: SourceProvider + Clone + Send + Sync> UpdateServer<P> {
         Self { source_provider }
     }
 
-    /// Run the update server loop.
+    /// Run the update server loop over a WebSocket.
     pub fn run(self, tt: &dyn TurboTasksApi, ws: HyperWebsocket) {
+        self.run_with_client(tt, async move { Ok(WebSocketClient::from(ws.await?)) });
+    }
+
+    /// Run the update server loop over a Server-Sent Events session.
+    pub fn run_sse(self, tt: &dyn TurboTasksApi, client: SseClient) {
+        self.run_with_client(tt, future::ready(Ok(client)));
+    }
+
+    fn run_with_client<C: UpdateClient>(
+        self,
+        tt: &dyn TurboTasksApi,
+        client: impl Future<Output = Result<C>> + Send + 'static,
+    ) {
         tt.run_once_process(Box::pin(async move {
-            if let Err(err) = self.run_internal(ws).await {
+            let result = async move { self.run_internal(client.await?).await }.await;
+            if let Err(err) = result {
                 println!("[UpdateServer]: error {:#}", err);
             }
             Ok(())
         }));
     }
 
-    async fn run_internal(self, ws: HyperWebsocket) -> Result<()> {
-        let mut client: UpdateClient = ws.await?.into();
-
+    async fn run_internal(self, mut client: impl UpdateClient) -> Result<()> {
         let mut streams = StreamMap::new();
 
         loop {
@@ -65,7 +86,7 @@ impl<P: SourceProvider + Clone + Send + Sync> UpdateServer<P> {
                             streams.remove(&resource);
                         }
                         None => {
-                            // WebSocket was closed, stop sending updates
+                            // Connection was closed, stop sending updates
                             break;
                         }
                     }
@@ -81,7 +102,7 @@ impl<P: SourceProvider + Clone + Send + Sync> UpdateServer<P> {
     }
 
     async fn send_update(
-        client: &mut UpdateClient,
+        client: &mut impl UpdateClient,
         resource: ResourceIdentifier,
         update: &UpdateStreamItem,
     ) -> Result<()> {
@@ -119,14 +140,14 @@ impl<P: SourceProvider + Clone + Send + Sync> UpdateServer<P> {
 }
 
 pin_project! {
-    struct UpdateClient {
+    struct WebSocketClient {
         #[pin]
         ws: WebSocketStream<Upgraded>,
         ended: bool,
     }
 }
 
-impl Stream for UpdateClient {
+impl Stream for WebSocketClient {
     type Item = Result<ClientMessage>;
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
@@ -163,13 +184,13 @@ impl Stream for UpdateClient {
     }
 }
 
-impl FusedStream for UpdateClient {
+impl FusedStream for WebSocketClient {
     fn is_terminated(&self) -> bool {
         self.ended || self.ws.is_terminated()
     }
 }
 
-impl<'a> Sink<ClientUpdateInstruction<'a>> for UpdateClient {
+impl<'a> Sink<ClientUpdateInstruction<'a>> for WebSocketClient {
     type Error = Error;
 
     fn poll_ready(
@@ -215,7 +236,7 @@ impl<'a> Sink<ClientUpdateInstruction<'a>> for UpdateClient {
     }
 }
 
-impl From<WebSocketStream<Upgraded>> for UpdateClient {
+impl From<WebSocketStream<Upgraded>> for WebSocketClient {
     fn from(ws: WebSocketStream<Upgraded>) -> Self {
         Self { ws, ended: false }
     }
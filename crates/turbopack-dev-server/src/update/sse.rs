@@ -0,0 +1,322 @@
+//! A Server-Sent Events transport for the update protocol.
+//!
+//! This is used as a fallback when WebSocket upgrades are not available, e.g.
+//! when a proxy strips the `Upgrade` header. The client opens an event stream
+//! with a `GET` request on the HMR path, and receives a `session` event with
+//! its session id first. Every [ClientUpdateInstruction] is then sent as a
+//! regular `message` event. [ClientMessage]s are `POST`ed as JSON to
+//! `<HMR path>/<session id>`.
+
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use anyhow::{Context as _, Error, Result};
+use futures::{
+    channel::mpsc::{channel, unbounded, Sender, UnboundedReceiver, UnboundedSender},
+    prelude::*,
+    stream::FusedStream,
+};
+use hyper::{
+    body::{Bytes, HttpBody},
+    Body, Request, Response,
+};
+use parking_lot::Mutex;
+
+use super::protocol::{ClientMessage, ClientUpdateInstruction};
+
+/// The number of server messages that can be buffered before sending blocks.
+const EVENT_BUFFER_SIZE: usize = 16;
+
+/// The largest [ClientMessage] body that is accepted. Messages only identify
+/// a resource, so anything bigger is not sent by a well-behaved client.
+const MAX_MESSAGE_SIZE: usize = 64 * 1024;
+
+static NEXT_SESSION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Keeps track of all connected event stream clients, so that messages
+/// posted by a client can be routed to its update server.
+#[derive(Clone, Default)]
+pub struct SseSessions {
+    sessions: Arc<Mutex<HashMap<u64, UnboundedSender<ClientMessage>>>>,
+}
+
+impl SseSessions {
+    /// Starts a new session. Returns the client to pass to the update server
+    /// and the streaming response to send to the browser.
+    pub(crate) fn connect(&self) -> Result<(SseClient, Response<Body>)> {
+        let id = NEXT_SESSION_ID.fetch_add(1, Ordering::Relaxed);
+        let (messages_tx, messages) = unbounded();
+        let (events, events_rx) = channel(EVENT_BUFFER_SIZE);
+        self.sessions.lock().insert(id, messages_tx);
+
+        // The guard is owned by the body stream, so the session is removed as
+        // soon as hyper drops the body when the browser disconnects. This
+        // also ends the client's message stream, which stops its update
+        // server.
+        let guard = SessionGuard {
+            id,
+            sessions: self.clone(),
+        };
+        let session_event = Bytes::from(format!("event: session\ndata: {id}\n\n"));
+        let body = stream::once(future::ready(session_event))
+            .chain(events_rx)
+            .map(move |event| {
+                let _ = &guard;
+                Ok::<_, Infallible>(event)
+            });
+        let response = Response::builder()
+            .status(200)
+            .header("Content-Type", "text/event-stream")
+            .header("Cache-Control", "no-cache")
+            // Prevents nginx-style proxies from buffering the stream.
+            .header("X-Accel-Buffering", "no")
+            .body(Body::wrap_stream(body))?;
+
+        let client = SseClient {
+            id,
+            sessions: self.clone(),
+            messages,
+            events,
+        };
+        Ok((client, response))
+    }
+
+    /// Handles a [ClientMessage] posted by the client of the given session.
+    /// Bodies larger than [MAX_MESSAGE_SIZE] are rejected with a 413, and
+    /// bodies which are not a valid message with a 400.
+    pub async fn handle_post(
+        &self,
+        session: &str,
+        request: Request<Body>,
+    ) -> Result<Response<Body>> {
+        let sender = session
+            .parse::<u64>()
+            .ok()
+            .and_then(|id| self.sessions.lock().get(&id).cloned());
+        let Some(sender) = sender else {
+            return Ok(Response::builder().status(404).body(Body::empty())?);
+        };
+
+        let Some(body) = read_body(request.into_body(), MAX_MESSAGE_SIZE).await? else {
+            return Ok(Response::builder().status(413).body(Body::empty())?);
+        };
+        let message: ClientMessage = match serde_json::from_slice(&body) {
+            Ok(message) => message,
+            Err(err) => {
+                return Ok(Response::builder()
+                    .status(400)
+                    .body(Body::from(format!("invalid event stream message: {err}")))?)
+            }
+        };
+        if sender.unbounded_send(message).is_err() {
+            // The update server has already stopped.
+            return Ok(Response::builder().status(404).body(Body::empty())?);
+        }
+
+        Ok(Response::builder().status(204).body(Body::empty())?)
+    }
+
+    fn remove(&self, id: u64) {
+        self.sessions.lock().remove(&id);
+    }
+}
+
+/// Reads a request body, or returns `None` when it is larger than `limit`.
+async fn read_body(mut body: Body, limit: usize) -> Result<Option<Vec<u8>>> {
+    if HttpBody::size_hint(&body).lower() > limit as u64 {
+        return Ok(None);
+    }
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.context("reading event stream message")?;
+        if bytes.len() + chunk.len() > limit {
+            return Ok(None);
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(Some(bytes))
+}
+
+/// Removes a session when the event stream body is dropped.
+struct SessionGuard {
+    id: u64,
+    sessions: SseSessions,
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        self.sessions.remove(self.id);
+    }
+}
+
+/// Returns true if the request wants to open an event stream.
+pub fn is_event_stream_request(request: &Request<Body>) -> bool {
+    request.method() == hyper::Method::GET
+        && request
+            .headers()
+            .get_all(hyper::header::ACCEPT)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .any(|value| value.contains("text/event-stream"))
+}
+
+/// The server side of an event stream session.
+pub(crate) struct SseClient {
+    id: u64,
+    sessions: SseSessions,
+    messages: UnboundedReceiver<ClientMessage>,
+    events: Sender<Bytes>,
+}
+
+impl Stream for SseClient {
+    type Item = Result<ClientMessage>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut()
+            .messages
+            .poll_next_unpin(cx)
+            .map(|message| message.map(Ok))
+    }
+}
+
+impl FusedStream for SseClient {
+    fn is_terminated(&self) -> bool {
+        self.messages.is_terminated()
+    }
+}
+
+impl<'a> Sink<ClientUpdateInstruction<'a>> for SseClient {
+    type Error = Error;
+
+    fn poll_ready(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<std::result::Result<(), Self::Error>> {
+        self.get_mut()
+            .events
+            .poll_ready(cx)
+            .map(|res| res.context("polling event stream ready"))
+    }
+
+    fn start_send(
+        self: Pin<&mut Self>,
+        item: ClientUpdateInstruction<'a>,
+    ) -> std::result::Result<(), Self::Error> {
+        let event = format!("data: {}\n\n", serde_json::to_string(&item)?);
+
+        self.get_mut()
+            .events
+            .start_send(Bytes::from(event))
+            .context("sending to event stream")
+    }
+
+    fn poll_flush(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<std::result::Result<(), Self::Error>> {
+        self.get_mut()
+            .events
+            .poll_flush_unpin(cx)
+            .map(|res| res.context("flushing event stream"))
+    }
+
+    fn poll_close(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<std::result::Result<(), Self::Error>> {
+        self.get_mut()
+            .events
+            .poll_close_unpin(cx)
+            .map(|res| res.context("closing event stream"))
+    }
+}
+
+impl Drop for SseClient {
+    fn drop(&mut self) {
+        self.sessions.remove(self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+
+    use super::*;
+
+    fn post_body(sessions: &SseSessions, session: &str, body: Body) -> Response<Body> {
+        let request = Request::post("/").body(body).unwrap();
+        block_on(sessions.handle_post(session, request)).unwrap()
+    }
+
+    fn post(sessions: &SseSessions, session: &str) -> Response<Body> {
+        post_body(
+            sessions,
+            session,
+            Body::from(r#"{"type":"subscribe","path":"index.js"}"#),
+        )
+    }
+
+    fn session_id(response: &mut Response<Body>) -> String {
+        let event = block_on(response.body_mut().data()).unwrap().unwrap();
+        let event = std::str::from_utf8(&event).unwrap();
+        event
+            .strip_prefix("event: session\ndata: ")
+            .and_then(|rest| rest.strip_suffix("\n\n"))
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn rejects_invalid_messages() {
+        let sessions = SseSessions::default();
+        let (_client, mut response) = sessions.connect().unwrap();
+        let id = session_id(&mut response);
+
+        assert_eq!(post_body(&sessions, &id, Body::from("{")).status(), 400);
+        assert_eq!(
+            post_body(&sessions, &id, Body::from(r#"{"type":"unknown"}"#)).status(),
+            400
+        );
+        let large = vec![b' '; MAX_MESSAGE_SIZE + 1];
+        assert_eq!(post_body(&sessions, &id, Body::from(large)).status(), 413);
+        // A streamed body without a known length is limited while reading
+        let chunks = stream::iter(
+            (0..=MAX_MESSAGE_SIZE / 1024).map(|_| Ok::<_, Infallible>(vec![b' '; 1024])),
+        );
+        assert_eq!(
+            post_body(&sessions, &id, Body::wrap_stream(chunks)).status(),
+            413
+        );
+        assert_eq!(post(&sessions, &id).status(), 204);
+    }
+
+    #[test]
+    fn disconnect_removes_session() {
+        let sessions = SseSessions::default();
+        let (mut client, mut response) = sessions.connect().unwrap();
+        let id = session_id(&mut response);
+
+        assert_eq!(post(&sessions, &id).status(), 204);
+        match block_on(client.next()) {
+            Some(Ok(ClientMessage::Subscribe { resource })) => {
+                assert_eq!(resource.path, "index.js")
+            }
+            _ => panic!("expected a subscribe message"),
+        }
+
+        // The browser disconnects.
+        drop(response);
+
+        assert!(sessions.sessions.lock().is_empty());
+        assert!(block_on(client.next()).is_none());
+        assert_eq!(post(&sessions, &id).status(), 404);
+    }
+}
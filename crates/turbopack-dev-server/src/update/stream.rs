@@ -55,7 +55,15 @@ async fn get_content_wrapper(
 async fn resolve_static_content(
     content_source_result: ContentSourceResultVc,
 ) -> Result<Option<VersionedContentVc>> {
-    Ok(match *content_source_result.await?.content.await? {
+    let mut content = content_source_result.await?.content;
+    // Updates are not delayed.
+    while let ContentSourceContent::Delayed {
+        content: delayed, ..
+    } = *content.await?
+    {
+        content = delayed;
+    }
+    Ok(match *content.await? {
         ContentSourceContent::NotFound => None,
         ContentSourceContent::HttpProxy(_) => {
             panic!("HTTP proxying is not supported in UpdateStream")
@@ -64,6 +72,7 @@ async fn resolve_static_content(
         ContentSourceContent::NeedData(_) => {
             bail!("this might only happen temporary as get_content_wrapper resolves the data")
         }
+        ContentSourceContent::Delayed { .. } => unreachable!("delays are skipped above"),
     })
 }
 
@@ -0,0 +1,506 @@
+/** @typedef {import('../types').ChunkPath} ChunkPath */
+/** @typedef {import('../types/protocol').Issue} Issue */
+/** @typedef {import('../types/protocol').CodeFrameLine} CodeFrameLine */
+
+/**
+ * @typedef {Object} StackFrame
+ * @property {string | null} methodName
+ * @property {string} file
+ * @property {number} line 1-based, as reported by the browser
+ * @property {number} column 1-based, as reported by the browser
+ */
+
+/**
+ * @typedef {Object} OriginalPosition
+ * @property {string} source
+ * @property {number} line 0-based
+ * @property {number} column 0-based
+ * @property {string | null} content
+ */
+
+/**
+ * @typedef {Object} RuntimeError
+ * @property {string} message
+ * @property {Array<{ frame: StackFrame, original: OriginalPosition | null }>} frames
+ */
+
+/**
+ * A minimal error overlay for apps that don't bring their own. It shows
+ * critical issues reported through HMR updates, as well as uncaught runtime
+ * errors mapped back to their original sources.
+ *
+ * This is only embedded in chunks when the chunking context enables the error
+ * overlay, so frameworks with their own overlay (e.g. Next.js) don't get it.
+ */
+const ERROR_OVERLAY = (() => {
+  const CRITICAL_SEVERITIES = ["bug", "fatal", "error"];
+  const SOURCE_MAP_PREFIX = "/__turbopack_sourcemap__";
+  const CODE_FRAME_CONTEXT_LINES = 3;
+  const MAX_FRAMES = 10;
+  const BASE64 =
+    "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+  const STACK_FRAME_RE =
+    /^\s*(?:at\s+(?:(.*?)\s+\()?|(.*?)@)(\S+?):(\d+):(\d+)\)?\s*$/;
+
+  /**
+   * Critical issues of the last update message of each chunk.
+   *
+   * @type {Map<ChunkPath, Issue[]>}
+   */
+  const issuesByChunk = new Map();
+  /** @type {RuntimeError[]} */
+  let runtimeErrors = [];
+  /** @type {Map<string, Promise<any>>} */
+  const sourceMapCache = new Map();
+  /** @type {HTMLElement | null} */
+  let host = null;
+
+  function isDisabled() {
+    return typeof document === "undefined";
+  }
+
+  /**
+   * @param {ChunkPath} chunkPath
+   * @param {Issue[]} issues
+   */
+  function reportIssues(chunkPath, issues) {
+    const critical = issues.filter((issue) =>
+      CRITICAL_SEVERITIES.includes(issue.severity)
+    );
+    if (critical.length > 0) {
+      issuesByChunk.set(chunkPath, critical);
+    } else if (!issuesByChunk.delete(chunkPath)) {
+      return;
+    }
+    render();
+  }
+
+  /**
+   * Runtime errors are assumed to be fixed by the next successful update.
+   */
+  function reportUpdateApplied() {
+    sourceMapCache.clear();
+    if (runtimeErrors.length === 0) {
+      return;
+    }
+    runtimeErrors = [];
+    render();
+  }
+
+  /**
+   * @param {unknown} error
+   */
+  async function reportRuntimeError(error) {
+    if (isDisabled()) {
+      return;
+    }
+
+    const message =
+      error instanceof Error
+        ? `${error.name}: ${error.message}`
+        : String(error);
+    const stack = error instanceof Error ? error.stack ?? "" : "";
+    const frames = parseStack(stack).slice(0, MAX_FRAMES);
+    const resolved = await Promise.all(
+      frames.map(async (frame) => ({
+        frame,
+        original: await resolveFrame(frame).catch(() => null),
+      }))
+    );
+
+    runtimeErrors.push({ message, frames: resolved });
+    render();
+  }
+
+  /**
+   * @param {string} stack
+   * @returns {StackFrame[]}
+   */
+  function parseStack(stack) {
+    /** @type {StackFrame[]} */
+    const frames = [];
+    for (const line of stack.split("\n")) {
+      const match = STACK_FRAME_RE.exec(line);
+      if (match == null) {
+        continue;
+      }
+      frames.push({
+        methodName: match[1] || match[2] || null,
+        file: match[3],
+        line: parseInt(match[4], 10),
+        column: parseInt(match[5], 10),
+      });
+    }
+    return frames;
+  }
+
+  /**
+   * Maps a stack frame back to its original source, using the source map
+   * served by the dev server.
+   *
+   * @param {StackFrame} frame
+   * @returns {Promise<OriginalPosition | null>}
+   */
+  async function resolveFrame(frame) {
+    let url;
+    try {
+      url = new URL(frame.file, location.href);
+    } catch (_) {
+      return null;
+    }
+    if (url.origin !== location.origin) {
+      return null;
+    }
+
+    const mapUrl = `${SOURCE_MAP_PREFIX}${url.pathname}.map`;
+    let map = sourceMapCache.get(mapUrl);
+    if (map == null) {
+      map = fetch(mapUrl).then((res) => (res.ok ? res.json() : null));
+      sourceMapCache.set(mapUrl, map);
+    }
+
+    const sourceMap = await map;
+    if (sourceMap == null) {
+      return null;
+    }
+    return originalPositionFor(sourceMap, frame.line - 1, frame.column - 1);
+  }
+
+  /**
+   * @param {string} segment
+   * @returns {number[]}
+   */
+  function decodeVlqSegment(segment) {
+    const values = [];
+    let value = 0;
+    let shift = 0;
+    for (const char of segment) {
+      const digit = BASE64.indexOf(char);
+      value += (digit & 31) << shift;
+      if (digit & 32) {
+        shift += 5;
+      } else {
+        const negative = value & 1;
+        value >>>= 1;
+        values.push(negative ? -value : value);
+        value = 0;
+        shift = 0;
+      }
+    }
+    return values;
+  }
+
+  /**
+   * Looks up the original position of a generated position in a regular or
+   * sectioned (index) source map. Lines and columns are 0-based.
+   *
+   * @param {any} map
+   * @param {number} line
+   * @param {number} column
+   * @returns {OriginalPosition | null}
+   */
+  function originalPositionFor(map, line, column) {
+    if (Array.isArray(map.sections)) {
+      let section = null;
+      for (const candidate of map.sections) {
+        const offset = candidate.offset;
+        if (
+          offset.line > line ||
+          (offset.line === line && offset.column > column)
+        ) {
+          break;
+        }
+        section = candidate;
+      }
+      if (section == null) {
+        return null;
+      }
+      const offset = section.offset;
+      return originalPositionFor(
+        section.map,
+        line - offset.line,
+        line === offset.line ? column - offset.column : column
+      );
+    }
+
+    const lines = map.mappings.split(";");
+    let sourceIndex = 0;
+    let originalLine = 0;
+    let originalColumn = 0;
+    let found = null;
+    for (let i = 0; i <= line && i < lines.length; i++) {
+      let generatedColumn = 0;
+      for (const segment of lines[i].split(",")) {
+        if (segment === "") {
+          continue;
+        }
+        const fields = decodeVlqSegment(segment);
+        generatedColumn += fields[0];
+        if (fields.length < 4) {
+          continue;
+        }
+        sourceIndex += fields[1];
+        originalLine += fields[2];
+        originalColumn += fields[3];
+        if (i === line && generatedColumn <= column) {
+          found = { sourceIndex, line: originalLine, column: originalColumn };
+        }
+      }
+    }
+
+    if (found == null) {
+      return null;
+    }
+    return {
+      source: map.sources[found.sourceIndex],
+      line: found.line,
+      column: found.column,
+      content: map.sourcesContent?.[found.sourceIndex] ?? null,
+    };
+  }
+
+  /**
+   * @param {string} tag
+   * @param {Record<string, string>} attrs
+   * @param {Array<Node | string>} children
+   * @returns {HTMLElement}
+   */
+  function h(tag, attrs, children) {
+    const element = document.createElement(tag);
+    for (const [name, value] of Object.entries(attrs)) {
+      element.setAttribute(name, value);
+    }
+    // `append` treats strings as text, so messages are never parsed as HTML.
+    element.append(...children);
+    return element;
+  }
+
+  /**
+   * @param {CodeFrameLine[]} lines
+   * @param {number} startLine
+   * @param {number} endLine
+   * @returns {HTMLElement}
+   */
+  function renderCodeFrame(lines, startLine, endLine) {
+    return h(
+      "pre",
+      { class: "code-frame" },
+      lines.map(({ line, content }) =>
+        h(
+          "div",
+          {
+            class:
+              line >= startLine && line <= endLine ? "line marked" : "line",
+          },
+          [h("span", { class: "gutter" }, [String(line + 1)]), content]
+        )
+      )
+    );
+  }
+
+  /**
+   * @param {string} content
+   * @param {number} line
+   * @returns {CodeFrameLine[]}
+   */
+  function codeFrameFromContent(content, line) {
+    const start = Math.max(0, line - CODE_FRAME_CONTEXT_LINES);
+    return content
+      .split("\n")
+      .slice(start, line + CODE_FRAME_CONTEXT_LINES + 1)
+      .map((content, i) => ({ line: start + i, content }));
+  }
+
+  /**
+   * @param {Issue} issue
+   * @returns {HTMLElement}
+   */
+  function renderIssue(issue) {
+    const children = [
+      h("h2", {}, [`${issue.severity} - ${issue.category}: ${issue.title}`]),
+    ];
+    if (issue.source != null) {
+      const { asset, start, end } = issue.source;
+      children.push(
+        h("p", { class: "location" }, [
+          `${asset.path}:${start.line + 1}:${start.column + 1}`,
+        ])
+      );
+      if (issue.source.code_frame.length > 0) {
+        children.push(
+          renderCodeFrame(issue.source.code_frame, start.line, end.line)
+        );
+      }
+    } else if (issue.context) {
+      children.push(h("p", { class: "location" }, [issue.context]));
+    }
+    if (issue.description) {
+      children.push(h("p", {}, [issue.description]));
+    }
+    if (issue.detail) {
+      children.push(h("pre", { class: "detail" }, [issue.detail]));
+    }
+    return h("section", { class: "issue" }, children);
+  }
+
+  /**
+   * @param {RuntimeError} error
+   * @returns {HTMLElement}
+   */
+  function renderRuntimeError(error) {
+    const children = [
+      h("h2", {}, [`Unhandled runtime error: ${error.message}`]),
+    ];
+    let renderedCodeFrame = false;
+    for (const { frame, original } of error.frames) {
+      const name = frame.methodName ?? "<anonymous>";
+      if (original == null) {
+        children.push(
+          h("p", { class: "frame" }, [
+            `${name} (${frame.file}:${frame.line}:${frame.column})`,
+          ])
+        );
+        continue;
+      }
+      children.push(
+        h("p", { class: "frame" }, [
+          `${name} (${original.source}:${original.line + 1}:${
+            original.column + 1
+          })`,
+        ])
+      );
+      // Only the innermost frame with a known source gets a code frame.
+      if (!renderedCodeFrame && original.content != null) {
+        renderedCodeFrame = true;
+        children.push(
+          renderCodeFrame(
+            codeFrameFromContent(original.content, original.line),
+            original.line,
+            original.line
+          )
+        );
+      }
+    }
+    return h("section", { class: "issue" }, children);
+  }
+
+  function render() {
+    if (isDisabled()) {
+      return;
+    }
+    if (document.body == null) {
+      document.addEventListener("DOMContentLoaded", render, { once: true });
+      return;
+    }
+
+    const issues = Array.from(issuesByChunk.values()).flat();
+    if (issues.length === 0 && runtimeErrors.length === 0) {
+      host?.remove();
+      host = null;
+      return;
+    }
+
+    if (host == null) {
+      host = document.createElement("turbopack-error-overlay");
+      host.attachShadow({ mode: "open" });
+    }
+    if (!host.isConnected) {
+      document.body.appendChild(host);
+    }
+
+    const dismiss = h("button", { class: "dismiss" }, ["×"]);
+    dismiss.onclick = () => {
+      runtimeErrors = [];
+      host?.remove();
+    };
+
+    const root = /** @type {ShadowRoot} */ (host.shadowRoot);
+    root.replaceChildren(
+      h("style", {}, [STYLES]),
+      h("div", { class: "backdrop" }, [
+        h("div", { class: "dialog" }, [
+          dismiss,
+          ...issues.map(renderIssue),
+          ...runtimeErrors.map(renderRuntimeError),
+        ]),
+      ])
+    );
+  }
+
+  const STYLES = `
+    .backdrop {
+      position: fixed;
+      inset: 0;
+      z-index: 2147483647;
+      overflow: auto;
+      background: rgba(0, 0, 0, 0.6);
+      font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", sans-serif;
+    }
+    .dialog {
+      position: relative;
+      max-width: 960px;
+      margin: 48px auto;
+      padding: 16px 24px;
+      border-top: 4px solid #e5484d;
+      border-radius: 4px;
+      background: #fff;
+      color: #222;
+    }
+    .dismiss {
+      position: absolute;
+      top: 8px;
+      right: 8px;
+      border: none;
+      background: none;
+      font-size: 24px;
+      cursor: pointer;
+    }
+    h2 {
+      margin: 16px 0 8px;
+      font-size: 16px;
+      color: #e5484d;
+    }
+    .location, .frame {
+      margin: 4px 0;
+      font-family: monospace;
+      color: #555;
+    }
+    pre {
+      overflow: auto;
+      padding: 8px;
+      border-radius: 4px;
+      background: #f6f6f6;
+      font-size: 13px;
+    }
+    .line.marked {
+      background: #ffe4e4;
+    }
+    .gutter {
+      display: inline-block;
+      width: 4em;
+      margin-right: 1em;
+      text-align: right;
+      color: #999;
+    }
+  `;
+
+  if (
+    typeof self !== "undefined" &&
+    typeof self.addEventListener === "function"
+  ) {
+    self.addEventListener("error", (event) => {
+      reportRuntimeError(event.error ?? event.message);
+    });
+    self.addEventListener("unhandledrejection", (event) => {
+      reportRuntimeError(event.reason);
+    });
+  }
+
+  return {
+    reportIssues,
+    reportUpdateApplied,
+  };
+})();
+
+BACKEND.reportIssues = ERROR_OVERLAY.reportIssues;
+BACKEND.reportUpdateApplied = ERROR_OVERLAY.reportUpdateApplied;
@@ -1,5 +1,5 @@
 import { RefreshRuntimeGlobals } from "@next/react-refresh-utils/dist/runtime";
-import { ServerMessage } from "./protocol";
+import { Issue, ServerMessage } from "./protocol";
 import { Hot } from "./hot";
 
 export type RefreshHelpers = RefreshRuntimeGlobals["$RefreshHelpers$"];
@@ -79,6 +79,17 @@ interface RuntimeBackend {
   loadChunk: (chunkPath: ChunkPath, from: ModuleId) => Promise<void>;
 
   restart: () => void;
+
+  /**
+   * Called with the issues of every update message received for a chunk,
+   * before the update is applied.
+   */
+  reportIssues?: (chunkPath: ChunkPath, issues: Issue[]) => void;
+
+  /**
+   * Called after an update has been applied successfully.
+   */
+  reportUpdateApplied?: () => void;
 }
 
 export type UpdateCallback = (update: ServerMessage) => void;
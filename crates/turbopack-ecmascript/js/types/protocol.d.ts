@@ -68,10 +68,16 @@ export type SourcePos = {
   column: number;
 };
 
+export type CodeFrameLine = {
+  line: number;
+  content: string;
+};
+
 export type IssueSource = {
   asset: IssueAsset;
   start: SourcePos;
   end: SourcePos;
+  code_frame: CodeFrameLine[];
 };
 
 export type Issue = {
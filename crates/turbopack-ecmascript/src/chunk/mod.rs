@@ -346,6 +346,7 @@ pub struct EcmascriptChunkContent {
     output_root: FileSystemPathVc,
     evaluate: Option<EcmascriptChunkContentEvaluateVc>,
     environment: EnvironmentVc,
+    enable_error_overlay: bool,
 }
 
 #[turbo_tasks::value(transparent)]
@@ -456,6 +457,7 @@ impl EcmascriptChunkContentVc {
             output_root,
             evaluate,
             environment: context.environment(),
+            enable_error_overlay: *context.is_error_overlay_enabled().await?,
         }
         .cell())
     }
@@ -644,7 +646,8 @@ impl EcmascriptChunkContentVc {
                 }
             "# };
 
-            let specific_runtime_code = match *this.environment.chunk_loading().await? {
+            let chunk_loading = &*this.environment.chunk_loading().await?;
+            let specific_runtime_code = match chunk_loading {
                 ChunkLoading::None => return Err(anyhow!("unsupported environment")),
                 ChunkLoading::NodeJs => embed_file!("js/src/runtime.nodejs.js").await?,
                 ChunkLoading::Dom => embed_file!("js/src/runtime.dom.js").await?,
@@ -655,6 +658,18 @@ impl EcmascriptChunkContentVc {
                 FileContent::Content(file) => code.push_source(file.content(), None),
             };
 
+            // The overlay hooks itself into the `BACKEND` of the DOM runtime.
+            if this.enable_error_overlay && matches!(chunk_loading, ChunkLoading::Dom) {
+                let overlay_code = embed_file!("js/src/overlay.dom.js").await?;
+
+                match &*overlay_code {
+                    FileContent::NotFound => {
+                        return Err(anyhow!("error overlay code is not found"))
+                    }
+                    FileContent::Content(file) => code.push_source(file.content(), None),
+                };
+            }
+
             let shared_runtime_code = embed_file!("js/src/runtime.js").await?;
 
             match &*shared_runtime_code {
@@ -377,6 +377,7 @@ impl EcmascriptChunkItem for ModuleChunkItem {
 
 pub fn register() {
     turbo_tasks::register();
+    turbo_tasks_env::register();
     turbo_tasks_fs::register();
     turbopack_core::register();
     include!(concat!(env!("OUT_DIR"), "/register.rs"));
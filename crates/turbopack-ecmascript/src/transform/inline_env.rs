@@ -0,0 +1,254 @@
+use indexmap::IndexMap;
+use swc_core::{
+    common::{Mark, DUMMY_SP},
+    ecma::{
+        ast::{
+            AssignExpr, Expr, Lit, MemberExpr, MemberProp, MetaPropExpr, MetaPropKind, Pat,
+            PatOrExpr, Program, Str, UnaryExpr, UnaryOp, UpdateExpr,
+        },
+        transforms::optimization::simplify::{dead_branch_remover, expr_simplifier},
+        visit::{VisitMut, VisitMutWith},
+    },
+};
+
+/// Replaces `process.env.NAME` and `import.meta.env.NAME` with the value of
+/// `NAME` in `env` as a string literal. Variables which are not part of `env`
+/// are left as they are.
+///
+/// When anything has been inlined, constant expressions are simplified and
+/// branches which became unreachable are removed, so code behind feature flags
+/// like `if (process.env.FEATURE === "1")` isn't part of the output when the
+/// flag isn't set.
+pub fn inline_env(program: &mut Program, env: &IndexMap<String, String>, unresolved_mark: Mark) {
+    let mut visitor = InlineEnv {
+        env,
+        unresolved_mark,
+        inlined: false,
+    };
+    program.visit_mut_with(&mut visitor);
+    if visitor.inlined {
+        program.visit_mut_with(&mut expr_simplifier(unresolved_mark, Default::default()));
+        program.visit_mut_with(&mut dead_branch_remover(unresolved_mark));
+    }
+}
+
+struct InlineEnv<'a> {
+    env: &'a IndexMap<String, String>,
+    unresolved_mark: Mark,
+    inlined: bool,
+}
+
+impl<'a> InlineEnv<'a> {
+    /// Whether `expr` is `process.env` of the global `process`, or
+    /// `import.meta.env`.
+    fn is_env_object(&self, expr: &Expr) -> bool {
+        let Expr::Member(MemberExpr {
+            obj,
+            prop: MemberProp::Ident(prop),
+            ..
+        }) = expr else {
+            return false;
+        };
+        if &*prop.sym != "env" {
+            return false;
+        }
+        match &**obj {
+            Expr::Ident(ident) => {
+                &*ident.sym == "process" && ident.span.ctxt.outer() == self.unresolved_mark
+            }
+            Expr::MetaProp(MetaPropExpr {
+                kind: MetaPropKind::ImportMeta,
+                ..
+            }) => true,
+            _ => false,
+        }
+    }
+
+    fn env_value(&self, expr: &Expr) -> Option<&'a String> {
+        let Expr::Member(MemberExpr { obj, prop, .. }) = expr else {
+            return None;
+        };
+        if !self.is_env_object(obj) {
+            return None;
+        }
+        let name = match prop {
+            MemberProp::Ident(ident) => &*ident.sym,
+            MemberProp::Computed(computed) => match &*computed.expr {
+                Expr::Lit(Lit::Str(str)) => &*str.value,
+                _ => return None,
+            },
+            MemberProp::PrivateName(_) => return None,
+        };
+        self.env.get(name)
+    }
+
+    /// Visits an expression which is written to or deleted. The target itself
+    /// is not inlined, but the object and computed keys of a member are, e.g.
+    /// `process.env.KEY` in `obj[process.env.KEY] = value`.
+    fn visit_mut_target(&mut self, target: &mut Expr) {
+        match target {
+            Expr::Member(member) => {
+                member.obj.visit_mut_with(self);
+                if let MemberProp::Computed(prop) = &mut member.prop {
+                    prop.visit_mut_with(self);
+                }
+            }
+            Expr::Paren(paren) => self.visit_mut_target(&mut paren.expr),
+            _ => target.visit_mut_with(self),
+        }
+    }
+}
+
+impl VisitMut for InlineEnv<'_> {
+    fn visit_mut_expr(&mut self, expr: &mut Expr) {
+        if let Some(value) = self.env_value(expr) {
+            *expr = Expr::Lit(Lit::Str(Str {
+                span: DUMMY_SP,
+                value: value.as_str().into(),
+                raw: None,
+            }));
+            self.inlined = true;
+            return;
+        }
+        expr.visit_mut_children_with(self);
+    }
+
+    // Env variables which are written to or deleted are not inlined
+
+    fn visit_mut_assign_expr(&mut self, expr: &mut AssignExpr) {
+        match &mut expr.left {
+            PatOrExpr::Expr(target) => self.visit_mut_target(target),
+            PatOrExpr::Pat(pat) => pat.visit_mut_with(self),
+        }
+        expr.right.visit_mut_with(self);
+    }
+
+    fn visit_mut_pat(&mut self, pat: &mut Pat) {
+        // Expressions in patterns are targets, e.g. in destructuring
+        // assignments or `for (process.env.NAME of names)`
+        match pat {
+            Pat::Expr(target) => self.visit_mut_target(target),
+            _ => pat.visit_mut_children_with(self),
+        }
+    }
+
+    fn visit_mut_update_expr(&mut self, expr: &mut UpdateExpr) {
+        self.visit_mut_target(&mut expr.arg);
+    }
+
+    fn visit_mut_unary_expr(&mut self, expr: &mut UnaryExpr) {
+        if expr.op == UnaryOp::Delete {
+            self.visit_mut_target(&mut expr.arg);
+        } else {
+            expr.visit_mut_children_with(self);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+    use swc_core::{
+        common::{sync::Lrc, FileName, Globals, Mark, SourceMap, GLOBALS},
+        ecma::{
+            ast::EsVersion,
+            codegen::{text_writer::JsWriter, Emitter},
+            parser::parse_file_as_program,
+            transforms::base::resolver,
+            visit::VisitMutWith,
+        },
+    };
+
+    use super::inline_env;
+
+    /// Inlines `FLAG=1` and `KEY=key` into `code`.
+    fn inline(code: &str) -> String {
+        let env = IndexMap::from([
+            ("FLAG".to_string(), "1".to_string()),
+            ("KEY".to_string(), "key".to_string()),
+        ]);
+        let cm: Lrc<SourceMap> = Default::default();
+        let fm = cm.new_source_file(FileName::Anon, code.to_string());
+        GLOBALS.set(&Globals::new(), || {
+            let mut program = parse_file_as_program(
+                &fm,
+                Default::default(),
+                EsVersion::latest(),
+                None,
+                &mut vec![],
+            )
+            .unwrap();
+            let unresolved_mark = Mark::new();
+            program.visit_mut_with(&mut resolver(unresolved_mark, Mark::new(), false));
+            inline_env(&mut program, &env, unresolved_mark);
+
+            let mut bytes = vec![];
+            let mut emitter = Emitter {
+                cfg: swc_core::ecma::codegen::Config {
+                    minify: true,
+                    ..Default::default()
+                },
+                cm: cm.clone(),
+                comments: None,
+                wr: JsWriter::new(cm.clone(), "\n", &mut bytes, None),
+            };
+            emitter.emit_program(&program).unwrap();
+            String::from_utf8(bytes).unwrap()
+        })
+    }
+
+    #[test]
+    fn inlines_process_env() {
+        assert_eq!(
+            inline("use(process.env.KEY, process.env['KEY'], process.env.OTHER);"),
+            r#"use("key","key",process.env.OTHER);"#
+        );
+    }
+
+    #[test]
+    fn inlines_import_meta_env() {
+        assert_eq!(inline("use(import.meta.env.KEY);"), r#"use("key");"#);
+    }
+
+    #[test]
+    fn skips_local_process() {
+        assert_eq!(
+            inline("function f(process) { use(process.env.KEY); }"),
+            "function f(process){use(process.env.KEY);}"
+        );
+    }
+
+    #[test]
+    fn skips_targets() {
+        assert_eq!(
+            inline(
+                r#"process.env.KEY = 1;
+process.env.KEY++;
+delete process.env.KEY;
+({ a: process.env.KEY } = obj);"#
+            ),
+            "process.env.KEY=1;process.env.KEY++;delete process.env.KEY;({a:process.env.KEY}=obj);"
+        );
+    }
+
+    #[test]
+    fn inlines_computed_keys_of_targets() {
+        assert_eq!(
+            inline(
+                "obj[process.env.KEY] = 1; obj[process.env.KEY]++; delete obj[process.env.KEY];"
+            ),
+            r#"obj["key"]=1;obj["key"]++;delete obj["key"];"#
+        );
+    }
+
+    #[test]
+    fn removes_unreachable_branches() {
+        assert_eq!(
+            inline(
+                r#"if (process.env.FLAG === "1") { enabled(); } else { disabled(); }
+if (process.env.FLAG !== "1") { disabled(); }"#
+            ),
+            "enabled();"
+        );
+    }
+}
@@ -1,3 +1,4 @@
+mod inline_env;
 mod server_to_client_proxy;
 
 use std::{path::Path, sync::Arc};
@@ -24,10 +25,14 @@ use turbo_tasks::{
     primitives::{StringVc, StringsVc},
     trace::TraceRawVcs,
 };
+use turbo_tasks_env::ProcessEnvVc;
 use turbo_tasks_fs::FileSystemPathVc;
 use turbopack_core::environment::EnvironmentVc;
 
-use self::server_to_client_proxy::{create_proxy_module, is_client_module};
+use self::{
+    inline_env::inline_env,
+    server_to_client_proxy::{create_proxy_module, is_client_module},
+};
 
 #[derive(
     Debug, Copy, Clone, Eq, PartialEq, PartialOrd, Ord, Hash, Serialize, Deserialize, TraceRawVcs,
@@ -56,6 +61,11 @@ pub enum EcmascriptInputTransform {
     CommonJs,
     Custom,
     Emotion,
+    /// Inlines the variables of the env as string literals where they are
+    /// read from `process.env` or `import.meta.env`, and removes branches
+    /// which became unreachable by that. The env should be filtered to the
+    /// variables which are safe to expose, e.g. with `FilterProcessEnvVc`.
+    InlineEnv(ProcessEnvVc),
     /// This enables a Next.js transform which will eliminate some exports
     /// from a page file, as well as any imports exclusively used by these
     /// exports.
@@ -161,6 +171,10 @@ impl EcmascriptInputTransform {
                     comments.clone(),
                 ))
             }
+            EcmascriptInputTransform::InlineEnv(env) => {
+                let env = env.read_all().await?;
+                inline_env(program, &env, unresolved_mark);
+            }
             EcmascriptInputTransform::PresetEnv(env) => {
                 let versions = env.runtime_versions().await?;
                 let config = swc_core::ecma::preset_env::Config {
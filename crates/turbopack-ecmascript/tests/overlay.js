@@ -0,0 +1,206 @@
+// Runs the error overlay runtime (passed as the first argument) against a
+// minimal fake DOM. Invoked by `overlay.rs`.
+
+const assert = require("assert/strict");
+const fs = require("fs");
+
+class Element {
+  constructor(tag) {
+    this.tag = tag;
+    this.attrs = {};
+    this.children = [];
+    this.parent = null;
+    this.shadowRoot = null;
+  }
+
+  setAttribute(name, value) {
+    this.attrs[name] = value;
+  }
+
+  append(...children) {
+    for (const child of children) {
+      if (child instanceof Element) {
+        child.parent = this;
+      }
+      this.children.push(child);
+    }
+  }
+
+  appendChild(child) {
+    this.append(child);
+  }
+
+  replaceChildren(...children) {
+    this.children = [];
+    this.append(...children);
+  }
+
+  remove() {
+    if (this.parent != null) {
+      this.parent.children = this.parent.children.filter((c) => c !== this);
+      this.parent = null;
+    }
+  }
+
+  get isConnected() {
+    return this.parent != null;
+  }
+
+  attachShadow() {
+    this.shadowRoot = new Element("#shadow-root");
+    return this.shadowRoot;
+  }
+
+  get textContent() {
+    return this.children
+      .map((c) => (c instanceof Element ? c.textContent : c))
+      .join("");
+  }
+
+  findAll(predicate) {
+    const found = predicate(this) ? [this] : [];
+    for (const child of [this.shadowRoot, ...this.children]) {
+      if (child instanceof Element) {
+        found.push(...child.findAll(predicate));
+      }
+    }
+    return found;
+  }
+}
+
+const body = new Element("body");
+const document = {
+  body,
+  createElement: (tag) => new Element(tag),
+  addEventListener() {},
+};
+
+const listeners = {};
+const self = {
+  addEventListener(type, listener) {
+    listeners[type] = listener;
+  },
+};
+
+const sourceMaps = {
+  "/__turbopack_sourcemap__/_chunks/index.js.map": {
+    version: 3,
+    sources: ["input/index.js"],
+    sourcesContent: ["a();\nb();\nthrow new Error();\nd();"],
+    // The second generated line maps to the third line of the source.
+    mappings: ";AAEA",
+  },
+};
+
+const fetch = async (url) => ({
+  ok: url in sourceMaps,
+  json: async () => sourceMaps[url],
+});
+const location = new URL("http://localhost:3000/");
+
+const BACKEND = {};
+const runtime = fs.readFileSync(process.argv[2], "utf8");
+new Function("BACKEND", "document", "self", "fetch", "location", runtime)(
+  BACKEND,
+  document,
+  self,
+  fetch,
+  location
+);
+
+function overlay() {
+  const hosts = body.children.filter(
+    (c) => c instanceof Element && c.tag === "turbopack-error-overlay"
+  );
+  assert.ok(hosts.length <= 1);
+  return hosts[0] ?? null;
+}
+
+function withClass(element, name) {
+  return element.findAll((e) =>
+    (e.attrs.class ?? "").split(" ").includes(name)
+  );
+}
+
+function issue(severity, title) {
+  return {
+    severity,
+    context: "input/index.js",
+    category: "parse",
+    title,
+    description: "",
+    detail: "",
+    documentation_link: "",
+    source: {
+      asset: { path: "input/index.js" },
+      start: { line: 1, column: 2 },
+      end: { line: 1, column: 4 },
+      code_frame: [
+        { line: 0, content: "a();" },
+        { line: 1, content: "b(;" },
+        { line: 2, content: "c();" },
+      ],
+    },
+    sub_issues: [],
+    formatted: "",
+  };
+}
+
+async function main() {
+  // Warnings alone don't show the overlay.
+  BACKEND.reportIssues("chunk.js", [issue("warning", "a warning")]);
+  assert.equal(overlay(), null);
+
+  BACKEND.reportIssues("chunk.js", [
+    issue("warning", "a warning"),
+    issue("error", "unexpected token"),
+  ]);
+  const host = overlay();
+  assert.notEqual(host, null);
+  const text = host.shadowRoot.textContent;
+  assert.match(text, /error - parse: unexpected token/);
+  assert.doesNotMatch(text, /a warning/);
+  assert.match(text, /input\/index\.js:2:3/);
+  const marked = withClass(host, "marked");
+  assert.equal(marked.length, 1);
+  assert.equal(marked[0].textContent, "2b(;");
+
+  // Messages are rendered as text, never as markup.
+  BACKEND.reportIssues("chunk.js", [issue("error", "<img src=x>")]);
+  assert.equal(overlay().findAll((e) => e.tag === "img").length, 0);
+  assert.match(overlay().shadowRoot.textContent, /<img src=x>/);
+
+  // An update without critical issues hides the overlay.
+  BACKEND.reportIssues("chunk.js", []);
+  assert.equal(overlay(), null);
+
+  // Runtime errors are mapped back to their original source.
+  const error = new Error("boom");
+  error.stack = [
+    "Error: boom",
+    "    at throwing (http://localhost:3000/_chunks/index.js:2:5)",
+    "    at http://example.com/other.js:10:1",
+  ].join("\n");
+  listeners.error({ error });
+  // Wait for the source maps to be fetched.
+  await new Promise((resolve) => setTimeout(resolve, 0));
+  const runtimeText = overlay().shadowRoot.textContent;
+  assert.match(runtimeText, /Unhandled runtime error: Error: boom/);
+  assert.match(runtimeText, /throwing \(input\/index\.js:3:1\)/);
+  assert.match(
+    runtimeText,
+    /<anonymous> \(http:\/\/example\.com\/other\.js:10:1\)/
+  );
+  const runtimeMarked = withClass(overlay(), "marked");
+  assert.equal(runtimeMarked.length, 1);
+  assert.equal(runtimeMarked[0].textContent, "3throw new Error();");
+
+  // The next successful update dismisses runtime errors.
+  BACKEND.reportUpdateApplied();
+  assert.equal(overlay(), null);
+}
+
+main().catch((error) => {
+  console.error(error);
+  process.exit(1);
+});
@@ -0,0 +1,19 @@
+use std::{path::Path, process::Command};
+
+/// Runs the error overlay runtime in node against a fake DOM, see
+/// `overlay.js` for the assertions.
+#[test]
+fn error_overlay_runtime() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let output = Command::new("node")
+        .arg(manifest_dir.join("tests/overlay.js"))
+        .arg(manifest_dir.join("js/src/overlay.dom.js"))
+        .output()
+        .expect("failed to run node");
+    assert!(
+        output.status.success(),
+        "---------- Stdout ----------\n{}\n---------- Stderr ----------\n{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr),
+    );
+}
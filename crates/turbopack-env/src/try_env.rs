@@ -2,7 +2,9 @@ use std::future::IntoFuture;
 
 use anyhow::Result;
 use turbo_tasks::primitives::{OptionStringVc, StringVc};
-use turbo_tasks_env::{DotenvProcessEnvVc, EnvMapVc, ProcessEnv, ProcessEnvVc};
+use turbo_tasks_env::{
+    DotenvProcessEnvVc, EnvMapVc, LayeredDotenvProcessEnvVc, ProcessEnv, ProcessEnvVc,
+};
 use turbo_tasks_fs::FileSystemPathVc;
 
 use crate::ProcessEnvIssue;
@@ -10,7 +12,11 @@ use crate::ProcessEnvIssue;
 #[turbo_tasks::value]
 pub struct TryDotenvProcessEnv {
     prior: ProcessEnvVc,
+    dotenv: ProcessEnvVc,
+    /// The dotenv file or the directory of the dotenv files, for issues.
     path: FileSystemPathVc,
+    /// Reports the layered dotenv files which have been skipped.
+    layered: Option<LayeredDotenvProcessEnvVc>,
 }
 
 impl TryDotenvProcessEnv {
@@ -18,9 +24,22 @@ impl TryDotenvProcessEnv {
         &self,
         op: impl Fn(ProcessEnvVc) -> V,
     ) -> Result<V> {
-        let r = op(DotenvProcessEnvVc::new(Some(self.prior), self.path).as_process_env());
+        let r = op(self.dotenv);
         match r.await {
-            Ok(_) => Ok(r),
+            Ok(_) => {
+                if let Some(layered) = self.layered {
+                    for error in layered.errors().await?.iter() {
+                        ProcessEnvIssue {
+                            path: error.path,
+                            description: StringVc::cell(error.message.clone()),
+                        }
+                        .cell()
+                        .as_issue()
+                        .emit();
+                    }
+                }
+                Ok(r)
+            }
             Err(e) => {
                 let r = op(self.prior);
                 // If the prior process env also reports an error we don't want to report our
@@ -44,7 +63,28 @@ impl TryDotenvProcessEnv {
 impl TryDotenvProcessEnvVc {
     #[turbo_tasks::function]
     pub fn new(prior: ProcessEnvVc, path: FileSystemPathVc) -> Self {
-        TryDotenvProcessEnv { prior, path }.cell()
+        TryDotenvProcessEnv {
+            prior,
+            dotenv: DotenvProcessEnvVc::new(Some(prior), path).as_process_env(),
+            path,
+            layered: None,
+        }
+        .cell()
+    }
+
+    /// Loads the layered dotenv files of `directory` for `mode`, see
+    /// [LayeredDotenvProcessEnvVc]. Files which can't be parsed are reported
+    /// one by one and skipped.
+    #[turbo_tasks::function]
+    pub fn new_layered(prior: ProcessEnvVc, directory: FileSystemPathVc, mode: String) -> Self {
+        let layered = LayeredDotenvProcessEnvVc::new(prior, directory, mode);
+        TryDotenvProcessEnv {
+            prior,
+            dotenv: layered.as_process_env(),
+            path: directory,
+            layered: Some(layered),
+        }
+        .cell()
     }
 }
 
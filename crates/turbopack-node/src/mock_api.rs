@@ -0,0 +1,494 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use turbo_tasks::{primitives::StringVc, trace::TraceRawVcs, TryJoinIterExt, Value};
+use turbo_tasks_fs::{rope::Rope, DirectoryContent, DirectoryEntry, FileContent, FileSystemPathVc};
+use turbopack_core::{
+    introspect::{
+        asset::IntrospectableAssetVc, Introspectable, IntrospectableChildrenVc, IntrospectableVc,
+    },
+    source_asset::SourceAssetVc,
+};
+use turbopack_dev_server::source::{
+    query::QueryValue,
+    specificity::{SpecificityReadRef, SpecificityVc},
+    ContentSource, ContentSourceContent, ContentSourceContentVc, ContentSourceData,
+    ContentSourceDataFilter, ContentSourceDataVary, ContentSourceResult, ContentSourceResultVc,
+    ContentSourceVc, NeededData, ProxyResult, ProxyResultVc,
+};
+
+use crate::path_regex::{PathRegex, PathRegexVc};
+
+/// Query parameter to override the delay of a single response, in
+/// milliseconds.
+pub const DELAY_QUERY_PARAM: &str = "__mock_delay";
+/// Query parameter to override the status code of a single response.
+pub const STATUS_QUERY_PARAM: &str = "__mock_status";
+
+const METHODS: &[&str] = &["GET", "HEAD", "POST", "PUT", "PATCH", "DELETE", "OPTIONS"];
+
+/// Serves fixture files from a directory as a mock HTTP API, for developing
+/// against APIs that don't exist yet.
+///
+/// Fixture paths are matched like pages, see [PathRegex::from_pathname]:
+/// `users/[id].json` matches `users/42`, `files/[...path].json` matches one
+/// or more segments and `[[...path]]` also matches none. `index` files match
+/// their directory. An
+/// HTTP method can be put before the extension (`users/[id].post.json`) to
+/// only match that method; fixtures without one match every method.
+///
+/// `.http` fixtures contain a raw response with an optional status line and
+/// headers, separated from the body by an empty line. All other fixtures are
+/// served with status 200 and a content type guessed from their extension.
+/// Occurrences of `{{name}}` in a fixture body are replaced with the value of
+/// the matched `[name]` segment.
+///
+/// A deleted fixture is not found until the directory listing catches up.
+///
+/// Responses can be delayed by a default `delay_ms`, and both the delay and
+/// the status code can be overridden per request with the
+/// [DELAY_QUERY_PARAM] and [STATUS_QUERY_PARAM] query parameters. As
+/// fixtures are read through the file system, changes to them are served on
+/// the next request.
+#[turbo_tasks::value(shared)]
+pub struct MockApiContentSource {
+    pub prefix: String,
+    pub dir: FileSystemPathVc,
+    pub delay_ms: u64,
+}
+
+#[turbo_tasks::value_impl]
+impl MockApiContentSourceVc {
+    #[turbo_tasks::function]
+    pub fn new(prefix: String, dir: FileSystemPathVc, delay_ms: u64) -> MockApiContentSourceVc {
+        let mut prefix = prefix;
+        if !prefix.is_empty() && !prefix.ends_with('/') {
+            prefix.push('/');
+        }
+        MockApiContentSource {
+            prefix,
+            dir,
+            delay_ms,
+        }
+        .cell()
+    }
+
+    /// All fixtures in the directory.
+    #[turbo_tasks::function]
+    pub async fn routes(self) -> Result<MockRoutesVc> {
+        let dir = self.await?.dir;
+        Ok(mock_routes(dir, dir))
+    }
+}
+
+/// A fixture file and the requests it matches.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TraceRawVcs)]
+pub struct MockRoute {
+    /// The pathname of the fixture, e.g. `users/[id]`.
+    pub pathname: String,
+    /// The uppercase HTTP method this fixture is restricted to, if any.
+    pub method: Option<String>,
+    pub file: FileSystemPathVc,
+    pub path_regex: PathRegexVc,
+    pub specificity: SpecificityVc,
+}
+
+impl MockRoute {
+    /// Creates a route from the path of a fixture relative to the fixture
+    /// directory.
+    fn from_fixture_path(path: &str, file: FileSystemPathVc) -> Self {
+        let (pathname, method) = parse_fixture_path(path);
+        MockRoute {
+            path_regex: path_regex(pathname.clone()),
+            specificity: specificity(&pathname),
+            pathname,
+            method,
+            file,
+        }
+    }
+
+    fn matches_method(&self, method: &str) -> bool {
+        self.method
+            .as_deref()
+            .map_or(true, |m| m.eq_ignore_ascii_case(method))
+    }
+}
+
+impl std::fmt::Display for MockRoute {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} /{}",
+            self.method.as_deref().unwrap_or("*"),
+            self.pathname
+        )
+    }
+}
+
+/// Splits the path of a fixture into the pathname it serves and the HTTP
+/// method it is restricted to.
+fn parse_fixture_path(path: &str) -> (String, Option<String>) {
+    let mut stem = match path.rsplit_once('.') {
+        // The dots of a catch-all segment aren't an extension.
+        Some((stem, extension)) if !extension.contains(['/', ']']) => stem,
+        _ => path,
+    };
+
+    let mut method = None;
+    if let Some((rest, suffix)) = stem.rsplit_once('.') {
+        let suffix = suffix.to_ascii_uppercase();
+        if METHODS.contains(&suffix.as_str()) {
+            stem = rest;
+            method = Some(suffix);
+        }
+    }
+
+    let pathname = if stem == "index" {
+        ""
+    } else {
+        stem.strip_suffix("/index").unwrap_or(stem)
+    };
+    (pathname.to_string(), method)
+}
+
+#[turbo_tasks::function]
+fn path_regex(pathname: String) -> Result<PathRegexVc> {
+    Ok(PathRegexVc::cell(PathRegex::from_pathname(&pathname)?))
+}
+
+/// Static segments are more specific than dynamic segments, which are more
+/// specific than catch-all segments.
+fn specificity(pathname: &str) -> SpecificityVc {
+    let mut specificity = SpecificityVc::exact();
+    for (position, segment) in pathname.split('/').enumerate() {
+        let position = position as u32;
+        if segment.starts_with("[[") || segment.starts_with("[...") {
+            specificity = specificity.with_catch_all(position);
+        } else if segment.starts_with('[') {
+            specificity = specificity.with_dynamic_segment(position);
+        }
+    }
+    specificity
+}
+
+#[turbo_tasks::value(transparent)]
+pub struct MockRoutes(Vec<MockRoute>);
+
+/// Collects the fixtures in `dir` and its subdirectories.
+#[turbo_tasks::function]
+async fn mock_routes(root: FileSystemPathVc, dir: FileSystemPathVc) -> Result<MockRoutesVc> {
+    let root_path = root.await?;
+    let mut routes = Vec::new();
+    if let DirectoryContent::Entries(entries) = &*dir.read_dir().await? {
+        let mut subdirectories = Vec::new();
+        for entry in entries.values() {
+            match entry {
+                DirectoryEntry::File(file) | DirectoryEntry::Symlink(file) => {
+                    if let Some(path) = root_path.get_path_to(&*file.await?) {
+                        routes.push(MockRoute::from_fixture_path(path, *file));
+                    }
+                }
+                DirectoryEntry::Directory(dir) => subdirectories.push(mock_routes(root, *dir)),
+                DirectoryEntry::Other(_) | DirectoryEntry::Error => {}
+            }
+        }
+        for subroutes in subdirectories.into_iter().try_join().await? {
+            routes.extend(subroutes.iter().cloned());
+        }
+    }
+    Ok(MockRoutesVc::cell(routes))
+}
+
+#[turbo_tasks::value(transparent)]
+struct OptionProxyResult(Option<ProxyResultVc>);
+
+/// Reads a fixture into the response it describes, or `None` when it has
+/// been deleted.
+#[turbo_tasks::function]
+async fn read_fixture(file: FileSystemPathVc) -> Result<OptionProxyResultVc> {
+    let path = file.await?;
+    let content = match &*file.read().await? {
+        FileContent::Content(content) => content.content().clone(),
+        FileContent::NotFound => return Ok(OptionProxyResultVc::cell(None)),
+    };
+
+    if path.extension() == Some("http") {
+        return Ok(OptionProxyResultVc::cell(Some(
+            parse_http_fixture(&content.to_str()?).cell(),
+        )));
+    }
+
+    let content_type = mime_guess::from_path(path.file_name()).first_or_octet_stream();
+    Ok(OptionProxyResultVc::cell(Some(
+        ProxyResult {
+            status: 200,
+            headers: vec!["content-type".to_string(), content_type.to_string()],
+            body: content,
+        }
+        .cell(),
+    )))
+}
+
+/// Parses a raw HTTP response: an optional `HTTP/1.1 <status>` line, headers,
+/// an empty line and the body.
+fn parse_http_fixture(content: &str) -> ProxyResult {
+    let (head, body) = content
+        .split_once("\r\n\r\n")
+        .or_else(|| content.split_once("\n\n"))
+        .unwrap_or((content, ""));
+
+    let mut status = 200;
+    let mut headers = Vec::new();
+    for (i, line) in head.lines().enumerate() {
+        if i == 0 && line.starts_with("HTTP/") {
+            if let Some(code) = line.split_whitespace().nth(1).and_then(|s| s.parse().ok()) {
+                status = code;
+            }
+            continue;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push(name.trim().to_ascii_lowercase());
+            headers.push(value.trim().to_string());
+        }
+    }
+
+    ProxyResult {
+        status,
+        headers,
+        body: Rope::from(body.to_string()),
+    }
+}
+
+/// Replaces `{{name}}` placeholders in a fixture body with matched params.
+fn substitute_params(body: &Rope, params: &IndexMap<String, String>) -> Result<Rope> {
+    if params.is_empty() {
+        return Ok(body.clone());
+    }
+    let Ok(text) = body.to_str() else {
+        // Binary fixtures are served as-is.
+        return Ok(body.clone());
+    };
+    let mut text = text.into_owned();
+    for (name, value) in params {
+        // Optional catch-all segments include their leading slash.
+        let value = value.trim_start_matches('/');
+        text = text.replace(&format!("{{{{{name}}}}}"), value);
+    }
+    Ok(Rope::from(text))
+}
+
+fn query_param<T: std::str::FromStr>(data: &ContentSourceData, name: &str) -> Option<T> {
+    match data.query.as_ref()?.get(name)? {
+        QueryValue::String(value) => value.parse().ok(),
+        _ => None,
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl ContentSource for MockApiContentSource {
+    #[turbo_tasks::function]
+    async fn get(
+        self_vc: MockApiContentSourceVc,
+        path: &str,
+        data: Value<ContentSourceData>,
+    ) -> Result<ContentSourceResultVc> {
+        let this = self_vc.await?;
+        let Some(subpath) = path.strip_prefix(&this.prefix) else {
+            return Ok(ContentSourceResultVc::not_found());
+        };
+        let subpath = subpath
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>()
+            .join("/");
+        let mut matches = Vec::new();
+        for route in self_vc.routes().await?.iter() {
+            if let Some(params) = route.path_regex.await?.get_matches(&subpath) {
+                matches.push((route.clone(), params));
+            }
+        }
+        if matches.is_empty() {
+            return Ok(ContentSourceResultVc::not_found());
+        }
+
+        let Some(method) = &data.method else {
+            return Ok(ContentSourceResultVc::exact(
+                ContentSourceContent::NeedData(NeededData {
+                    source: self_vc.into(),
+                    path: path.to_string(),
+                    vary: ContentSourceDataVary {
+                        method: true,
+                        query: Some(ContentSourceDataFilter::Subset(
+                            [DELAY_QUERY_PARAM.to_string(), STATUS_QUERY_PARAM.to_string()]
+                                .into(),
+                        )),
+                        ..Default::default()
+                    },
+                })
+                .cell(),
+            ));
+        };
+
+        // The most specific route wins, and routes restricted to the method
+        // win over unrestricted ones.
+        let mut best: Option<((SpecificityReadRef, bool), MockRoute, _)> = None;
+        for (route, params) in matches {
+            if !route.matches_method(method) {
+                continue;
+            }
+            let rank = (route.specificity.await?, route.method.is_some());
+            if best
+                .as_ref()
+                .map_or(true, |(best_rank, ..)| rank > *best_rank)
+            {
+                best = Some((rank, route, params));
+            }
+        }
+        let Some((_, route, params)) = best else {
+            return Ok(ContentSourceResultVc::exact(
+                ContentSourceContent::HttpProxy(
+                    ProxyResult {
+                        status: 405,
+                        headers: Vec::new(),
+                        body: Rope::default(),
+                    }
+                    .cell(),
+                )
+                .cell(),
+            ));
+        };
+
+        let Some(fixture) = *read_fixture(route.file).await? else {
+            return Ok(ContentSourceResultVc::not_found());
+        };
+        let fixture = fixture.await?;
+        let status = query_param(&data, STATUS_QUERY_PARAM).unwrap_or(fixture.status);
+        let mut content: ContentSourceContentVc = ContentSourceContent::HttpProxy(
+            ProxyResult {
+                status,
+                headers: fixture.headers.clone(),
+                body: substitute_params(&fixture.body, &params)?,
+            }
+            .cell(),
+        )
+        .cell();
+        // The result of `get` is reused for identical requests, so the delay
+        // is applied by the dev server.
+        let delay_ms = query_param(&data, DELAY_QUERY_PARAM).unwrap_or(this.delay_ms);
+        if delay_ms > 0 {
+            content = ContentSourceContent::Delayed { content, delay_ms }.cell();
+        }
+
+        Ok(ContentSourceResult {
+            specificity: route.specificity,
+            content,
+        }
+        .cell())
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl Introspectable for MockApiContentSource {
+    #[turbo_tasks::function]
+    fn ty(&self) -> StringVc {
+        StringVc::cell("mock api content source".to_string())
+    }
+
+    #[turbo_tasks::function]
+    fn details(&self) -> StringVc {
+        StringVc::cell(format!(
+            "serves fixtures at /{prefix} with a delay of {delay}ms",
+            prefix = self.prefix,
+            delay = self.delay_ms
+        ))
+    }
+
+    #[turbo_tasks::function]
+    async fn children(self_vc: MockApiContentSourceVc) -> Result<IntrospectableChildrenVc> {
+        let children = self_vc
+            .routes()
+            .await?
+            .iter()
+            .map(|route| {
+                (
+                    StringVc::cell(route.to_string()),
+                    IntrospectableAssetVc::new(SourceAssetVc::new(route.file).as_asset()),
+                )
+            })
+            .collect::<HashSet<_>>();
+        Ok(IntrospectableChildrenVc::cell(children))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_http_fixture_with_status_and_headers() {
+        let result = parse_http_fixture(
+            "HTTP/1.1 201 Created\r\nContent-Type: application/json\r\nX-Id:  7 \r\n\r\n{\"id\": \
+             7}\n\nrest",
+        );
+        assert_eq!(result.status, 201);
+        assert_eq!(
+            result.headers,
+            ["content-type", "application/json", "x-id", "7"]
+        );
+        assert_eq!(result.body.to_str().unwrap(), "{\"id\": 7}\n\nrest");
+    }
+
+    #[test]
+    fn parse_http_fixture_without_status_line() {
+        let result = parse_http_fixture("Location: /login\n\n");
+        assert_eq!(result.status, 200);
+        assert_eq!(result.headers, ["location", "/login"]);
+        assert_eq!(result.body.to_str().unwrap(), "");
+
+        let result = parse_http_fixture("HTTP/1.1 204");
+        assert_eq!(result.status, 204);
+        assert!(result.headers.is_empty());
+    }
+
+    #[test]
+    fn parse_fixture_path() {
+        fn parse(path: &str) -> (String, Option<String>) {
+            super::parse_fixture_path(path)
+        }
+
+        assert_eq!(
+            parse("users/[id].post.json"),
+            ("users/[id]".to_string(), Some("POST".to_string()))
+        );
+        assert_eq!(parse("index.json"), (String::new(), None));
+        assert_eq!(parse("users/index.http"), ("users".to_string(), None));
+        assert_eq!(
+            parse("files/[...path].json"),
+            ("files/[...path]".to_string(), None)
+        );
+        assert_eq!(parse("[[...all]]"), ("[[...all]]".to_string(), None));
+        // Only known methods are treated as a method suffix.
+        assert_eq!(parse("data.v2.json"), ("data.v2".to_string(), None));
+    }
+
+    #[test]
+    fn substitute_params() -> Result<()> {
+        let params = IndexMap::from([
+            ("id".to_string(), "42".to_string()),
+            ("path".to_string(), "/a/b".to_string()),
+        ]);
+        let body = Rope::from("{\"id\": {{id}}, \"path\": \"{{path}}\", \"other\": \"{{other}}\"}");
+        assert_eq!(
+            super::substitute_params(&body, &params)?.to_str()?,
+            "{\"id\": 42, \"path\": \"a/b\", \"other\": \"{{other}}\"}"
+        );
+
+        // Binary fixtures are served as-is.
+        let binary = super::substitute_params(&Rope::from(vec![0xff, 0xfe]), &params)?;
+        assert_eq!(binary.len(), 2);
+        assert!(binary.to_str().is_err());
+        Ok(())
+    }
+}
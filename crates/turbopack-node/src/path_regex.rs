@@ -1,4 +1,4 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use indexmap::IndexMap;
 use turbo_tasks::{
     primitives::{Regex, StringVc},
@@ -15,6 +15,49 @@ pub struct PathRegex {
 }
 
 impl PathRegex {
+    /// Creates a regular expression for a pathname, where `[name]` segments
+    /// match a single segment, `[...name]` one or more segments and
+    /// `[[...name]]` any number of segments.
+    pub fn from_pathname(path: &str) -> Result<PathRegex> {
+        let mut path_regex = PathRegexBuilder::new();
+        for segment in path.split('/') {
+            if let Some(segment) = segment.strip_prefix('[') {
+                if let Some(segment) = segment.strip_prefix("[...") {
+                    if let Some((placeholder, rem)) = segment.split_once("]]") {
+                        path_regex.push_optional_catch_all(placeholder, rem);
+                    } else {
+                        bail!(
+                            "path ({}) contains '[[' without matching ']]' at '[[...{}'",
+                            path,
+                            segment
+                        );
+                    }
+                } else if let Some(segment) = segment.strip_prefix("...") {
+                    if let Some((placeholder, rem)) = segment.split_once(']') {
+                        path_regex.push_catch_all(placeholder, rem);
+                    } else {
+                        bail!(
+                            "path ({}) contains '[' without matching ']' at '[...{}'",
+                            path,
+                            segment
+                        );
+                    }
+                } else if let Some((placeholder, rem)) = segment.split_once(']') {
+                    path_regex.push_dynamic_segment(placeholder, rem);
+                } else {
+                    bail!(
+                        "path ({}) contains '[' without matching ']' at '[{}'",
+                        path,
+                        segment
+                    );
+                }
+            } else {
+                path_regex.push_static_segment(segment);
+            }
+        }
+        path_regex.build()
+    }
+
     /// Returns true if the given path matches the regular expression.
     pub fn is_match(&self, path: &str) -> bool {
         self.regex.is_match(path)
@@ -0,0 +1,181 @@
+use std::{fs, path::Path};
+
+use anyhow::{bail, Result};
+use tempfile::TempDir;
+use turbo_tasks::{TurboTasks, Value};
+use turbo_tasks_fs::{DiskFileSystemVc, FileSystem};
+use turbo_tasks_memory::MemoryBackend;
+use turbopack_dev_server::source::{
+    query::{Query, QueryValue},
+    ContentSource, ContentSourceContent, ContentSourceData, ContentSourceResultVc,
+};
+use turbopack_node::mock_api::{MockApiContentSourceVc, DELAY_QUERY_PARAM, STATUS_QUERY_PARAM};
+
+/// A response of the mock API.
+#[derive(Debug, PartialEq, Eq)]
+struct Response {
+    status: u16,
+    body: String,
+    delay_ms: u64,
+}
+
+fn fixtures(files: &[(&str, &str)]) -> TempDir {
+    turbopack_node::register();
+    let dir = TempDir::new().unwrap();
+    for (path, content) in files {
+        let path = dir.path().join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+    dir
+}
+
+fn mock_api(dir: &Path) -> MockApiContentSourceVc {
+    let fs = DiskFileSystemVc::new("fixtures".to_string(), dir.to_string_lossy().into_owned());
+    MockApiContentSourceVc::new("api".to_string(), fs.root(), 0)
+}
+
+/// Gets `path` from the source like the dev server does, or `None` when
+/// nothing is found.
+async fn get(
+    source: MockApiContentSourceVc,
+    method: &str,
+    path: &str,
+    query: &[(&str, &str)],
+) -> Result<Option<Response>> {
+    let mut result: ContentSourceResultVc = source.get(path, Value::new(Default::default()));
+    let mut content = result.await?.content;
+    if let ContentSourceContent::NeedData(needed) = &*content.await? {
+        let mut data = ContentSourceData {
+            method: Some(method.to_string()),
+            ..Default::default()
+        };
+        if needed.vary.query.is_some() {
+            let mut data_query = Query::default();
+            for (name, value) in query {
+                data_query.insert(name.to_string(), QueryValue::String(value.to_string()));
+            }
+            data.query = Some(data_query);
+        }
+        result = needed.source.get(&needed.path, Value::new(data));
+        content = result.await?.content;
+    }
+    let mut delay_ms = 0;
+    if let ContentSourceContent::Delayed {
+        content: delayed,
+        delay_ms: delayed_ms,
+    } = *content.await?
+    {
+        content = delayed;
+        delay_ms = delayed_ms;
+    }
+    Ok(match &*content.await? {
+        ContentSourceContent::NotFound => None,
+        ContentSourceContent::HttpProxy(proxy) => {
+            let proxy = proxy.await?;
+            Some(Response {
+                status: proxy.status,
+                body: proxy.body.to_str()?.into_owned(),
+                delay_ms,
+            })
+        }
+        content => bail!("unexpected content {content:?}"),
+    })
+}
+
+fn ok(body: &str) -> Option<Response> {
+    Some(Response {
+        status: 200,
+        body: body.to_string(),
+        delay_ms: 0,
+    })
+}
+
+#[tokio::test]
+async fn serves_the_most_specific_fixture() -> Result<()> {
+    let dir = fixtures(&[
+        ("users/[id].json", r#"{"id": "{{id}}"}"#),
+        ("users/me.json", r#"{"me": true}"#),
+        ("users/[id].delete.http", "HTTP/1.1 204\n\n"),
+        ("files/[...path].txt", "{{path}}"),
+        ("index.json", "[]"),
+    ]);
+    let path = dir.path().to_path_buf();
+    let tt = TurboTasks::new(MemoryBackend::new());
+    tt.run_once(async move {
+        let source = mock_api(&path);
+        assert_eq!(
+            get(source, "GET", "api/users/42", &[]).await?,
+            ok(r#"{"id": "42"}"#)
+        );
+        assert_eq!(
+            get(source, "GET", "api/users/me", &[]).await?,
+            ok(r#"{"me": true}"#)
+        );
+        assert_eq!(
+            get(source, "DELETE", "api/users/42", &[]).await?,
+            Some(Response {
+                status: 204,
+                body: String::new(),
+                delay_ms: 0,
+            })
+        );
+        assert_eq!(get(source, "GET", "api/files/a/b", &[]).await?, ok("a/b"));
+        assert_eq!(get(source, "GET", "api/files", &[]).await?, None);
+        assert_eq!(get(source, "GET", "api/", &[]).await?, ok("[]"));
+        assert_eq!(get(source, "GET", "api/users/42/posts", &[]).await?, None);
+        assert_eq!(get(source, "GET", "other/users/42", &[]).await?, None);
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn applies_overrides() -> Result<()> {
+    let dir = fixtures(&[("users.post.json", "{}")]);
+    let path = dir.path().to_path_buf();
+    let tt = TurboTasks::new(MemoryBackend::new());
+    tt.run_once(async move {
+        let source = mock_api(&path);
+        assert_eq!(
+            get(source, "GET", "api/users", &[]).await?,
+            Some(Response {
+                status: 405,
+                body: String::new(),
+                delay_ms: 0,
+            })
+        );
+        assert_eq!(
+            get(
+                source,
+                "POST",
+                "api/users",
+                &[(DELAY_QUERY_PARAM, "50"), (STATUS_QUERY_PARAM, "500")]
+            )
+            .await?,
+            Some(Response {
+                status: 500,
+                body: "{}".to_string(),
+                delay_ms: 50,
+            })
+        );
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn deleted_fixture_is_not_found() -> Result<()> {
+    let dir = fixtures(&[("gone.json", "{}")]);
+    let path = dir.path().to_path_buf();
+    let tt = TurboTasks::new(MemoryBackend::new());
+    tt.run_once(async move {
+        let source = mock_api(&path);
+        // The fixture is listed, but deleted before it is read.
+        assert_eq!(source.routes().await?.len(), 1);
+        fs::remove_file(path.join("gone.json"))?;
+        assert_eq!(get(source, "GET", "api/gone", &[]).await?, None);
+        Ok(())
+    })
+    .await
+}
@@ -12,7 +12,7 @@ use serde::Deserialize;
 use similar::TextDiff;
 use test_generator::test_resources;
 use turbo_tasks::{debug::ValueDebug, NothingVc, TryJoinIterExt, TurboTasks, Value};
-use turbo_tasks_env::DotenvProcessEnvVc;
+use turbo_tasks_env::{DotenvProcessEnvVc, FilterProcessEnvVc, ProcessEnvVc};
 use turbo_tasks_fs::{
     util::sys_to_unix, DirectoryContent, DirectoryEntry, DiskFileSystemVc, File, FileContent,
     FileSystem, FileSystemEntryType, FileSystemPathVc, FileSystemVc,
@@ -61,11 +61,18 @@ static WORKSPACE_ROOT: Lazy<String> = Lazy::new(|| {
 });
 
 #[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct SnapshotOptions {
     #[serde(default = "default_browserslist")]
     browserslist: String,
     #[serde(default = "default_entry")]
     entry: String,
+    #[serde(default)]
+    error_overlay: bool,
+    /// Inlines the variables of `input/.env` starting with this prefix, like
+    /// `NEXT_PUBLIC_` in Next.js client code.
+    #[serde(default)]
+    inline_env: Option<String>,
 }
 
 impl Default for SnapshotOptions {
@@ -73,6 +80,8 @@ impl Default for SnapshotOptions {
         SnapshotOptions {
             browserslist: default_browserslist(),
             entry: default_entry(),
+            error_overlay: false,
+            inline_env: None,
         }
     }
 }
@@ -140,7 +149,16 @@ async fn run_test(resource: String) -> Result<FileSystemPathVc> {
     let entry_asset = project_path.join(&options.entry);
     let entry_paths = vec![entry_asset];
 
-    let runtime_entries = maybe_load_env(project_fs.into(), fs_path).await?;
+    let dotenv = maybe_load_env(project_fs.into(), fs_path).await?;
+    let runtime_entries = dotenv.map(|(dotenv_path, env)| {
+        EcmascriptChunkPlaceablesVc::cell(vec![
+            ProcessEnvAssetVc::new(dotenv_path, env).as_ecmascript_chunk_placeable()
+        ])
+    });
+    let inline_env = match (dotenv, options.inline_env) {
+        (Some((_, env)), Some(prefix)) => Some(FilterProcessEnvVc::new(env, prefix).into()),
+        _ => None,
+    };
 
     let env = EnvironmentVc::new(
         Value::new(ExecutionEnvironment::Browser(
@@ -164,6 +182,7 @@ async fn run_test(resource: String) -> Result<FileSystemPathVc> {
             enable_emotion: true,
             enable_styled_components: true,
             preset_env_versions: Some(env),
+            inline_env,
             rules: vec![(
                 ContextCondition::InDirectory("node_modules".to_string()),
                 ModuleOptionsContext {
@@ -196,9 +215,12 @@ async fn run_test(resource: String) -> Result<FileSystemPathVc> {
 
     let chunk_root_path = path.join("output");
     let static_root_path = path.join("static");
-    let chunking_context =
-        DevChunkingContextVc::builder(project_root, path, chunk_root_path, static_root_path, env)
-            .build();
+    let mut chunking_context =
+        DevChunkingContextVc::builder(project_root, path, chunk_root_path, static_root_path, env);
+    if options.error_overlay {
+        chunking_context = chunking_context.error_overlay();
+    }
+    let chunking_context = chunking_context.build();
 
     let expected_paths = expected(chunk_root_path)
         .await?
@@ -325,7 +347,7 @@ async fn diff(path: FileSystemPathVc, actual: AssetContentVc) -> Result<()> {
 async fn maybe_load_env(
     project_fs: FileSystemVc,
     path: &Path,
-) -> Result<Option<EcmascriptChunkPlaceablesVc>> {
+) -> Result<Option<(FileSystemPathVc, ProcessEnvVc)>> {
     let dotenv_path = path.join("input/.env");
     let dotenv_path = sys_to_unix(dotenv_path.to_str().unwrap());
     let dotenv_path = project_fs.root().join(&dotenv_path);
@@ -335,10 +357,7 @@ async fn maybe_load_env(
     }
 
     let env = DotenvProcessEnvVc::new(None, dotenv_path);
-    let asset = ProcessEnvAssetVc::new(dotenv_path, env.into());
-    Ok(Some(EcmascriptChunkPlaceablesVc::cell(vec![
-        asset.as_ecmascript_chunk_placeable()
-    ])))
+    Ok(Some((dotenv_path, env.into())))
 }
 
 async fn expected(dir: FileSystemPathVc) -> Result<HashSet<FileSystemPathVc>> {
@@ -858,13 +858,18 @@ function getAffectedModuleEffects(moduleId) {
  * @param {import('../types/protocol').ServerMessage} update
  */
 function handleApply(chunkPath, update) {
+  BACKEND.reportIssues?.(chunkPath, update.issues);
+
   switch (update.type) {
     case "partial":
       applyUpdate(chunkPath, update.instruction);
+      BACKEND.reportUpdateApplied?.();
       break;
     case "restart":
       BACKEND.restart();
       break;
+    case "issues":
+      break;
     default:
       throw new Error(`Unknown update type: ${update.type}`);
   }
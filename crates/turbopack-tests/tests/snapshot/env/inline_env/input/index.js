@@ -0,0 +1,7 @@
+if (process.env.NEXT_PUBLIC_FEATURE === "on") {
+  console.log("feature enabled");
+} else {
+  console.log("feature disabled");
+}
+
+console.log(process.env.SECRET);
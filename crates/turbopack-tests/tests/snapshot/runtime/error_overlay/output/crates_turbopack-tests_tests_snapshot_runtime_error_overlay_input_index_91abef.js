@@ -0,0 +1,1567 @@
+(self.TURBOPACK = self.TURBOPACK || []).push(["output/crates_turbopack-tests_tests_snapshot_runtime_error_overlay_input_index_91abef.js", {
+
+"[project]/crates/turbopack-tests/tests/snapshot/runtime/error_overlay/input/index.js (ecmascript)": (function({ r: __turbopack_require__, x: __turbopack_external_require__, i: __turbopack_import__, s: __turbopack_esm__, v: __turbopack_export_value__, c: __turbopack_cache__, l: __turbopack_load__, j: __turbopack_cjs__, p: process, g: global, __dirname, m: module, e: exports }) { !function() {
+
+console.log("hello world");
+
+}.call(this) }),
+}, ({ loadedChunks, instantiateRuntimeModule }) => {
+    if(!(true && loadedChunks.has("output/crates_turbopack-tests_tests_snapshot_runtime_error_overlay_input_index_0eaebb.js"))) return true;
+    instantiateRuntimeModule("[project]/crates/turbopack-tests/tests/snapshot/runtime/error_overlay/input/index.js (ecmascript)");
+}]);
+(() => {
+if (!Array.isArray(globalThis.TURBOPACK)) {
+    return;
+}
+/** @typedef {import('../types/backend').RuntimeBackend} RuntimeBackend */
+
+/** @type {RuntimeBackend} */
+const BACKEND = {
+  loadChunk(chunkPath, _from) {
+    return new Promise((resolve, reject) => {
+      if (chunkPath.endsWith(".css")) {
+        const link = document.createElement("link");
+        link.rel = "stylesheet";
+        link.href = `/${chunkPath}`;
+        link.onerror = () => {
+          reject();
+        };
+        link.onload = () => {
+          // CSS chunks do not register themselves, and as such must be marked as
+          // loaded instantly.
+          resolve();
+        };
+        document.body.appendChild(link);
+      } else if (chunkPath.endsWith(".js")) {
+        const script = document.createElement("script");
+        script.src = `/${chunkPath}`;
+        // We'll only mark the chunk as loaded once the script has been executed,
+        // which happens in `registerChunk`. Hence the absence of `resolve()` in
+        // this branch.
+        script.onerror = () => {
+          reject();
+        };
+        document.body.appendChild(script);
+      } else {
+        throw new Error(`can't infer type of chunk from path ${chunkPath}`);
+      }
+    });
+  },
+
+  restart: () => self.location.reload(),
+};
+/** @typedef {import('../types').ChunkPath} ChunkPath */
+/** @typedef {import('../types/protocol').Issue} Issue */
+/** @typedef {import('../types/protocol').CodeFrameLine} CodeFrameLine */
+
+/**
+ * @typedef {Object} StackFrame
+ * @property {string | null} methodName
+ * @property {string} file
+ * @property {number} line 1-based, as reported by the browser
+ * @property {number} column 1-based, as reported by the browser
+ */
+
+/**
+ * @typedef {Object} OriginalPosition
+ * @property {string} source
+ * @property {number} line 0-based
+ * @property {number} column 0-based
+ * @property {string | null} content
+ */
+
+/**
+ * @typedef {Object} RuntimeError
+ * @property {string} message
+ * @property {Array<{ frame: StackFrame, original: OriginalPosition | null }>} frames
+ */
+
+/**
+ * A minimal error overlay for apps that don't bring their own. It shows
+ * critical issues reported through HMR updates, as well as uncaught runtime
+ * errors mapped back to their original sources.
+ *
+ * This is only embedded in chunks when the chunking context enables the error
+ * overlay, so frameworks with their own overlay (e.g. Next.js) don't get it.
+ */
+const ERROR_OVERLAY = (() => {
+  const CRITICAL_SEVERITIES = ["bug", "fatal", "error"];
+  const SOURCE_MAP_PREFIX = "/__turbopack_sourcemap__";
+  const CODE_FRAME_CONTEXT_LINES = 3;
+  const MAX_FRAMES = 10;
+  const BASE64 =
+    "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+  const STACK_FRAME_RE =
+    /^\s*(?:at\s+(?:(.*?)\s+\()?|(.*?)@)(\S+?):(\d+):(\d+)\)?\s*$/;
+
+  /**
+   * Critical issues of the last update message of each chunk.
+   *
+   * @type {Map<ChunkPath, Issue[]>}
+   */
+  const issuesByChunk = new Map();
+  /** @type {RuntimeError[]} */
+  let runtimeErrors = [];
+  /** @type {Map<string, Promise<any>>} */
+  const sourceMapCache = new Map();
+  /** @type {HTMLElement | null} */
+  let host = null;
+
+  function isDisabled() {
+    return typeof document === "undefined";
+  }
+
+  /**
+   * @param {ChunkPath} chunkPath
+   * @param {Issue[]} issues
+   */
+  function reportIssues(chunkPath, issues) {
+    const critical = issues.filter((issue) =>
+      CRITICAL_SEVERITIES.includes(issue.severity)
+    );
+    if (critical.length > 0) {
+      issuesByChunk.set(chunkPath, critical);
+    } else if (!issuesByChunk.delete(chunkPath)) {
+      return;
+    }
+    render();
+  }
+
+  /**
+   * Runtime errors are assumed to be fixed by the next successful update.
+   */
+  function reportUpdateApplied() {
+    sourceMapCache.clear();
+    if (runtimeErrors.length === 0) {
+      return;
+    }
+    runtimeErrors = [];
+    render();
+  }
+
+  /**
+   * @param {unknown} error
+   */
+  async function reportRuntimeError(error) {
+    if (isDisabled()) {
+      return;
+    }
+
+    const message =
+      error instanceof Error
+        ? `${error.name}: ${error.message}`
+        : String(error);
+    const stack = error instanceof Error ? error.stack ?? "" : "";
+    const frames = parseStack(stack).slice(0, MAX_FRAMES);
+    const resolved = await Promise.all(
+      frames.map(async (frame) => ({
+        frame,
+        original: await resolveFrame(frame).catch(() => null),
+      }))
+    );
+
+    runtimeErrors.push({ message, frames: resolved });
+    render();
+  }
+
+  /**
+   * @param {string} stack
+   * @returns {StackFrame[]}
+   */
+  function parseStack(stack) {
+    /** @type {StackFrame[]} */
+    const frames = [];
+    for (const line of stack.split("\n")) {
+      const match = STACK_FRAME_RE.exec(line);
+      if (match == null) {
+        continue;
+      }
+      frames.push({
+        methodName: match[1] || match[2] || null,
+        file: match[3],
+        line: parseInt(match[4], 10),
+        column: parseInt(match[5], 10),
+      });
+    }
+    return frames;
+  }
+
+  /**
+   * Maps a stack frame back to its original source, using the source map
+   * served by the dev server.
+   *
+   * @param {StackFrame} frame
+   * @returns {Promise<OriginalPosition | null>}
+   */
+  async function resolveFrame(frame) {
+    let url;
+    try {
+      url = new URL(frame.file, location.href);
+    } catch (_) {
+      return null;
+    }
+    if (url.origin !== location.origin) {
+      return null;
+    }
+
+    const mapUrl = `${SOURCE_MAP_PREFIX}${url.pathname}.map`;
+    let map = sourceMapCache.get(mapUrl);
+    if (map == null) {
+      map = fetch(mapUrl).then((res) => (res.ok ? res.json() : null));
+      sourceMapCache.set(mapUrl, map);
+    }
+
+    const sourceMap = await map;
+    if (sourceMap == null) {
+      return null;
+    }
+    return originalPositionFor(sourceMap, frame.line - 1, frame.column - 1);
+  }
+
+  /**
+   * @param {string} segment
+   * @returns {number[]}
+   */
+  function decodeVlqSegment(segment) {
+    const values = [];
+    let value = 0;
+    let shift = 0;
+    for (const char of segment) {
+      const digit = BASE64.indexOf(char);
+      value += (digit & 31) << shift;
+      if (digit & 32) {
+        shift += 5;
+      } else {
+        const negative = value & 1;
+        value >>>= 1;
+        values.push(negative ? -value : value);
+        value = 0;
+        shift = 0;
+      }
+    }
+    return values;
+  }
+
+  /**
+   * Looks up the original position of a generated position in a regular or
+   * sectioned (index) source map. Lines and columns are 0-based.
+   *
+   * @param {any} map
+   * @param {number} line
+   * @param {number} column
+   * @returns {OriginalPosition | null}
+   */
+  function originalPositionFor(map, line, column) {
+    if (Array.isArray(map.sections)) {
+      let section = null;
+      for (const candidate of map.sections) {
+        const offset = candidate.offset;
+        if (
+          offset.line > line ||
+          (offset.line === line && offset.column > column)
+        ) {
+          break;
+        }
+        section = candidate;
+      }
+      if (section == null) {
+        return null;
+      }
+      const offset = section.offset;
+      return originalPositionFor(
+        section.map,
+        line - offset.line,
+        line === offset.line ? column - offset.column : column
+      );
+    }
+
+    const lines = map.mappings.split(";");
+    let sourceIndex = 0;
+    let originalLine = 0;
+    let originalColumn = 0;
+    let found = null;
+    for (let i = 0; i <= line && i < lines.length; i++) {
+      let generatedColumn = 0;
+      for (const segment of lines[i].split(",")) {
+        if (segment === "") {
+          continue;
+        }
+        const fields = decodeVlqSegment(segment);
+        generatedColumn += fields[0];
+        if (fields.length < 4) {
+          continue;
+        }
+        sourceIndex += fields[1];
+        originalLine += fields[2];
+        originalColumn += fields[3];
+        if (i === line && generatedColumn <= column) {
+          found = { sourceIndex, line: originalLine, column: originalColumn };
+        }
+      }
+    }
+
+    if (found == null) {
+      return null;
+    }
+    return {
+      source: map.sources[found.sourceIndex],
+      line: found.line,
+      column: found.column,
+      content: map.sourcesContent?.[found.sourceIndex] ?? null,
+    };
+  }
+
+  /**
+   * @param {string} tag
+   * @param {Record<string, string>} attrs
+   * @param {Array<Node | string>} children
+   * @returns {HTMLElement}
+   */
+  function h(tag, attrs, children) {
+    const element = document.createElement(tag);
+    for (const [name, value] of Object.entries(attrs)) {
+      element.setAttribute(name, value);
+    }
+    // `append` treats strings as text, so messages are never parsed as HTML.
+    element.append(...children);
+    return element;
+  }
+
+  /**
+   * @param {CodeFrameLine[]} lines
+   * @param {number} startLine
+   * @param {number} endLine
+   * @returns {HTMLElement}
+   */
+  function renderCodeFrame(lines, startLine, endLine) {
+    return h(
+      "pre",
+      { class: "code-frame" },
+      lines.map(({ line, content }) =>
+        h(
+          "div",
+          {
+            class:
+              line >= startLine && line <= endLine ? "line marked" : "line",
+          },
+          [h("span", { class: "gutter" }, [String(line + 1)]), content]
+        )
+      )
+    );
+  }
+
+  /**
+   * @param {string} content
+   * @param {number} line
+   * @returns {CodeFrameLine[]}
+   */
+  function codeFrameFromContent(content, line) {
+    const start = Math.max(0, line - CODE_FRAME_CONTEXT_LINES);
+    return content
+      .split("\n")
+      .slice(start, line + CODE_FRAME_CONTEXT_LINES + 1)
+      .map((content, i) => ({ line: start + i, content }));
+  }
+
+  /**
+   * @param {Issue} issue
+   * @returns {HTMLElement}
+   */
+  function renderIssue(issue) {
+    const children = [
+      h("h2", {}, [`${issue.severity} - ${issue.category}: ${issue.title}`]),
+    ];
+    if (issue.source != null) {
+      const { asset, start, end } = issue.source;
+      children.push(
+        h("p", { class: "location" }, [
+          `${asset.path}:${start.line + 1}:${start.column + 1}`,
+        ])
+      );
+      if (issue.source.code_frame.length > 0) {
+        children.push(
+          renderCodeFrame(issue.source.code_frame, start.line, end.line)
+        );
+      }
+    } else if (issue.context) {
+      children.push(h("p", { class: "location" }, [issue.context]));
+    }
+    if (issue.description) {
+      children.push(h("p", {}, [issue.description]));
+    }
+    if (issue.detail) {
+      children.push(h("pre", { class: "detail" }, [issue.detail]));
+    }
+    return h("section", { class: "issue" }, children);
+  }
+
+  /**
+   * @param {RuntimeError} error
+   * @returns {HTMLElement}
+   */
+  function renderRuntimeError(error) {
+    const children = [
+      h("h2", {}, [`Unhandled runtime error: ${error.message}`]),
+    ];
+    let renderedCodeFrame = false;
+    for (const { frame, original } of error.frames) {
+      const name = frame.methodName ?? "<anonymous>";
+      if (original == null) {
+        children.push(
+          h("p", { class: "frame" }, [
+            `${name} (${frame.file}:${frame.line}:${frame.column})`,
+          ])
+        );
+        continue;
+      }
+      children.push(
+        h("p", { class: "frame" }, [
+          `${name} (${original.source}:${original.line + 1}:${
+            original.column + 1
+          })`,
+        ])
+      );
+      // Only the innermost frame with a known source gets a code frame.
+      if (!renderedCodeFrame && original.content != null) {
+        renderedCodeFrame = true;
+        children.push(
+          renderCodeFrame(
+            codeFrameFromContent(original.content, original.line),
+            original.line,
+            original.line
+          )
+        );
+      }
+    }
+    return h("section", { class: "issue" }, children);
+  }
+
+  function render() {
+    if (isDisabled()) {
+      return;
+    }
+    if (document.body == null) {
+      document.addEventListener("DOMContentLoaded", render, { once: true });
+      return;
+    }
+
+    const issues = Array.from(issuesByChunk.values()).flat();
+    if (issues.length === 0 && runtimeErrors.length === 0) {
+      host?.remove();
+      host = null;
+      return;
+    }
+
+    if (host == null) {
+      host = document.createElement("turbopack-error-overlay");
+      host.attachShadow({ mode: "open" });
+    }
+    if (!host.isConnected) {
+      document.body.appendChild(host);
+    }
+
+    const dismiss = h("button", { class: "dismiss" }, ["×"]);
+    dismiss.onclick = () => {
+      runtimeErrors = [];
+      host?.remove();
+    };
+
+    const root = /** @type {ShadowRoot} */ (host.shadowRoot);
+    root.replaceChildren(
+      h("style", {}, [STYLES]),
+      h("div", { class: "backdrop" }, [
+        h("div", { class: "dialog" }, [
+          dismiss,
+          ...issues.map(renderIssue),
+          ...runtimeErrors.map(renderRuntimeError),
+        ]),
+      ])
+    );
+  }
+
+  const STYLES = `
+    .backdrop {
+      position: fixed;
+      inset: 0;
+      z-index: 2147483647;
+      overflow: auto;
+      background: rgba(0, 0, 0, 0.6);
+      font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", sans-serif;
+    }
+    .dialog {
+      position: relative;
+      max-width: 960px;
+      margin: 48px auto;
+      padding: 16px 24px;
+      border-top: 4px solid #e5484d;
+      border-radius: 4px;
+      background: #fff;
+      color: #222;
+    }
+    .dismiss {
+      position: absolute;
+      top: 8px;
+      right: 8px;
+      border: none;
+      background: none;
+      font-size: 24px;
+      cursor: pointer;
+    }
+    h2 {
+      margin: 16px 0 8px;
+      font-size: 16px;
+      color: #e5484d;
+    }
+    .location, .frame {
+      margin: 4px 0;
+      font-family: monospace;
+      color: #555;
+    }
+    pre {
+      overflow: auto;
+      padding: 8px;
+      border-radius: 4px;
+      background: #f6f6f6;
+      font-size: 13px;
+    }
+    .line.marked {
+      background: #ffe4e4;
+    }
+    .gutter {
+      display: inline-block;
+      width: 4em;
+      margin-right: 1em;
+      text-align: right;
+      color: #999;
+    }
+  `;
+
+  if (
+    typeof self !== "undefined" &&
+    typeof self.addEventListener === "function"
+  ) {
+    self.addEventListener("error", (event) => {
+      reportRuntimeError(event.error ?? event.message);
+    });
+    self.addEventListener("unhandledrejection", (event) => {
+      reportRuntimeError(event.reason);
+    });
+  }
+
+  return {
+    reportIssues,
+    reportUpdateApplied,
+  };
+})();
+
+BACKEND.reportIssues = ERROR_OVERLAY.reportIssues;
+BACKEND.reportUpdateApplied = ERROR_OVERLAY.reportUpdateApplied;
+/* eslint-disable @next/next/no-assign-module-variable */
+
+/** @typedef {import('../types').ChunkRegistration} ChunkRegistration */
+/** @typedef {import('../types').ModuleFactory} ModuleFactory */
+
+/** @typedef {import('../types').ChunkPath} ChunkPath */
+/** @typedef {import('../types').ModuleId} ModuleId */
+/** @typedef {import('../types').GetFirstModuleChunk} GetFirstModuleChunk */
+
+/** @typedef {import('../types').Module} Module */
+/** @typedef {import('../types').Exports} Exports */
+/** @typedef {import('../types').EsmInteropNamespace} EsmInteropNamespace */
+/** @typedef {import('../types').Runnable} Runnable */
+
+/** @typedef {import('../types').Runtime} Runtime */
+
+/** @typedef {import('../types').RefreshHelpers} RefreshHelpers */
+/** @typedef {import('../types/hot').Hot} Hot */
+/** @typedef {import('../types/hot').HotData} HotData */
+/** @typedef {import('../types/hot').AcceptCallback} AcceptCallback */
+/** @typedef {import('../types/hot').AcceptErrorHandler} AcceptErrorHandler */
+/** @typedef {import('../types/hot').HotState} HotState */
+/** @typedef {import('../types/protocol').EcmascriptChunkUpdate} EcmascriptChunkUpdate */
+/** @typedef {import('../types/protocol').HmrUpdateEntry} HmrUpdateEntry */
+
+/** @typedef {import('../types/runtime').Loader} Loader */
+/** @typedef {import('../types/runtime').ModuleEffect} ModuleEffect */
+
+/** @type {Array<Runnable>} */
+let runnable = [];
+/** @type {Object.<ModuleId, ModuleFactory>} */
+const moduleFactories = { __proto__: null };
+/** @type {Object.<ModuleId, Module>} */
+const moduleCache = { __proto__: null };
+/**
+ * Contains the IDs of all chunks that have been loaded.
+ *
+ * @type {Set<ChunkPath>}
+ */
+const loadedChunks = new Set();
+/**
+ * Maps a chunk ID to the chunk's loader if the chunk is currently being loaded.
+ *
+ * @type {Map<ChunkPath, Loader>}
+ */
+const chunkLoaders = new Map();
+/**
+ * Maps module IDs to persisted data between executions of their hot module
+ * implementation (`hot.data`).
+ *
+ * @type {Map<ModuleId, HotData>}
+ */
+const moduleHotData = new Map();
+/**
+ * Maps module instances to their hot module state.
+ *
+ * @type {Map<Module, HotState>}
+ */
+const moduleHotState = new Map();
+/**
+ * Module IDs that are instantiated as part of the runtime of a chunk.
+ *
+ * @type {Set<ModuleId>}
+ */
+const runtimeModules = new Set();
+/**
+ * Map from module ID to the chunks that contain this module.
+ *
+ * In HMR, we need to keep track of which modules are contained in which so
+ * chunks. This is so we don't eagerly dispose of a module when it is removed
+ * from chunk A, but still exists in chunk B.
+ *
+ * @type {Map<ModuleId, Set<ChunkPath>>}
+ */
+const moduleChunksMap = new Map();
+const hOP = Object.prototype.hasOwnProperty;
+const _process =
+  typeof process !== "undefined"
+    ? process
+    : {
+        env: {},
+        // Some modules rely on `process.browser` to execute browser-specific code.
+        // NOTE: `process.browser` is specific to Webpack.
+        browser: true,
+      };
+
+const toStringTag = typeof Symbol !== "undefined" && Symbol.toStringTag;
+
+/**
+ * @param {any} obj
+ * @param {PropertyKey} name
+ * @param {PropertyDescriptor & ThisType<any>} options
+ */
+function defineProp(obj, name, options) {
+  if (!hOP.call(obj, name)) Object.defineProperty(obj, name, options);
+}
+
+/**
+ * Adds the getters to the exports object
+ *
+ * @param {Exports} exports
+ * @param {Record<string, () => any>} getters
+ */
+function esm(exports, getters) {
+  defineProp(exports, "__esModule", { value: true });
+  if (toStringTag) defineProp(exports, toStringTag, { value: "Module" });
+  for (const key in getters) {
+    defineProp(exports, key, { get: getters[key], enumerable: true });
+  }
+}
+
+/**
+ * Adds the getters to the exports object
+ *
+ * @param {Exports} exports
+ * @param {Record<string, any>} props
+ */
+function cjs(exports, props) {
+  for (const key in props) {
+    defineProp(exports, key, { get: () => props[key], enumerable: true });
+  }
+}
+
+/**
+ * @param {Module} module
+ * @param {any} value
+ */
+function exportValue(module, value) {
+  module.exports = value;
+}
+
+/**
+ * @param {Record<string, any>} obj
+ * @param {string} key
+ */
+function createGetter(obj, key) {
+  return () => obj[key];
+}
+
+/**
+ * @param {Exports} raw
+ * @param {EsmInteropNamespace} ns
+ * @param {boolean} [allowExportDefault]
+ */
+function interopEsm(raw, ns, allowExportDefault) {
+  /** @type {Object.<string, () => any>} */
+  const getters = { __proto__: null };
+  for (const key in raw) {
+    getters[key] = createGetter(raw, key);
+  }
+  if (!(allowExportDefault && "default" in getters)) {
+    getters["default"] = () => raw;
+  }
+  esm(ns, getters);
+}
+
+/**
+ * @param {Module} sourceModule
+ * @param {ModuleId} id
+ * @param {boolean} allowExportDefault
+ * @returns {EsmInteropNamespace}
+ */
+function esmImport(sourceModule, id, allowExportDefault) {
+  const module = getOrInstantiateModuleFromParent(id, sourceModule);
+  const raw = module.exports;
+  if (raw.__esModule) return raw;
+  if (module.interopNamespace) return module.interopNamespace;
+  const ns = (module.interopNamespace = {});
+  interopEsm(raw, ns, allowExportDefault);
+  return ns;
+}
+
+/**
+ * @param {Module} sourceModule
+ * @param {ModuleId} id
+ * @returns {Exports}
+ */
+function commonJsRequire(sourceModule, id) {
+  return getOrInstantiateModuleFromParent(id, sourceModule).exports;
+}
+
+function externalRequire(id, esm) {
+  let raw;
+  try {
+    raw = require(id);
+  } catch (err) {
+    // TODO(alexkirsz) This can happen when a client-side module tries to load
+    // an external module we don't provide a shim for (e.g. querystring, url).
+    // For now, we fail semi-silently, but in the future this should be a
+    // compilation error.
+    throw new Error(`Failed to load external module ${id}: ${err}`);
+  }
+  if (!esm || raw.__esModule) {
+    return raw;
+  }
+  const ns = {};
+  interopEsm(raw, ns, true);
+  return ns;
+}
+
+/**
+ * @param {ModuleId} from
+ * @param {string} chunkPath
+ * @returns {Promise<any> | undefined}
+ */
+function loadChunk(from, chunkPath) {
+  if (loadedChunks.has(chunkPath)) {
+    return Promise.resolve();
+  }
+
+  const chunkLoader = getOrCreateChunkLoader(chunkPath, from);
+
+  return chunkLoader.promise;
+}
+
+/**
+ * @param {string} chunkPath
+ * @param {ModuleId} from
+ * @returns {Loader}
+ */
+function getOrCreateChunkLoader(chunkPath, from) {
+  let chunkLoader = chunkLoaders.get(chunkPath);
+  if (chunkLoader) {
+    return chunkLoader;
+  }
+
+  let resolve;
+  let reject;
+  const promise = new Promise((innerResolve, innerReject) => {
+    resolve = innerResolve;
+    reject = innerReject;
+  });
+
+  const onError = (error) => {
+    chunkLoaders.delete(chunkPath);
+    reject(
+      new Error(
+        `Failed to load chunk from ${chunkPath}${error ? `: ${error}` : ""}`
+      )
+    );
+  };
+
+  const onLoad = () => {
+    loadedChunks.add(chunkPath);
+    chunkLoaders.delete(chunkPath);
+    resolve();
+  };
+
+  chunkLoader = {
+    promise,
+    onLoad,
+  };
+  chunkLoaders.set(chunkPath, chunkLoader);
+
+  BACKEND.loadChunk(chunkPath, from).then(onLoad, onError);
+
+  return chunkLoader;
+}
+
+/**
+ * @enum {number}
+ */
+const SourceType = {
+  /**
+   * The module was instantiated because it was included in an evaluated chunk's
+   * runtime.
+   */
+  Runtime: 0,
+  /**
+   * The module was instantiated because a parent module imported it.
+   */
+  Parent: 1,
+  /**
+   * The module was instantiated because it was included in a chunk's hot module
+   * update.
+   */
+  Update: 2,
+};
+
+/**
+ *
+ * @param {ModuleId} id
+ * @param {SourceType} sourceType
+ * @param {ModuleId} [sourceId]
+ * @returns {Module}
+ */
+function instantiateModule(id, sourceType, sourceId) {
+  const moduleFactory = moduleFactories[id];
+  if (typeof moduleFactory !== "function") {
+    // This can happen if modules incorrectly handle HMR disposes/updates,
+    // e.g. when they keep a `setTimeout` around which still executes old code
+    // and contains e.g. a `require("something")` call.
+    let instantiationReason;
+    switch (sourceType) {
+      case SourceType.Runtime:
+        instantiationReason = "as a runtime entry";
+        break;
+      case SourceType.Parent:
+        instantiationReason = `because it was required from module ${sourceId}`;
+        break;
+      case SourceType.Update:
+        instantiationReason = "because of an HMR update";
+        break;
+    }
+    throw new Error(
+      `Module ${id} was instantiated ${instantiationReason}, but the module factory is not available. It might have been deleted in an HMR update.`
+    );
+  }
+
+  const hotData = moduleHotData.get(id);
+  const { hot, hotState } = createModuleHot(hotData);
+
+  /** @type {Module} */
+  const module = {
+    exports: {},
+    loaded: false,
+    id,
+    parents: [],
+    children: [],
+    interopNamespace: undefined,
+    hot,
+  };
+  moduleCache[id] = module;
+  moduleHotState.set(module, hotState);
+
+  if (sourceType === SourceType.Runtime) {
+    runtimeModules.add(id);
+  } else if (sourceType === SourceType.Parent) {
+    module.parents.push(sourceId);
+
+    // No need to add this module as a child of the parent module here, this
+    // has already been taken care of in `getOrInstantiateModuleFromParent`.
+  }
+
+  runModuleExecutionHooks(module, () => {
+    moduleFactory.call(module.exports, {
+      e: module.exports,
+      r: commonJsRequire.bind(null, module),
+      x: externalRequire,
+      i: esmImport.bind(null, module),
+      s: esm.bind(null, module.exports),
+      j: cjs.bind(null, module.exports),
+      v: exportValue.bind(null, module),
+      m: module,
+      c: moduleCache,
+      l: loadChunk.bind(null, id),
+      p: _process,
+      g: globalThis,
+      __dirname: module.id.replace(/(^|\/)[\/]+$/, ""),
+    });
+  });
+
+  module.loaded = true;
+  if (module.interopNamespace) {
+    // in case of a circular dependency: cjs1 -> esm2 -> cjs1
+    interopEsm(module.exports, module.interopNamespace);
+  }
+
+  return module;
+}
+
+/**
+ * NOTE(alexkirsz) Webpack has an "module execution" interception hook that
+ * Next.js' React Refresh runtime hooks into to add module context to the
+ * refresh registry.
+ *
+ * @param {Module} module
+ * @param {() => void} executeModule
+ */
+function runModuleExecutionHooks(module, executeModule) {
+  const cleanupReactRefreshIntercept =
+    typeof globalThis.$RefreshInterceptModuleExecution$ === "function"
+      ? globalThis.$RefreshInterceptModuleExecution$(module.id)
+      : () => {};
+
+  executeModule();
+
+  if ("$RefreshHelpers$" in globalThis) {
+    // This pattern can also be used to register the exports of
+    // a module with the React Refresh runtime.
+    registerExportsAndSetupBoundaryForReactRefresh(
+      module,
+      globalThis.$RefreshHelpers$
+    );
+  }
+
+  cleanupReactRefreshIntercept();
+}
+
+/**
+ * Retrieves a module from the cache, or instantiate it if it is not cached.
+ *
+ * @param {ModuleId} id
+ * @param {Module} sourceModule
+ * @returns {Module}
+ */
+function getOrInstantiateModuleFromParent(id, sourceModule) {
+  if (!sourceModule.hot.active) {
+    console.warn(
+      `Unexpected import of module ${id} from module ${sourceModule.id}, which was deleted by an HMR update`
+    );
+  }
+
+  const module = moduleCache[id];
+
+  if (sourceModule.children.indexOf(id) === -1) {
+    sourceModule.children.push(id);
+  }
+
+  if (module) {
+    if (module.parents.indexOf(sourceModule.id) === -1) {
+      module.parents.push(sourceModule.id);
+    }
+
+    return module;
+  }
+
+  return instantiateModule(id, SourceType.Parent, sourceModule.id);
+}
+
+/**
+ * This is adapted from https://github.com/vercel/next.js/blob/3466862d9dc9c8bb3131712134d38757b918d1c0/packages/react-refresh-utils/internal/ReactRefreshModule.runtime.ts
+ *
+ * @param {Module} module
+ * @param {RefreshHelpers} helpers
+ */
+function registerExportsAndSetupBoundaryForReactRefresh(module, helpers) {
+  const currentExports = module.exports;
+  const prevExports = module.hot.data.prevExports ?? null;
+
+  helpers.registerExportsForReactRefresh(currentExports, module.id);
+
+  // A module can be accepted automatically based on its exports, e.g. when
+  // it is a Refresh Boundary.
+  if (helpers.isReactRefreshBoundary(currentExports)) {
+    // Save the previous exports on update so we can compare the boundary
+    // signatures.
+    module.hot.dispose((data) => {
+      data.prevExports = currentExports;
+    });
+    // Unconditionally accept an update to this module, we'll check if it's
+    // still a Refresh Boundary later.
+    module.hot.accept();
+
+    // This field is set when the previous version of this module was a
+    // Refresh Boundary, letting us know we need to check for invalidation or
+    // enqueue an update.
+    if (prevExports !== null) {
+      // A boundary can become ineligible if its exports are incompatible
+      // with the previous exports.
+      //
+      // For example, if you add/remove/change exports, we'll want to
+      // re-execute the importing modules, and force those components to
+      // re-render. Similarly, if you convert a class component to a
+      // function, we want to invalidate the boundary.
+      if (
+        helpers.shouldInvalidateReactRefreshBoundary(
+          prevExports,
+          currentExports
+        )
+      ) {
+        module.hot.invalidate();
+      } else {
+        helpers.scheduleUpdate();
+      }
+    }
+  } else {
+    // Since we just executed the code for the module, it's possible that the
+    // new exports made it ineligible for being a boundary.
+    // We only care about the case when we were _previously_ a boundary,
+    // because we already accepted this update (accidental side effect).
+    const isNoLongerABoundary = prevExports !== null;
+    if (isNoLongerABoundary) {
+      module.hot.invalidate();
+    }
+  }
+}
+
+/**
+ * @param {ModuleId[]} dependencyChain
+ * @returns {string}
+ */
+function formatDependencyChain(dependencyChain) {
+  return `Dependency chain: ${dependencyChain.join(" -> ")}`;
+}
+
+/**
+ * @param {HmrUpdateEntry} factory
+ * @returns {ModuleFactory}
+ * @private
+ */
+function _eval({ code, url, map }) {
+  code += `\n\n//# sourceURL=${location.origin}${url}`;
+  if (map) code += `\n//# sourceMappingURL=${map}`;
+  return eval(code);
+}
+
+/**
+ * @param {EcmascriptChunkUpdate} update
+ * @returns {{outdatedModules: Set<any>, newModuleFactories: Map<any, any>}}
+ */
+function computeOutdatedModules(update) {
+  const outdatedModules = new Set();
+  const newModuleFactories = new Map();
+
+  for (const [moduleId, factory] of Object.entries(update.added)) {
+    newModuleFactories.set(moduleId, _eval(factory));
+  }
+
+  for (const [moduleId, factory] of Object.entries(update.modified)) {
+    const effect = getAffectedModuleEffects(moduleId);
+
+    switch (effect.type) {
+      case "unaccepted":
+        throw new Error(
+          `cannot apply update: unaccepted module. ${formatDependencyChain(
+            effect.dependencyChain
+          )}.`
+        );
+      case "self-declined":
+        throw new Error(
+          `cannot apply update: self-declined module. ${formatDependencyChain(
+            effect.dependencyChain
+          )}.`
+        );
+      case "accepted":
+        newModuleFactories.set(moduleId, _eval(factory));
+        for (const outdatedModuleId of effect.outdatedModules) {
+          outdatedModules.add(outdatedModuleId);
+        }
+        break;
+      // TODO(alexkirsz) Dependencies: handle dependencies effects.
+    }
+  }
+
+  return { outdatedModules, newModuleFactories };
+}
+
+/**
+ * @param {Iterable<ModuleId>} outdatedModules
+ * @returns {{ moduleId: ModuleId, errorHandler: true | Function }[]}
+ */
+function computeOutdatedSelfAcceptedModules(outdatedModules) {
+  const outdatedSelfAcceptedModules = [];
+  for (const moduleId of outdatedModules) {
+    const module = moduleCache[moduleId];
+    const hotState = moduleHotState.get(module);
+    if (module && hotState.selfAccepted && !hotState.selfInvalidated) {
+      outdatedSelfAcceptedModules.push({
+        moduleId,
+        errorHandler: hotState.selfAccepted,
+      });
+    }
+  }
+  return outdatedSelfAcceptedModules;
+}
+
+/**
+ * @param {ChunkPath} chunkPath
+ * @param {Iterable<ModuleId>} outdatedModules
+ * @param {Iterable<ModuleId>} deletedModules
+ */
+function disposePhase(chunkPath, outdatedModules, deletedModules) {
+  for (const moduleId of outdatedModules) {
+    const module = moduleCache[moduleId];
+    if (!module) {
+      continue;
+    }
+
+    const data = disposeModule(module);
+
+    moduleHotData.set(moduleId, data);
+  }
+
+  for (const moduleId of deletedModules) {
+    const module = moduleCache[moduleId];
+    if (!module) {
+      continue;
+    }
+
+    const noRemainingChunks = removeModuleFromChunk(moduleId, chunkPath);
+
+    if (noRemainingChunks) {
+      disposeModule(module);
+
+      moduleHotData.delete(moduleId);
+    }
+  }
+
+  // TODO(alexkirsz) Dependencies: remove outdated dependency from module
+  // children.
+}
+
+/**
+ * Disposes of an instance of a module.
+ *
+ * Returns the persistent hot data that should be kept for the next module
+ * instance.
+ *
+ * @param {Module} module
+ * @returns {{}}
+ */
+function disposeModule(module) {
+  const hotState = moduleHotState.get(module);
+  const data = {};
+
+  // Run the `hot.dispose` handler, if any, passing in the persistent
+  // `hot.data` object.
+  for (const disposeHandler of hotState.disposeHandlers) {
+    disposeHandler(data);
+  }
+
+  // This used to warn in `getOrInstantiateModuleFromParent` when a disposed
+  // module is still importing other modules.
+  module.hot.active = false;
+
+  delete moduleCache[module.id];
+  moduleHotState.delete(module);
+
+  // TODO(alexkirsz) Dependencies: delete the module from outdated deps.
+
+  // Remove the disposed module from its children's parents list.
+  // It will be added back once the module re-instantiates and imports its
+  // children again.
+  for (const childId of module.children) {
+    const child = moduleCache[childId];
+    if (!child) {
+      continue;
+    }
+
+    const idx = child.parents.indexOf(module.id);
+    if (idx >= 0) {
+      child.parents.splice(idx, 1);
+    }
+  }
+
+  return data;
+}
+
+/**
+ *
+ * @param {ChunkPath} chunkPath
+ * @param {{ moduleId: ModuleId, errorHandler: true | Function }[]} outdatedSelfAcceptedModules
+ * @param {Map<string, ModuleFactory>} newModuleFactories
+ */
+function applyPhase(
+  chunkPath,
+  outdatedSelfAcceptedModules,
+  newModuleFactories
+) {
+  // Update module factories.
+  for (const [moduleId, factory] of newModuleFactories.entries()) {
+    moduleFactories[moduleId] = factory;
+    addModuleToChunk(moduleId, chunkPath);
+  }
+
+  // TODO(alexkirsz) Run new runtime entries here.
+
+  // TODO(alexkirsz) Dependencies: call accept handlers for outdated deps.
+
+  // Re-instantiate all outdated self-accepted modules.
+  for (const { moduleId, errorHandler } of outdatedSelfAcceptedModules) {
+    try {
+      instantiateModule(moduleId, SourceType.Update);
+    } catch (err) {
+      if (typeof errorHandler === "function") {
+        try {
+          errorHandler(err, { moduleId, module: moduleCache[moduleId] });
+        } catch (_) {
+          // Ignore error.
+        }
+      }
+    }
+  }
+}
+
+/**
+ *
+ * @param {ChunkPath} chunkPath
+ * @param {EcmascriptChunkUpdate} update
+ */
+function applyUpdate(chunkPath, update) {
+  const { outdatedModules, newModuleFactories } =
+    computeOutdatedModules(update);
+
+  const deletedModules = new Set(update.deleted);
+
+  const outdatedSelfAcceptedModules =
+    computeOutdatedSelfAcceptedModules(outdatedModules);
+
+  disposePhase(chunkPath, outdatedModules, deletedModules);
+  applyPhase(chunkPath, outdatedSelfAcceptedModules, newModuleFactories);
+}
+
+/**
+ *
+ * @param {ModuleId} moduleId
+ * @returns {ModuleEffect}
+ */
+function getAffectedModuleEffects(moduleId) {
+  const outdatedModules = new Set();
+
+  /** @typedef {{moduleId?: ModuleId, dependencyChain: ModuleId[]}} QueueItem */
+
+  /** @type {QueueItem[]} */
+  const queue = [
+    {
+      moduleId,
+      dependencyChain: [],
+    },
+  ];
+
+  while (queue.length > 0) {
+    const { moduleId, dependencyChain } =
+      /** @type {QueueItem} */ queue.shift();
+    outdatedModules.add(moduleId);
+
+    // We've arrived at the runtime of the chunk, which means that nothing
+    // else above can accept this update.
+    if (moduleId === undefined) {
+      return {
+        type: "unaccepted",
+        dependencyChain,
+      };
+    }
+
+    const module = moduleCache[moduleId];
+    const hotState = moduleHotState.get(module);
+
+    if (
+      // The module is not in the cache. Since this is a "modified" update,
+      // it means that the module was never instantiated before.
+      !module || // The module accepted itself without invalidating globalThis.
+      // TODO is that right?
+      (hotState.selfAccepted && !hotState.selfInvalidated)
+    ) {
+      continue;
+    }
+
+    if (hotState.selfDeclined) {
+      return {
+        type: "self-declined",
+        dependencyChain,
+        moduleId,
+      };
+    }
+
+    if (runtimeModules.has(moduleId)) {
+      queue.push({
+        moduleId: undefined,
+        dependencyChain: [...dependencyChain, moduleId],
+      });
+      continue;
+    }
+
+    for (const parentId of module.parents) {
+      const parent = moduleCache[parentId];
+
+      if (!parent) {
+        // TODO(alexkirsz) Is this even possible?
+        continue;
+      }
+
+      // TODO(alexkirsz) Dependencies: check accepted and declined
+      // dependencies here.
+
+      queue.push({
+        moduleId: parentId,
+        dependencyChain: [...dependencyChain, moduleId],
+      });
+    }
+  }
+
+  return {
+    type: "accepted",
+    moduleId,
+    outdatedModules,
+  };
+}
+
+/**
+ * @param {ChunkPath} chunkPath
+ * @param {import('../types/protocol').ServerMessage} update
+ */
+function handleApply(chunkPath, update) {
+  BACKEND.reportIssues?.(chunkPath, update.issues);
+
+  switch (update.type) {
+    case "partial":
+      applyUpdate(chunkPath, update.instruction);
+      BACKEND.reportUpdateApplied?.();
+      break;
+    case "restart":
+      BACKEND.restart();
+      break;
+    case "issues":
+      break;
+    default:
+      throw new Error(`Unknown update type: ${update.type}`);
+  }
+}
+
+/**
+ * @param {HotData} [hotData]
+ * @returns {{hotState: HotState, hot: Hot}}
+ */
+function createModuleHot(hotData) {
+  /** @type {HotState} */
+  const hotState = {
+    selfAccepted: false,
+    selfDeclined: false,
+    selfInvalidated: false,
+    disposeHandlers: [],
+  };
+
+  /**
+   * TODO(alexkirsz) Support full (dep, callback, errorHandler) form.
+   *
+   * @param {string | string[] | AcceptErrorHandler} [dep]
+   * @param {AcceptCallback} [_callback]
+   * @param {AcceptErrorHandler} [_errorHandler]
+   */
+  function accept(dep, _callback, _errorHandler) {
+    if (dep === undefined) {
+      hotState.selfAccepted = true;
+    } else if (typeof dep === "function") {
+      hotState.selfAccepted = dep;
+    } else {
+      throw new Error("unsupported `accept` signature");
+    }
+  }
+
+  /** @type {Hot} */
+  const hot = {
+    // TODO(alexkirsz) This is not defined in the HMR API. It was used to
+    // decide whether to warn whenever an HMR-disposed module required other
+    // modules. We might want to remove it.
+    active: true,
+
+    data: hotData ?? {},
+
+    accept: accept,
+
+    decline: (dep) => {
+      if (dep === undefined) {
+        hotState.selfDeclined = true;
+      } else {
+        throw new Error("unsupported `decline` signature");
+      }
+    },
+
+    dispose: (callback) => {
+      hotState.disposeHandlers.push(callback);
+    },
+
+    addDisposeHandler: (callback) => {
+      hotState.disposeHandlers.push(callback);
+    },
+
+    removeDisposeHandler: (callback) => {
+      const idx = hotState.disposeHandlers.indexOf(callback);
+      if (idx >= 0) {
+        hotState.disposeHandlers.splice(idx, 1);
+      }
+    },
+
+    invalidate: () => {
+      hotState.selfInvalidated = true;
+      // TODO(alexkirsz) The original HMR code had management-related code
+      // here.
+    },
+
+    // NOTE(alexkirsz) This is part of the management API, which we don't
+    // implement, but the Next.js React Refresh runtime uses this to decide
+    // whether to schedule an update.
+    status: () => "idle",
+
+    // NOTE(alexkirsz) Since we always return "idle" for now, these are no-ops.
+    addStatusHandler: (_handler) => {},
+    removeStatusHandler: (_handler) => {},
+  };
+
+  return { hot, hotState };
+}
+
+/**
+ * Adds a module to a chunk.
+ *
+ * @param {ModuleId} moduleId
+ * @param {ChunkPath} chunkPath
+ */
+function addModuleToChunk(moduleId, chunkPath) {
+  let moduleChunks = moduleChunksMap.get(moduleId);
+  if (!moduleChunks) {
+    moduleChunks = new Set([chunkPath]);
+    moduleChunksMap.set(moduleId, moduleChunks);
+  } else {
+    moduleChunks.add(chunkPath);
+  }
+}
+
+/**
+ * Returns the first chunk that included a module.
+ *
+ * @type {GetFirstModuleChunk}
+ */
+function getFirstModuleChunk(moduleId) {
+  const moduleChunkPaths = moduleChunksMap.get(moduleId);
+  if (moduleChunkPaths == null) {
+    return null;
+  }
+
+  return moduleChunkPaths.values().next().value;
+}
+
+/**
+ * Removes a module from a chunk. Returns true there are no remaining chunks
+ * including this module.
+ *
+ * @param {ModuleId} moduleId
+ * @param {ChunkPath} chunkPath
+ * @returns {boolean}
+ */
+function removeModuleFromChunk(moduleId, chunkPath) {
+  const moduleChunks = moduleChunksMap.get(moduleId);
+  moduleChunks.delete(chunkPath);
+
+  if (moduleChunks.size > 0) {
+    return false;
+  }
+
+  moduleChunksMap.delete(moduleId);
+  return true;
+}
+
+/**
+ * Instantiates a runtime module.
+ */
+/**
+ *
+ * @param {ModuleId} moduleId
+ * @returns {Module}
+ */
+function instantiateRuntimeModule(moduleId) {
+  return instantiateModule(moduleId, SourceType.Runtime);
+}
+
+/**
+ * Subscribes to chunk updates from the update server and applies them.
+ *
+ * @param {ChunkPath} chunkPath
+ */
+function subscribeToChunkUpdates(chunkPath) {
+  // This adds a chunk update listener once the handler code has been loaded
+  globalThis.TURBOPACK_CHUNK_UPDATE_LISTENERS.push([
+    chunkPath,
+    handleApply.bind(null, chunkPath),
+  ]);
+}
+
+function markChunkAsLoaded(chunkPath) {
+  const chunkLoader = chunkLoaders.get(chunkPath);
+  if (!chunkLoader) {
+    loadedChunks.add(chunkPath);
+
+    // This happens for all initial chunks that are loaded directly from
+    // the HTML.
+    return;
+  }
+
+  // Only chunks that are loaded via `loadChunk` will have a loader.
+  chunkLoader.onLoad();
+}
+
+/** @type {Runtime} */
+const runtime = {
+  loadedChunks,
+  modules: moduleFactories,
+  cache: moduleCache,
+  instantiateRuntimeModule,
+};
+
+/**
+ * @param {ChunkRegistration} chunkRegistration
+ */
+function registerChunk([chunkPath, chunkModules, ...run]) {
+  markChunkAsLoaded(chunkPath);
+  subscribeToChunkUpdates(chunkPath);
+  for (const [moduleId, moduleFactory] of Object.entries(chunkModules)) {
+    if (!moduleFactories[moduleId]) {
+      moduleFactories[moduleId] = moduleFactory;
+    }
+    addModuleToChunk(moduleId, chunkPath);
+  }
+  runnable.push(...run);
+  runnable = runnable.filter((r) => r(runtime));
+}
+
+globalThis.TURBOPACK_CHUNK_UPDATE_LISTENERS =
+  globalThis.TURBOPACK_CHUNK_UPDATE_LISTENERS || [];
+
+globalThis.TURBOPACK.forEach(registerChunk);
+globalThis.TURBOPACK = {
+  push: registerChunk,
+};
+})();
+
+
+//# sourceMappingURL=crates_turbopack-tests_tests_snapshot_runtime_error_overlay_input_index_91abef.js.map
\ No newline at end of file
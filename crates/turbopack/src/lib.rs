@@ -29,9 +29,12 @@ use turbo_tasks::{
     primitives::{BoolVc, StringVc},
     CompletionVc, Value,
 };
-use turbo_tasks_fs::FileSystemPathVc;
+use turbo_tasks_fs::{
+    output::{write_output_files, OutputContent, OutputDiffVc, OutputFilesVc},
+    FileSystemPathVc, LinkContent,
+};
 use turbopack_core::{
-    asset::AssetVc,
+    asset::{AssetContent, AssetVc, AssetsVc},
     context::{AssetContext, AssetContextVc},
     environment::EnvironmentVc,
     issue::{unsupported_module::UnsupportedModuleIssue, Issue, IssueVc},
@@ -485,6 +488,51 @@ pub async fn emit_asset_into_dir(
     })
 }
 
+/// Emits the `assets` and the assets they reference that are inside of
+/// `output_dir` at once and removes the files in `output_dir` that are no
+/// longer emitted, see [write_output_files]. Unlike [emit_with_completion],
+/// this assumes that `output_dir` contains nothing else.
+#[turbo_tasks::function]
+pub async fn emit_atomically(
+    assets: AssetsVc,
+    output_dir: FileSystemPathVc,
+) -> Result<OutputDiffVc> {
+    let dir = &*output_dir.await?;
+    let mut files = Vec::new();
+    let mut emitted = HashSet::new();
+    let mut queue = assets
+        .await?
+        .iter()
+        .map(|asset| aggregate(*asset))
+        .collect::<Vec<_>>();
+    while let Some(aggregated) = queue.pop() {
+        match &*aggregated.content().await? {
+            AggregatedGraphNodeContent::Asset(asset) => {
+                let path = asset.path().await?;
+                // Assets can be referenced from multiple of the `assets`
+                if !path.is_inside(dir) || !emitted.insert(path.path.clone()) {
+                    continue;
+                }
+                let content = match &*asset.content().await? {
+                    AssetContent::File(file) => OutputContent::File(*file),
+                    AssetContent::Redirect { target, link_type } => OutputContent::Link(
+                        LinkContent::Link {
+                            target: target.clone(),
+                            link_type: *link_type,
+                        }
+                        .cell(),
+                    ),
+                };
+                files.push((asset.path(), content));
+            }
+            AggregatedGraphNodeContent::Children(children) => {
+                queue.extend(children.iter().copied());
+            }
+        }
+    }
+    Ok(write_output_files(output_dir, OutputFilesVc::cell(files)))
+}
+
 #[turbo_tasks::function]
 pub fn print_most_referenced(asset: AssetVc) {
     let aggregated = aggregate(asset);
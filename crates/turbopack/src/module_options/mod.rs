@@ -59,6 +59,7 @@ impl ModuleOptionsVc {
             ref enable_postcss_transform,
             ref enable_webpack_loaders,
             preset_env_versions,
+            inline_env,
             ref custom_ecmascript_app_transforms,
             ref custom_ecmascript_transforms,
             ref custom_rules,
@@ -74,8 +75,13 @@ impl ModuleOptionsVc {
                 }
             }
         }
+        // Transforms which are applied to all code, including node_modules
+        let mut shared_transforms = custom_ecmascript_transforms.clone();
+        if let Some(env) = inline_env {
+            shared_transforms.push(EcmascriptInputTransform::InlineEnv(env));
+        }
         let mut transforms = custom_ecmascript_app_transforms.clone();
-        transforms.extend(custom_ecmascript_transforms.iter().cloned());
+        transforms.extend(shared_transforms.iter().cloned());
 
         // Order of transforms is important. e.g. if the React transform occurs before
         // Styled JSX, there won't be JSX nodes for Styled JSX to transform.
@@ -99,11 +105,10 @@ impl ModuleOptionsVc {
         }
 
         let app_transforms = EcmascriptInputTransformsVc::cell(transforms);
-        let vendor_transforms =
-            EcmascriptInputTransformsVc::cell(custom_ecmascript_transforms.clone());
+        let vendor_transforms = EcmascriptInputTransformsVc::cell(shared_transforms.clone());
         let ts_app_transforms = if enable_typescript_transform {
             let mut base_transforms = vec![EcmascriptInputTransform::TypeScript];
-            base_transforms.extend(custom_ecmascript_transforms.iter().cloned());
+            base_transforms.extend(shared_transforms.iter().cloned());
             EcmascriptInputTransformsVc::cell(
                 base_transforms
                     .iter()
@@ -1,6 +1,7 @@
 use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use turbo_tasks::{primitives::StringsVc, trace::TraceRawVcs};
+use turbo_tasks_env::ProcessEnvVc;
 use turbopack_core::{environment::EnvironmentVc, resolve::options::ImportMappingVc};
 use turbopack_ecmascript::EcmascriptInputTransform;
 use turbopack_node::execution_context::ExecutionContextVc;
@@ -56,6 +57,10 @@ pub struct ModuleOptionsContext {
     pub enable_typescript_transform: bool,
     pub enable_mdx: bool,
     pub preset_env_versions: Option<EnvironmentVc>,
+    /// Inlines the variables of this env where they are read from
+    /// `process.env` or `import.meta.env`, and removes the branches that
+    /// became unreachable.
+    pub inline_env: Option<ProcessEnvVc>,
     pub custom_ecmascript_app_transforms: Vec<EcmascriptInputTransform>,
     pub custom_ecmascript_transforms: Vec<EcmascriptInputTransform>,
     /// Custom rules to be applied after all default rules.
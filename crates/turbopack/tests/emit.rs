@@ -0,0 +1,58 @@
+#![feature(min_specialization)]
+
+use std::{fs, path::Path};
+
+use anyhow::Result;
+use turbo_tasks::TurboTasks;
+use turbo_tasks_fs::{DiskFileSystemVc, FileSystem};
+use turbo_tasks_memory::MemoryBackend;
+use turbopack::{emit_atomically, rebase::RebasedAssetVc, register};
+use turbopack_core::{
+    asset::{AssetVc, AssetsVc},
+    source_asset::SourceAssetVc,
+};
+
+/// Emits the files `names` of the `input` directory into the `output`
+/// directory with [emit_atomically] and returns the removed files.
+async fn emit(root: &Path, names: &[&'static str]) -> Result<Vec<String>> {
+    let root = root.to_string_lossy().into_owned();
+    let names = names.to_vec();
+    let tt = TurboTasks::new(MemoryBackend::new());
+    tt.run_once(async move {
+        let fs = DiskFileSystemVc::new("project".to_string(), root).root();
+        let input = fs.join("input");
+        let output = fs.join("output");
+        let mut assets: Vec<AssetVc> = Vec::new();
+        for name in names {
+            let source = SourceAssetVc::new(input.join(name));
+            assets.push(RebasedAssetVc::new(source.into(), input, output).into());
+        }
+        let diff = emit_atomically(AssetsVc::cell(assets), output).await?;
+        Ok(diff.removed.clone())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn emit_atomically_removes_stale_files() -> Result<()> {
+    register();
+    let root = std::env::temp_dir().join(format!("turbopack-emit-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("input"))?;
+    fs::write(root.join("input/a.txt"), "a")?;
+    fs::write(root.join("input/b.txt"), "b")?;
+
+    // Assets emitted twice are written once
+    assert!(emit(&root, &["a.txt", "b.txt", "a.txt"]).await?.is_empty());
+    assert_eq!(fs::read_to_string(root.join("output/a.txt"))?, "a");
+    assert_eq!(fs::read_to_string(root.join("output/b.txt"))?, "b");
+
+    fs::write(root.join("output/stale.txt"), "stale")?;
+    assert_eq!(emit(&root, &["a.txt"]).await?, ["b.txt", "stale.txt"]);
+    assert_eq!(fs::read_to_string(root.join("output/a.txt"))?, "a");
+    assert!(!root.join("output/b.txt").exists());
+    assert!(!root.join("output/stale.txt").exists());
+
+    fs::remove_dir_all(&root)?;
+    Ok(())
+}